@@ -0,0 +1,29 @@
+//! Benchmark xác minh prekey bundle: từng bundle một so với hàng loạt
+//!
+//! Chạy bằng `cargo bench --bench bundle_verification`.
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use e2ee_core::keys::prekey::{SignedPreKey, SignedPreKeyPair};
+use e2ee_core::keys::{verify_bundles, verify_bundles_batch, IdentityKeyPair, PreKeyBundle};
+
+fn signed_bundle(identity: &IdentityKeyPair) -> PreKeyBundle {
+    let signed_prekey = SignedPreKeyPair::generate(1, identity).expect("Failed to generate signed prekey");
+    PreKeyBundle::new(
+        identity.public_key_hex(),
+        identity.verifying_key(),
+        SignedPreKey::from(&signed_prekey),
+        None,
+    )
+}
+
+fn bench_bundle_verification(c: &mut Criterion) {
+    let bundles: Vec<PreKeyBundle> = (0..50).map(|_| signed_bundle(&IdentityKeyPair::generate())).collect();
+
+    let mut group = c.benchmark_group("verify 50 bundles");
+    group.bench_function("serial", |b| b.iter(|| verify_bundles(&bundles)));
+    group.bench_function("batch", |b| b.iter(|| verify_bundles_batch(&bundles)));
+    group.finish();
+}
+
+criterion_group!(benches, bench_bundle_verification);
+criterion_main!(benches);
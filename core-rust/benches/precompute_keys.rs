@@ -0,0 +1,38 @@
+//! Benchmark gửi liên tục: khóa gửi tính trước so với dẫn xuất khi gửi
+//!
+//! Chạy bằng `cargo bench --bench precompute_keys`.
+
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion};
+use e2ee_core::ratchet::DoubleRatchet;
+
+const BURST: usize = 200;
+
+fn fresh_ratchet(precompute: bool) -> DoubleRatchet {
+    let mut dr = DoubleRatchet::from_shared_secret(&[32u8; 32], true).expect("Failed to create Double Ratchet");
+    if precompute {
+        dr.precompute_send_keys(BURST).expect("Failed to precompute keys");
+    }
+    dr
+}
+
+fn send_burst(mut dr: DoubleRatchet) -> DoubleRatchet {
+    let plaintext = [0u8; 16];
+    for _ in 0..BURST {
+        dr.encrypt_envelope(&plaintext).expect("Failed to encrypt");
+    }
+    dr
+}
+
+fn bench_precomputed_sends(c: &mut Criterion) {
+    let mut group = c.benchmark_group("send 200 messages");
+    group.bench_function("inline", |b| {
+        b.iter_batched(|| fresh_ratchet(false), send_burst, BatchSize::SmallInput)
+    });
+    group.bench_function("precomputed", |b| {
+        b.iter_batched(|| fresh_ratchet(true), send_burst, BatchSize::SmallInput)
+    });
+    group.finish();
+}
+
+criterion_group!(benches, bench_precomputed_sends);
+criterion_main!(benches);
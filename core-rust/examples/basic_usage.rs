@@ -9,9 +9,8 @@
 use e2ee_core::keys::{IdentityKeyPair, PreKeyBundle};
 use e2ee_core::keys::prekey::{OneTimePreKey, OneTimePreKeyPair, SignedPreKey, SignedPreKeyPair};
 use e2ee_core::message::MessageEnvelope;
-use e2ee_core::ratchet::DoubleRatchet;
 use e2ee_core::x3dh::{X3DHInitiator, X3DHResponder};
-use x25519_dalek::{EphemeralSecret, PublicKey};
+use x25519_dalek::{PublicKey, StaticSecret};
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     println!("=== E2EE Core Library Usage Example ===\n");
@@ -32,7 +31,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     // ============================================================
     println!("Step 2: Bob generates prekeys...");
     let bob_signed_prekey = SignedPreKeyPair::generate(1, &bob_identity)?;
-    let bob_one_time_prekey = OneTimePreKeyPair::generate(1);
+    let bob_one_time_prekey = OneTimePreKeyPair::generate(1)?;
     
     println!("  Signed prekey ID: {}", bob_signed_prekey.key_id());
     println!("  One-time prekey ID: {}", bob_one_time_prekey.key_id());
@@ -77,12 +76,8 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     
     // Bob needs to provide the one-time prekey private key
     let bob_one_time_private_ref = bob_one_time_prekey.private_key();
-    let bob_one_time_private_bytes = unsafe {
-        std::mem::transmute_copy::<EphemeralSecret, [u8; 32]>(bob_one_time_private_ref)
-    };
-    let bob_one_time_private = unsafe {
-        std::mem::transmute::<[u8; 32], EphemeralSecret>(bob_one_time_private_bytes)
-    };
+    let bob_one_time_private_bytes = bob_one_time_private_ref.to_bytes();
+    let bob_one_time_private = StaticSecret::from(bob_one_time_private_bytes);
     let bob_one_time_public = PublicKey::from(&bob_one_time_private);
     
     let mut bob = X3DHResponder::new(bob_identity.clone(), bob_signed_prekey.clone());
@@ -96,7 +91,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     println!("  Bob derived shared secret (32 bytes)");
     
     // Verify both sides have the same shared secret
-    if alice_result.shared_secret == bob_result.shared_secret {
+    if bob_result.verify_key_confirmation(&alice_result.key_confirmation()).is_ok() {
         println!("  ✓ Shared secrets match!");
     } else {
         println!("  ✗ Shared secrets don't match!");
@@ -109,10 +104,8 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     // ============================================================
     println!("Step 5b: Bob responds with INVALID ephemeral key (expected error)...");
     let mut bob_neg = X3DHResponder::new(bob_identity.clone(), bob_signed_prekey.clone());
-    // Reconstruct a new EphemeralSecret from saved bytes, since EphemeralSecret was moved above
-    let bob_one_time_private2 = unsafe {
-        std::mem::transmute::<[u8; 32], EphemeralSecret>(bob_one_time_private_bytes)
-    };
+    // Reconstruct the one-time prekey from saved bytes, since the first one was moved above
+    let bob_one_time_private2 = StaticSecret::from(bob_one_time_private_bytes);
     let bob_one_time_public2 = PublicKey::from(&bob_one_time_private2);
     bob_neg.set_one_time_prekey(1, bob_one_time_private2, bob_one_time_public2);
     let invalid_eph = ""; // empty -> invalid
@@ -132,8 +125,8 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     // ============================================================
     println!("Step 6: Initializing Double Ratchet...");
     // Alice is the initiator, Bob is the responder
-    let mut alice_dr = DoubleRatchet::from_shared_secret(&alice_result.shared_secret, true)?;
-    let mut bob_dr = DoubleRatchet::from_shared_secret(&bob_result.shared_secret, false)?;
+    let mut alice_dr = alice_result.into_ratchet()?;
+    let mut bob_dr = bob_result.into_ratchet()?;
     
     println!("  Alice Double Ratchet initialized (initiator)");
     println!("  Bob Double Ratchet initialized (responder)");
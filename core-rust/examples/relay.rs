@@ -0,0 +1,350 @@
+//! In-memory relay server example for E2EE Core library
+//!
+//! This example demonstrates:
+//! 1. A relay that stores prekey bundles and forwards opaque envelopes
+//! 2. Clients establishing sessions through the relay (X3DH over the wire)
+//! 3. Envelopes routed by conversation id (the session routing id, which
+//!    both peers derive independently)
+//! 4. Peers polling the relay and decrypting messages in order
+//!
+//! The relay only ever sees public keys, routing ids and base64 envelopes.
+//! Everything runs in one process; the relay's methods stand in for HTTP
+//! endpoints (`PUT /bundles/{user}`, `POST /conversations/{id}`, ...).
+
+use e2ee_core::ffi::api::{
+    create_session_initiator_with_ephemeral, create_session_responder, decrypt_message_full,
+    encrypt_message, generate_identity_key_pair, generate_prekey_bundle, get_public_key_hex_from_json,
+    session_routing_id,
+};
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+
+/// Handshake an initiator leaves at the relay for the responder
+/// 
+/// Carries the public X3DH values the responder needs to derive the same
+/// session, plus the prekey ids taken from the bundle the initiator used.
+#[derive(Debug, Clone)]
+pub struct Handshake {
+    /// Name of the initiating user
+    pub from: String,
+    /// Initiator's identity public key (hex)
+    pub identity_hex: String,
+    /// Initiator's X3DH ephemeral public key (hex)
+    pub ephemeral_hex: String,
+    /// Signed prekey id of the bundle the initiator used
+    pub signed_prekey_id: u32,
+    /// One-time prekey id of the bundle the initiator used, if it had one
+    pub one_time_prekey_id: Option<u32>,
+}
+
+/// Envelope stored by the relay under a conversation id
+#[derive(Debug, Clone)]
+pub struct Posted {
+    /// Relay-assigned sequence number, increasing per conversation
+    pub sequence: u64,
+    /// Name of the sending user
+    pub sender: String,
+    /// Base64-encoded MessageEnvelope (opaque to the relay)
+    pub envelope_base64: String,
+}
+
+/// Tiny in-memory relay
+/// 
+/// Holds published prekey bundles, pending handshakes per user and the
+/// envelope log of every conversation. Each map sits behind its own lock.
+#[derive(Default)]
+pub struct Relay {
+    bundles: Mutex<HashMap<String, String>>,
+    handshakes: Mutex<HashMap<String, VecDeque<Handshake>>>,
+    conversations: Mutex<HashMap<String, Vec<Posted>>>,
+}
+
+impl Relay {
+    /// Create an empty relay
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// `PUT /bundles/{user}`: publish a prekey bundle
+    pub fn publish_bundle(&self, user: &str, bundle_json: String) {
+        self.bundles.lock().unwrap().insert(user.to_string(), bundle_json);
+    }
+
+    /// `GET /bundles/{user}`: fetch and remove a prekey bundle
+    /// 
+    /// The bundle carries a one-time prekey, so it is handed out only once.
+    pub fn fetch_bundle(&self, user: &str) -> Result<String, String> {
+        self.bundles.lock().unwrap()
+            .remove(user)
+            .ok_or_else(|| format!("no prekey bundle published for {}", user))
+    }
+
+    /// `POST /handshakes/{user}`: leave a handshake for a user
+    pub fn send_handshake(&self, to: &str, handshake: Handshake) {
+        self.handshakes.lock().unwrap()
+            .entry(to.to_string())
+            .or_default()
+            .push_back(handshake);
+    }
+
+    /// `GET /handshakes/{user}`: take every pending handshake for a user
+    pub fn take_handshakes(&self, user: &str) -> Vec<Handshake> {
+        self.handshakes.lock().unwrap()
+            .remove(user)
+            .map(Vec::from)
+            .unwrap_or_default()
+    }
+
+    /// `POST /conversations/{id}`: append an envelope to a conversation
+    /// 
+    /// # Returns
+    /// The sequence number assigned to the envelope
+    pub fn submit(&self, conversation_id: &str, sender: &str, envelope_base64: String) -> u64 {
+        let mut conversations = self.conversations.lock().unwrap();
+        let log = conversations.entry(conversation_id.to_string()).or_default();
+        let sequence = log.len() as u64 + 1;
+        log.push(Posted {
+            sequence,
+            sender: sender.to_string(),
+            envelope_base64,
+        });
+        sequence
+    }
+
+    /// `GET /conversations/{id}?after={cursor}`: envelopes from other senders, in order
+    pub fn poll(&self, conversation_id: &str, recipient: &str, after: u64) -> Vec<Posted> {
+        self.conversations.lock().unwrap()
+            .get(conversation_id)
+            .map(|log| {
+                log.iter()
+                    .filter(|posted| posted.sequence > after && posted.sender != recipient)
+                    .cloned()
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Number of envelopes stored for a conversation
+    pub fn conversation_len(&self, conversation_id: &str) -> usize {
+        self.conversations.lock().unwrap()
+            .get(conversation_id)
+            .map_or(0, Vec::len)
+    }
+}
+
+/// A client's view of one conversation
+struct Conversation {
+    session_id: String,
+    conversation_id: String,
+    /// Highest relay sequence number already processed
+    cursor: u64,
+}
+
+/// Messaging client talking to the relay through the FFI session API
+pub struct Client {
+    name: String,
+    identity_json: String,
+    relay: Arc<Relay>,
+    conversations: HashMap<String, Conversation>,
+}
+
+impl Client {
+    /// Create a client and publish its prekey bundle
+    /// 
+    /// Prekey ids live in a process-wide store, so every client in the
+    /// process needs its own ids.
+    pub fn new(
+        name: &str,
+        relay: Arc<Relay>,
+        signed_prekey_id: u32,
+        one_time_prekey_id: u32,
+    ) -> Result<Self, String> {
+        let identity_json = generate_identity_key_pair();
+        let bundle_json = generate_prekey_bundle(identity_json.clone(), signed_prekey_id, Some(one_time_prekey_id));
+        if bundle_json.contains("\"error\"") {
+            return Err(format!("Failed to generate prekey bundle: {}", bundle_json));
+        }
+        relay.publish_bundle(name, bundle_json);
+        
+        Ok(Self {
+            name: name.to_string(),
+            identity_json,
+            relay,
+            conversations: HashMap::new(),
+        })
+    }
+
+    /// Get the client's identity public key (hex)
+    pub fn identity_hex(&self) -> String {
+        get_public_key_hex_from_json(self.identity_json.clone())
+    }
+
+    /// Get the conversation id shared with a peer, once a session exists
+    pub fn conversation_id(&self, peer: &str) -> Option<&str> {
+        self.conversations.get(peer).map(|c| c.conversation_id.as_str())
+    }
+
+    /// Start a conversation with a peer from the peer's published bundle
+    pub fn start_conversation(&mut self, peer: &str) -> Result<(), String> {
+        let bundle_json = self.relay.fetch_bundle(peer)?;
+        let bundle: serde_json::Value = serde_json::from_str(&bundle_json)
+            .map_err(|e| format!("Invalid bundle JSON: {}", e))?;
+        
+        let init_json = create_session_initiator_with_ephemeral(self.identity_json.clone(), bundle_json);
+        if init_json.starts_with("Error") {
+            return Err(init_json);
+        }
+        let init: serde_json::Value = serde_json::from_str(&init_json)
+            .map_err(|e| format!("Invalid initiator JSON: {}", e))?;
+        let field = |name: &str| {
+            init[name].as_str()
+                .map(str::to_string)
+                .ok_or_else(|| format!("Missing {} in {}", name, init_json))
+        };
+        let session_id = field("session_id")?;
+        
+        self.relay.send_handshake(peer, Handshake {
+            from: self.name.clone(),
+            identity_hex: field("alice_identity_hex")?,
+            ephemeral_hex: field("alice_ephemeral_public_key_hex")?,
+            signed_prekey_id: bundle["signed_prekey"]["key_id"].as_u64()
+                .ok_or("Bundle without signed prekey id")? as u32,
+            one_time_prekey_id: bundle["one_time_prekey"]["key_id"].as_u64().map(|id| id as u32),
+        });
+        self.add_conversation(peer, session_id)
+    }
+
+    /// Answer every handshake waiting at the relay
+    /// 
+    /// # Returns
+    /// Names of the peers a session was established with
+    pub fn accept_handshakes(&mut self) -> Result<Vec<String>, String> {
+        let mut peers = Vec::new();
+        for handshake in self.relay.take_handshakes(&self.name) {
+            let session_id = create_session_responder(
+                self.identity_json.clone(),
+                handshake.signed_prekey_id,
+                handshake.one_time_prekey_id,
+                handshake.identity_hex,
+                handshake.ephemeral_hex,
+            );
+            if session_id.starts_with("Error") {
+                return Err(session_id);
+            }
+            self.add_conversation(&handshake.from, session_id)?;
+            peers.push(handshake.from);
+        }
+        Ok(peers)
+    }
+
+    fn add_conversation(&mut self, peer: &str, session_id: String) -> Result<(), String> {
+        let conversation_id = session_routing_id(session_id.clone());
+        if conversation_id.starts_with("Error") {
+            return Err(conversation_id);
+        }
+        self.conversations.insert(peer.to_string(), Conversation {
+            session_id,
+            conversation_id,
+            cursor: 0,
+        });
+        Ok(())
+    }
+
+    fn conversation(&self, peer: &str) -> Result<&Conversation, String> {
+        self.conversations.get(peer).ok_or_else(|| format!("no conversation with {}", peer))
+    }
+
+    /// Encrypt a text message and submit it to the relay
+    /// 
+    /// # Returns
+    /// The relay sequence number of the envelope
+    pub fn send(&self, peer: &str, text: &str) -> Result<u64, String> {
+        let conversation = self.conversation(peer)?;
+        let envelope_base64 = encrypt_message(conversation.session_id.clone(), text.as_bytes().to_vec());
+        if envelope_base64.starts_with("Error") {
+            return Err(envelope_base64);
+        }
+        Ok(self.relay.submit(&conversation.conversation_id, &self.name, envelope_base64))
+    }
+
+    /// Poll the relay and decrypt every new message from a peer, in relay order
+    pub fn receive(&mut self, peer: &str) -> Result<Vec<String>, String> {
+        let (session_id, conversation_id, cursor) = {
+            let conversation = self.conversation(peer)?;
+            (conversation.session_id.clone(), conversation.conversation_id.clone(), conversation.cursor)
+        };
+        
+        let mut messages = Vec::new();
+        for posted in self.relay.poll(&conversation_id, &self.name, cursor) {
+            let result: serde_json::Value = serde_json::from_str(&decrypt_message_full(session_id.clone(), posted.envelope_base64))
+                .map_err(|e| format!("Invalid decrypt result: {}", e))?;
+            if result["ok"] != true {
+                return Err(format!("Failed to decrypt message {}: {}", posted.sequence, result["error"]));
+            }
+            let plaintext = result["plaintext_base64"].as_str()
+                .ok_or("Missing plaintext")
+                .and_then(|b64| {
+                    use base64::{engine::general_purpose, Engine as _};
+                    general_purpose::STANDARD.decode(b64).map_err(|_| "Invalid plaintext base64")
+                })?;
+            messages.push(String::from_utf8(plaintext).map_err(|e| format!("Message is not UTF-8: {}", e))?);
+            
+            if let Some(conversation) = self.conversations.get_mut(peer) {
+                conversation.cursor = posted.sequence;
+            }
+        }
+        Ok(messages)
+    }
+}
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    println!("=== E2EE Core Relay Example ===\n");
+
+    let relay = Arc::new(Relay::new());
+
+    // ============================================================
+    // Step 1: Clients register and publish prekey bundles
+    // ============================================================
+    println!("Step 1: Alice and Bob publish prekey bundles...");
+    let mut alice = Client::new("alice", Arc::clone(&relay), 1, 1)?;
+    let mut bob = Client::new("bob", Arc::clone(&relay), 2, 2)?;
+    println!("  Alice identity: {}", alice.identity_hex());
+    println!("  Bob identity:   {}", bob.identity_hex());
+    println!();
+
+    // ============================================================
+    // Step 2: Alice starts a conversation, Bob answers the handshake
+    // ============================================================
+    println!("Step 2: Establishing the session through the relay...");
+    alice.start_conversation("bob")?;
+    let peers = bob.accept_handshakes()?;
+    println!("  Bob accepted handshakes from: {:?}", peers);
+
+    let conversation_id = alice.conversation_id("bob").ok_or("Alice has no conversation")?.to_string();
+    assert_eq!(bob.conversation_id("alice"), Some(conversation_id.as_str()));
+    println!("  Shared conversation id: {}", conversation_id);
+    println!();
+
+    // ============================================================
+    // Step 3: Exchange messages through the relay
+    // ============================================================
+    println!("Step 3: Exchanging messages...");
+    for text in ["Hi Bob!", "Are you there?"] {
+        let sequence = alice.send("bob", text)?;
+        println!("  Alice -> relay #{}: {:?}", sequence, text);
+    }
+    for text in bob.receive("alice")? {
+        println!("  Bob received: {:?}", text);
+    }
+
+    let sequence = bob.send("alice", "Hi Alice, loud and clear.")?;
+    println!("  Bob -> relay #{}", sequence);
+    for text in alice.receive("bob")? {
+        println!("  Alice received: {:?}", text);
+    }
+    println!();
+
+    println!("The relay stored {} opaque envelopes.", relay.conversation_len(&conversation_id));
+    println!("=== Example completed successfully! ===");
+    Ok(())
+}
@@ -0,0 +1,157 @@
+//! AEAD sealing shared by the ratchet and auxiliary encrypted payloads
+//! 
+//! AES-256-GCM (`seal`/`open`) and XChaCha20-Poly1305
+//! (`seal_xchacha`/`open_xchacha`). Nonces are never generated here: each caller owns its nonce scheme (the
+//! ratchet derives one per message key, resumption tickets draw a random one)
+//! and must never reuse a nonce under the same key.
+
+use crate::error::{E2EEError, Result};
+use chacha20poly1305::aead::{Aead, KeyInit, Payload};
+use chacha20poly1305::{XChaCha20Poly1305, XNonce};
+use ring::aead::{Aad, LessSafeKey, Nonce, UnboundKey, AES_256_GCM};
+
+/// Length of the authentication tag appended to every ciphertext
+pub const TAG_LEN: usize = 16;
+
+/// Length of an AES-256-GCM nonce
+pub const NONCE_LEN: usize = 12;
+
+/// Length of an XChaCha20-Poly1305 nonce
+pub const XNONCE_LEN: usize = 24;
+
+fn aead_key(key: &[u8; 32]) -> Result<LessSafeKey> {
+    let unbound_key = UnboundKey::new(&AES_256_GCM, key)
+        .map_err(|e| E2EEError::CryptoError(format!("Failed to create key: {}", e)))?;
+    Ok(LessSafeKey::new(unbound_key))
+}
+
+/// Encrypt and authenticate with AES-256-GCM
+/// 
+/// # Arguments
+/// * `key` - 256-bit key
+/// * `nonce` - 96-bit nonce, unique for this key
+/// * `aad` - Associated data authenticated alongside the ciphertext
+/// * `plaintext` - Data to encrypt
+/// 
+/// # Returns
+/// Ciphertext with the `TAG_LEN`-byte tag appended
+pub fn seal(key: &[u8; 32], nonce: &[u8; NONCE_LEN], aad: &[u8], plaintext: &[u8]) -> Result<Vec<u8>> {
+    let mut ciphertext = plaintext.to_vec();
+    aead_key(key)?
+        .seal_in_place_append_tag(Nonce::assume_unique_for_key(*nonce), Aad::from(aad), &mut ciphertext)
+        .map_err(|e| E2EEError::CryptoError(format!("Encryption failed: {}", e)))?;
+    Ok(ciphertext)
+}
+
+/// Verify and decrypt an AES-256-GCM ciphertext produced by `seal`
+/// 
+/// Decrypts a copy; `ciphertext` itself is never modified.
+/// 
+/// # Arguments
+/// * `key` - 256-bit key
+/// * `nonce` - Nonce used when sealing
+/// * `aad` - Associated data used when sealing
+/// * `ciphertext` - Ciphertext with its tag appended
+/// 
+/// # Returns
+/// Plaintext, or `CryptoError("Decryption failed: ..")` if anything was tampered with
+pub fn open(key: &[u8; 32], nonce: &[u8; NONCE_LEN], aad: &[u8], ciphertext: &[u8]) -> Result<Vec<u8>> {
+    let mut plaintext = ciphertext.to_vec();
+    let plaintext_len = aead_key(key)?
+        .open_in_place(Nonce::assume_unique_for_key(*nonce), Aad::from(aad), &mut plaintext)
+        .map_err(|e| E2EEError::CryptoError(format!("Decryption failed: {}", e)))?
+        .len();
+    
+    plaintext.truncate(plaintext_len);
+    Ok(plaintext)
+}
+
+/// Encrypt and authenticate with XChaCha20-Poly1305
+/// 
+/// The 192-bit nonce is long enough to be drawn at random for every message.
+/// 
+/// # Arguments
+/// * `key` - 256-bit key
+/// * `nonce` - 192-bit nonce, unique for this key
+/// * `aad` - Associated data authenticated alongside the ciphertext
+/// * `plaintext` - Data to encrypt
+/// 
+/// # Returns
+/// Ciphertext with the `TAG_LEN`-byte tag appended
+pub fn seal_xchacha(key: &[u8; 32], nonce: &[u8; XNONCE_LEN], aad: &[u8], plaintext: &[u8]) -> Result<Vec<u8>> {
+    XChaCha20Poly1305::new(key.into())
+        .encrypt(XNonce::from_slice(nonce), Payload { msg: plaintext, aad })
+        .map_err(|e| E2EEError::CryptoError(format!("Encryption failed: {}", e)))
+}
+
+/// Verify and decrypt an XChaCha20-Poly1305 ciphertext produced by `seal_xchacha`
+/// 
+/// # Arguments
+/// * `key` - 256-bit key
+/// * `nonce` - Nonce used when sealing
+/// * `aad` - Associated data used when sealing
+/// * `ciphertext` - Ciphertext with its tag appended
+/// 
+/// # Returns
+/// Plaintext, or `CryptoError("Decryption failed: ..")` if anything was tampered with
+pub fn open_xchacha(key: &[u8; 32], nonce: &[u8; XNONCE_LEN], aad: &[u8], ciphertext: &[u8]) -> Result<Vec<u8>> {
+    XChaCha20Poly1305::new(key.into())
+        .decrypt(XNonce::from_slice(nonce), Payload { msg: ciphertext, aad })
+        .map_err(|e| E2EEError::CryptoError(format!("Decryption failed: {}", e)))
+}
+
+/// Number of recent (key, nonce) pairs `NonceReuseGuard` remembers
+#[cfg(debug_assertions)]
+const MAX_TRACKED_NONCES: usize = 4096;
+
+/// Record of the (key, nonce) pairs sealed under, to catch nonce reuse early
+/// 
+/// Reusing a nonce under the same GCM key leaks the XOR of the plaintexts
+/// and the authentication key. Only compiled with debug assertions: pairs are
+/// remembered as SHA-256 digests, one per sealed message, which is too costly
+/// for release builds.
+/// 
+/// Memory is bounded: the owner clears the guard whenever it starts a new
+/// sending chain (keys never repeat across chains), and only the last
+/// `MAX_TRACKED_NONCES` pairs of a chain are kept. The guard lives in memory
+/// only; it is not part of a saved ratchet state, so a restored ratchet
+/// starts with an empty one.
+#[cfg(debug_assertions)]
+#[derive(Default)]
+pub(crate) struct NonceReuseGuard {
+    seen: std::collections::HashSet<[u8; 32]>,
+    /// Digests in insertion order, oldest first, for eviction
+    order: std::collections::VecDeque<[u8; 32]>,
+}
+
+#[cfg(debug_assertions)]
+impl NonceReuseGuard {
+    /// Record a pair about to be sealed under
+    /// 
+    /// # Returns
+    /// Ok(()), or `CryptoError` if the exact pair was recorded before
+    pub(crate) fn record(&mut self, key: &[u8; 32], nonce: &[u8; NONCE_LEN]) -> Result<()> {
+        let mut context = ring::digest::Context::new(&ring::digest::SHA256);
+        context.update(key);
+        context.update(nonce);
+        let mut digest = [0u8; 32];
+        digest.copy_from_slice(context.finish().as_ref());
+        
+        if !self.seen.insert(digest) {
+            return Err(E2EEError::CryptoError("AEAD key/nonce reuse detected".to_string()));
+        }
+        self.order.push_back(digest);
+        if self.order.len() > MAX_TRACKED_NONCES {
+            if let Some(oldest) = self.order.pop_front() {
+                self.seen.remove(&oldest);
+            }
+        }
+        Ok(())
+    }
+
+    /// Forget every recorded pair, e.g. when a new sending chain starts
+    pub(crate) fn clear(&mut self) {
+        self.seen.clear();
+        self.order.clear();
+    }
+}
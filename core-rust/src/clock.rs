@@ -0,0 +1,58 @@
+//! Time source for expiry checks
+//! 
+//! Time-dependent logic takes its notion of "now" from a `Clock` instead of
+//! calling `SystemTime::now()` directly, so tests can pin the time and
+//! platforms without a system clock can supply their own.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Source of the current time
+pub trait Clock: Send + Sync {
+    /// Current time as seconds since the Unix epoch
+    fn now_secs(&self) -> u64;
+}
+
+/// Clock backed by the operating system's wall clock (the default)
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now_secs(&self) -> u64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0)
+    }
+}
+
+/// Manually driven clock for tests
+/// 
+/// Starts at the given time and only moves when `set` or `advance` is called.
+#[derive(Debug, Default)]
+pub struct MockClock {
+    now: AtomicU64,
+}
+
+impl MockClock {
+    /// Create a mock clock reading `now_secs`
+    pub fn new(now_secs: u64) -> Self {
+        Self { now: AtomicU64::new(now_secs) }
+    }
+
+    /// Set the current time
+    pub fn set(&self, now_secs: u64) {
+        self.now.store(now_secs, Ordering::SeqCst);
+    }
+
+    /// Move the clock forward by `secs` seconds
+    pub fn advance(&self, secs: u64) {
+        self.now.fetch_add(secs, Ordering::SeqCst);
+    }
+}
+
+impl Clock for MockClock {
+    fn now_secs(&self) -> u64 {
+        self.now.load(Ordering::SeqCst)
+    }
+}
@@ -0,0 +1,93 @@
+use crate::error::{E2EEError, Result};
+
+/// Parse a hex string into a 32-byte array
+/// 
+/// All hex coming from untrusted input (bundles, envelopes, peer keys) must go
+/// through this helper so malformed input surfaces as an error, never a panic.
+/// 
+/// # Arguments
+/// * `s` - Hex string (64 hex characters)
+/// 
+/// # Returns
+/// 32-byte array, or `SerializationError` for invalid characters, odd length or wrong size
+pub fn parse_hex_32(s: &str) -> Result<[u8; 32]> {
+    parse_hex_array(s)
+}
+
+/// Parse a hex string into a 64-byte array (e.g. an Ed25519 signature)
+/// 
+/// # Arguments
+/// * `s` - Hex string (128 hex characters)
+/// 
+/// # Returns
+/// 64-byte array, or `SerializationError` for invalid characters, odd length or wrong size
+pub fn parse_hex_64(s: &str) -> Result<[u8; 64]> {
+    parse_hex_array(s)
+}
+
+/// Type byte Signal prepends to serialized X25519 public keys
+pub const CURVE_POINT_TYPE_DJB: u8 = 0x05;
+
+/// Parse an X25519 public key in raw or type-prefixed form
+/// 
+/// Accepts the raw 32-byte encoding used by this crate, as well as Signal's
+/// 33-byte encoding whose first byte is `CURVE_POINT_TYPE_DJB`.
+/// 
+/// # Arguments
+/// * `bytes` - 32 raw bytes, or 33 bytes starting with 0x05
+/// 
+/// # Returns
+/// 32-byte curve point, or `SerializationError` for an unknown type byte or wrong size
+pub fn parse_curve_point(bytes: &[u8]) -> Result<[u8; 32]> {
+    let raw = match bytes.len() {
+        32 => bytes,
+        33 if bytes[0] == CURVE_POINT_TYPE_DJB => &bytes[1..],
+        33 => {
+            return Err(E2EEError::SerializationError(
+                format!("Unknown curve point type: 0x{:02x}", bytes[0])
+            ));
+        }
+        len => {
+            return Err(E2EEError::SerializationError(
+                format!("Invalid curve point length: expected 32 or 33 bytes, got {}", len)
+            ));
+        }
+    };
+
+    let mut point = [0u8; 32];
+    point.copy_from_slice(raw);
+    Ok(point)
+}
+
+/// Parse a hex-encoded X25519 public key
+/// 
+/// # Arguments
+/// * `s` - Hex string of a raw (32-byte) or, if allowed, type-prefixed (33-byte) key
+/// * `accept_prefixed` - Whether the 33-byte Signal encoding is accepted
+/// 
+/// # Returns
+/// 32-byte curve point, or `SerializationError` for malformed input
+pub fn parse_curve_point_hex(s: &str, accept_prefixed: bool) -> Result<[u8; 32]> {
+    if !accept_prefixed {
+        return parse_hex_32(s);
+    }
+    let bytes = hex::decode(s)
+        .map_err(|e| E2EEError::SerializationError(format!("Failed to decode hex: {}", e)))?;
+    parse_curve_point(&bytes)
+}
+
+/// Parse a hex string into a fixed-size byte array
+fn parse_hex_array<const N: usize>(s: &str) -> Result<[u8; N]> {
+    let bytes = hex::decode(s)
+        .map_err(|e| E2EEError::SerializationError(format!("Failed to decode hex: {}", e)))?;
+
+    if bytes.len() != N {
+        return Err(E2EEError::SerializationError(
+            format!("Invalid hex length: expected {} bytes, got {}", N, bytes.len())
+        ));
+    }
+
+    let mut array = [0u8; N];
+    array.copy_from_slice(&bytes);
+    Ok(array)
+}
@@ -22,6 +22,19 @@ pub enum E2EEError {
     /// Invalid state
     #[error("State error: {0}")]
     StateError(String),
+
+    /// No session registered under the given id
+    #[error("Session not found: {0}")]
+    SessionNotFound(String),
+
+    /// Key id missing from a key store (e.g. prekey store miss)
+    #[error("Key not found: {0}")]
+    KeyNotFound(String),
+
+    /// Key-confirmation MAC did not match: the parties derived different
+    /// shared secrets (wrong prekeys or a MITM)
+    #[error("Key confirmation failed")]
+    KeyConfirmationFailed,
 }
 
 /// Result type alias for E2EE operations
@@ -2,28 +2,167 @@
 //! 
 //! This module exports high-level functions for Flutter/Dart to use the E2EE core.
 
-use crate::ffi::keys::{IdentityKeyPairBytes, PreKeyBundleJSON, get_public_key_hex};
-use crate::ffi::session::{Session, SessionRegistry, generate_session_id};
-use crate::keys::{IdentityKeyPair, PreKeyBundle};
-use crate::keys::prekey::{SignedPreKeyPair, OneTimePreKeyPair};
-use crate::message::MessageEnvelope;
+use crate::encoding::parse_hex_32;
+use crate::ffi::keys::{generate_prekey_material, IdentityKeyPairBytes, OneTimePreKeyJSON, PreKeyBundleJSON, get_public_key_hex};
+use crate::error::{E2EEError, Result};
+use crate::ffi::session::{Session, SessionRegistry, SESSION_STATE_VERSION, deterministic_session_id, generate_session_id};
+use crate::ffi::store::{InMemoryPreKeyStore, PreKeyStore};
+use crate::keys::{verify_bundles_batch, Contact, IdentityKeyPair, PreKeyBundle};
+use crate::keys::prekey::{OneTimePreKeyId, OneTimePreKeyPair, SignedPreKeyId};
+use crate::message::{MessageEnvelope, MessageType, SealedMessage, MAX_ENVELOPE_BYTES};
+use crate::ratchet::{DoubleRatchet, RatchetState};
 use crate::x3dh::{X3DHInitiator, X3DHResponder};
+use base64::{engine::general_purpose, Engine as _};
 use flutter_rust_bridge::frb;
-use std::collections::HashMap;
-use std::sync::{Arc, Mutex};
+use parking_lot::{Mutex, RwLock};
+use std::collections::{BTreeSet, HashMap};
+use std::sync::Arc;
+use zeroize::Zeroize;
 use serde_json;
 
 // Global session registry
 static SESSION_REGISTRY: once_cell::sync::Lazy<SessionRegistry> = 
-    once_cell::sync::Lazy::new(|| SessionRegistry::new());
+    once_cell::sync::Lazy::new(SessionRegistry::new);
 
 // Persist generated prekeys so responder can reuse the exact same keys
-static SIGNED_PREKEY_STORE: once_cell::sync::Lazy<Mutex<HashMap<u32, SignedPreKeyPair>>> =
-    once_cell::sync::Lazy::new(|| Mutex::new(HashMap::new()));
-// Store only private key bytes of one-time prekeys; reconstruct when needed
-static ONE_TIME_PREKEY_STORE: once_cell::sync::Lazy<Mutex<HashMap<u32, [u8; 32]>>> =
+// In memory by default; embedders can swap in their own backend
+static PREKEY_STORE: once_cell::sync::Lazy<RwLock<Box<dyn PreKeyStore>>> =
+    once_cell::sync::Lazy::new(|| RwLock::new(Box::new(InMemoryPreKeyStore::new())));
+
+// One-time prekey ids used by handshakes since the last replenish, per identity public key (hex)
+static CONSUMED_ONE_TIME_PREKEYS: once_cell::sync::Lazy<Mutex<HashMap<String, BTreeSet<u32>>>> =
     once_cell::sync::Lazy::new(|| Mutex::new(HashMap::new()));
 
+/// Replace the backend that persists generated prekeys
+/// 
+/// Prekeys held by the previous backend are not migrated; set the backend
+/// before generating any bundle.
+/// 
+/// # Arguments
+/// * `store` - New prekey store backend
+#[frb(ignore)]
+pub fn set_prekey_store_backend(store: Box<dyn PreKeyStore>) {
+    *PREKEY_STORE.write() = store;
+}
+
+/// Build an X3DH responder from the prekeys persisted by `generate_prekey_bundle`
+/// 
+/// The one-time prekey is only read here. `respond` does not authenticate
+/// the initiator, so `consume_one_time_prekey` belongs after the first
+/// message decrypts, not after `respond`; `open_sealed_message` does this.
+/// `create_session_responder` has no message to check yet and consumes the
+/// key right after `respond`, so a forged initiation can still burn it there.
+/// 
+/// # Returns
+/// X3DHResponder ready to respond, or `KeyNotFound` if a prekey id is missing
+fn load_responder(
+    identity: IdentityKeyPair,
+    signed_prekey_id: u32,
+    one_time_prekey_id: Option<u32>,
+) -> Result<X3DHResponder> {
+    let signed_prekey = PREKEY_STORE.read()
+        .get_signed(SignedPreKeyId(signed_prekey_id))
+        .ok_or_else(|| E2EEError::KeyNotFound(format!("signed prekey id {}", signed_prekey_id)))?;
+    
+    let mut responder = X3DHResponder::new(identity, signed_prekey);
+    
+    // Set one-time prekey if provided
+    if let Some(otp_id) = one_time_prekey_id {
+        use x25519_dalek::{PublicKey, StaticSecret};
+        let mut otp_private_bytes = PREKEY_STORE.read()
+            .get_one_time(OneTimePreKeyId(otp_id))
+            .ok_or_else(|| E2EEError::KeyNotFound(format!("one-time prekey id {}", otp_id)))?;
+        let otp_private = StaticSecret::from(otp_private_bytes);
+        otp_private_bytes.zeroize();
+        let otp_public = PublicKey::from(&otp_private);
+        responder.set_one_time_prekey(otp_id, otp_private, otp_public);
+    }
+    
+    Ok(responder)
+}
+
+/// Remove a one-time prekey after a successful handshake and record it for
+/// `replenish_one_time_prekeys`
+/// 
+/// # Arguments
+/// * `identity_hex` - Responder identity public key (hex) the prekey belongs to
+/// * `one_time_prekey_id` - One-time prekey used by the handshake, if any
+/// 
+/// # Returns
+/// Ok(()), or `KeyNotFound` if a concurrent handshake consumed the prekey first
+fn consume_one_time_prekey(identity_hex: String, one_time_prekey_id: Option<u32>) -> Result<()> {
+    let Some(otp_id) = one_time_prekey_id else {
+        return Ok(());
+    };
+    
+    PREKEY_STORE.read()
+        .take_one_time(OneTimePreKeyId(otp_id))
+        .ok_or_else(|| E2EEError::KeyNotFound(format!("one-time prekey id {}", otp_id)))?
+        .zeroize();
+    CONSUMED_ONE_TIME_PREKEYS.lock()
+        .entry(identity_hex)
+        .or_default()
+        .insert(otp_id);
+    Ok(())
+}
+
+/// Cipher suites this build can speak, default first
+const SUPPORTED_SUITES: &[&str] = &[
+    "X25519-Ed25519-AES256GCM-HKDFSHA256",
+    "X25519-Ed25519-XCHACHA20POLY1305-HKDFSHA256",
+];
+
+/// Report crate and protocol versions for compatibility checks
+/// 
+/// Apps compare this against what they persisted before loading stored
+/// sessions after a library update.
+/// 
+/// # Returns
+/// JSON string with `crate_version`, `envelope_version`, `state_version`
+/// and `supported_suites`
+#[frb(sync)]
+pub fn protocol_info() -> String {
+    serde_json::json!({
+        "crate_version": env!("CARGO_PKG_VERSION"),
+        "envelope_version": MessageEnvelope::VERSION,
+        "state_version": SESSION_STATE_VERSION,
+        "supported_suites": SUPPORTED_SUITES,
+    })
+    .to_string()
+}
+
+/// List the cipher suites this build supports
+/// 
+/// # Returns
+/// JSON array of SuiteDescriptor (`cipher`, `kdf`, `header_encryption`), default first
+#[frb(sync)]
+pub fn supported_suites() -> String {
+    match serde_json::to_string(&crate::ratchet::supported_suites()) {
+        Ok(json) => json,
+        Err(e) => format!("Error: Failed to serialize suites: {}", e),
+    }
+}
+
+/// Get the cipher suite a session uses
+/// 
+/// # Arguments
+/// * `session_id` - Session ID
+/// 
+/// # Returns
+/// JSON string of the SuiteDescriptor, or error message if the session is unknown
+#[frb(sync)]
+pub fn session_suite(session_id: String) -> String {
+    let suite = match SESSION_REGISTRY.try_get(&session_id).and_then(|session| session.current_suite()) {
+        Ok(suite) => suite,
+        Err(e) => return format!("Error: {}", e),
+    };
+    
+    match serde_json::to_string(&suite) {
+        Ok(json) => json,
+        Err(e) => format!("Error: Failed to serialize suite: {}", e),
+    }
+}
+
 /// Generate a new identity key pair
 /// 
 /// # Returns
@@ -52,6 +191,21 @@ pub fn get_public_key_hex_from_json(identity_bytes_json: String) -> String {
     }
 }
 
+/// Get the display fingerprint of an identity public key
+/// 
+/// # Arguments
+/// * `identity_hex` - X25519 identity public key as hex string
+/// 
+/// # Returns
+/// Fingerprint as 8 space-separated groups of uppercase hex, or error message if invalid
+#[frb(sync)]
+pub fn identity_fingerprint_display(identity_hex: String) -> String {
+    match crate::keys::identity_fingerprint_display(&identity_hex) {
+        Ok(fingerprint) => fingerprint,
+        Err(e) => format!("Error: {}", e),
+    }
+}
+
 /// Generate prekey bundle for a user
 /// 
 /// # Arguments
@@ -78,45 +232,135 @@ pub fn generate_prekey_bundle(
         Err(e) => return format!("{{\"error\": \"Failed to create identity: {}\"}}", e),
     };
     
-    // Generate signed prekey (persist for responder)
-    let signed_prekey = match SignedPreKeyPair::generate(signed_prekey_id, &identity) {
-        Ok(sp) => sp,
-        Err(e) => return format!("{{\"error\": \"Failed to generate signed prekey: {}\"}}", e),
+    // Generate prekeys, then persist the private half for the responder
+    let one_time_prekey_ids: Vec<u32> = one_time_prekey_id.into_iter().collect();
+    let (bundle_json, material) = match generate_prekey_material(&identity, signed_prekey_id, &one_time_prekey_ids) {
+        Ok(generated) => generated,
+        Err(e) => return format!("{{\"error\": \"Failed to generate prekeys: {}\"}}", e),
     };
-    {
-        if let Ok(mut store) = SIGNED_PREKEY_STORE.lock() {
-            store.insert(signed_prekey_id, signed_prekey.clone());
-        }
+    if let Err(e) = material.store_into(PREKEY_STORE.read().as_ref()) {
+        return format!("{{\"error\": \"Failed to store prekeys: {}\"}}", e);
     }
     
-    // Generate one-time prekey if requested (persist private key bytes for responder)
-    let one_time_prekey = one_time_prekey_id.map(|id| {
-        let otp = OneTimePreKeyPair::generate(id);
-        use x25519_dalek::EphemeralSecret;
-        let otp_priv = otp.private_key();
-        let otp_priv_bytes = unsafe {
-            std::mem::transmute_copy::<EphemeralSecret, [u8; 32]>(otp_priv)
-        };
-        if let Ok(mut store) = ONE_TIME_PREKEY_STORE.lock() {
-            store.insert(id, otp_priv_bytes);
-        }
-        otp
-    });
+    serde_json::to_string(&bundle_json)
+        .unwrap_or_else(|e| format!("{{\"error\": \"Failed to serialize bundle: {}\"}}", e))
+}
+
+/// Generate a new batch of one-time prekeys and report the ones used up
+/// 
+/// The private halves go into the prekey store. Ids consumed by handshakes
+/// since the previous call are reported once, so the app can delete them
+/// server-side, and the consumed set is then cleared.
+/// 
+/// # Arguments
+/// * `identity_json` - JSON string of IdentityKeyPairBytes
+/// * `new_start_id` - ID of the first new one-time prekey
+/// * `count` - Number of one-time prekeys to generate (ids `new_start_id..new_start_id + count`)
+/// 
+/// # Returns
+/// JSON string with `one_time_prekeys` (array of OneTimePreKeyJSON to upload)
+/// and `consumed_ids` (sorted), or error message
+#[frb(sync)]
+pub fn replenish_one_time_prekeys(identity_json: String, new_start_id: u32, count: u32) -> String {
+    let identity_bytes = match serde_json::from_str::<IdentityKeyPairBytes>(&identity_json) {
+        Ok(bytes) => bytes,
+        Err(e) => return format!("Error: Failed to parse identity: {}", e),
+    };
+    if new_start_id.checked_add(count).is_none() {
+        return "Error: One-time prekey ids overflow".to_string();
+    }
     
-    // Create prekey bundle
-    use crate::keys::prekey::{SignedPreKey, OneTimePreKey};
-    let prekey_bundle = PreKeyBundle::new(
-        identity.public_key_hex(),
-        identity.verifying_key(),
-        SignedPreKey::from(&signed_prekey),
-        one_time_prekey.as_ref().map(|otp| OneTimePreKey::from(otp)),
+    let one_time_prekeys = match (new_start_id..new_start_id + count)
+        .map(OneTimePreKeyPair::generate)
+        .collect::<Result<Vec<_>>>()
+    {
+        Ok(prekeys) => prekeys,
+        Err(e) => return format!("Error: Failed to generate prekeys: {}", e),
+    };
+    PREKEY_STORE.read().extend_one_time(
+        one_time_prekeys.iter()
+            .map(|otp| (OneTimePreKeyId(otp.key_id()), otp.private_key_bytes()))
+            .collect(),
     );
     
-    // Convert to JSON
-    let bundle_json = PreKeyBundleJSON::from_prekey_bundle(&prekey_bundle);
+    let publics: Vec<OneTimePreKeyJSON> = one_time_prekeys.iter()
+        .map(|otp| OneTimePreKeyJSON {
+            public_key_hex: otp.public_key_hex(),
+            key_id: otp.key_id(),
+        })
+        .collect();
+    let consumed_ids: Vec<u32> = CONSUMED_ONE_TIME_PREKEYS.lock()
+        .remove(&get_public_key_hex(&identity_bytes))
+        .unwrap_or_default()
+        .into_iter()
+        .collect();
     
-    serde_json::to_string(&bundle_json)
-        .unwrap_or_else(|e| format!("{{\"error\": \"Failed to serialize bundle: {}\"}}", e))
+    serde_json::json!({
+        "one_time_prekeys": publics,
+        "consumed_ids": consumed_ids,
+    })
+    .to_string()
+}
+
+/// Turn a fetched prekey bundle into a verified contact record
+/// 
+/// The contact keeps the peer's identity keys only, so it stays valid after
+/// the bundle's prekeys are used up.
+/// 
+/// # Arguments
+/// * `bundle_json` - JSON string of the peer's PreKeyBundleJSON
+/// 
+/// # Returns
+/// JSON string of the Contact, or error message if the bundle is malformed
+/// or its signature is invalid
+#[frb(sync)]
+pub fn save_contact(bundle_json: String) -> String {
+    let bundle_json = match serde_json::from_str::<PreKeyBundleJSON>(&bundle_json) {
+        Ok(b) => b,
+        Err(e) => return format!("Error: Failed to parse prekey bundle: {}", e),
+    };
+    
+    let prekey_bundle = match bundle_json.to_prekey_bundle() {
+        Ok(b) => b,
+        Err(e) => return format!("Error: Failed to create prekey bundle: {}", e),
+    };
+    
+    let contact = match Contact::from_verified_bundle(&prekey_bundle) {
+        Ok(c) => c,
+        Err(e) => return format!("Error: Prekey bundle signature verification failed: {}", e),
+    };
+    
+    match serde_json::to_string(&contact) {
+        Ok(json) => json,
+        Err(e) => format!("Error: Failed to serialize contact: {}", e),
+    }
+}
+
+/// Verify the signed prekey signatures of many bundles (for key servers)
+/// 
+/// Uses Ed25519 batch verification over all bundles that parse.
+/// 
+/// # Arguments
+/// * `bundles_json` - JSON strings of PreKeyBundleJSON
+/// 
+/// # Returns
+/// One flag per input, in order (false if the bundle is malformed or its signature is invalid)
+#[frb(sync)]
+pub fn verify_bundles_json(bundles_json: Vec<String>) -> Vec<bool> {
+    let parsed: Vec<Option<PreKeyBundle>> = bundles_json.iter()
+        .map(|json| {
+            serde_json::from_str::<PreKeyBundleJSON>(json).ok()
+                .and_then(|bundle_json| bundle_json.to_prekey_bundle().ok())
+        })
+        .collect();
+    
+    let is_parsed: Vec<bool> = parsed.iter().map(Option::is_some).collect();
+    let bundles: Vec<PreKeyBundle> = parsed.into_iter().flatten().collect();
+    let mut batch_results = verify_bundles_batch(&bundles).into_iter();
+    
+    is_parsed.into_iter()
+        .map(|ok| ok && batch_results.next().unwrap_or(false))
+        .collect()
 }
 
 /// Create a session as initiator (Alice)
@@ -170,19 +414,16 @@ pub fn create_session_initiator(
     
     // Create session with shared secret
     let session_id = generate_session_id();
-    let session = match Session::from_shared_secret(
-        x3dh_result.shared_secret,
-        true, // is_initiator
-        session_id.clone(),
-    ) {
+    let session = match Session::from_x3dh_result(&x3dh_result, session_id.clone()) {
         Ok(s) => Arc::new(s),
         Err(e) => return format!("Error: Failed to create session: {}", e),
     };
     
     // Register session
-    SESSION_REGISTRY.register(session_id.clone(), session);
-    
-    session_id
+    match SESSION_REGISTRY.try_register(session_id.clone(), session) {
+        Ok(()) => session_id,
+        Err(e) => format!("Error: {}", e),
+    }
 }
 
 /// Create a session as initiator (Alice) and return ephemeral info
@@ -204,6 +445,36 @@ pub fn create_session_initiator(
 pub fn create_session_initiator_with_ephemeral(
     identity_bytes_json: String,
     prekey_bundle_json: String,
+) -> String {
+    initiate_session_json(identity_bytes_json, prekey_bundle_json, false)
+}
+
+/// Create a session as initiator (Alice) under an id both parties derive
+/// 
+/// Same as `create_session_initiator_with_ephemeral`, but the session ID is
+/// derived from the routing ID (`deterministic_session_id`) instead of being
+/// random, so Bob's `create_session_responder_deterministic` registers the
+/// conversation under the same ID on his side.
+/// 
+/// # Arguments
+/// * `identity_bytes_json` - JSON string of Alice's IdentityKeyPairBytes
+/// * `prekey_bundle_json` - JSON string of Bob's PreKeyBundleJSON
+/// 
+/// # Returns
+/// Same JSON as `create_session_initiator_with_ephemeral`, or error message
+/// if a session is already registered under the derived ID
+#[frb(sync)]
+pub fn create_session_initiator_deterministic(
+    identity_bytes_json: String,
+    prekey_bundle_json: String,
+) -> String {
+    initiate_session_json(identity_bytes_json, prekey_bundle_json, true)
+}
+
+fn initiate_session_json(
+    identity_bytes_json: String,
+    prekey_bundle_json: String,
+    deterministic: bool,
 ) -> String {
     // Parse identity from JSON
     let identity_bytes = match serde_json::from_str::<IdentityKeyPairBytes>(&identity_bytes_json) {
@@ -240,18 +511,15 @@ pub fn create_session_initiator_with_ephemeral(
     };
     
     // Create session with shared secret
-    let session_id = generate_session_id();
-    let session = match Session::from_shared_secret(
-        x3dh_result.shared_secret,
-        true, // is_initiator
-        session_id.clone(),
-    ) {
-        Ok(s) => Arc::new(s),
+    let session = match Session::from_x3dh_result(&x3dh_result, generate_session_id()) {
+        Ok(s) => s,
         Err(e) => return format!("Error: Failed to create session: {}", e),
     };
     
-    // Register session
-    SESSION_REGISTRY.register(session_id.clone(), session);
+    let session_id = match register_new_session(session, deterministic) {
+        Ok(id) => id,
+        Err(e) => return format!("Error: {}", e),
+    };
     
     // Return JSON with session and hex keys
     let resp = serde_json::json!({
@@ -262,6 +530,32 @@ pub fn create_session_initiator_with_ephemeral(
     resp.to_string()
 }
 
+/// Restore a session from a state saved with `encrypt_message_with_state`
+/// 
+/// # Arguments
+/// * `state_base64` - Saved ratchet state
+/// * `routing_id` - Routing ID of the original session (`session_routing_id`)
+/// * `expected_is_initiator` - Role the caller restores into, or None to skip the check
+/// 
+/// # Returns
+/// New session ID if successful, or error message (e.g. a role mismatch)
+#[frb(sync)]
+pub fn import_session(state_base64: String, routing_id: String, expected_is_initiator: Option<bool>) -> String {
+    let state = match RatchetState::from_base64(&state_base64) {
+        Ok(state) => state,
+        Err(e) => return format!("Error: {}", e),
+    };
+    
+    let session_id = generate_session_id();
+    match Session::from_state(&state, routing_id, expected_is_initiator, session_id.clone()) {
+        Ok(session) => match SESSION_REGISTRY.try_register(session_id.clone(), Arc::new(session)) {
+            Ok(()) => session_id,
+            Err(e) => format!("Error: {}", e),
+        },
+        Err(e) => format!("Error: Failed to import session: {}", e),
+    }
+}
+
 /// Create a session as responder (Bob)
 /// 
 /// Responds to X3DH handshake and creates DoubleRatchet session.
@@ -282,6 +576,58 @@ pub fn create_session_responder(
     one_time_prekey_id: Option<u32>,
     alice_identity_hex: String,
     alice_ephemeral_public_key_hex: String,
+) -> String {
+    respond_session(
+        identity_bytes_json,
+        signed_prekey_id,
+        one_time_prekey_id,
+        alice_identity_hex,
+        alice_ephemeral_public_key_hex,
+        false,
+    )
+}
+
+/// Create a session as responder (Bob) under an id both parties derive
+/// 
+/// Same as `create_session_responder`, but the session ID is derived from
+/// the routing ID and matches the one Alice got from
+/// `create_session_initiator_deterministic`.
+/// 
+/// # Arguments
+/// * `identity_bytes_json` - JSON string of Bob's IdentityKeyPairBytes
+/// * `signed_prekey_id` - ID of the signed prekey Bob used
+/// * `one_time_prekey_id` - ID of the one-time prekey Bob used (optional)
+/// * `alice_identity_hex` - Alice's identity public key (hex)
+/// * `alice_ephemeral_public_key_hex` - Alice's ephemeral public key from X3DH (hex)
+/// 
+/// # Returns
+/// Session ID (UUID string) if successful, or error message if a session is
+/// already registered under the derived ID
+#[frb(sync)]
+pub fn create_session_responder_deterministic(
+    identity_bytes_json: String,
+    signed_prekey_id: u32,
+    one_time_prekey_id: Option<u32>,
+    alice_identity_hex: String,
+    alice_ephemeral_public_key_hex: String,
+) -> String {
+    respond_session(
+        identity_bytes_json,
+        signed_prekey_id,
+        one_time_prekey_id,
+        alice_identity_hex,
+        alice_ephemeral_public_key_hex,
+        true,
+    )
+}
+
+fn respond_session(
+    identity_bytes_json: String,
+    signed_prekey_id: u32,
+    one_time_prekey_id: Option<u32>,
+    alice_identity_hex: String,
+    alice_ephemeral_public_key_hex: String,
+    deterministic: bool,
 ) -> String {
     // Parse identity from JSON
     let identity_bytes = match serde_json::from_str::<IdentityKeyPairBytes>(&identity_bytes_json) {
@@ -295,48 +641,43 @@ pub fn create_session_responder(
     };
     
     // Load the exact prekeys Bob generated earlier
-    let signed_prekey = match SIGNED_PREKEY_STORE.lock().ok().and_then(|m| m.get(&signed_prekey_id).cloned()) {
-        Some(sp) => sp,
-        None => return format!("Error: Missing signed prekey id {} in store", signed_prekey_id),
+    let identity_hex = identity.public_key_hex();
+    let responder = match load_responder(identity, signed_prekey_id, one_time_prekey_id) {
+        Ok(r) => r,
+        Err(e) => return format!("Error: {}", e),
     };
     
-    let mut responder = X3DHResponder::new(identity.clone(), signed_prekey.clone());
-    
-    // Set one-time prekey if provided
-    if let Some(otp_id) = one_time_prekey_id {
-        use x25519_dalek::{EphemeralSecret, PublicKey};
-        let otp_private_bytes = match ONE_TIME_PREKEY_STORE.lock().ok().and_then(|m| m.get(&otp_id).cloned()) {
-            Some(bytes) => bytes,
-            None => return format!("Error: Missing one-time prekey id {} in store", otp_id),
-        };
-        let otp_private_reconstructed = unsafe {
-            std::mem::transmute::<[u8; 32], EphemeralSecret>(otp_private_bytes)
-        };
-        let otp_public = PublicKey::from(&otp_private_reconstructed);
-        responder.set_one_time_prekey(otp_id, otp_private_reconstructed, otp_public);
-    }
-    
     // Respond to X3DH handshake
     let x3dh_result = match responder.respond(&alice_identity_hex, &alice_ephemeral_public_key_hex) {
         Ok(r) => r,
         Err(e) => return format!("Error: X3DH handshake failed: {}", e),
     };
+    if let Err(e) = consume_one_time_prekey(identity_hex, one_time_prekey_id) {
+        return format!("Error: {}", e);
+    }
     
-    // Create session with shared secret
-    let session_id = generate_session_id();
-    let session = match Session::from_shared_secret(
-        x3dh_result.shared_secret,
-        false, // is_initiator
-        session_id.clone(),
-    ) {
-        Ok(s) => Arc::new(s),
+    // Create session with shared secret, recording Alice as the peer
+    let session = match Session::from_x3dh_response(&x3dh_result, &alice_identity_hex, generate_session_id()) {
+        Ok(s) => s,
         Err(e) => return format!("Error: Failed to create session: {}", e),
     };
     
-    // Register session
-    SESSION_REGISTRY.register(session_id.clone(), session);
+    register_new_session(session, deterministic).unwrap_or_else(|e| format!("Error: {}", e))
+}
+
+/// Register a freshly created session, optionally under its deterministic id
+/// 
+/// # Returns
+/// The id the session was registered under, or `StateError` if a session
+/// already holds that id (it is never replaced)
+fn register_new_session(mut session: Session, deterministic: bool) -> Result<String> {
+    if deterministic {
+        session.id = deterministic_session_id(session.routing_id())?;
+    }
     
-    session_id
+    let session_id = session.id.clone();
+    SESSION_REGISTRY.try_register(session_id.clone(), Arc::new(session))?;
+    Ok(session_id)
 }
 
 /// Encrypt a message using a session
@@ -349,9 +690,9 @@ pub fn create_session_responder(
 /// Base64-encoded MessageEnvelope if successful, or error message
 #[frb(sync)]
 pub fn encrypt_message(session_id: String, plaintext: Vec<u8>) -> String {
-    let session = match SESSION_REGISTRY.get(&session_id) {
-        Some(s) => s,
-        None => return format!("Error: Session not found: {}", session_id),
+    let session = match SESSION_REGISTRY.try_get(&session_id) {
+        Ok(s) => s,
+        Err(e) => return format!("Error: {}", e),
     };
     
     let envelope = match session.encrypt(&plaintext) {
@@ -365,8 +706,67 @@ pub fn encrypt_message(session_id: String, plaintext: Vec<u8>) -> String {
     }
 }
 
+/// Encrypt a message and return the new session state to persist with it
+/// 
+/// The envelope and the state snapshot are produced under one session lock,
+/// so saving `state_base64` after sending can never lose a used counter.
+/// The state contains every session secret: store it in secure storage only.
+/// 
+/// # Arguments
+/// * `session_id` - Session ID
+/// * `plaintext` - Plaintext message bytes
+/// 
+/// # Returns
+/// JSON string: {
+///   "ok": bool,
+///   "envelope_base64": String | null,
+///   "state_base64": String | null,
+///   "error": String | null
+/// }
+#[frb(sync)]
+pub fn encrypt_message_with_state(session_id: String, plaintext: Vec<u8>) -> String {
+    let error_json = |error: String| {
+        serde_json::json!({
+            "ok": false,
+            "envelope_base64": null,
+            "state_base64": null,
+            "error": error,
+        })
+        .to_string()
+    };
+    
+    let session = match SESSION_REGISTRY.try_get(&session_id) {
+        Ok(s) => s,
+        Err(e) => return error_json(e.to_string()),
+    };
+    
+    let (envelope, state) = match session.encrypt_and_snapshot(&plaintext) {
+        Ok(result) => result,
+        Err(e) => return error_json(format!("Encryption failed: {}", e)),
+    };
+    
+    let envelope_base64 = match envelope.to_base64() {
+        Ok(b64) => b64,
+        Err(e) => return error_json(format!("Failed to serialize envelope: {}", e)),
+    };
+    
+    match state.to_base64() {
+        Ok(state_base64) => serde_json::json!({
+            "ok": true,
+            "envelope_base64": envelope_base64,
+            "state_base64": state_base64,
+            "error": null,
+        })
+        .to_string(),
+        Err(e) => error_json(format!("Failed to serialize session state: {}", e)),
+    }
+}
+
 /// Decrypt a message using a session
 /// 
+/// Deprecated in favour of `decrypt_message_full`: errors are returned as
+/// bytes and cannot be told apart from a plaintext starting with "Error:".
+/// 
 /// # Arguments
 /// * `session_id` - Session ID
 /// * `envelope_base64` - Base64-encoded MessageEnvelope
@@ -375,12 +775,12 @@ pub fn encrypt_message(session_id: String, plaintext: Vec<u8>) -> String {
 /// Decrypted plaintext bytes if successful, or error message
 #[frb(sync)]
 pub fn decrypt_message(session_id: String, envelope_base64: String) -> Vec<u8> {
-    let session = match SESSION_REGISTRY.get(&session_id) {
-        Some(s) => s,
-        None => return b"Error: Session not found".to_vec(),
+    let session = match SESSION_REGISTRY.try_get(&session_id) {
+        Ok(s) => s,
+        Err(e) => return format!("Error: {}", e).into_bytes(),
     };
     
-    let envelope = match MessageEnvelope::from_base64(&envelope_base64) {
+    let envelope = match MessageEnvelope::from_base64_limited(&envelope_base64, MAX_ENVELOPE_BYTES) {
         Ok(e) => e,
         Err(e) => return format!("Error: Failed to parse envelope: {}", e).into_bytes(),
     };
@@ -391,12 +791,537 @@ pub fn decrypt_message(session_id: String, envelope_base64: String) -> Vec<u8> {
     }
 }
 
+/// Decrypt a message and return plaintext plus metadata as JSON
+/// 
+/// # Arguments
+/// * `session_id` - Session ID
+/// * `envelope_base64` - Base64-encoded MessageEnvelope
+/// 
+/// # Returns
+/// JSON string: {
+///   "ok": bool,
+///   "plaintext_base64": String | null,
+///   "message_number": u64 | null,
+///   "sent_at": u64 | null,
+///   "error": String | null
+/// }
+#[frb(sync)]
+pub fn decrypt_message_full(session_id: String, envelope_base64: String) -> String {
+    let error_json = |error: String| {
+        serde_json::json!({
+            "ok": false,
+            "plaintext_base64": null,
+            "message_number": null,
+            "sent_at": null,
+            "error": error,
+        })
+        .to_string()
+    };
+    
+    let session = match SESSION_REGISTRY.try_get(&session_id) {
+        Ok(s) => s,
+        Err(e) => return error_json(e.to_string()),
+    };
+    
+    let envelope = match MessageEnvelope::from_base64_limited(&envelope_base64, MAX_ENVELOPE_BYTES) {
+        Ok(e) => e,
+        Err(e) => return error_json(format!("Failed to parse envelope: {}", e)),
+    };
+    
+    match session.decrypt_full(&envelope) {
+        Ok(decrypted) => serde_json::json!({
+            "ok": true,
+            "plaintext_base64": general_purpose::STANDARD.encode(&decrypted.plaintext),
+            "message_number": decrypted.message_number,
+            "sent_at": decrypted.sent_at,
+            "error": null,
+        })
+        .to_string(),
+        Err(e) => error_json(format!("Decryption failed: {}", e)),
+    }
+}
+
+/// Report message numbers that have not been received yet
+/// 
+/// Drives retransmission requests for reliable delivery.
+/// 
+/// # Arguments
+/// * `session_id` - Session ID
+/// * `up_to` - Exclusive upper bound on message numbers (e.g. highest number seen + 1)
+/// 
+/// # Returns
+/// Missing message numbers in ascending order (empty if the session is unknown)
+#[frb(sync)]
+pub fn session_missing_messages(session_id: String, up_to: u64) -> Vec<u64> {
+    SESSION_REGISTRY.try_get(&session_id)
+        .and_then(|session| session.missing_messages(up_to))
+        .unwrap_or_default()
+}
+
+/// Make `decrypt_message` idempotent for duplicate deliveries
+/// 
+/// For at-least-once transports: a byte-identical envelope among the last
+/// `window` decrypted ones returns the same plaintext again instead of an
+/// error. The cached plaintexts are kept in memory only and zeroized on
+/// eviction or close.
+/// 
+/// # Arguments
+/// * `session_id` - Session ID
+/// * `window` - Number of recent messages to remember (0 disables the cache)
+/// 
+/// # Returns
+/// true if the setting was applied (false if the session is unknown)
+#[frb(sync)]
+pub fn session_enable_idempotent_decrypt(session_id: String, window: u32) -> bool {
+    SESSION_REGISTRY.try_get(&session_id)
+        .and_then(|session| session.enable_idempotent_decrypt(window as usize))
+        .is_ok()
+}
+
+/// Check whether a session has decrypted a given message, for delivery status
+/// 
+/// # Arguments
+/// * `session_id` - Session ID
+/// * `message_number` - Header message number (starting at 1)
+/// 
+/// # Returns
+/// true if the message was decrypted (false if not, or if the session is unknown)
+#[frb(sync)]
+pub fn session_has_received(session_id: String, message_number: u64) -> bool {
+    SESSION_REGISTRY.try_get(&session_id)
+        .and_then(|session| session.has_received(message_number))
+        .unwrap_or(false)
+}
+
+/// Check whether a session has completed its first DH ratchet
+/// 
+/// Lets clients tell "waiting for first reply" apart from a fully
+/// bidirectional session.
+/// 
+/// # Arguments
+/// * `session_id` - Session ID
+/// 
+/// # Returns
+/// true once the session has ratcheted (false if the session is unknown)
+#[frb(sync)]
+pub fn session_has_ratcheted(session_id: String) -> bool {
+    SESSION_REGISTRY.try_get(&session_id)
+        .and_then(|session| session.has_ratcheted())
+        .unwrap_or(false)
+}
+
+/// Get the number of messages a session can send before a DH ratchet is forced
+/// 
+/// # Arguments
+/// * `session_id` - Session ID
+/// 
+/// # Returns
+/// Remaining sends, or -1 if the session has no per-chain message cap (or is unknown)
+#[frb(sync)]
+pub fn session_messages_until_rekey(session_id: String) -> i64 {
+    SESSION_REGISTRY.try_get(&session_id)
+        .and_then(|session| session.messages_until_rekey())
+        .ok()
+        .flatten()
+        .map_or(-1, |remaining| i64::try_from(remaining).unwrap_or(i64::MAX))
+}
+
+/// Get the non-secret ratchet state of a session
+/// 
+/// Peers suspecting a desync can exchange this over the secure channel: one
+/// side's `sending_number` should match the other's `receiving_number`.
+/// 
+/// # Arguments
+/// * `session_id` - Session ID
+/// 
+/// # Returns
+/// JSON string of the PublicRatchetState, or error message if the session is unknown
+#[frb(sync)]
+pub fn session_public_state(session_id: String) -> String {
+    let state = match SESSION_REGISTRY.try_get(&session_id).and_then(|session| session.public_state()) {
+        Ok(state) => state,
+        Err(e) => return format!("Error: {}", e),
+    };
+    
+    match serde_json::to_string(&state) {
+        Ok(json) => json,
+        Err(e) => format!("Error: Failed to serialize public state: {}", e),
+    }
+}
+
+/// Get a session's message counters
+/// 
+/// # Arguments
+/// * `session_id` - Session ID
+/// 
+/// # Returns
+/// JSON string of the SessionStats (`messages_sent`, `messages_received`),
+/// or error message if the session is unknown
+#[frb(sync)]
+pub fn session_stats(session_id: String) -> String {
+    let stats = match SESSION_REGISTRY.try_get(&session_id) {
+        Ok(session) => session.stats(),
+        Err(e) => return format!("Error: {}", e),
+    };
+    
+    match serde_json::to_string(&stats) {
+        Ok(json) => json,
+        Err(e) => format!("Error: Failed to serialize session stats: {}", e),
+    }
+}
+
+/// Reset a session's ratchet onto a new shared secret
+/// 
+/// Both peers must call this with the same secret and opposite roles. The
+/// session id stays the same; envelopes from before the rebase no longer decrypt.
+/// 
+/// # Arguments
+/// * `session_id` - Session ID
+/// * `new_secret_hex` - New 32-byte shared secret as hex string
+/// * `is_initiator` - Role of this side in the new ratchet
+/// 
+/// # Returns
+/// The (unchanged) session ID if successful, or error message
+#[frb(sync)]
+pub fn rebase_session(session_id: String, new_secret_hex: String, is_initiator: bool) -> String {
+    let result = parse_hex_32(&new_secret_hex).and_then(|mut new_secret| {
+        let rebased = SESSION_REGISTRY.try_get(&session_id)
+            .and_then(|session| session.rebase(new_secret, is_initiator));
+        new_secret.zeroize();
+        rebased
+    });
+    
+    match result {
+        Ok(()) => session_id,
+        Err(e) => format!("Error: {}", e),
+    }
+}
+
+/// Get the routing ID of a session
+/// 
+/// Both parties compute the same routing ID, unlike their local session IDs.
+/// 
+/// # Arguments
+/// * `session_id` - Session ID
+/// 
+/// # Returns
+/// Routing ID as hex string, or error message if the session is unknown
+#[frb(sync)]
+pub fn session_routing_id(session_id: String) -> String {
+    match SESSION_REGISTRY.try_get(&session_id) {
+        Ok(session) => session.routing_id().to_string(),
+        Err(e) => format!("Error: {}", e),
+    }
+}
+
+/// Get a registered session by ID (for tests exercising the FFI layer)
+/// 
+/// Only available with `test-support`.
+#[cfg(feature = "test-support")]
+#[frb(ignore)]
+pub fn registered_session(session_id: &str) -> Option<Arc<Session>> {
+    SESSION_REGISTRY.get(&session_id.to_string())
+}
+
 /// Close a session
 /// 
+/// Zeroizes the session's ratchet before removing it from the registry.
+/// 
 /// # Arguments
 /// * `session_id` - Session ID
 #[frb(sync)]
 pub fn close_session(session_id: String) {
-    SESSION_REGISTRY.remove(&session_id);
+    // Wipe the ratchet so clones held elsewhere can no longer use it
+    if let Ok(session) = SESSION_REGISTRY.try_remove(&session_id) {
+        session.close();
+    }
 }
 
+/// Close a session, confirming that its key material was wiped
+/// 
+/// Like `close_session`, but reports whether the zeroization took effect,
+/// for apps that must attest sensitive data was erased.
+/// 
+/// # Arguments
+/// * `session_id` - Session ID
+/// 
+/// # Returns
+/// true if the session was found, closed, wiped and removed from the registry;
+/// false if the session is unknown or the wipe could not be confirmed
+#[frb(sync)]
+pub fn secure_close_session(session_id: String) -> bool {
+    let Ok(session) = SESSION_REGISTRY.try_remove(&session_id) else {
+        return false;
+    };
+    session.close_and_verify() && !SESSION_REGISTRY.contains(&session_id)
+}
+
+
+/// Encrypt a one-off message directly to a prekey bundle (sealed sender style)
+/// 
+/// Runs X3DH against the bundle, encrypts a single message with a throwaway
+/// Double Ratchet and packs everything the recipient needs into one blob.
+/// No session is registered on either side.
+/// 
+/// # Arguments
+/// * `sender_identity_json` - JSON string of the sender's IdentityKeyPairBytes
+/// * `bundle_json` - JSON string of the recipient's PreKeyBundleJSON
+/// * `plaintext` - Plaintext message bytes
+/// 
+/// # Returns
+/// Base64-encoded SealedMessage if successful, or error message
+#[frb(sync)]
+pub fn seal_to_bundle(
+    sender_identity_json: String,
+    bundle_json: String,
+    plaintext: Vec<u8>,
+) -> String {
+    seal_to_bundle_with_device(sender_identity_json, bundle_json, plaintext, None)
+}
+
+/// Encrypt a one-off message to a prekey bundle, addressed to one recipient device
+/// 
+/// Like `seal_to_bundle`, but the envelope header carries `device_id` so a
+/// relay can route it. The id is authenticated with the message.
+/// 
+/// # Arguments
+/// * `sender_identity_json` - JSON string of the sender's IdentityKeyPairBytes
+/// * `bundle_json` - JSON string of the recipient's PreKeyBundleJSON
+/// * `plaintext` - Plaintext message bytes
+/// * `device_id` - Recipient device (registration) id
+/// 
+/// # Returns
+/// Base64-encoded SealedMessage if successful, or error message
+#[frb(sync)]
+pub fn seal_to_bundle_for_device(
+    sender_identity_json: String,
+    bundle_json: String,
+    plaintext: Vec<u8>,
+    device_id: u32,
+) -> String {
+    seal_to_bundle_with_device(sender_identity_json, bundle_json, plaintext, Some(device_id))
+}
+
+fn seal_to_bundle_with_device(
+    sender_identity_json: String,
+    bundle_json: String,
+    plaintext: Vec<u8>,
+    device_id: Option<u32>,
+) -> String {
+    // Parse identity from JSON
+    let identity_bytes = match serde_json::from_str::<IdentityKeyPairBytes>(&sender_identity_json) {
+        Ok(bytes) => bytes,
+        Err(e) => return format!("Error: Failed to parse identity: {}", e),
+    };
+    
+    let identity = match identity_bytes.to_identity_key_pair() {
+        Ok(id) => id,
+        Err(e) => return format!("Error: Failed to create identity: {}", e),
+    };
+    
+    // Parse prekey bundle from JSON
+    let bundle_json = match serde_json::from_str::<PreKeyBundleJSON>(&bundle_json) {
+        Ok(b) => b,
+        Err(e) => return format!("Error: Failed to parse prekey bundle: {}", e),
+    };
+    
+    let prekey_bundle = match bundle_json.to_prekey_bundle() {
+        Ok(b) => b,
+        Err(e) => return format!("Error: Failed to create prekey bundle: {}", e),
+    };
+    
+    // Verify bundle signature
+    if let Err(e) = prekey_bundle.verify_signature() {
+        return format!("Error: Prekey bundle signature verification failed: {}", e);
+    }
+    
+    // Initiate X3DH handshake
+    let sender_identity_hex = identity.public_key_hex();
+    let initiator = X3DHInitiator::new(identity);
+    let x3dh_result = match initiator.initiate(&prekey_bundle) {
+        Ok(r) => r,
+        Err(e) => return format!("Error: X3DH handshake failed: {}", e),
+    };
+    
+    // Encrypt the single message with a throwaway ratchet
+    let mut ratchet = match DoubleRatchet::from_shared_secret(&x3dh_result.shared_secret, true) {
+        Ok(dr) => dr,
+        Err(e) => return format!("Error: Failed to create ratchet: {}", e),
+    };
+    
+    let encrypted = match device_id {
+        Some(device_id) => ratchet.encrypt_envelope_for_device(&plaintext, device_id),
+        None => ratchet.encrypt_envelope(&plaintext),
+    };
+    let mut envelope = match encrypted {
+        Ok(e) => e,
+        Err(e) => return format!("Error: Encryption failed: {}", e),
+    };
+    envelope.message_type = MessageType::PreKey;
+    
+    let sealed = SealedMessage {
+        sender_identity_hex,
+        ephemeral_public_key_hex: x3dh_result.ephemeral_public_key_hex,
+        signed_prekey_id: bundle_json.signed_prekey.key_id,
+        one_time_prekey_id: bundle_json.one_time_prekey.as_ref().map(|otp| otp.key_id),
+        envelope,
+    };
+    
+    match sealed.to_base64() {
+        Ok(b64) => b64,
+        Err(e) => format!("Error: Failed to serialize sealed message: {}", e),
+    }
+}
+
+/// Open a message produced by `seal_to_bundle`
+/// 
+/// # Arguments
+/// * `recipient_identity_json` - JSON string of the recipient's IdentityKeyPairBytes
+/// * `signed_prekey_id` - ID of the signed prekey the bundle was built from
+/// * `one_time_prekey_id` - ID of the one-time prekey the bundle was built from (optional)
+/// * `sealed_base64` - Base64-encoded SealedMessage
+/// 
+/// # Returns
+/// Decrypted plaintext bytes if successful, or error message
+#[frb(sync)]
+pub fn open_sealed(
+    recipient_identity_json: String,
+    signed_prekey_id: u32,
+    one_time_prekey_id: Option<u32>,
+    sealed_base64: String,
+) -> Vec<u8> {
+    open_sealed_with_device(recipient_identity_json, signed_prekey_id, one_time_prekey_id, None, sealed_base64)
+}
+
+/// Open a message produced by `seal_to_bundle` and report who sent it
+/// 
+/// The sender identity is only returned once decryption succeeded, and the
+/// identity is part of the X3DH key derivation, so the attribution can be
+/// trusted: a relay that swaps in another identity makes decryption fail.
+/// 
+/// # Arguments
+/// * `recipient_identity_json` - JSON string of the recipient's IdentityKeyPairBytes
+/// * `signed_prekey_id` - ID of the signed prekey the bundle was built from
+/// * `one_time_prekey_id` - ID of the one-time prekey the bundle was built from (optional)
+/// * `sealed_base64` - Base64-encoded SealedMessage
+/// 
+/// # Returns
+/// JSON string: {
+///   "ok": bool,
+///   "plaintext_base64": String | null,
+///   "sender_identity_hex": String | null,
+///   "error": String | null
+/// }
+#[frb(sync)]
+pub fn open_sealed_with_sender(
+    recipient_identity_json: String,
+    signed_prekey_id: u32,
+    one_time_prekey_id: Option<u32>,
+    sealed_base64: String,
+) -> String {
+    match open_sealed_message(recipient_identity_json, signed_prekey_id, one_time_prekey_id, None, sealed_base64) {
+        Ok((plaintext, sender_identity_hex)) => serde_json::json!({
+            "ok": true,
+            "plaintext_base64": general_purpose::STANDARD.encode(&plaintext),
+            "sender_identity_hex": sender_identity_hex,
+            "error": null,
+        }),
+        Err(e) => serde_json::json!({
+            "ok": false,
+            "plaintext_base64": null,
+            "sender_identity_hex": null,
+            "error": e,
+        }),
+    }
+    .to_string()
+}
+
+/// Open a message produced by `seal_to_bundle_for_device` on the given device
+/// 
+/// # Arguments
+/// * `recipient_identity_json` - JSON string of the recipient's IdentityKeyPairBytes
+/// * `signed_prekey_id` - ID of the signed prekey the bundle was built from
+/// * `one_time_prekey_id` - ID of the one-time prekey the bundle was built from (optional)
+/// * `device_id` - This device's id
+/// * `sealed_base64` - Base64-encoded SealedMessage
+/// 
+/// # Returns
+/// Decrypted plaintext bytes if successful, or error message (including when
+/// the message targets another device)
+#[frb(sync)]
+pub fn open_sealed_for_device(
+    recipient_identity_json: String,
+    signed_prekey_id: u32,
+    one_time_prekey_id: Option<u32>,
+    device_id: u32,
+    sealed_base64: String,
+) -> Vec<u8> {
+    open_sealed_with_device(recipient_identity_json, signed_prekey_id, one_time_prekey_id, Some(device_id), sealed_base64)
+}
+
+fn open_sealed_with_device(
+    recipient_identity_json: String,
+    signed_prekey_id: u32,
+    one_time_prekey_id: Option<u32>,
+    device_id: Option<u32>,
+    sealed_base64: String,
+) -> Vec<u8> {
+    match open_sealed_message(recipient_identity_json, signed_prekey_id, one_time_prekey_id, device_id, sealed_base64) {
+        Ok((plaintext, _sender_identity_hex)) => plaintext,
+        Err(e) => format!("Error: {}", e).into_bytes(),
+    }
+}
+
+/// Run the responder side of X3DH for a sealed message and decrypt it
+/// 
+/// The claimed sender identity feeds DH1 of the X3DH computation, so a
+/// message attributed to anyone but the real sender derives the wrong key and
+/// fails to decrypt.
+/// 
+/// # Returns
+/// Plaintext and the verified sender identity (lowercase hex), or the error message
+fn open_sealed_message(
+    recipient_identity_json: String,
+    signed_prekey_id: u32,
+    one_time_prekey_id: Option<u32>,
+    device_id: Option<u32>,
+    sealed_base64: String,
+) -> std::result::Result<(Vec<u8>, String), String> {
+    // Parse identity from JSON
+    let identity_bytes = serde_json::from_str::<IdentityKeyPairBytes>(&recipient_identity_json)
+        .map_err(|e| format!("Failed to parse identity: {}", e))?;
+    
+    let identity = identity_bytes.to_identity_key_pair()
+        .map_err(|e| format!("Failed to create identity: {}", e))?;
+    
+    let sealed = SealedMessage::from_base64(&sealed_base64)
+        .map_err(|e| format!("Failed to parse sealed message: {}", e))?;
+    
+    // The blob must have been sealed to the prekeys the caller is about to use
+    if sealed.signed_prekey_id != signed_prekey_id || sealed.one_time_prekey_id != one_time_prekey_id {
+        return Err("Sealed message was not sealed to the given prekey ids".to_string());
+    }
+    
+    // A device only opens messages addressed to it; the id is authenticated on decrypt
+    if device_id.is_some() && sealed.envelope.header.recipient_device_id != device_id {
+        return Err("Sealed message targets another device".to_string());
+    }
+    
+    let identity_hex = identity.public_key_hex();
+    let responder = load_responder(identity, signed_prekey_id, one_time_prekey_id)
+        .map_err(|e| e.to_string())?;
+    
+    // Respond to X3DH handshake
+    let x3dh_result = responder.respond(&sealed.sender_identity_hex, &sealed.ephemeral_public_key_hex)
+        .map_err(|e| format!("X3DH handshake failed: {}", e))?;
+    
+    let mut ratchet = DoubleRatchet::from_shared_secret(&x3dh_result.shared_secret, false)
+        .map_err(|e| format!("Failed to create ratchet: {}", e))?;
+    
+    let plaintext = ratchet.decrypt_envelope(&sealed.envelope)
+        .map_err(|e| format!("Decryption failed: {}", e))?;
+    consume_one_time_prekey(identity_hex, one_time_prekey_id)
+        .map_err(|e| e.to_string())?;
+    
+    Ok((plaintext, x3dh_result.peer_identity_hex.clone()))
+}
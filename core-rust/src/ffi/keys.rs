@@ -1,7 +1,12 @@
+use crate::encoding::{parse_curve_point_hex, parse_hex_32, parse_hex_64};
 use crate::error::{E2EEError, Result};
+use crate::ffi::store::PreKeyStore;
+use crate::keys::identity::reject_weak_secrets;
 use crate::keys::{IdentityKeyPair, PreKeyBundle};
-use crate::keys::prekey::{SignedPreKey, OneTimePreKey};
+use crate::keys::prekey::{OneTimePreKeyId, SignedPreKeyId, SignedPreKey, OneTimePreKey};
+use crate::keys::prekey::{OneTimePreKeyPair, SignedPreKeyPair};
 use serde::{Deserialize, Serialize};
+use zeroize::Zeroize;
 
 /// Identity key pair bytes for FFI
 /// 
@@ -61,9 +66,15 @@ impl IdentityKeyPairBytes {
             ));
         }
         
-        // Reconstruct X25519 keys
         let mut x25519_private_bytes = [0u8; 32];
         x25519_private_bytes.copy_from_slice(&self.x25519_private_key);
+        let mut ed25519_private_bytes = [0u8; 32];
+        ed25519_private_bytes.copy_from_slice(&self.ed25519_private_key);
+        
+        // Reject degenerate secrets before building any key from them
+        reject_weak_secrets(&x25519_private_bytes, &ed25519_private_bytes)?;
+        
+        // Reconstruct X25519 keys
         let x25519_private = unsafe {
             std::mem::transmute::<[u8; 32], EphemeralSecret>(x25519_private_bytes)
         };
@@ -79,9 +90,7 @@ impl IdentityKeyPairBytes {
         }
         
         // Reconstruct Ed25519 keys
-        let mut ed25519_private_bytes = [0u8; 32];
-        ed25519_private_bytes.copy_from_slice(&self.ed25519_private_key);
-        let ed25519_secret_key: SecretKey = ed25519_private_bytes.into();
+        let ed25519_secret_key: SecretKey = ed25519_private_bytes;
         let ed25519_signing_key = SigningKey::from_bytes(&ed25519_secret_key);
         let ed25519_verifying_key = ed25519_signing_key.verifying_key();
         
@@ -102,12 +111,80 @@ impl IdentityKeyPairBytes {
             ed25519_public_bytes,
         )
     }
+
+    /// Convert to the compact form holding only the private keys
+    /// 
+    /// The full form is validated first, so a pair whose stored publics do not
+    /// match its privates is rejected instead of being silently "repaired".
+    /// 
+    /// # Returns
+    /// CompactIdentityBytes, or `SerializationError` for an inconsistent pair
+    pub fn to_compact(&self) -> Result<CompactIdentityBytes> {
+        let identity = self.to_identity_key_pair()?;
+        Ok(CompactIdentityBytes::from_identity_key_pair(&identity))
+    }
+}
+
+/// Compact identity key pair bytes for secure storage
+/// 
+/// Holds only the two 32-byte private keys; both public keys are derived on
+/// load, so stored publics can never disagree with their privates.
+/// 
+/// These bytes should never be exposed publicly.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CompactIdentityBytes {
+    /// X25519 private key bytes (32 bytes)
+    pub x25519_private_key: Vec<u8>,
+    /// Ed25519 private key bytes (32 bytes)
+    pub ed25519_private_key: Vec<u8>,
+}
+
+impl CompactIdentityBytes {
+    /// Create from IdentityKeyPair
+    pub fn from_identity_key_pair(identity: &IdentityKeyPair) -> Self {
+        Self {
+            x25519_private_key: identity.private_key_bytes().to_vec(),
+            ed25519_private_key: identity.signing_key().to_bytes().to_vec(),
+        }
+    }
+
+    /// Expand to the full format, deriving both public keys
+    /// 
+    /// # Returns
+    /// IdentityKeyPairBytes, or `SerializationError` for a wrong key length
+    pub fn to_full(&self) -> Result<IdentityKeyPairBytes> {
+        use ed25519_dalek::SigningKey;
+        use x25519_dalek::{PublicKey, StaticSecret};
+        
+        let x25519_private_bytes: [u8; 32] = self.x25519_private_key.as_slice().try_into()
+            .map_err(|_| E2EEError::SerializationError("Invalid X25519 key length".to_string()))?;
+        let ed25519_private_bytes: [u8; 32] = self.ed25519_private_key.as_slice().try_into()
+            .map_err(|_| E2EEError::SerializationError("Invalid Ed25519 key length".to_string()))?;
+        
+        let x25519_public = PublicKey::from(&StaticSecret::from(x25519_private_bytes));
+        let ed25519_public = SigningKey::from_bytes(&ed25519_private_bytes).verifying_key();
+        
+        Ok(IdentityKeyPairBytes {
+            x25519_private_key: x25519_private_bytes.to_vec(),
+            x25519_public_key: x25519_public.as_bytes().to_vec(),
+            ed25519_private_key: ed25519_private_bytes.to_vec(),
+            ed25519_public_key: ed25519_public.to_bytes().to_vec(),
+        })
+    }
+
+    /// Convert to IdentityKeyPair
+    /// 
+    /// Runs the same checks as `IdentityKeyPairBytes::to_identity_key_pair`
+    /// (key lengths, degenerate secrets).
+    pub fn to_identity_key_pair(&self) -> Result<IdentityKeyPair> {
+        self.to_full()?.to_identity_key_pair()
+    }
 }
 
 /// PreKeyBundle JSON representation for FFI
 /// 
 /// Contains the prekey bundle data in a JSON-serializable format.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct PreKeyBundleJSON {
     /// Identity public key (X25519) as hex string
     pub identity_public_hex: String,
@@ -120,7 +197,7 @@ pub struct PreKeyBundleJSON {
 }
 
 /// Signed prekey JSON representation
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct SignedPreKeyJSON {
     /// Public key as hex string
     pub public_key_hex: String,
@@ -131,7 +208,7 @@ pub struct SignedPreKeyJSON {
 }
 
 /// One-time prekey JSON representation
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct OneTimePreKeyJSON {
     /// Public key as hex string
     pub public_key_hex: String,
@@ -165,65 +242,34 @@ impl PreKeyBundleJSON {
     /// This is used when the responder receives the bundle and needs to convert it back.
     /// Note: The responder already has the keys, so this is mainly for validation.
     pub fn to_prekey_bundle(&self) -> Result<PreKeyBundle> {
+        self.to_prekey_bundle_compat(false)
+    }
+
+    /// Convert to PreKeyBundle, optionally accepting Signal-style curve points
+    /// 
+    /// # Arguments
+    /// * `accept_prefixed` - Also accept 33-byte `0x05`-prefixed X25519 keys;
+    ///   they are normalized to the raw 32-byte form
+    /// 
+    /// # Returns
+    /// PreKeyBundle, or `SerializationError` for malformed keys
+    pub fn to_prekey_bundle_compat(&self, accept_prefixed: bool) -> Result<PreKeyBundle> {
         use x25519_dalek::PublicKey;
         use ed25519_dalek::{Signature, VerifyingKey};
         
-        // Parse identity public key
-        let identity_bytes = hex::decode(&self.identity_public_hex)
-            .map_err(|e| E2EEError::SerializationError(format!("Failed to decode identity key: {}", e)))?;
-        
-        if identity_bytes.len() != 32 {
-            return Err(E2EEError::SerializationError(
-                "Invalid identity key length".to_string()
-            ));
-        }
-        
-        let mut identity_pub_bytes = [0u8; 32];
-        identity_pub_bytes.copy_from_slice(&identity_bytes);
-        let _identity_public = PublicKey::from(identity_pub_bytes);
+        // Parse identity public key (kept as raw hex in the bundle)
+        let identity_public = parse_curve_point_hex(&self.identity_public_hex, accept_prefixed)?;
         
         // Parse Ed25519 verifying key
-        let ed25519_verifying_key_bytes = hex::decode(&self.identity_ed25519_verifying_key_hex)
-            .map_err(|e| E2EEError::SerializationError(format!("Failed to decode Ed25519 verifying key: {}", e)))?;
-        
-        if ed25519_verifying_key_bytes.len() != 32 {
-            return Err(E2EEError::SerializationError(
-                "Invalid Ed25519 verifying key length".to_string()
-            ));
-        }
-        
-        let mut ed25519_verifying_key_bytes_array = [0u8; 32];
-        ed25519_verifying_key_bytes_array.copy_from_slice(&ed25519_verifying_key_bytes);
-        let ed25519_verifying_key = VerifyingKey::from_bytes(&ed25519_verifying_key_bytes_array)
+        let ed25519_verifying_key_bytes = parse_hex_32(&self.identity_ed25519_verifying_key_hex)?;
+        let ed25519_verifying_key = VerifyingKey::from_bytes(&ed25519_verifying_key_bytes)
             .map_err(|e| E2EEError::SerializationError(format!("Failed to parse Ed25519 verifying key: {}", e)))?;
         
         // Parse signed prekey
-        let signed_prekey_bytes = hex::decode(&self.signed_prekey.public_key_hex)
-            .map_err(|e| E2EEError::SerializationError(format!("Failed to decode signed prekey: {}", e)))?;
-        
-        if signed_prekey_bytes.len() != 32 {
-            return Err(E2EEError::SerializationError(
-                "Invalid signed prekey length".to_string()
-            ));
-        }
-        
-        let mut signed_prekey_pub_bytes = [0u8; 32];
-        signed_prekey_pub_bytes.copy_from_slice(&signed_prekey_bytes);
-        let signed_prekey_public = PublicKey::from(signed_prekey_pub_bytes);
+        let signed_prekey_public = PublicKey::from(parse_curve_point_hex(&self.signed_prekey.public_key_hex, accept_prefixed)?);
         
         // Parse signature
-        let signature_bytes = hex::decode(&self.signed_prekey.signature_hex)
-            .map_err(|e| E2EEError::SerializationError(format!("Failed to decode signature: {}", e)))?;
-        
-        if signature_bytes.len() != 64 {
-            return Err(E2EEError::SerializationError(
-                "Invalid signature length".to_string()
-            ));
-        }
-        
-        let mut sig_bytes = [0u8; 64];
-        sig_bytes.copy_from_slice(&signature_bytes);
-        let signature = Signature::from_bytes(&sig_bytes);
+        let signature = Signature::from_bytes(&parse_hex_64(&self.signed_prekey.signature_hex)?);
         
         // Create signed prekey using from_components
         let signed_prekey = SignedPreKey::from_components(
@@ -233,24 +279,178 @@ impl PreKeyBundleJSON {
         );
         
         // Parse one-time prekey if present
-        let one_time_prekey = self.one_time_prekey.as_ref().map(|otp| {
-            let otp_bytes = hex::decode(&otp.public_key_hex)
-                .expect("Failed to decode one-time prekey");
-            let mut otp_pub_bytes = [0u8; 32];
-            otp_pub_bytes.copy_from_slice(&otp_bytes);
-            let otp_public = PublicKey::from(otp_pub_bytes);
-            
-            OneTimePreKey::from_components(otp_public, otp.key_id)
-        });
+        let one_time_prekey = match self.one_time_prekey.as_ref() {
+            Some(otp) => {
+                let otp_public = PublicKey::from(parse_curve_point_hex(&otp.public_key_hex, accept_prefixed)?);
+                Some(OneTimePreKey::from_components(otp_public, otp.key_id))
+            }
+            None => None,
+        };
         
         // Create PreKeyBundle
         Ok(PreKeyBundle::new(
-            self.identity_public_hex.clone(),
+            hex::encode(identity_public),
             ed25519_verifying_key,
             signed_prekey,
             one_time_prekey,
         ))
     }
+
+    /// Rebuild the bundle from its parsed keys, keeping only the public fields
+    /// 
+    /// Use before (re-)publishing a bundle: every key is re-parsed and
+    /// re-encoded as lowercase hex, and the signed prekey signature is checked
+    /// against the identity's Ed25519 key.
+    /// 
+    /// # Returns
+    /// Sanitized bundle, or an error if a field is malformed or the signature
+    /// does not verify
+    pub fn sanitized(&self) -> Result<Self> {
+        let bundle = self.to_prekey_bundle()?;
+        bundle.verify_signature()?;
+        
+        let mut sanitized = Self::from_prekey_bundle(&bundle);
+        sanitized.identity_public_hex = hex::encode(parse_hex_32(&self.identity_public_hex)?);
+        Ok(sanitized)
+    }
+}
+
+/// Private half of freshly generated prekeys
+/// 
+/// Produced by `generate_prekey_material` next to the public bundle; the
+/// caller decides where to persist it (e.g. `store_into` a `PreKeyStore`).
+/// Contains private keys: keep it in secure storage only. Private key fields
+/// are zeroized when the material is dropped.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct PrivatePreKeyMaterial {
+    /// Signed prekey private data
+    pub signed_prekey: SignedPreKeyPrivateJSON,
+    /// One-time prekey private data, in the order their ids were requested
+    pub one_time_prekeys: Vec<OneTimePreKeyPrivateJSON>,
+}
+
+/// Signed prekey private data
+#[derive(Clone, Serialize, Deserialize)]
+pub struct SignedPreKeyPrivateJSON {
+    /// X25519 private key as hex string
+    pub private_key_hex: String,
+    /// Identity signature over the public key, as hex string
+    pub signature_hex: String,
+    /// Key ID
+    pub key_id: u32,
+}
+
+/// One-time prekey private data
+#[derive(Clone, Serialize, Deserialize)]
+pub struct OneTimePreKeyPrivateJSON {
+    /// X25519 private key as hex string
+    pub private_key_hex: String,
+    /// Key ID
+    pub key_id: u32,
+}
+
+impl Drop for SignedPreKeyPrivateJSON {
+    fn drop(&mut self) {
+        self.private_key_hex.zeroize();
+    }
+}
+
+impl Drop for OneTimePreKeyPrivateJSON {
+    fn drop(&mut self) {
+        self.private_key_hex.zeroize();
+    }
+}
+
+impl PrivatePreKeyMaterial {
+    /// Rebuild the signed prekey pair
+    /// 
+    /// # Returns
+    /// SignedPreKeyPair, or an error for malformed hex or an invalid id
+    pub fn signed_prekey_pair(&self) -> Result<SignedPreKeyPair> {
+        use ed25519_dalek::Signature;
+        
+        let private_key = parse_hex_32(&self.signed_prekey.private_key_hex)?;
+        let signature = Signature::from_bytes(&parse_hex_64(&self.signed_prekey.signature_hex)?);
+        SignedPreKeyPair::from_private_bytes(self.signed_prekey.key_id, private_key, signature)
+    }
+
+    /// Derive the public half of every one-time prekey, e.g. to upload them all
+    /// 
+    /// # Returns
+    /// One OneTimePreKeyJSON per one-time prekey, or `SerializationError` for malformed hex
+    pub fn one_time_prekey_publics(&self) -> Result<Vec<OneTimePreKeyJSON>> {
+        use x25519_dalek::{PublicKey, StaticSecret};
+        
+        self.one_time_prekeys.iter().map(|otp| {
+            let public_key = PublicKey::from(&StaticSecret::from(parse_hex_32(&otp.private_key_hex)?));
+            Ok(OneTimePreKeyJSON {
+                public_key_hex: hex::encode(public_key.as_bytes()),
+                key_id: otp.key_id,
+            })
+        }).collect()
+    }
+
+    /// Persist every prekey into a prekey store
+    /// 
+    /// # Arguments
+    /// * `store` - Store the responder later loads the prekeys from
+    /// 
+    /// # Returns
+    /// Ok(()), or an error if the material is malformed (nothing is stored then)
+    pub fn store_into(&self, store: &dyn PreKeyStore) -> Result<()> {
+        let signed_prekey = self.signed_prekey_pair()?;
+        let one_time_prekeys = self.one_time_prekeys.iter()
+            .map(|otp| Ok((OneTimePreKeyId(otp.key_id), parse_hex_32(&otp.private_key_hex)?)))
+            .collect::<Result<Vec<_>>>()?;
+        
+        store.put_signed(SignedPreKeyId(signed_prekey.key_id()), signed_prekey);
+        store.extend_one_time(one_time_prekeys);
+        Ok(())
+    }
+}
+
+/// Generate a signed prekey and one-time prekeys, returning public and private halves
+/// 
+/// Pure: nothing is persisted. The bundle carries the first one-time prekey
+/// (if any); publish the rest with `PrivatePreKeyMaterial::one_time_prekey_publics`.
+/// 
+/// # Arguments
+/// * `identity` - Identity key pair that signs the signed prekey
+/// * `signed_prekey_id` - ID for the signed prekey
+/// * `one_time_prekey_ids` - IDs for the one-time prekeys (may be empty)
+/// 
+/// # Returns
+/// (bundle, private material), or `KeyGenerationError` for an invalid id
+pub fn generate_prekey_material(
+    identity: &IdentityKeyPair,
+    signed_prekey_id: u32,
+    one_time_prekey_ids: &[u32],
+) -> Result<(PreKeyBundleJSON, PrivatePreKeyMaterial)> {
+    let signed_prekey = SignedPreKeyPair::generate(signed_prekey_id, identity)?;
+    let one_time_prekeys = one_time_prekey_ids.iter()
+        .map(|&id| OneTimePreKeyPair::generate(id))
+        .collect::<Result<Vec<_>>>()?;
+    
+    let bundle = PreKeyBundle::new(
+        identity.public_key_hex(),
+        identity.verifying_key(),
+        SignedPreKey::from(&signed_prekey),
+        one_time_prekeys.first().map(OneTimePreKey::from),
+    );
+    
+    let material = PrivatePreKeyMaterial {
+        signed_prekey: SignedPreKeyPrivateJSON {
+            private_key_hex: hex::encode(signed_prekey.private_key_bytes()),
+            signature_hex: signed_prekey.signature_hex(),
+            key_id: signed_prekey.key_id(),
+        },
+        one_time_prekeys: one_time_prekeys.iter().map(|otp| OneTimePreKeyPrivateJSON {
+            private_key_hex: hex::encode(otp.private_key_bytes()),
+            key_id: otp.key_id(),
+        }).collect(),
+    };
+    
+    Ok((PreKeyBundleJSON::from_prekey_bundle(&bundle), material))
 }
 
 // Helper functions for FFI
@@ -1,7 +1,10 @@
 pub mod session;
 pub mod keys;
 pub mod api;
+pub mod store;
 
-pub use session::{Session, SessionRegistry, SessionId, generate_session_id};
-pub use keys::{IdentityKeyPairBytes, PreKeyBundleJSON, get_public_key_hex};
+pub use session::{Session, SessionRegistry, SessionStats, SessionId, SESSION_STATE_VERSION, deterministic_session_id, generate_session_id};
+pub use keys::{CompactIdentityBytes, IdentityKeyPairBytes, PreKeyBundleJSON, get_public_key_hex};
+pub use keys::{generate_prekey_material, PrivatePreKeyMaterial};
+pub use store::{InMemoryPreKeyStore, PreKeyStore, SessionStore};
 
@@ -1,12 +1,27 @@
+use crate::encoding::parse_hex_32;
 use crate::error::{E2EEError, Result};
-use crate::ratchet::DoubleRatchet;
+use crate::ratchet::{
+    DecryptSource, DecryptedMessage, DoubleRatchet, DoubleRatchetBuilder, PublicRatchetState, RatchetState,
+    SuiteDescriptor,
+};
+use crate::x3dh::{X3DHResponseResult, X3DHResult};
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
-use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
 use uuid::Uuid;
+use x25519_dalek::{PublicKey, StaticSecret};
+use zeroize::Zeroizing;
 
 /// Session ID type (UUID)
 pub type SessionId = String;
 
+pub use crate::ratchet::SESSION_STATE_VERSION;
+
+/// HKDF info for the responder's first DH key after `Session::rebase`
+const REBASE_DH_SEED_INFO: &[u8] = b"rebase-dh-seed";
+
 /// Session containing DoubleRatchet state
 /// 
 /// Wraps DoubleRatchet and provides thread-safe access through Arc<Mutex<>>.
@@ -15,6 +30,23 @@ pub struct Session {
     pub double_ratchet: Arc<Mutex<DoubleRatchet>>,
     /// Session ID
     pub id: SessionId,
+    /// Routing ID shared by both parties, derived from the X3DH shared secret
+    routing_id: String,
+    /// Peer's identity public key (hex), when known
+    peer_identity_hex: Option<String>,
+    /// Number of messages encrypted successfully
+    messages_sent: AtomicU64,
+    /// Number of messages decrypted successfully
+    messages_received: AtomicU64,
+}
+
+/// Message counters of a session, for metrics and quota checks
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SessionStats {
+    /// Messages encrypted successfully since the session was created
+    pub messages_sent: u64,
+    /// Messages decrypted successfully since the session was created
+    pub messages_received: u64,
 }
 
 impl Session {
@@ -33,11 +65,118 @@ impl Session {
         session_id: SessionId,
     ) -> Result<Self> {
         let double_ratchet = DoubleRatchet::from_shared_secret(&shared_secret, is_initiator)?;
+        Self::from_double_ratchet(double_ratchet, &shared_secret, session_id)
+    }
+
+    /// Create an initiator session from an X3DH result
+    /// 
+    /// The Double Ratchet is seeded with the responder's signed prekey, so DH
+    /// ratchets stay in step with a session made by `from_x3dh_response`.
+    /// 
+    /// # Arguments
+    /// * `result` - Result of `X3DHInitiator::initiate`
+    /// * `session_id` - Session ID (UUID string)
+    /// 
+    /// # Returns
+    /// New Session instance
+    pub fn from_x3dh_result(result: &X3DHResult, session_id: SessionId) -> Result<Self> {
+        let double_ratchet = match result.ratchet_public_key {
+            Some(ref ratchet_public_key) => DoubleRatchet::from_x3dh_initiator(&result.shared_secret, ratchet_public_key)?,
+            None => DoubleRatchet::from_shared_secret(&result.shared_secret, true)?,
+        };
+        Self::from_double_ratchet(double_ratchet, &result.shared_secret, session_id)
+    }
+
+    /// Restore a session from a saved ratchet state
+    /// 
+    /// The state does not hold the shared secret, so the routing ID saved
+    /// alongside it is passed in. When the caller knows which role the slot
+    /// it restores into belongs to, a state saved by the other role is
+    /// refused here instead of surfacing later as failed decrypts.
+    /// 
+    /// # Arguments
+    /// * `state` - Snapshot from `DoubleRatchet::to_state`
+    /// * `routing_id` - Routing ID of the original session
+    /// * `expected_is_initiator` - Role the caller expects, or None to skip the check
+    /// * `session_id` - Session ID (UUID string)
+    /// 
+    /// # Returns
+    /// Restored Session, or `StateError` if the saved role is not the expected one
+    pub fn from_state(
+        state: &RatchetState,
+        routing_id: String,
+        expected_is_initiator: Option<bool>,
+        session_id: SessionId,
+    ) -> Result<Self> {
+        let role_name = |is_initiator: bool| if is_initiator { "initiator" } else { "responder" };
+        if let Some(expected) = expected_is_initiator {
+            if expected != state.is_initiator() {
+                return Err(E2EEError::StateError(format!(
+                    "role mismatch on import: state belongs to the {}, expected the {}",
+                    role_name(state.is_initiator()),
+                    role_name(expected)
+                )));
+            }
+        }
         
-        Ok(Self {
+        Ok(Self::with_routing_id(DoubleRatchet::from_state(state)?, routing_id, session_id))
+    }
+
+    fn from_double_ratchet(double_ratchet: DoubleRatchet, shared_secret: &[u8; 32], session_id: SessionId) -> Result<Self> {
+        let routing_id = derive_routing_id(shared_secret)?;
+        Ok(Self::with_routing_id(double_ratchet, routing_id, session_id))
+    }
+
+    fn with_routing_id(double_ratchet: DoubleRatchet, routing_id: String, session_id: SessionId) -> Self {
+        Self {
             double_ratchet: Arc::new(Mutex::new(double_ratchet)),
             id: session_id,
-        })
+            routing_id,
+            peer_identity_hex: None,
+            messages_sent: AtomicU64::new(0),
+            messages_received: AtomicU64::new(0),
+        }
+    }
+
+    /// Create a responder session from an X3DH response, recording who the peer is
+    /// 
+    /// # Arguments
+    /// * `result` - Result of `X3DHResponder::respond`
+    /// * `peer_identity_hex` - Identity the caller believes it is talking to
+    /// * `session_id` - Session ID (UUID string)
+    /// 
+    /// # Returns
+    /// New Session instance, or `ProtocolError` if `peer_identity_hex` is not
+    /// the identity the shared secret was derived against
+    pub fn from_x3dh_response(
+        result: &X3DHResponseResult,
+        peer_identity_hex: &str,
+        session_id: SessionId,
+    ) -> Result<Self> {
+        if parse_hex_32(peer_identity_hex)? != parse_hex_32(&result.peer_identity_hex)? {
+            return Err(E2EEError::ProtocolError(
+                "Peer identity does not match the X3DH response".to_string(),
+            ));
+        }
+        
+        let double_ratchet = match result.ratchet_private_key {
+            Some(ref ratchet_private_key) => DoubleRatchet::from_x3dh_responder(&result.shared_secret, ratchet_private_key)?,
+            None => DoubleRatchet::from_shared_secret(&result.shared_secret, false)?,
+        };
+        let mut session = Self::from_double_ratchet(double_ratchet, &result.shared_secret, session_id)?;
+        session.peer_identity_hex = Some(result.peer_identity_hex.clone());
+        Ok(session)
+    }
+
+    /// Lock the Double Ratchet
+    /// 
+    /// A panic while the ratchet was locked leaves its state undefined, so a
+    /// poisoned lock is reported as `StateError` rather than recovered; the
+    /// FFI turns it into an error value instead of panicking across the boundary.
+    fn lock_ratchet(&self) -> Result<MutexGuard<'_, DoubleRatchet>> {
+        self.double_ratchet
+            .lock()
+            .map_err(|e| E2EEError::StateError(format!("Failed to lock DoubleRatchet: {}", e)))
     }
 
     /// Get the session ID
@@ -45,6 +184,20 @@ impl Session {
         &self.id
     }
 
+    /// Get the routing ID
+    /// 
+    /// Unlike the session ID, which is a random local UUID, the routing ID is
+    /// computed identically by initiator and responder, so both parties can
+    /// use it to address the conversation on a relay.
+    pub fn routing_id(&self) -> &str {
+        &self.routing_id
+    }
+
+    /// Get the peer's identity public key (hex), if the session recorded it
+    pub fn peer_identity_hex(&self) -> Option<&str> {
+        self.peer_identity_hex.as_deref()
+    }
+
     /// Encrypt a message using this session's Double Ratchet
     /// 
     /// # Arguments
@@ -53,11 +206,31 @@ impl Session {
     /// # Returns
     /// MessageEnvelope containing encrypted message and metadata
     pub fn encrypt(&self, plaintext: &[u8]) -> Result<crate::message::MessageEnvelope> {
-        let mut dr = self.double_ratchet
-            .lock()
-            .map_err(|e| E2EEError::StateError(format!("Failed to lock DoubleRatchet: {}", e)))?;
+        let mut dr = self.lock_ratchet()?;
+        
+        let envelope = dr.encrypt_envelope(plaintext)?;
+        self.messages_sent.fetch_add(1, Ordering::Relaxed);
+        Ok(envelope)
+    }
+
+    /// Encrypt a message and snapshot the resulting ratchet state atomically
+    /// 
+    /// Both happen under a single lock, so the returned state always reflects
+    /// exactly this send; persisting it means a crash can never roll the
+    /// sending counter back to a value that was already used.
+    /// 
+    /// # Arguments
+    /// * `plaintext` - Plaintext message to encrypt
+    /// 
+    /// # Returns
+    /// The envelope and the ratchet state to persist after sending it
+    pub fn encrypt_and_snapshot(&self, plaintext: &[u8]) -> Result<(crate::message::MessageEnvelope, RatchetState)> {
+        let mut dr = self.lock_ratchet()?;
         
-        dr.encrypt_envelope(plaintext)
+        let envelope = dr.encrypt_envelope(plaintext)?;
+        self.messages_sent.fetch_add(1, Ordering::Relaxed);
+        let state = dr.to_state()?;
+        Ok((envelope, state))
     }
 
     /// Decrypt a message using this session's Double Ratchet
@@ -68,26 +241,184 @@ impl Session {
     /// # Returns
     /// Decrypted plaintext message
     pub fn decrypt(&self, envelope: &crate::message::MessageEnvelope) -> Result<Vec<u8>> {
+        Ok(self.decrypt_full(envelope)?.plaintext)
+    }
+
+    /// Decrypt a message and return the plaintext with its verified metadata
+    /// 
+    /// # Arguments
+    /// * `envelope` - MessageEnvelope containing encrypted message
+    /// 
+    /// # Returns
+    /// DecryptedMessage with plaintext, timestamp and message number
+    pub fn decrypt_full(&self, envelope: &crate::message::MessageEnvelope) -> Result<DecryptedMessage> {
+        let mut dr = self.lock_ratchet()?;
+        
+        let decrypted = dr.decrypt_envelope_full(envelope)?;
+        // A duplicate served from the idempotent-decrypt cache is not a new message
+        if decrypted.source != DecryptSource::DuplicateCache {
+            self.messages_received.fetch_add(1, Ordering::Relaxed);
+        }
+        Ok(decrypted)
+    }
+
+    /// Get the received message numbers lower than `up_to` that are still missing
+    /// 
+    /// # Arguments
+    /// * `up_to` - Exclusive upper bound on message numbers
+    /// 
+    /// # Returns
+    /// Missing message numbers in ascending order
+    pub fn missing_messages(&self, up_to: u64) -> Result<Vec<u64>> {
+        let dr = self.lock_ratchet()?;
+        
+        Ok(dr.missing_before(up_to))
+    }
+
+    /// Check whether a message number has been decrypted in this session
+    /// 
+    /// # Arguments
+    /// * `message_number` - Header message number (starting at 1)
+    pub fn has_received(&self, message_number: u64) -> Result<bool> {
+        Ok(self.lock_ratchet()?.has_received(message_number))
+    }
+
+    /// Close the session and zeroize its ratchet in place
+    /// 
+    /// Takes effect for every `Arc` clone of this session, not just the one in
+    /// the registry: later encrypt/decrypt calls fail with `StateError`. A
+    /// poisoned lock does not stop the wipe.
+    pub fn close(&self) {
+        self.double_ratchet
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .close();
+    }
+
+    /// Close the session and confirm its ratchet was zeroized
+    /// 
+    /// # Returns
+    /// true if the ratchet is closed and holds no key material afterwards
+    pub fn close_and_verify(&self) -> bool {
         let mut dr = self.double_ratchet
             .lock()
-            .map_err(|e| E2EEError::StateError(format!("Failed to lock DoubleRatchet: {}", e)))?;
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        dr.close();
+        dr.is_closed() && dr.is_wiped()
+    }
+
+    /// Reset the session's ratchet onto a new shared secret, keeping the session
+    /// 
+    /// For peers that agreed on a fresh secret out of band, e.g. after a
+    /// key-compromise recovery. The old ratchet is zeroized and replaced by one
+    /// built with the old ratchet's `config`, so counters and skipped keys
+    /// start over; the session id, routing id and peer identity are kept.
+    /// Both sides derive the responder's first DH key from the new secret and
+    /// are seeded with it as after X3DH, so the initiator's first message
+    /// already runs through a DH ratchet.
+    /// 
+    /// # Arguments
+    /// * `new_shared_secret` - 32-byte secret agreed with the peer
+    /// * `is_initiator` - Role of this side in the new ratchet (the peer takes the other one)
+    /// 
+    /// # Returns
+    /// Ok(()), or `StateError` if the session is closed
+    pub fn rebase(&self, new_shared_secret: [u8; 32], is_initiator: bool) -> Result<()> {
+        let mut dr = self.lock_ratchet()?;
+        if dr.is_closed() {
+            return Err(E2EEError::StateError("session closed".to_string()));
+        }
         
-        dr.decrypt_envelope(envelope)
+        let builder = DoubleRatchetBuilder::from_config(&dr.config());
+        let seed_private = StaticSecret::from(*Zeroizing::new(
+            crate::kdf::hkdf_32(&new_shared_secret, b"", REBASE_DH_SEED_INFO)?,
+        ));
+        let rebased = if is_initiator {
+            builder.build_x3dh_initiator(&new_shared_secret, PublicKey::from(&seed_private).as_bytes())?
+        } else {
+            builder.build_x3dh_responder(&new_shared_secret, &seed_private)?
+        };
+        
+        // Dropping the old ratchet zeroizes its keys
+        *dr = rebased;
+        Ok(())
+    }
+
+    /// Check whether the session has completed its first DH ratchet
+    /// 
+    /// # Returns
+    /// true once a DH ratchet step has run, false for a one-directional session
+    pub fn has_ratcheted(&self) -> Result<bool> {
+        Ok(self.lock_ratchet()?.has_ratcheted())
+    }
+
+    /// Return the same plaintext for duplicate deliveries of recent envelopes
+    /// 
+    /// See `DoubleRatchet::enable_idempotent_decrypt`.
+    /// 
+    /// # Arguments
+    /// * `window` - Number of recent messages to remember (0 disables the cache)
+    pub fn enable_idempotent_decrypt(&self, window: usize) -> Result<()> {
+        self.lock_ratchet()?.enable_idempotent_decrypt(window);
+        Ok(())
+    }
+
+    /// Enable or disable acceptance of Signal-style 33-byte DH keys
+    /// 
+    /// # Arguments
+    /// * `accept` - true to accept `0x05`-prefixed keys in incoming headers
+    pub fn set_accept_prefixed_keys(&self, accept: bool) -> Result<()> {
+        self.lock_ratchet()?.set_accept_prefixed_keys(accept);
+        Ok(())
+    }
+
+    /// Get the number of messages that can be sent before a DH ratchet is forced
+    /// 
+    /// # Returns
+    /// Remaining sends, or None if the session has no per-chain message cap
+    pub fn messages_until_rekey(&self) -> Result<Option<u64>> {
+        Ok(self.lock_ratchet()?.messages_until_rekey())
+    }
+
+    /// Get the cipher suite the session's ratchet uses
+    pub fn current_suite(&self) -> Result<SuiteDescriptor> {
+        Ok(self.lock_ratchet()?.current_suite())
+    }
+
+    /// Export the non-secret ratchet state for comparison with the peer
+    pub fn public_state(&self) -> Result<PublicRatchetState> {
+        Ok(self.lock_ratchet()?.public_state())
+    }
+
+    /// Get the session's message counters
+    /// 
+    /// Only successful encrypts and decrypts are counted. The counters cover
+    /// the whole session, including any time before a `rebase`.
+    pub fn stats(&self) -> SessionStats {
+        SessionStats {
+            messages_sent: self.messages_sent.load(Ordering::Relaxed),
+            messages_received: self.messages_received.load(Ordering::Relaxed),
+        }
     }
 }
 
 /// Thread-safe registry for managing multiple sessions
 /// 
-/// Uses Arc<Mutex<>> for thread-safe access to the session map.
+/// Uses Arc<RwLock<>> for thread-safe access to the session map: lookups
+/// share a read lock and never fail on a poisoned lock.
+/// 
+/// The FFI goes through the `try_*` methods, which report a missing or
+/// already registered session as an `E2EEError`; the infallible methods
+/// remain for internal use.
 pub struct SessionRegistry {
-    sessions: Arc<Mutex<HashMap<SessionId, Arc<Session>>>>,
+    sessions: Arc<RwLock<HashMap<SessionId, Arc<Session>>>>,
 }
 
 impl SessionRegistry {
     /// Create a new session registry
     pub fn new() -> Self {
         Self {
-            sessions: Arc::new(Mutex::new(HashMap::new())),
+            sessions: Arc::new(RwLock::new(HashMap::new())),
         }
     }
 
@@ -97,10 +428,28 @@ impl SessionRegistry {
     /// * `session_id` - Session ID
     /// * `session` - Session instance
     pub fn register(&self, session_id: SessionId, session: Arc<Session>) {
-        let mut sessions = self.sessions
-            .lock()
-            .expect("Failed to lock session registry");
+        let mut sessions = self.sessions.write();
+        sessions.insert(session_id, session);
+    }
+
+    /// Register a new session, refusing to replace one registered under the same ID
+    /// 
+    /// The check and the insert happen under one write lock, so two callers
+    /// racing for the same (e.g. deterministic) ID cannot both succeed.
+    /// 
+    /// # Arguments
+    /// * `session_id` - Session ID
+    /// * `session` - Session instance
+    /// 
+    /// # Returns
+    /// Ok(()), or `StateError` if a session is already registered under the ID
+    pub fn try_register(&self, session_id: SessionId, session: Arc<Session>) -> Result<()> {
+        let mut sessions = self.sessions.write();
+        if sessions.contains_key(&session_id) {
+            return Err(E2EEError::StateError(format!("Session {} is already registered", session_id)));
+        }
         sessions.insert(session_id, session);
+        Ok(())
     }
 
     /// Get a session by ID
@@ -111,10 +460,20 @@ impl SessionRegistry {
     /// # Returns
     /// Some(Arc<Session>) if found, None otherwise
     pub fn get(&self, session_id: &SessionId) -> Option<Arc<Session>> {
-        let sessions = self.sessions
-            .lock()
-            .expect("Failed to lock session registry");
-        sessions.get(session_id).map(|s| Arc::clone(s))
+        let sessions = self.sessions.read();
+        sessions.get(session_id).map(Arc::clone)
+    }
+
+    /// Get a session by ID, failing with `SessionNotFound` if it is not registered
+    /// 
+    /// # Arguments
+    /// * `session_id` - Session ID
+    /// 
+    /// # Returns
+    /// Arc<Session> if found, `E2EEError::SessionNotFound` with the id otherwise
+    pub fn try_get(&self, session_id: &SessionId) -> Result<Arc<Session>> {
+        self.get(session_id)
+            .ok_or_else(|| E2EEError::SessionNotFound(session_id.clone()))
     }
 
     /// Remove a session by ID
@@ -122,12 +481,23 @@ impl SessionRegistry {
     /// # Arguments
     /// * `session_id` - Session ID
     pub fn remove(&self, session_id: &SessionId) {
-        let mut sessions = self.sessions
-            .lock()
-            .expect("Failed to lock session registry");
+        let mut sessions = self.sessions.write();
         sessions.remove(session_id);
     }
 
+    /// Remove a session by ID, failing with `SessionNotFound` if it is not registered
+    /// 
+    /// # Arguments
+    /// * `session_id` - Session ID
+    /// 
+    /// # Returns
+    /// The removed session, or `E2EEError::SessionNotFound` with the id
+    pub fn try_remove(&self, session_id: &SessionId) -> Result<Arc<Session>> {
+        let mut sessions = self.sessions.write();
+        sessions.remove(session_id)
+            .ok_or_else(|| E2EEError::SessionNotFound(session_id.clone()))
+    }
+
     /// Check if a session exists
     /// 
     /// # Arguments
@@ -136,9 +506,7 @@ impl SessionRegistry {
     /// # Returns
     /// true if session exists, false otherwise
     pub fn contains(&self, session_id: &SessionId) -> bool {
-        let sessions = self.sessions
-            .lock()
-            .expect("Failed to lock session registry");
+        let sessions = self.sessions.read();
         sessions.contains_key(session_id)
     }
 }
@@ -149,8 +517,39 @@ impl Default for SessionRegistry {
     }
 }
 
+/// Derive the routing ID from the X3DH shared secret
+/// 
+/// HKDF-SHA256 with the "routing" label keeps the routing ID independent of
+/// the chain keys derived from the same secret.
+/// 
+/// # Returns
+/// Routing ID as a 64-character hex string
+fn derive_routing_id(shared_secret: &[u8; 32]) -> Result<String> {
+    let routing_id = crate::kdf::hkdf_32(shared_secret, &[], b"routing")?;
+    Ok(hex::encode(routing_id))
+}
+
 /// Generate a new session ID (UUID string)
 pub fn generate_session_id() -> SessionId {
     Uuid::new_v4().to_string()
 }
 
+/// Derive a session ID from a routing ID
+/// 
+/// Both parties of a conversation share the routing ID, so they derive the
+/// same session ID and can key their UI by it. The ID is a version 8
+/// (custom) UUID built from an HKDF of the routing ID, so it is never
+/// mistaken for a random version 4 ID.
+/// 
+/// # Arguments
+/// * `routing_id` - Routing ID of the session (`Session::routing_id`)
+/// 
+/// # Returns
+/// Session ID (UUID string)
+pub fn deterministic_session_id(routing_id: &str) -> Result<SessionId> {
+    let derived = crate::kdf::hkdf_32(routing_id.as_bytes(), &[], b"session-id")?;
+    let mut bytes = [0u8; 16];
+    bytes.copy_from_slice(&derived[..16]);
+    Ok(Uuid::new_v8(bytes).to_string())
+}
+
@@ -0,0 +1,155 @@
+use crate::error::{E2EEError, Result};
+use crate::ffi::session::{Session, SessionId, SessionRegistry};
+use crate::keys::prekey::{OneTimePreKeyId, SignedPreKeyId, SignedPreKeyPair};
+use parking_lot::RwLock;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// Storage backend for the responder's prekeys
+/// 
+/// The FFI persists prekeys through this trait, so embedders can keep them in
+/// a platform secure enclave (Keychain, Keystore) instead of process memory.
+/// Set a backend with `ffi::api::set_prekey_store_backend`.
+/// 
+/// Signed and one-time prekeys are looked up by their own id types, so a
+/// signed prekey and a one-time prekey may share the same numeric id.
+pub trait PreKeyStore: Send + Sync {
+    /// Get a signed prekey pair by id
+    fn get_signed(&self, id: SignedPreKeyId) -> Option<SignedPreKeyPair>;
+
+    /// Store a signed prekey pair under its id
+    fn put_signed(&self, id: SignedPreKeyId, prekey: SignedPreKeyPair);
+
+    /// Get the private key bytes of a one-time prekey without consuming it
+    /// 
+    /// Used to attempt a handshake; the prekey is only taken once it succeeds.
+    fn get_one_time(&self, id: OneTimePreKeyId) -> Option<[u8; 32]>;
+
+    /// Remove and return the private key bytes of a one-time prekey
+    /// 
+    /// One-time prekeys are consumed by the handshake that uses them.
+    fn take_one_time(&self, id: OneTimePreKeyId) -> Option<[u8; 32]>;
+
+    /// Store the private key bytes of a one-time prekey under its id
+    fn put_one_time(&self, id: OneTimePreKeyId, private_key: [u8; 32]);
+
+    /// Store many signed prekey pairs at once
+    /// 
+    /// The default calls `put_signed` for each entry; backends with a batch
+    /// write can override it.
+    fn extend_signed(&self, prekeys: Vec<(SignedPreKeyId, SignedPreKeyPair)>) {
+        for (id, prekey) in prekeys {
+            self.put_signed(id, prekey);
+        }
+    }
+
+    /// Store many one-time prekeys at once (see `extend_signed`)
+    fn extend_one_time(&self, private_keys: Vec<(OneTimePreKeyId, [u8; 32])>) {
+        for (id, private_key) in private_keys {
+            self.put_one_time(id, private_key);
+        }
+    }
+}
+
+/// Storage backend for established sessions
+pub trait SessionStore: Send + Sync {
+    /// Register a session under its id
+    fn register(&self, session_id: SessionId, session: Arc<Session>);
+
+    /// Get a session by id
+    fn get(&self, session_id: &SessionId) -> Option<Arc<Session>>;
+
+    /// Remove a session by id
+    fn remove(&self, session_id: &SessionId);
+}
+
+/// Default in-memory prekey store
+/// 
+/// RwLock (parking_lot, no poisoning): lookups take read locks, inserts take write locks.
+#[derive(Default)]
+pub struct InMemoryPreKeyStore {
+    signed: RwLock<HashMap<SignedPreKeyId, SignedPreKeyPair>>,
+    // Store only private key bytes of one-time prekeys; reconstruct when needed
+    one_time: RwLock<HashMap<OneTimePreKeyId, [u8; 32]>>,
+}
+
+impl InMemoryPreKeyStore {
+    /// Create an empty in-memory prekey store
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Copy every prekey of another store into this one
+    /// 
+    /// Used when rebalancing prekeys across key-server shards. An id present
+    /// in both stores must hold the same key; otherwise nothing is merged.
+    /// 
+    /// # Arguments
+    /// * `other` - Store to copy prekeys from (left unchanged)
+    /// 
+    /// # Returns
+    /// Ok(()), or `StateError` naming the first id that holds different keys
+    pub fn merge(&self, other: &Self) -> Result<()> {
+        // Snapshot the other store first, so two stores merging into each other cannot deadlock
+        let other_signed = other.signed.read().clone();
+        let other_one_time = other.one_time.read().clone();
+        let mut signed = self.signed.write();
+        let mut one_time = self.one_time.write();
+        
+        // Check every collision before inserting anything
+        for (id, prekey) in other_signed.iter() {
+            if let Some(existing) = signed.get(id) {
+                if existing.private_key_bytes() != prekey.private_key_bytes()
+                    || existing.signature_bytes() != prekey.signature_bytes()
+                {
+                    return Err(E2EEError::StateError(format!("signed prekey id {} collides with a different key", id)));
+                }
+            }
+        }
+        for (id, private_key) in other_one_time.iter() {
+            if one_time.get(id).is_some_and(|existing| existing != private_key) {
+                return Err(E2EEError::StateError(format!("one-time prekey id {} collides with a different key", id)));
+            }
+        }
+        
+        signed.extend(other_signed);
+        one_time.extend(other_one_time);
+        Ok(())
+    }
+}
+
+impl PreKeyStore for InMemoryPreKeyStore {
+    fn get_signed(&self, id: SignedPreKeyId) -> Option<SignedPreKeyPair> {
+        self.signed.read().get(&id).cloned()
+    }
+
+    fn put_signed(&self, id: SignedPreKeyId, prekey: SignedPreKeyPair) {
+        self.signed.write().insert(id, prekey);
+    }
+
+    fn get_one_time(&self, id: OneTimePreKeyId) -> Option<[u8; 32]> {
+        self.one_time.read().get(&id).copied()
+    }
+
+    fn take_one_time(&self, id: OneTimePreKeyId) -> Option<[u8; 32]> {
+        self.one_time.write().remove(&id)
+    }
+
+    fn put_one_time(&self, id: OneTimePreKeyId, private_key: [u8; 32]) {
+        self.one_time.write().insert(id, private_key);
+    }
+}
+
+impl SessionStore for SessionRegistry {
+    fn register(&self, session_id: SessionId, session: Arc<Session>) {
+        SessionRegistry::register(self, session_id, session)
+    }
+
+    fn get(&self, session_id: &SessionId) -> Option<Arc<Session>> {
+        SessionRegistry::get(self, session_id)
+    }
+
+    fn remove(&self, session_id: &SessionId) {
+        SessionRegistry::remove(self, session_id)
+    }
+}
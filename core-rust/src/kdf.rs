@@ -0,0 +1,66 @@
+//! HKDF-SHA256 key derivation shared by the handshake, the ratchet and the session layer
+//! 
+//! Every derivation in the crate goes through these helpers so salt and info
+//! handling stay identical everywhere. An empty salt is equivalent to the
+//! RFC 5869 default of `HashLen` zero bytes.
+
+use crate::error::{E2EEError, Result};
+
+/// Maximum HKDF-SHA256 output length (255 * HashLen)
+pub const MAX_OUTPUT_LEN: usize = 255 * 32;
+
+/// Output length wrapper so `ring` can expand to an arbitrary size
+struct OutputLen(usize);
+
+impl ring::hkdf::KeyType for OutputLen {
+    fn len(&self) -> usize {
+        self.0
+    }
+}
+
+/// HKDF-SHA256 extract-then-expand
+/// 
+/// # Arguments
+/// * `ikm` - Input key material
+/// * `salt` - Salt (may be empty)
+/// * `info` - Context/label bound into the output
+/// * `out_len` - Number of output bytes (at most `MAX_OUTPUT_LEN`)
+/// 
+/// # Returns
+/// `out_len` bytes of output key material, or `CryptoError` if `out_len` is too large
+pub fn hkdf_expand(ikm: &[u8], salt: &[u8], info: &[u8], out_len: usize) -> Result<Vec<u8>> {
+    let prk = ring::hkdf::Salt::new(ring::hkdf::HKDF_SHA256, salt).extract(ikm);
+
+    let info_array = [info];
+    let okm = prk.expand(&info_array, OutputLen(out_len))
+        .map_err(|e| E2EEError::CryptoError(format!("HKDF expand failed: {}", e)))?;
+
+    let mut output = vec![0u8; out_len];
+    okm.fill(&mut output)
+        .map_err(|e| E2EEError::CryptoError(format!("HKDF fill failed: {}", e)))?;
+
+    Ok(output)
+}
+
+/// HKDF-SHA256 derivation of a single 32-byte key
+/// 
+/// # Arguments
+/// * `ikm` - Input key material
+/// * `salt` - Salt (may be empty)
+/// * `info` - Context/label bound into the output
+/// 
+/// # Returns
+/// 32-byte derived key
+pub fn hkdf_32(ikm: &[u8], salt: &[u8], info: &[u8]) -> Result<[u8; 32]> {
+    let prk = ring::hkdf::Salt::new(ring::hkdf::HKDF_SHA256, salt).extract(ikm);
+
+    let info_array = [info];
+    let okm = prk.expand(&info_array, ring::hkdf::HKDF_SHA256)
+        .map_err(|e| E2EEError::CryptoError(format!("HKDF expand failed: {}", e)))?;
+
+    let mut output = [0u8; 32];
+    okm.fill(&mut output)
+        .map_err(|e| E2EEError::CryptoError(format!("HKDF fill failed: {}", e)))?;
+
+    Ok(output)
+}
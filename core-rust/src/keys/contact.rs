@@ -0,0 +1,66 @@
+use crate::clock::{Clock, SystemClock};
+use crate::encoding::parse_hex_32;
+use crate::error::{E2EEError, Result};
+use crate::keys::PreKeyBundle;
+use ed25519_dalek::VerifyingKey;
+use serde::{Deserialize, Serialize};
+
+/// Verified identity record of a peer, cached independently of its prekeys
+/// 
+/// Only created from a bundle whose signed prekey signature checks out, so the
+/// X25519 identity and the Ed25519 verifying key are known to belong together.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Contact {
+    /// Peer's X25519 identity public key (lowercase hex)
+    identity_hex: String,
+    /// Peer's Ed25519 verifying key (lowercase hex)
+    ed25519_verifying_key_hex: String,
+    /// When the bundle was verified (seconds since Unix epoch)
+    verified_at: u64,
+}
+
+impl Contact {
+    /// Create a contact from a prekey bundle after verifying its signature
+    /// 
+    /// # Arguments
+    /// * `bundle` - Peer's prekey bundle
+    /// 
+    /// # Returns
+    /// Contact stamped with the current time, or an error if the signature is invalid
+    pub fn from_verified_bundle(bundle: &PreKeyBundle) -> Result<Self> {
+        Self::from_verified_bundle_with_clock(bundle, &SystemClock)
+    }
+
+    /// Create a contact from a verified prekey bundle, reading the time from `clock`
+    /// 
+    /// # Arguments
+    /// * `bundle` - Peer's prekey bundle
+    /// * `clock` - Time source for `verified_at`
+    pub fn from_verified_bundle_with_clock(bundle: &PreKeyBundle, clock: &dyn Clock) -> Result<Self> {
+        if !bundle.verify_signature()? {
+            return Err(E2EEError::CryptoError("Prekey bundle signature is invalid".to_string()));
+        }
+        
+        Ok(Self {
+            identity_hex: hex::encode(parse_hex_32(bundle.identity_public_hex())?),
+            ed25519_verifying_key_hex: hex::encode(bundle.identity_ed25519_verifying_key().to_bytes()),
+            verified_at: clock.now_secs(),
+        })
+    }
+
+    /// Get the peer's X25519 identity public key (lowercase hex)
+    pub fn identity_hex(&self) -> &str {
+        &self.identity_hex
+    }
+
+    /// Get the peer's Ed25519 verifying key
+    pub fn ed25519_verifying_key(&self) -> Result<VerifyingKey> {
+        VerifyingKey::from_bytes(&parse_hex_32(&self.ed25519_verifying_key_hex)?)
+            .map_err(|e| E2EEError::SerializationError(format!("Failed to parse Ed25519 verifying key: {}", e)))
+    }
+
+    /// Get when the bundle was verified (seconds since Unix epoch)
+    pub fn verified_at(&self) -> u64 {
+        self.verified_at
+    }
+}
@@ -1,6 +1,6 @@
 use rand::rngs::OsRng;
-use rand::RngCore;
-use x25519_dalek::{EphemeralSecret, PublicKey};
+use rand::{CryptoRng, RngCore};
+use x25519_dalek::{EphemeralSecret, PublicKey, StaticSecret};
 use ed25519_dalek::{SigningKey, VerifyingKey, SecretKey};
 
 /// Identity key pair for X3DH protocol
@@ -23,8 +23,18 @@ impl IdentityKeyPair {
     /// Generates both X25519 (for key exchange) and Ed25519 (for signing) key pairs.
     /// Uses `OsRng` for cryptographically secure random number generation.
     pub fn generate() -> Self {
+        Self::generate_with_rng(&mut OsRng)
+    }
+
+    /// Generate a new identity key pair from the given RNG
+    /// 
+    /// A seeded RNG makes the keys reproducible, for known-answer tests only.
+    /// 
+    /// # Arguments
+    /// * `rng` - Cryptographically secure RNG
+    pub fn generate_with_rng<R: RngCore + CryptoRng>(rng: &mut R) -> Self {
         // Generate X25519 key pair for key exchange
-        let private_key = EphemeralSecret::random_from_rng(OsRng);
+        let private_key = EphemeralSecret::random_from_rng(&mut *rng);
         let public_key = PublicKey::from(&private_key);
         
         // Extract scalar bytes from EphemeralSecret using unsafe
@@ -42,8 +52,8 @@ impl IdentityKeyPair {
         // Generate Ed25519 key pair for signing
         // We use a different random seed to ensure independence
         let mut ed25519_secret_bytes = [0u8; 32];
-        OsRng.fill_bytes(&mut ed25519_secret_bytes);
-        let ed25519_secret_key: SecretKey = ed25519_secret_bytes.into();
+        rng.fill_bytes(&mut ed25519_secret_bytes);
+        let ed25519_secret_key: SecretKey = ed25519_secret_bytes;
         let ed25519_signing_key = SigningKey::from_bytes(&ed25519_secret_key);
         
         Self {
@@ -68,18 +78,12 @@ impl IdentityKeyPair {
         hex::encode(self.public_key_bytes())
     }
 
-    /// Get the private key as EphemeralSecret for DH operations
+    /// Get the private key as StaticSecret for DH operations
     /// 
-    /// Creates a new EphemeralSecret from the stored bytes.
-    /// Note: Each call creates a new EphemeralSecret, so this can be used multiple times.
-    pub(crate) fn private_key_as_ephemeral(&self) -> EphemeralSecret {
-        // Reconstruct EphemeralSecret from bytes
-        // This is safe because we're reconstructing from valid scalar bytes
-        unsafe {
-            // We transmute the bytes into EphemeralSecret
-            // This is safe because EphemeralSecret is just a wrapper around [u8; 32]
-            std::mem::transmute::<[u8; 32], EphemeralSecret>(self.private_key_bytes)
-        }
+    /// StaticSecret performs DH by reference, so one value serves every DH
+    /// computation of a handshake.
+    pub(crate) fn private_key_as_static(&self) -> StaticSecret {
+        StaticSecret::from(self.private_key_bytes)
     }
     
     /// Get the private key bytes for serialization/cloning
@@ -122,6 +126,9 @@ impl IdentityKeyPair {
     ) -> crate::error::Result<Self> {
         use crate::error::E2EEError;
         
+        // Reject degenerate secrets before building any key from them
+        reject_weak_secrets(&x25519_private_key, &ed25519_private_key)?;
+        
         // Reconstruct X25519 keys
         let x25519_private = unsafe {
             std::mem::transmute::<[u8; 32], EphemeralSecret>(x25519_private_key)
@@ -136,7 +143,7 @@ impl IdentityKeyPair {
         }
         
         // Reconstruct Ed25519 keys
-        let ed25519_secret_key: SecretKey = ed25519_private_key.into();
+        let ed25519_secret_key: SecretKey = ed25519_private_key;
         let ed25519_signing_key = SigningKey::from_bytes(&ed25519_secret_key);
         let ed25519_verifying_key = ed25519_signing_key.verifying_key();
         
@@ -153,6 +160,82 @@ impl IdentityKeyPair {
             ed25519_signing_key,
         })
     }
+
+    /// Create IdentityKeyPair from existing private key material, e.g. when migrating from another app
+    /// 
+    /// Both public keys are derived from the given secrets, so unlike
+    /// `from_bytes` there is nothing to cross-check.
+    /// 
+    /// # Arguments
+    /// * `ed25519_seed` - Ed25519 signing key seed (32 bytes)
+    /// * `x25519_scalar` - X25519 private scalar (32 bytes)
+    /// 
+    /// # Returns
+    /// IdentityKeyPair using exactly this material, or `KeyGenerationError` for a weak secret
+    pub fn from_ed25519_seed_and_x25519(
+        ed25519_seed: [u8; 32],
+        x25519_scalar: [u8; 32],
+    ) -> crate::error::Result<Self> {
+        reject_weak_secrets(&x25519_scalar, &ed25519_seed)?;
+        
+        let public_key = PublicKey::from(&StaticSecret::from(x25519_scalar));
+        
+        Ok(Self {
+            private_key_bytes: x25519_scalar,
+            public_key,
+            ed25519_signing_key: SigningKey::from_bytes(&ed25519_seed),
+        })
+    }
+}
+
+/// Reject obviously weak identity secrets
+/// 
+/// An all-zero or all-0xFF Ed25519 secret, or an all-zero X25519 scalar, is
+/// what uninitialized or wiped storage looks like, never a generated key.
+/// 
+/// # Returns
+/// `KeyGenerationError` naming the weak key, Ok otherwise
+pub(crate) fn reject_weak_secrets(
+    x25519_private_key: &[u8; 32],
+    ed25519_private_key: &[u8; 32],
+) -> crate::error::Result<()> {
+    use crate::error::E2EEError;
+    
+    if x25519_private_key.iter().all(|&b| b == 0x00) {
+        return Err(E2EEError::KeyGenerationError(
+            "Weak X25519 private key: all-zero scalar".to_string()
+        ));
+    }
+    if ed25519_private_key.iter().all(|&b| b == 0x00) || ed25519_private_key.iter().all(|&b| b == 0xff) {
+        return Err(E2EEError::KeyGenerationError(
+            "Weak Ed25519 private key: all-zero or all-one secret".to_string()
+        ));
+    }
+    
+    Ok(())
+}
+
+/// Render an identity public key as a fingerprint for display and read-aloud comparison
+/// 
+/// The fingerprint is SHA-256 of the key bytes, in uppercase hex split into
+/// 8 space-separated groups of 8 characters. It is deterministic, and hex
+/// case in the input does not matter.
+/// 
+/// # Arguments
+/// * `identity_hex` - X25519 identity public key as hex string
+/// 
+/// # Returns
+/// Grouped fingerprint, or `SerializationError` if `identity_hex` is not 32 bytes of hex
+pub fn identity_fingerprint_display(identity_hex: &str) -> crate::error::Result<String> {
+    let identity_bytes = crate::encoding::parse_hex_32(identity_hex)?;
+    let digest = ring::digest::digest(&ring::digest::SHA256, &identity_bytes);
+    
+    let groups: Vec<String> = digest
+        .as_ref()
+        .chunks(4)
+        .map(hex::encode_upper)
+        .collect();
+    Ok(groups.join(" "))
 }
 
 impl Clone for IdentityKeyPair {
@@ -160,7 +243,7 @@ impl Clone for IdentityKeyPair {
         // We can clone because we store the bytes, not EphemeralSecret
         // For Ed25519 signing key, we need to extract bytes and recreate
         let ed25519_secret_bytes = self.ed25519_signing_key.to_bytes();
-        let ed25519_secret_key: SecretKey = ed25519_secret_bytes.into();
+        let ed25519_secret_key: SecretKey = ed25519_secret_bytes;
         let ed25519_signing_key = SigningKey::from_bytes(&ed25519_secret_key);
         
         Self {
@@ -1,6 +1,10 @@
+pub mod contact;
 pub mod identity;
 pub mod prekey;
 
-pub use identity::IdentityKeyPair;
+pub use contact::Contact;
+pub use identity::{identity_fingerprint_display, IdentityKeyPair};
 pub use prekey::{PreKeyBundle, SignedPreKey, OneTimePreKey, SignedPreKeyPair, OneTimePreKeyPair};
+pub use prekey::{OneTimePreKeyId, SignedPreKeyId};
+pub use prekey::{validate_key_id, verify_bundles, verify_bundles_batch, MAX_KEY_ID};
 
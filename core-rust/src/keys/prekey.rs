@@ -2,7 +2,55 @@ use crate::error::{E2EEError, Result};
 use crate::keys::identity::IdentityKeyPair;
 use ed25519_dalek::{VerifyingKey, Signature, Signer, Verifier};
 use rand::rngs::OsRng;
-use x25519_dalek::{EphemeralSecret, PublicKey};
+use rand::{CryptoRng, RngCore};
+use serde::{Deserialize, Serialize};
+use x25519_dalek::{EphemeralSecret, PublicKey, StaticSecret};
+
+/// Largest valid prekey id
+/// 
+/// Prekey ids run from 1 to 2^24 inclusive; 0 is reserved as "no key" so a
+/// caller using it as a sentinel is caught at generation time.
+pub const MAX_KEY_ID: u32 = 1 << 24;
+
+/// Check that a prekey id is in the valid range `1..=MAX_KEY_ID`
+/// 
+/// # Arguments
+/// * `key_id` - Prekey id to check
+/// 
+/// # Returns
+/// Ok(()), or `KeyGenerationError` for 0 or an id above `MAX_KEY_ID`
+pub fn validate_key_id(key_id: u32) -> Result<()> {
+    if key_id == 0 || key_id > MAX_KEY_ID {
+        return Err(E2EEError::KeyGenerationError(format!(
+            "Invalid prekey id {} (must be 1..={})",
+            key_id, MAX_KEY_ID
+        )));
+    }
+    Ok(())
+}
+
+/// Id of a signed prekey
+/// 
+/// Signed and one-time prekeys have independent id spaces: the same number
+/// may name one of each. Distinct types keep a lookup from crossing them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SignedPreKeyId(pub u32);
+
+/// Id of a one-time prekey (see `SignedPreKeyId`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct OneTimePreKeyId(pub u32);
+
+impl std::fmt::Display for SignedPreKeyId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl std::fmt::Display for OneTimePreKeyId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
 
 /// Signed prekey pair with Ed25519 signature
 /// 
@@ -20,11 +68,30 @@ impl SignedPreKeyPair {
     /// Generate a new signed prekey pair and sign it with the identity key
     /// 
     /// # Arguments
-    /// * `key_id` - Unique identifier for this prekey
+    /// * `key_id` - Unique identifier for this prekey (`1..=MAX_KEY_ID`)
     /// * `identity_pair` - Identity key pair to sign the prekey
     pub fn generate(key_id: u32, identity_pair: &IdentityKeyPair) -> Result<Self> {
+        Self::generate_with_rng(key_id, identity_pair, &mut OsRng)
+    }
+
+    /// Generate a new signed prekey pair from the given RNG
+    /// 
+    /// A seeded RNG makes the prekey reproducible, for known-answer tests only.
+    /// Ed25519 signatures are deterministic, so the signature is too.
+    /// 
+    /// # Arguments
+    /// * `key_id` - Unique identifier for this prekey (`1..=MAX_KEY_ID`)
+    /// * `identity_pair` - Identity key pair to sign the prekey
+    /// * `rng` - Cryptographically secure RNG
+    pub fn generate_with_rng<R: RngCore + CryptoRng>(
+        key_id: u32,
+        identity_pair: &IdentityKeyPair,
+        rng: &mut R,
+    ) -> Result<Self> {
+        validate_key_id(key_id)?;
+        
         // Generate new X25519 prekey pair
-        let prekey = EphemeralSecret::random_from_rng(OsRng);
+        let prekey = EphemeralSecret::random_from_rng(rng);
         let prekey_public = PublicKey::from(&prekey);
         
         // Sign the prekey public key with Ed25519 identity signing key
@@ -50,6 +117,41 @@ impl SignedPreKeyPair {
         })
     }
 
+    /// Rebuild a signed prekey pair from its stored private key and signature
+    /// 
+    /// The signature is not checked here; verify it against the identity's
+    /// Ed25519 key with `verify_signature` when the source is not trusted.
+    /// 
+    /// # Arguments
+    /// * `key_id` - Prekey id (`1..=MAX_KEY_ID`)
+    /// * `private_key` - X25519 private key bytes
+    /// * `signature` - Identity signature over the prekey public key
+    /// 
+    /// # Returns
+    /// SignedPreKeyPair, or `KeyGenerationError` for an invalid id
+    pub fn from_private_bytes(key_id: u32, private_key: [u8; 32], signature: Signature) -> Result<Self> {
+        validate_key_id(key_id)?;
+        
+        let prekey_public = PublicKey::from(&StaticSecret::from(private_key));
+        Ok(Self {
+            prekey_bytes: private_key,
+            prekey_public,
+            signature,
+            key_id,
+        })
+    }
+
+    /// Re-sign this prekey with another identity, e.g. after an identity migration
+    /// 
+    /// The prekey itself (key pair and id) is unchanged; only the stored
+    /// signature is replaced, so it verifies against the new identity only.
+    /// 
+    /// # Arguments
+    /// * `new_identity` - Identity key pair to sign the prekey with
+    pub fn resign(&mut self, new_identity: &IdentityKeyPair) {
+        self.signature = new_identity.signing_key().sign(self.prekey_public.as_bytes());
+    }
+
     /// Verify the signature of this prekey
     pub fn verify_signature(&self, identity_public: &VerifyingKey) -> Result<bool> {
         let prekey_pub_bytes = self.prekey_public.as_bytes();
@@ -89,13 +191,16 @@ impl SignedPreKeyPair {
         self.key_id
     }
 
-    /// Get the private key as EphemeralSecret for DH operations
+    /// Get the private key as StaticSecret for DH operations
     /// 
-    /// Creates a new EphemeralSecret from the stored bytes.
-    pub(crate) fn private_key(&self) -> EphemeralSecret {
-        unsafe {
-            std::mem::transmute::<[u8; 32], EphemeralSecret>(self.prekey_bytes)
-        }
+    /// Creates a new StaticSecret from the stored bytes.
+    pub(crate) fn private_key(&self) -> StaticSecret {
+        StaticSecret::from(self.prekey_bytes)
+    }
+
+    /// Get the private key bytes (internal use, for exporting prekey material)
+    pub(crate) fn private_key_bytes(&self) -> [u8; 32] {
+        self.prekey_bytes
     }
 
     /// Get the signature
@@ -120,7 +225,7 @@ impl Clone for SignedPreKeyPair {
 /// 
 /// One-time prekeys are used once and then discarded to prevent replay attacks.
 pub struct OneTimePreKeyPair {
-    private_key: EphemeralSecret,
+    private_key: StaticSecret,
     public_key: PublicKey,
     key_id: u32,
 }
@@ -129,30 +234,40 @@ impl OneTimePreKeyPair {
     /// Generate a new one-time prekey pair
     /// 
     /// # Arguments
-    /// * `key_id` - Unique identifier for this prekey
-    pub fn generate(key_id: u32) -> Self {
-        let private_key = EphemeralSecret::random_from_rng(OsRng);
+    /// * `key_id` - Unique identifier for this prekey (`1..=MAX_KEY_ID`)
+    /// 
+    /// # Returns
+    /// OneTimePreKeyPair, or `KeyGenerationError` for an invalid id
+    pub fn generate(key_id: u32) -> Result<Self> {
+        Self::generate_with_rng(key_id, &mut OsRng)
+    }
+
+    /// Generate a new one-time prekey pair from the given RNG
+    /// 
+    /// # Arguments
+    /// * `key_id` - Unique identifier for this prekey (`1..=MAX_KEY_ID`)
+    /// * `rng` - Cryptographically secure RNG (seeded only in known-answer tests)
+    pub fn generate_with_rng<R: RngCore + CryptoRng>(key_id: u32, rng: &mut R) -> Result<Self> {
+        validate_key_id(key_id)?;
+        
+        let private_key = StaticSecret::random_from_rng(rng);
         let public_key = PublicKey::from(&private_key);
         
-        Self {
+        Ok(Self {
             private_key,
             public_key,
             key_id,
-        }
+        })
     }
 
     /// Get the private key reference
-    /// 
-    /// Note: EphemeralSecret doesn't implement Clone, so we return a reference.
-    /// For cloning the key, you need to serialize/deserialize instead.
-    pub fn private_key(&self) -> &EphemeralSecret {
+    pub fn private_key(&self) -> &StaticSecret {
         &self.private_key
     }
 
-    /// Get the private key reference (internal use)
-    #[allow(dead_code)]
-    pub(crate) fn private_key_ref(&self) -> &EphemeralSecret {
-        &self.private_key
+    /// Get the private key bytes (internal use, for exporting prekey material)
+    pub(crate) fn private_key_bytes(&self) -> [u8; 32] {
+        self.private_key.to_bytes()
     }
 
     /// Get the public key
@@ -176,9 +291,64 @@ impl OneTimePreKeyPair {
     }
 }
 
+/// Hex serde for X25519 public keys
+mod hex_public_key {
+    use crate::encoding::parse_hex_32;
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+    use x25519_dalek::PublicKey;
+
+    pub fn serialize<S: Serializer>(key: &PublicKey, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        serializer.serialize_str(&hex::encode(key.as_bytes()))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> std::result::Result<PublicKey, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        parse_hex_32(&s).map(PublicKey::from).map_err(Error::custom)
+    }
+}
+
+/// Hex serde for Ed25519 signatures
+mod hex_signature {
+    use crate::encoding::parse_hex_64;
+    use ed25519_dalek::Signature;
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(signature: &Signature, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        serializer.serialize_str(&hex::encode(signature.to_bytes()))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Signature, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        parse_hex_64(&s).map(|bytes| Signature::from_bytes(&bytes)).map_err(Error::custom)
+    }
+}
+
+/// Hex serde for Ed25519 verifying keys
+mod hex_verifying_key {
+    use crate::encoding::parse_hex_32;
+    use ed25519_dalek::VerifyingKey;
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(key: &VerifyingKey, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        serializer.serialize_str(&hex::encode(key.to_bytes()))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> std::result::Result<VerifyingKey, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        let bytes = parse_hex_32(&s).map_err(Error::custom)?;
+        VerifyingKey::from_bytes(&bytes)
+            .map_err(|e| Error::custom(format!("Failed to parse Ed25519 verifying key: {}", e)))
+    }
+}
+
 /// Public representation of a signed prekey
+/// 
+/// Serializes with the same hex field names as `SignedPreKeyJSON`.
+#[derive(Serialize, Deserialize)]
 pub struct SignedPreKey {
+    #[serde(rename = "public_key_hex", with = "hex_public_key")]
     public_key: PublicKey,
+    #[serde(rename = "signature_hex", with = "hex_signature")]
     signature: Signature,
     key_id: u32,
 }
@@ -188,7 +358,7 @@ impl SignedPreKey {
     pub fn from(key_pair: &SignedPreKeyPair) -> Self {
         Self {
             public_key: key_pair.prekey_public,
-            signature: key_pair.signature.clone(),
+            signature: key_pair.signature,
             key_id: key_pair.key_id,
         }
     }
@@ -233,7 +403,11 @@ impl SignedPreKey {
 }
 
 /// Public representation of a one-time prekey
+/// 
+/// Serializes with the same hex field names as `OneTimePreKeyJSON`.
+#[derive(Serialize, Deserialize)]
 pub struct OneTimePreKey {
+    #[serde(rename = "public_key_hex", with = "hex_public_key")]
     public_key: PublicKey,
     key_id: u32,
 }
@@ -272,8 +446,13 @@ impl OneTimePreKey {
 }
 
 /// Prekey bundle containing identity key, signed prekey, and optional one-time prekey
+/// 
+/// Serializes to the same JSON as `PreKeyBundleJSON`. Deserializing only
+/// checks the key encodings; call `verify_signature` before trusting it.
+#[derive(Serialize, Deserialize)]
 pub struct PreKeyBundle {
     identity_public_hex: String,
+    #[serde(rename = "identity_ed25519_verifying_key_hex", with = "hex_verifying_key")]
     identity_ed25519_verifying_key: VerifyingKey, // Ed25519 verifying key for signature verification
     signed_prekey: SignedPreKey,
     one_time_prekey: Option<OneTimePreKey>,
@@ -303,12 +482,26 @@ impl PreKeyBundle {
 
     /// Verify the signature of the signed prekey using the identity's Ed25519 verifying key
     /// 
+    /// A signed prekey equal to the identity key is refused even when the
+    /// signature is valid: it points at key reuse or a crafted bundle.
+    /// 
     /// # Returns
-    /// Ok(true) if signature is valid, Err otherwise
+    /// Ok(true) if signature is valid, `ProtocolError` if the signed prekey is
+    /// the identity key, Err otherwise
     pub fn verify_signature(&self) -> Result<bool> {
+        if self.signed_prekey_is_identity() {
+            return Err(E2EEError::ProtocolError("signed prekey must differ from identity key".to_string()));
+        }
+        
         self.signed_prekey.verify_signature(&self.identity_ed25519_verifying_key)
     }
 
+    /// Check whether the signed prekey is the identity key itself
+    fn signed_prekey_is_identity(&self) -> bool {
+        crate::encoding::parse_hex_32(&self.identity_public_hex)
+            .is_ok_and(|identity_public| &identity_public == self.signed_prekey.public_key.as_bytes())
+    }
+
     /// Get the identity public key as hex
     pub fn identity_public_hex(&self) -> &str {
         &self.identity_public_hex
@@ -328,5 +521,156 @@ impl PreKeyBundle {
     pub fn one_time_prekey(&self) -> Option<&OneTimePreKey> {
         self.one_time_prekey.as_ref()
     }
+
+    /// Length of every `to_bytes_padded` output
+    pub const PADDED_LEN: usize = 32 + 32 + 4 + 32 + 64 + 1 + 4 + 32;
+
+    /// Serialize the bundle into a fixed-size buffer
+    /// 
+    /// A key server serving these cannot leak through the size whether the
+    /// user still has one-time prekeys: the one-time prekey slot is always
+    /// present, flagged empty and zero-filled when there is none.
+    /// 
+    /// Layout (ids big-endian): identity X25519 key (32) | Ed25519 verifying
+    /// key (32) | signed prekey id (4) | signed prekey (32) | signature (64) |
+    /// one-time prekey flag (1) | one-time prekey id (4) | one-time prekey (32)
+    /// 
+    /// # Returns
+    /// `PADDED_LEN` bytes, or `SerializationError` if the identity hex is invalid
+    pub fn to_bytes_padded(&self) -> Result<Vec<u8>> {
+        let mut bytes = Vec::with_capacity(Self::PADDED_LEN);
+        bytes.extend_from_slice(&crate::encoding::parse_hex_32(&self.identity_public_hex)?);
+        bytes.extend_from_slice(self.identity_ed25519_verifying_key.as_bytes());
+        bytes.extend_from_slice(&self.signed_prekey.key_id.to_be_bytes());
+        bytes.extend_from_slice(self.signed_prekey.public_key.as_bytes());
+        bytes.extend_from_slice(&self.signed_prekey.signature.to_bytes());
+        
+        match self.one_time_prekey {
+            Some(ref one_time_prekey) => {
+                bytes.push(1);
+                bytes.extend_from_slice(&one_time_prekey.key_id.to_be_bytes());
+                bytes.extend_from_slice(one_time_prekey.public_key.as_bytes());
+            }
+            None => bytes.extend_from_slice(&[0u8; 1 + 4 + 32]),
+        }
+        
+        Ok(bytes)
+    }
+
+    /// Parse a bundle written by `to_bytes_padded`
+    /// 
+    /// The signature is not checked here; call `verify_signature` before use.
+    /// 
+    /// # Arguments
+    /// * `bytes` - Exactly `PADDED_LEN` bytes
+    /// 
+    /// # Returns
+    /// PreKeyBundle, or `SerializationError` for a wrong length, an unknown
+    /// one-time prekey flag, a non-zero empty slot or an invalid verifying key
+    pub fn from_bytes_padded(bytes: &[u8]) -> Result<Self> {
+        if bytes.len() != Self::PADDED_LEN {
+            return Err(E2EEError::SerializationError(format!(
+                "Padded bundle must be {} bytes, got {}",
+                Self::PADDED_LEN,
+                bytes.len()
+            )));
+        }
+        
+        let (identity, rest) = bytes.split_at(32);
+        let (verifying_key, rest) = rest.split_at(32);
+        let (signed_prekey_id, rest) = rest.split_at(4);
+        let (signed_prekey, rest) = rest.split_at(32);
+        let (signature, rest) = rest.split_at(64);
+        let (flag, rest) = rest.split_at(1);
+        let (one_time_prekey_id, one_time_prekey) = rest.split_at(4);
+        
+        let array_32 = |slice: &[u8]| -> [u8; 32] { slice.try_into().expect("slice is 32 bytes") };
+        let identity_ed25519_verifying_key = VerifyingKey::from_bytes(&array_32(verifying_key))
+            .map_err(|e| E2EEError::SerializationError(format!("Failed to parse Ed25519 verifying key: {}", e)))?;
+        let signature = Signature::from_bytes(signature.try_into().expect("slice is 64 bytes"));
+        let signed_prekey = SignedPreKey::from_components(
+            PublicKey::from(array_32(signed_prekey)),
+            signature,
+            u32::from_be_bytes(signed_prekey_id.try_into().expect("slice is 4 bytes")),
+        );
+        
+        let one_time_prekey = match flag[0] {
+            0 if one_time_prekey_id.iter().chain(one_time_prekey).all(|&b| b == 0) => None,
+            0 => {
+                return Err(E2EEError::SerializationError(
+                    "Empty one-time prekey slot must be zero-filled".to_string(),
+                ))
+            }
+            1 => Some(OneTimePreKey::from_components(
+                PublicKey::from(array_32(one_time_prekey)),
+                u32::from_be_bytes(one_time_prekey_id.try_into().expect("slice is 4 bytes")),
+            )),
+            other => {
+                return Err(E2EEError::SerializationError(format!(
+                    "Unknown one-time prekey flag {}",
+                    other
+                )))
+            }
+        };
+        
+        Ok(Self::new(hex::encode(identity), identity_ed25519_verifying_key, signed_prekey, one_time_prekey))
+    }
+}
+
+
+/// Verify the signed prekey signature of every bundle, one at a time
+/// 
+/// # Arguments
+/// * `bundles` - Prekey bundles to verify
+/// 
+/// # Returns
+/// One result per bundle, in order (Ok(true) if valid, Err otherwise)
+pub fn verify_bundles(bundles: &[PreKeyBundle]) -> Vec<Result<bool>> {
+    bundles.iter().map(|bundle| bundle.verify_signature()).collect()
 }
 
+/// Verify the signed prekey signatures of many bundles with Ed25519 batch verification
+/// 
+/// All signatures are checked in one batch operation. If the batch fails, it
+/// is split in halves recursively to pinpoint the invalid bundles, so a few
+/// bad signatures cost a handful of extra batches rather than a serial pass.
+/// 
+/// # Arguments
+/// * `bundles` - Prekey bundles to verify
+/// 
+/// # Returns
+/// One flag per bundle, in order (true if the signature is valid; false as
+/// well for a bundle whose signed prekey is its identity key)
+pub fn verify_bundles_batch(bundles: &[PreKeyBundle]) -> Vec<bool> {
+    let mut valid = vec![false; bundles.len()];
+    verify_batch_range(bundles, &mut valid);
+    for (bundle, valid) in bundles.iter().zip(valid.iter_mut()) {
+        *valid &= !bundle.signed_prekey_is_identity();
+    }
+    valid
+}
+
+fn verify_batch_range(bundles: &[PreKeyBundle], valid: &mut [bool]) {
+    if bundles.is_empty() {
+        return;
+    }
+    
+    let messages: Vec<&[u8]> = bundles.iter()
+        .map(|bundle| bundle.signed_prekey().public_key().as_bytes().as_slice())
+        .collect();
+    let signatures: Vec<Signature> = bundles.iter()
+        .map(|bundle| *bundle.signed_prekey().signature())
+        .collect();
+    let verifying_keys: Vec<VerifyingKey> = bundles.iter()
+        .map(|bundle| *bundle.identity_ed25519_verifying_key())
+        .collect();
+    
+    if ed25519_dalek::verify_batch(&messages, &signatures, &verifying_keys).is_ok() {
+        valid.fill(true);
+    } else if bundles.len() > 1 {
+        let mid = bundles.len() / 2;
+        let (valid_left, valid_right) = valid.split_at_mut(mid);
+        verify_batch_range(&bundles[..mid], valid_left);
+        verify_batch_range(&bundles[mid..], valid_right);
+    }
+}
@@ -1,5 +1,9 @@
 mod frb_generated; /* AUTO INJECTED BY flutter_rust_bridge. This line may not be accurate, and you can change it according to your needs. */
+pub mod aead;
+pub mod clock;
+pub mod encoding;
 pub mod error;
+pub mod kdf;
 pub mod keys;
 pub mod message;
 pub mod ratchet;
@@ -1,5 +1,6 @@
 use crate::error::{E2EEError, Result};
-use base64::{engine::general_purpose, Engine as _};
+use crate::message::PADDING_BUCKET;
+use base64::{engine::general_purpose, DecodeSliceError, Engine as _};
 use serde::{Deserialize, Serialize};
 
 /// Message type enumeration
@@ -16,12 +17,137 @@ pub enum MessageType {
 /// Message header containing ratchet metadata
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct MessageHeader {
-    /// DH public key for DH ratchet (as hex string)
+    /// DH public key for DH ratchet (as hex string); empty, and omitted from
+    /// the JSON, for one-way envelopes (see `ratchet::OneWaySender`)
+    #[serde(default, skip_serializing_if = "String::is_empty")]
     pub dh_public_key: String,
     /// Previous chain length
     pub previous_chain_length: u32,
     /// Message number in current chain
     pub message_number: u64,
+    /// Sender's timestamp (seconds since Unix epoch), authenticated as AEAD associated data
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sent_at: Option<u64>,
+    /// Whether the plaintext was padded before encryption (see `message::padding`)
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub padded: bool,
+    /// Device the message targets, for relays routing prekey messages to one
+    /// of the recipient's devices
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub recipient_device_id: Option<u32>,
+    /// Role the sender believes it has (`ROLE_INITIATOR` or `ROLE_RESPONDER`),
+    /// sent until the first DH ratchet to diagnose cross-wired sessions
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub role_hint: Option<u8>,
+    /// Peer message number acknowledged by this envelope; set only on receipts,
+    /// which carry an empty body
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub receipt_for: Option<u64>,
+    /// Set on the last message a capped sending chain can carry: the sender
+    /// needs a new DH key from the peer before it can send again
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub rekey_requested: bool,
+}
+
+/// `role_hint` value sent by the X3DH initiator
+pub const ROLE_INITIATOR: u8 = 0;
+
+/// `role_hint` value sent by the X3DH responder
+pub const ROLE_RESPONDER: u8 = 1;
+
+/// Default cap on the size of a received envelope (16 MiB)
+pub const MAX_ENVELOPE_BYTES: usize = 16 * 1024 * 1024;
+
+fn is_false(value: &bool) -> bool {
+    !*value
+}
+
+/// Reject envelopes whose version, type and flags cannot occur together
+/// 
+/// Runs before any crypto work, so malformed or adversarial envelopes fail
+/// cheaply. Checks:
+/// - `version` is one this build understands
+/// - a `KeyExchange` envelope has an empty, unpadded body and is no receipt
+/// - a `padded` ciphertext holds a whole number of `PADDING_BUCKET` blocks
+/// - `role_hint`, if present, is `ROLE_INITIATOR` or `ROLE_RESPONDER`
+/// 
+/// # Returns
+/// Ok(()) if the envelope is consistent, `E2EEError::ProtocolError` otherwise
+pub fn validate_envelope_consistency(envelope: &MessageEnvelope) -> Result<()> {
+    if envelope.version != MessageEnvelope::VERSION {
+        return Err(E2EEError::ProtocolError(format!(
+            "Unsupported envelope version {}",
+            envelope.version
+        )));
+    }
+    
+    if envelope.message_type == MessageType::KeyExchange
+        && (envelope.header.padded
+            || envelope.header.receipt_for.is_some()
+            || envelope.ciphertext.len() != ring::aead::AES_256_GCM.tag_len())
+    {
+        return Err(E2EEError::ProtocolError(
+            "Key exchange envelope must have an empty body".to_string(),
+        ));
+    }
+    
+    if envelope.header.padded {
+        let body_len = envelope.ciphertext.len().saturating_sub(ring::aead::AES_256_GCM.tag_len());
+        if body_len == 0 || !body_len.is_multiple_of(PADDING_BUCKET) {
+            return Err(E2EEError::ProtocolError(
+                "Padded envelope length does not match the padding bucket".to_string(),
+            ));
+        }
+    }
+    
+    if let Some(role_hint) = envelope.header.role_hint {
+        if role_hint != ROLE_INITIATOR && role_hint != ROLE_RESPONDER {
+            return Err(E2EEError::ProtocolError(format!("Unknown role hint {}", role_hint)));
+        }
+    }
+    
+    if envelope.header.receipt_for.is_some()
+        && (envelope.header.padded || envelope.ciphertext.len() != ring::aead::AES_256_GCM.tag_len())
+    {
+        return Err(E2EEError::ProtocolError("Receipt envelope must have an empty body".to_string()));
+    }
+    
+    Ok(())
+}
+
+impl MessageHeader {
+    /// Associated data bound into the AEAD tag
+    /// 
+    /// Empty when no optional metadata is present, so envelopes without a
+    /// timestamp, padding, device id, role hint or receipt keep their original
+    /// encoding. Adding, removing or changing any of these fields changes the
+    /// associated data and makes decryption fail.
+    pub fn associated_data(&self) -> Vec<u8> {
+        let mut aad = Vec::new();
+        if let Some(sent_at) = self.sent_at {
+            aad.extend_from_slice(b"sent_at");
+            aad.extend_from_slice(&sent_at.to_be_bytes());
+        }
+        if self.padded {
+            aad.extend_from_slice(b"padded");
+        }
+        if let Some(device_id) = self.recipient_device_id {
+            aad.extend_from_slice(b"recipient_device_id");
+            aad.extend_from_slice(&device_id.to_be_bytes());
+        }
+        if let Some(role_hint) = self.role_hint {
+            aad.extend_from_slice(b"role_hint");
+            aad.push(role_hint);
+        }
+        if let Some(receipt_for) = self.receipt_for {
+            aad.extend_from_slice(b"receipt_for");
+            aad.extend_from_slice(&receipt_for.to_be_bytes());
+        }
+        if self.rekey_requested {
+            aad.extend_from_slice(b"rekey_requested");
+        }
+        aad
+    }
 }
 
 /// Message envelope containing encrypted message and metadata
@@ -38,6 +164,12 @@ pub struct MessageEnvelope {
 }
 
 impl MessageEnvelope {
+    /// Envelope format version written by this build
+    pub const VERSION: u32 = 1;
+
+    /// Top-level fields every serialized envelope must contain
+    const REQUIRED_FIELDS: [&'static str; 4] = ["version", "message_type", "ciphertext", "header"];
+
     /// Create a regular message envelope
     /// 
     /// # Arguments
@@ -52,17 +184,55 @@ impl MessageEnvelope {
         message_number: u64,
     ) -> Self {
         Self {
-            version: 1,
+            version: Self::VERSION,
             message_type: MessageType::Regular,
             ciphertext,
             header: MessageHeader {
                 dh_public_key,
                 previous_chain_length,
                 message_number,
+                sent_at: None,
+                padded: false,
+                recipient_device_id: None,
+                role_hint: None,
+                receipt_for: None,
+                rekey_requested: false,
             },
         }
     }
 
+    /// Check the envelope's structure without any session key
+    /// 
+    /// For relays and clients that want to drop malformed envelopes before
+    /// queuing them. On top of `validate_envelope_consistency` (version, type
+    /// and flags), checks that:
+    /// - `dh_public_key` is 64 hex characters (one-way envelopes, which have
+    ///   none, do not pass)
+    /// - the ciphertext is at least an AEAD tag long
+    /// - `message_number` and `receipt_for` are not 0 (numbering starts at 1)
+    /// 
+    /// Passing does not mean the envelope authenticates; only decryption tells.
+    /// 
+    /// # Returns
+    /// Ok(()), `SerializationError` for a malformed DH key, `ProtocolError` otherwise
+    pub fn validate_structure(&self) -> Result<()> {
+        validate_envelope_consistency(self)?;
+        crate::encoding::parse_hex_32(&self.header.dh_public_key)?;
+        
+        if self.ciphertext.len() < ring::aead::AES_256_GCM.tag_len() {
+            return Err(E2EEError::ProtocolError("Ciphertext shorter than AEAD tag".to_string()));
+        }
+        
+        if self.header.message_number == 0 {
+            return Err(E2EEError::ProtocolError("Message number must start at 1".to_string()));
+        }
+        if self.header.receipt_for == Some(0) {
+            return Err(E2EEError::ProtocolError("Receipt for message 0".to_string()));
+        }
+        
+        Ok(())
+    }
+
     /// Serialize envelope to base64 string
     /// 
     /// # Returns
@@ -75,24 +245,158 @@ impl MessageEnvelope {
         Ok(b64)
     }
 
+    /// Serialize envelope to URL-safe base64 (no padding), for URLs and web transports
+    /// 
+    /// # Returns
+    /// Base64url-encoded JSON string, accepted by `from_base64`
+    pub fn to_base64_url(&self) -> Result<String> {
+        let json = serde_json::to_string(self)
+            .map_err(|e| E2EEError::SerializationError(format!("Failed to serialize envelope: {}", e)))?;
+        
+        Ok(general_purpose::URL_SAFE_NO_PAD.encode(json.as_bytes()))
+    }
+
     /// Deserialize envelope from base64 string
     /// 
+    /// Accepts the standard alphabet written by `to_base64` as well as the
+    /// URL-safe alphabet, padded or not (`to_base64_url`).
+    /// 
     /// # Arguments
     /// * `b64` - Base64-encoded JSON string
     /// 
     /// # Returns
-    /// Deserialized MessageEnvelope
+    /// Deserialized MessageEnvelope, or `SerializationError` naming the cause:
+    /// "empty envelope" for empty or whitespace-only input, invalid base64,
+    /// truncated JSON (valid base64 of an incomplete document), otherwise
+    /// invalid JSON, or a missing required field
     pub fn from_base64(b64: &str) -> Result<Self> {
-        let json_bytes = general_purpose::STANDARD.decode(b64)
+        if b64.trim().is_empty() {
+            return Err(E2EEError::SerializationError("empty envelope".to_string()));
+        }
+        
+        let mut json_bytes = vec![0u8; base64::decoded_len_estimate(b64.len())];
+        let decoded_len = Self::decode_any_alphabet(b64, &mut json_bytes)
             .map_err(|e| E2EEError::SerializationError(format!("Failed to decode base64: {}", e)))?;
+        json_bytes.truncate(decoded_len);
+        
+        Self::from_json_bytes(&json_bytes)
+    }
+
+    /// Decode base64 into `output`, trying the standard alphabet, then URL-safe
+    /// padded, then URL-safe unpadded; shared by the base64 entry points
+    /// 
+    /// # Returns
+    /// Number of bytes written, `OutputSliceTooSmall` as soon as any alphabet
+    /// runs out of room, otherwise the standard alphabet's decode error
+    fn decode_any_alphabet(b64: &str, output: &mut [u8]) -> std::result::Result<usize, DecodeSliceError> {
+        let standard_error = match general_purpose::STANDARD.decode_slice(b64, output) {
+            Ok(len) => return Ok(len),
+            Err(e) => e,
+        };
+        if matches!(standard_error, DecodeSliceError::OutputSliceTooSmall) {
+            return Err(standard_error);
+        }
         
-        let json_str = std::str::from_utf8(&json_bytes)
+        for engine in [&general_purpose::URL_SAFE, &general_purpose::URL_SAFE_NO_PAD] {
+            match engine.decode_slice(b64, output) {
+                Ok(len) => return Ok(len),
+                Err(DecodeSliceError::OutputSliceTooSmall) => return Err(DecodeSliceError::OutputSliceTooSmall),
+                Err(DecodeSliceError::DecodeError(_)) => {}
+            }
+        }
+        Err(standard_error)
+    }
+
+    /// Parse decoded envelope JSON, shared by the base64 entry points
+    fn from_json_bytes(json_bytes: &[u8]) -> Result<Self> {
+        let json_str = std::str::from_utf8(json_bytes)
             .map_err(|e| E2EEError::SerializationError(format!("Failed to decode UTF-8: {}", e)))?;
         
-        let envelope: MessageEnvelope = serde_json::from_str(json_str)
+        // Parse generically first, so a cut-off document is told apart from bad input
+        let value: serde_json::Value = serde_json::from_str(json_str).map_err(|e| {
+            if e.is_eof() {
+                E2EEError::SerializationError(format!("Truncated envelope JSON: {}", e))
+            } else {
+                E2EEError::SerializationError(format!("Invalid envelope JSON: {}", e))
+            }
+        })?;
+        
+        for field in Self::REQUIRED_FIELDS {
+            if value.get(field).is_none() {
+                return Err(E2EEError::SerializationError(format!(
+                    "Envelope missing required field `{}`",
+                    field
+                )));
+            }
+        }
+        
+        let envelope: MessageEnvelope = serde_json::from_value(value)
             .map_err(|e| E2EEError::SerializationError(format!("Failed to deserialize envelope: {}", e)))?;
         
         Ok(envelope)
     }
+
+    /// Deserialize envelope from base64 string, refusing oversized input
+    /// 
+    /// The encoded length is checked before anything is decoded, so a huge
+    /// blob from a malicious relay is rejected without allocating for it.
+    /// 
+    /// # Arguments
+    /// * `b64` - Base64-encoded JSON string
+    /// * `max_bytes` - Maximum decoded JSON length and ciphertext length
+    /// 
+    /// # Returns
+    /// Deserialized MessageEnvelope, or `ProtocolError("envelope too large")`
+    pub fn from_base64_limited(b64: &str, max_bytes: usize) -> Result<Self> {
+        let too_large = || E2EEError::ProtocolError("envelope too large".to_string());
+        
+        // Every 4 base64 characters decode to at most 3 bytes
+        if b64.len() / 4 * 3 > max_bytes {
+            return Err(too_large());
+        }
+        
+        let envelope = Self::from_base64(b64)?;
+        if envelope.ciphertext.len() > max_bytes {
+            return Err(too_large());
+        }
+        
+        Ok(envelope)
+    }
+
+    /// Deserialize envelope from base64 string, decoding into a bounded buffer
+    /// 
+    /// Unlike `from_base64_limited`, which estimates the decoded size from the
+    /// encoded length, the decode itself writes into a buffer of at most
+    /// `max_decoded_bytes`, so no allocation ever exceeds the bound. Accepts
+    /// the same alphabets as `from_base64`.
+    /// 
+    /// # Arguments
+    /// * `b64` - Base64-encoded JSON string
+    /// * `max_decoded_bytes` - Maximum decoded JSON length
+    /// 
+    /// # Returns
+    /// Deserialized MessageEnvelope, `ProtocolError` if the decoded JSON would
+    /// exceed the bound, otherwise the same errors as `from_base64`
+    pub fn from_base64_bounded(b64: &str, max_decoded_bytes: usize) -> Result<Self> {
+        if b64.trim().is_empty() {
+            return Err(E2EEError::SerializationError("empty envelope".to_string()));
+        }
+        
+        let capacity = base64::decoded_len_estimate(b64.len()).min(max_decoded_bytes);
+        let mut json_bytes = vec![0u8; capacity];
+        let decoded_len = Self::decode_any_alphabet(b64, &mut json_bytes)
+            .map_err(|e| match e {
+                DecodeSliceError::OutputSliceTooSmall => E2EEError::ProtocolError(format!(
+                    "envelope too large: decoded size exceeds {} bytes",
+                    max_decoded_bytes
+                )),
+                DecodeSliceError::DecodeError(e) => {
+                    E2EEError::SerializationError(format!("Failed to decode base64: {}", e))
+                }
+            })?;
+        json_bytes.truncate(decoded_len);
+        
+        Self::from_json_bytes(&json_bytes)
+    }
 }
 
@@ -1,4 +1,11 @@
 pub mod envelope;
+pub mod padding;
+pub mod sealed;
 
-pub use envelope::{MessageEnvelope, MessageHeader, MessageType};
+pub use envelope::{
+    validate_envelope_consistency, MessageEnvelope, MessageHeader, MessageType, MAX_ENVELOPE_BYTES, ROLE_INITIATOR,
+    ROLE_RESPONDER,
+};
+pub use padding::PADDING_BUCKET;
+pub use sealed::SealedMessage;
 
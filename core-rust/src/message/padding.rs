@@ -0,0 +1,57 @@
+use crate::error::{E2EEError, Result};
+
+/// Default padding bucket size in bytes
+pub const PADDING_BUCKET: usize = 256;
+
+/// Length of the plaintext length prefix (u32, big-endian)
+const LENGTH_PREFIX_LEN: usize = 4;
+
+/// Pad a plaintext up to the next multiple of `bucket` bytes
+/// 
+/// Layout: plaintext length (u32, big-endian) || plaintext || zero padding.
+/// The result is encrypted as a whole, so the padding is authenticated.
+/// 
+/// # Arguments
+/// * `plaintext` - Plaintext to pad
+/// * `bucket` - Bucket size in bytes (must be non-zero)
+/// 
+/// # Returns
+/// Padded plaintext whose length is a multiple of `bucket`
+pub fn pad(plaintext: &[u8], bucket: usize) -> Result<Vec<u8>> {
+    if bucket == 0 {
+        return Err(E2EEError::ProtocolError("Padding bucket must be non-zero".to_string()));
+    }
+    let length = u32::try_from(plaintext.len())
+        .map_err(|_| E2EEError::ProtocolError("Plaintext too large to pad".to_string()))?;
+
+    let unpadded_len = LENGTH_PREFIX_LEN + plaintext.len();
+    let padded_len = unpadded_len.div_ceil(bucket) * bucket;
+
+    let mut padded = Vec::with_capacity(padded_len);
+    padded.extend_from_slice(&length.to_be_bytes());
+    padded.extend_from_slice(plaintext);
+    padded.resize(padded_len, 0);
+    Ok(padded)
+}
+
+/// Strip the padding added by `pad`
+/// 
+/// # Arguments
+/// * `padded` - Padded plaintext
+/// 
+/// # Returns
+/// Original plaintext, or `ProtocolError` if the length prefix is inconsistent
+pub fn unpad(padded: &[u8]) -> Result<Vec<u8>> {
+    if padded.len() < LENGTH_PREFIX_LEN {
+        return Err(E2EEError::ProtocolError("Padded plaintext too short".to_string()));
+    }
+
+    let mut length_bytes = [0u8; LENGTH_PREFIX_LEN];
+    length_bytes.copy_from_slice(&padded[..LENGTH_PREFIX_LEN]);
+    let length = u32::from_be_bytes(length_bytes) as usize;
+
+    padded
+        .get(LENGTH_PREFIX_LEN..LENGTH_PREFIX_LEN + length)
+        .map(|plaintext| plaintext.to_vec())
+        .ok_or_else(|| E2EEError::ProtocolError("Invalid padding length prefix".to_string()))
+}
@@ -0,0 +1,54 @@
+use crate::error::{E2EEError, Result};
+use crate::message::MessageEnvelope;
+use base64::{engine::general_purpose, Engine as _};
+use serde::{Deserialize, Serialize};
+
+/// Self-contained one-off message sealed to a prekey bundle
+/// 
+/// Carries everything the recipient needs to run the responder side of X3DH
+/// (sender identity, ephemeral key, prekey ids) next to a single encrypted
+/// envelope, so no long-lived session has to exist on either side.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SealedMessage {
+    /// Sender's identity public key (X25519) as hex string
+    pub sender_identity_hex: String,
+    /// Sender's X3DH ephemeral public key as hex string
+    pub ephemeral_public_key_hex: String,
+    /// ID of the recipient's signed prekey used in X3DH
+    pub signed_prekey_id: u32,
+    /// ID of the recipient's one-time prekey used in X3DH (if any)
+    pub one_time_prekey_id: Option<u32>,
+    /// Encrypted message (message type `PreKey`)
+    pub envelope: MessageEnvelope,
+}
+
+impl SealedMessage {
+    /// Serialize sealed message to base64 string
+    /// 
+    /// # Returns
+    /// Base64-encoded JSON string
+    pub fn to_base64(&self) -> Result<String> {
+        let json = serde_json::to_string(self)
+            .map_err(|e| E2EEError::SerializationError(format!("Failed to serialize sealed message: {}", e)))?;
+
+        Ok(general_purpose::STANDARD.encode(json.as_bytes()))
+    }
+
+    /// Deserialize sealed message from base64 string
+    /// 
+    /// # Arguments
+    /// * `b64` - Base64-encoded JSON string
+    /// 
+    /// # Returns
+    /// Deserialized SealedMessage
+    pub fn from_base64(b64: &str) -> Result<Self> {
+        let json_bytes = general_purpose::STANDARD.decode(b64)
+            .map_err(|e| E2EEError::SerializationError(format!("Failed to decode base64: {}", e)))?;
+
+        let json_str = std::str::from_utf8(&json_bytes)
+            .map_err(|e| E2EEError::SerializationError(format!("Failed to decode UTF-8: {}", e)))?;
+
+        serde_json::from_str(json_str)
+            .map_err(|e| E2EEError::SerializationError(format!("Failed to deserialize sealed message: {}", e)))
+    }
+}
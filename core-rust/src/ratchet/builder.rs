@@ -0,0 +1,180 @@
+use crate::error::{E2EEError, Result};
+use crate::ratchet::double_ratchet::{DoubleRatchet, MAX_SKIP};
+use crate::ratchet::suite::SuiteDescriptor;
+use x25519_dalek::StaticSecret;
+
+/// Settings a ratchet was configured with
+/// 
+/// Two peers can only talk if their configurations agree on the suite and
+/// the context. Produced by `DoubleRatchet::config`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RatchetConfig {
+    /// Cipher, KDF and header encryption in use
+    pub suite: SuiteDescriptor,
+    /// Application context bound into the key schedule
+    pub context: Vec<u8>,
+    /// Maximum number of message keys skipped in one receiving chain gap
+    pub max_skip: u64,
+    /// Number of messages per chain before a DH ratchet is forced, or None
+    /// if no cap is configured
+    pub rekey_after: Option<u32>,
+}
+
+/// Fluent configuration of a `DoubleRatchet`
+/// 
+/// Starts from the defaults used by `DoubleRatchet::from_shared_secret`.
+/// Suite settings are checked against `supported_suites` when building and
+/// kept on the ratchet, so `config` reports them.
+#[derive(Debug, Clone)]
+pub struct DoubleRatchetBuilder {
+    cipher: String,
+    kdf: String,
+    header_encryption: bool,
+    context: Vec<u8>,
+    max_skip: u64,
+    rekey_after: Option<u32>,
+}
+
+impl Default for DoubleRatchetBuilder {
+    fn default() -> Self {
+        let suite = SuiteDescriptor::default_suite();
+        Self {
+            cipher: suite.cipher,
+            kdf: suite.kdf,
+            header_encryption: suite.header_encryption,
+            context: Vec::new(),
+            max_skip: MAX_SKIP,
+            rekey_after: None,
+        }
+    }
+}
+
+impl DoubleRatchetBuilder {
+    /// Create a builder with the default settings
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create a builder with the settings of an existing ratchet
+    /// 
+    /// # Arguments
+    /// * `config` - Settings from `DoubleRatchet::config`
+    pub fn from_config(config: &RatchetConfig) -> Self {
+        Self {
+            cipher: config.suite.cipher.clone(),
+            kdf: config.suite.kdf.clone(),
+            header_encryption: config.suite.header_encryption,
+            context: config.context.clone(),
+            max_skip: config.max_skip,
+            rekey_after: config.rekey_after,
+        }
+    }
+
+    /// Set the AEAD cipher for message bodies (e.g. "AES-256-GCM")
+    pub fn cipher(mut self, cipher: &str) -> Self {
+        self.cipher = cipher.to_string();
+        self
+    }
+
+    /// Set the KDF for root and chain keys (e.g. "HKDF-SHA256")
+    pub fn kdf(mut self, kdf: &str) -> Self {
+        self.kdf = kdf.to_string();
+        self
+    }
+
+    /// Set whether message headers are encrypted
+    pub fn header_encryption(mut self, enabled: bool) -> Self {
+        self.header_encryption = enabled;
+        self
+    }
+
+    /// Bind the key schedule to an application context (see
+    /// `DoubleRatchet::from_shared_secret_with_context`)
+    pub fn context(mut self, context: &[u8]) -> Self {
+        self.context = context.to_vec();
+        self
+    }
+
+    /// Set how many message keys may be skipped in one receiving chain gap
+    pub fn max_skip(mut self, max_skip: u64) -> Self {
+        self.max_skip = max_skip;
+        self
+    }
+
+    /// Force a DH ratchet after this many messages per chain
+    pub fn rekey_after(mut self, messages: u32) -> Self {
+        self.rekey_after = Some(messages);
+        self
+    }
+
+    /// Build a ratchet from a shared secret with the configured settings
+    /// 
+    /// # Arguments
+    /// * `shared_secret` - 32-byte shared secret from X3DH handshake
+    /// * `is_initiator` - true if this is the X3DH initiator (Alice), false if responder (Bob)
+    /// 
+    /// # Returns
+    /// DoubleRatchet, or `ProtocolError` for a suite this build does not
+    /// support or a zero `rekey_after`
+    pub fn build_from_shared_secret(&self, shared_secret: &[u8; 32], is_initiator: bool) -> Result<DoubleRatchet> {
+        let suite = SuiteDescriptor {
+            cipher: self.cipher.clone(),
+            kdf: self.kdf.clone(),
+            header_encryption: self.header_encryption,
+        };
+        if self.rekey_after == Some(0) {
+            return Err(E2EEError::ProtocolError("rekey_after must be at least 1".to_string()));
+        }
+        
+        let mut ratchet = DoubleRatchet::from_shared_secret_with_context(shared_secret, is_initiator, &self.context)?;
+        ratchet.set_suite(suite)?;
+        if let Some(limit) = self.rekey_after {
+            ratchet.set_chain_message_limit(limit);
+        }
+        ratchet.set_max_skip(self.max_skip);
+        Ok(ratchet)
+    }
+
+    /// Build the initiator's ratchet, seeded with the responder's first DH key
+    /// 
+    /// Configured counterpart of `DoubleRatchet::from_x3dh_initiator`.
+    /// 
+    /// # Arguments
+    /// * `shared_secret` - 32-byte shared secret from X3DH handshake
+    /// * `remote_ratchet_public` - Responder's initial DH public key
+    pub fn build_x3dh_initiator(&self, shared_secret: &[u8; 32], remote_ratchet_public: &[u8; 32]) -> Result<DoubleRatchet> {
+        let mut ratchet = self.build_from_shared_secret(shared_secret, true)?;
+        ratchet.set_initial_remote_dh(*remote_ratchet_public)?;
+        Ok(ratchet)
+    }
+
+    /// Build the responder's ratchet, using `ratchet_private` as its first DH key
+    /// 
+    /// Configured counterpart of `DoubleRatchet::from_x3dh_responder`.
+    /// 
+    /// # Arguments
+    /// * `shared_secret` - 32-byte shared secret from X3DH handshake
+    /// * `ratchet_private` - Private key whose public half the initiator was seeded with
+    pub fn build_x3dh_responder(&self, shared_secret: &[u8; 32], ratchet_private: &StaticSecret) -> Result<DoubleRatchet> {
+        let mut ratchet = self.build_from_shared_secret(shared_secret, false)?;
+        ratchet.seed_initial_dh_key(ratchet_private);
+        Ok(ratchet)
+    }
+}
+
+impl DoubleRatchet {
+    /// Start configuring a ratchet with `DoubleRatchetBuilder`
+    pub fn builder() -> DoubleRatchetBuilder {
+        DoubleRatchetBuilder::new()
+    }
+
+    /// Get the settings this ratchet was configured with
+    pub fn config(&self) -> RatchetConfig {
+        RatchetConfig {
+            suite: self.current_suite(),
+            context: self.context().to_vec(),
+            max_skip: self.max_skip(),
+            rekey_after: self.configured_chain_message_limit(),
+        }
+    }
+}
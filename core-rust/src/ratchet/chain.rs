@@ -1,4 +1,20 @@
 use crate::error::{E2EEError, Result};
+use zeroize::Zeroize;
+
+/// Maximum number of message keys a single chain can derive
+///
+/// This is the ceiling of the chain's `u32` message counter, not a security
+/// margin: it is what a chain is limited to when no cap is configured. A
+/// lower per-chain cap is opt-in (`DoubleRatchet::set_chain_message_limit`,
+/// `DoubleRatchetBuilder::rekey_after`); once a chain reaches its cap it
+/// refuses to derive further keys and a DH ratchet is mandatory.
+pub const MAX_CHAIN_MESSAGES: u32 = u32::MAX;
+
+/// Keys derived for a single message: `(encryption_key, auth_key)`
+/// 
+/// The encryption key only ever keys the AEAD; anything else that needs a
+/// per-message key (nonce derivation, signatures) uses the auth key.
+pub type MessageKeys = ([u8; 32], [u8; 32]);
 
 /// Chain key for Double Ratchet
 /// 
@@ -10,6 +26,10 @@ pub struct Chain {
     chain_key: [u8; 32],
     /// Message number in this chain
     message_number: u32,
+    /// Maximum number of message keys this chain may derive
+    message_limit: u32,
+    /// Application context used as HKDF salt (empty by default)
+    context: Vec<u8>,
 }
 
 impl Chain {
@@ -18,40 +38,105 @@ impl Chain {
     /// # Arguments
     /// * `chain_key` - Initial 32-byte chain key
     pub fn new(chain_key: [u8; 32]) -> Self {
+        Self::with_message_limit(chain_key, MAX_CHAIN_MESSAGES)
+    }
+
+    /// Create a new chain that refuses to derive more than `message_limit` keys
+    /// 
+    /// # Arguments
+    /// * `chain_key` - Initial 32-byte chain key
+    /// * `message_limit` - Maximum number of message keys (at most `MAX_CHAIN_MESSAGES`)
+    pub fn with_message_limit(chain_key: [u8; 32], message_limit: u32) -> Self {
+        Self::with_context(chain_key, message_limit, &[])
+    }
+
+    /// Create a new chain whose derivations are bound to an application context
+    /// 
+    /// The context is used as HKDF salt for every message and chain key, so
+    /// chains seeded with the same key but different contexts never agree.
+    /// 
+    /// # Arguments
+    /// * `chain_key` - Initial 32-byte chain key
+    /// * `message_limit` - Maximum number of message keys (at most `MAX_CHAIN_MESSAGES`)
+    /// * `context` - Application context (empty keeps the original key schedule)
+    pub fn with_context(chain_key: [u8; 32], message_limit: u32, context: &[u8]) -> Self {
         Self {
             chain_key,
             message_number: 0,
+            message_limit,
+            context: context.to_vec(),
         }
     }
 
-    /// Ratchet forward to derive the next chain key and message key
+    /// Rebuild a chain at a saved position
+    /// 
+    /// # Arguments
+    /// * `chain_key` - Chain key at that position
+    /// * `message_number` - Number of keys already derived
+    /// * `message_limit` - Maximum number of message keys
+    /// * `context` - Application context
+    pub(crate) fn from_parts(chain_key: [u8; 32], message_number: u32, message_limit: u32, context: &[u8]) -> Self {
+        Self {
+            chain_key,
+            message_number,
+            message_limit,
+            context: context.to_vec(),
+        }
+    }
+
+    /// Copy the chain at its current position, e.g. to derive keys tentatively
+    pub(crate) fn duplicate(&self) -> Self {
+        Self::from_parts(self.chain_key, self.message_number, self.message_limit, &self.context)
+    }
+
+    /// Ratchet forward to derive the next message keys and chain key
     /// 
     /// This method:
-    /// 1. Derives a message key from the current chain key
+    /// 1. Derives the message's encryption and auth keys from the current chain key
     /// 2. Ratchets the chain key forward using HKDF
     /// 3. Increments the message number
     /// 
     /// # Returns
-    /// A tuple containing (message_key, new_chain_key)
-    pub fn ratchet_forward(&mut self) -> Result<([u8; 32], [u8; 32])> {
-        // Derive message key from current chain key using HKDF
-        let message_key = self.derive_message_key()?;
+    /// MessageKeys `(encryption_key, auth_key)`, or `StateError` if the chain
+    /// has reached its message limit and must be replaced by a DH ratchet
+    pub fn ratchet_forward(&mut self) -> Result<MessageKeys> {
+        if self.is_exhausted() {
+            return Err(E2EEError::StateError(format!(
+                "Chain message limit reached ({} messages); DH ratchet required",
+                self.message_limit
+            )));
+        }
+        
+        let next_message_number = self.message_number
+            .checked_add(1)
+            .ok_or_else(|| E2EEError::StateError("message counter overflow, rekey required".to_string()))?;
+        
+        // Derive message keys from current chain key using HKDF
+        let encryption_key = self.derive_message_key()?;
+        let auth_key = self.derive_auth_key()?;
         
         // Ratchet chain key forward using HKDF
         let new_chain_key = self.derive_next_chain_key()?;
         self.chain_key = new_chain_key;
-        self.message_number += 1;
+        self.message_number = next_message_number;
         
-        Ok((message_key, self.chain_key))
+        Ok((encryption_key, auth_key))
     }
 
-    /// Derive message key from current chain key
+    /// Derive message (encryption) key from current chain key
     /// 
     /// Uses HKDF-SHA256 with label "message_key" to derive 32-byte message key
     fn derive_message_key(&self) -> Result<[u8; 32]> {
         self.hkdf_derive(&self.chain_key, b"message_key")
     }
 
+    /// Derive message auth key from current chain key
+    /// 
+    /// Uses HKDF-SHA256 with label "auth_key" to derive 32-byte auth key
+    fn derive_auth_key(&self) -> Result<[u8; 32]> {
+        self.hkdf_derive(&self.chain_key, b"auth_key")
+    }
+
     /// Derive next chain key from current chain key
     /// 
     /// Uses HKDF-SHA256 with label "chain_key" to derive next 32-byte chain key
@@ -61,24 +146,9 @@ impl Chain {
 
     /// HKDF derivation helper
     /// 
-    /// Derives 32-byte key using HKDF-SHA256
+    /// Derives 32-byte key using HKDF-SHA256, salted with the chain's context
     fn hkdf_derive(&self, ikm: &[u8], info: &[u8]) -> Result<[u8; 32]> {
-        let salt = ring::hkdf::Salt::new(ring::hkdf::HKDF_SHA256, &[]);
-        
-        // Extract PRK
-        let prk = salt.extract(ikm);
-        
-        // Expand to 32 bytes with info
-        // Create array reference to avoid temporary value issue
-        let info_array = [info];
-        let okm = prk.expand(&info_array, ring::hkdf::HKDF_SHA256)
-            .map_err(|e| E2EEError::CryptoError(format!("HKDF expand failed: {}", e)))?;
-        
-        let mut output = [0u8; 32];
-        okm.fill(&mut output)
-            .map_err(|e| E2EEError::CryptoError(format!("HKDF fill failed: {}", e)))?;
-        
-        Ok(output)
+        crate::kdf::hkdf_32(ikm, &self.context, info)
     }
 
     /// Get current message number
@@ -86,10 +156,35 @@ impl Chain {
         self.message_number
     }
 
-    /// Get current chain key (for testing/debugging)
-    #[allow(dead_code)]
+    /// Get the maximum number of message keys this chain may derive
+    pub fn message_limit(&self) -> u32 {
+        self.message_limit
+    }
+
+    /// Lower (or restore) the message limit of an existing chain
+    pub(crate) fn set_message_limit(&mut self, message_limit: u32) {
+        self.message_limit = message_limit;
+    }
+
+    /// Check whether the chain has derived all the message keys it is allowed to
+    pub fn is_exhausted(&self) -> bool {
+        self.message_number >= self.message_limit
+    }
+
+    /// Zeroize the chain key and exhaust the chain so it derives nothing more
+    pub(crate) fn wipe(&mut self) {
+        self.chain_key.zeroize();
+        self.message_limit = 0;
+    }
+
+    /// Get current chain key (for state snapshots and debugging)
     pub(crate) fn chain_key(&self) -> &[u8; 32] {
         &self.chain_key
     }
 }
 
+impl Drop for Chain {
+    fn drop(&mut self) {
+        self.chain_key.zeroize();
+    }
+}
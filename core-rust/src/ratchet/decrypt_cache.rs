@@ -0,0 +1,76 @@
+use crate::ratchet::double_ratchet::DecryptedMessage;
+use std::collections::VecDeque;
+use zeroize::Zeroize;
+
+/// A decrypted message remembered for duplicate deliveries
+struct CachedDecrypt {
+    /// Remote DH public key of the envelope
+    dh_public: [u8; 32],
+    message_number: u64,
+    /// SHA-256 over the envelope's associated data and ciphertext
+    digest: [u8; 32],
+    message: DecryptedMessage,
+}
+
+impl Drop for CachedDecrypt {
+    fn drop(&mut self) {
+        self.message.plaintext.zeroize();
+    }
+}
+
+/// Bounded cache of recent plaintexts, making decrypt idempotent for duplicates
+/// 
+/// Only a byte-identical envelope (same DH key, message number, associated
+/// data and ciphertext) is served from the cache, so a forged envelope
+/// reusing a message number still fails. Evicted plaintexts are zeroized.
+pub(crate) struct DecryptCache {
+    window: usize,
+    entries: VecDeque<CachedDecrypt>,
+}
+
+impl DecryptCache {
+    /// Create a cache holding the last `window` decrypted messages
+    pub(crate) fn new(window: usize) -> Self {
+        Self {
+            window,
+            entries: VecDeque::new(),
+        }
+    }
+
+    /// Digest identifying an envelope, over its associated data and ciphertext
+    pub(crate) fn digest(aad: &[u8], ciphertext: &[u8]) -> [u8; 32] {
+        let mut context = ring::digest::Context::new(&ring::digest::SHA256);
+        context.update(&(aad.len() as u64).to_be_bytes());
+        context.update(aad);
+        context.update(ciphertext);
+        
+        let mut digest = [0u8; 32];
+        digest.copy_from_slice(context.finish().as_ref());
+        digest
+    }
+
+    /// Look up the plaintext of an envelope decrypted earlier
+    pub(crate) fn get(&self, dh_public: &[u8; 32], message_number: u64, digest: &[u8; 32]) -> Option<DecryptedMessage> {
+        self.entries.iter()
+            .find(|entry| {
+                &entry.dh_public == dh_public && entry.message_number == message_number && &entry.digest == digest
+            })
+            .map(|entry| entry.message.clone())
+    }
+
+    /// Remember a decrypted message, evicting (and zeroizing) the oldest beyond the window
+    pub(crate) fn insert(&mut self, dh_public: [u8; 32], digest: [u8; 32], message: DecryptedMessage) {
+        if self.window == 0 {
+            return;
+        }
+        while self.entries.len() >= self.window {
+            self.entries.pop_front();
+        }
+        self.entries.push_back(CachedDecrypt {
+            dh_public,
+            message_number: message.message_number,
+            digest,
+            message,
+        });
+    }
+}
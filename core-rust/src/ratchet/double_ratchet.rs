@@ -1,10 +1,173 @@
+use crate::encoding::{parse_curve_point_hex, parse_hex_32};
 use crate::error::{E2EEError, Result};
-use crate::message::MessageEnvelope;
-use crate::ratchet::chain::Chain;
+use crate::message::padding::{pad, unpad};
+use crate::message::{validate_envelope_consistency, MessageEnvelope, MessageType, PADDING_BUCKET, ROLE_INITIATOR, ROLE_RESPONDER};
+use crate::ratchet::chain::{Chain, MessageKeys, MAX_CHAIN_MESSAGES};
+use crate::ratchet::decrypt_cache::DecryptCache;
+use crate::ratchet::state::{ChainState, PublicRatchetState, RatchetState, StoredMessageKeys, SESSION_STATE_VERSION};
+use crate::ratchet::suite::{MessageCipher, SuiteDescriptor};
 use rand::rngs::OsRng;
-use ring::aead::{Aad, LessSafeKey, Nonce, UnboundKey, AES_256_GCM};
 use ring::hmac;
-use x25519_dalek::{EphemeralSecret, PublicKey};
+use std::collections::{BTreeSet, HashMap, VecDeque};
+use x25519_dalek::{PublicKey, StaticSecret};
+use zeroize::{Zeroize, Zeroizing};
+
+/// Maximum number of message keys that may be skipped in a single receiving chain
+///
+/// Bounds the work (and memory) an attacker can force on the receiver by sending
+/// an envelope with a huge message number.
+pub const MAX_SKIP: u64 = 1000;
+
+/// Number of earlier remote DH public keys remembered after DH ratchets
+const MAX_RETIRED_DH_KEYS: usize = 32;
+
+/// Byte order used to encode an integer
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Endian {
+    /// Least significant byte first
+    Little,
+    /// Most significant byte first
+    Big,
+}
+
+/// Byte order of the 8-byte message number fed into the nonce HMAC
+/// 
+/// Part of the wire format: a peer using a different order derives different
+/// nonces and every decryption fails. Encryption and decryption both go
+/// through `DoubleRatchet::derive_nonce`, which reads this constant.
+pub const NONCE_MESSAGE_NUMBER_ENDIAN: Endian = Endian::Little;
+
+/// Result of decrypting an envelope, with the metadata authenticated by the AEAD
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DecryptedMessage {
+    /// Decrypted plaintext
+    pub plaintext: Vec<u8>,
+    /// Verified sender timestamp (if the envelope carried one)
+    pub sent_at: Option<u64>,
+    /// Message number from the envelope header
+    pub message_number: u64,
+    /// Our message number acknowledged by this envelope, if it is a receipt
+    pub receipt_for: Option<u64>,
+    /// The peer's sending chain hit its message cap: send it any message
+    /// (e.g. `encrypt_key_exchange`) so it learns a new DH key and can ratchet
+    pub rekey_requested: bool,
+    /// Where the message key came from
+    pub source: DecryptSource,
+}
+
+/// Where the key that decrypted a message came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DecryptSource {
+    /// Derived from the live receiving chain (message arrived in order or ahead)
+    Live,
+    /// Taken from the skipped-key store (message arrived out of order)
+    SkippedStore,
+    /// Served again from the idempotent-decrypt cache (duplicate delivery);
+    /// the ratchet did not advance
+    DuplicateCache,
+}
+
+/// Optional header fields bound into the AEAD associated data on encryption
+#[derive(Default)]
+struct HeaderMetadata {
+    sent_at: Option<u64>,
+    padded: bool,
+    recipient_device_id: Option<u32>,
+    receipt_for: Option<u64>,
+    key_exchange: bool,
+}
+
+/// Receive-side changes from decrypting one envelope, applied once it authenticates
+/// 
+/// Built by `stage_receive` from copies of the ratchet state, so an envelope
+/// with a forged header (new DH key, `previous_chain_length`, message number)
+/// cannot move the ratchet. Keys still held are zeroized on drop.
+#[derive(Default)]
+struct StagedReceive {
+    /// Skipped-key store entry that decrypted the message, removed on commit
+    used_stored_key: Option<([u8; 32], u64)>,
+    /// Keys of skipped messages to add to the skipped-key store
+    skipped_keys: Vec<(([u8; 32], u64), MessageKeys)>,
+    /// Receiving chain advanced past the message (None if it stays unchanged)
+    receiving_chain: Option<Chain>,
+    /// DH ratchet step taken for a new remote DH key
+    dh_ratchet: Option<StagedDhRatchet>,
+}
+
+/// Receiving half of a DH ratchet step; the sending half runs on commit
+struct StagedDhRatchet {
+    remote_dh_public: PublicKey,
+    root_key: [u8; 32],
+    /// Header message number just before the new receiving chain's first key
+    receiving_chain_start: u64,
+}
+
+impl Drop for StagedReceive {
+    fn drop(&mut self) {
+        for (_, (encryption_key, auth_key)) in self.skipped_keys.iter_mut() {
+            encryption_key.zeroize();
+            auth_key.zeroize();
+        }
+    }
+}
+
+impl Drop for StagedDhRatchet {
+    fn drop(&mut self) {
+        self.root_key.zeroize();
+    }
+}
+
+/// Values the receiver would use to decrypt an envelope (debugging only)
+#[cfg(feature = "test-support")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EnvelopeInspection {
+    /// AES-GCM nonce derived for this envelope, as hex
+    pub derived_nonce_hex: String,
+    /// Ciphertext length in bytes, excluding the 16-byte tag
+    pub ciphertext_len: usize,
+    /// AES-GCM authentication tag carried by the envelope, as hex
+    pub tag_hex: String,
+    /// Message key the receiving chain derives for this envelope, as hex
+    pub expected_message_key_hex: String,
+}
+
+/// Saved position of a sending chain, to simulate a state rewind (testing only)
+/// 
+/// Created by `DoubleRatchet::sending_chain_checkpoint`; the chain key is
+/// zeroized on drop.
+#[cfg(feature = "test-support")]
+pub struct SendingChainCheckpoint {
+    chain_key: [u8; 32],
+    chain_message_number: u32,
+    sending_message_number: u64,
+}
+
+#[cfg(feature = "test-support")]
+impl Drop for SendingChainCheckpoint {
+    fn drop(&mut self) {
+        self.chain_key.zeroize();
+    }
+}
+
+/// Hashed snapshot of a ratchet's state for diffing two instances (debugging only)
+/// 
+/// Every key is reported as the hex SHA-256 of its value; no secret is exposed.
+#[cfg(feature = "test-support")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AuditReport {
+    /// Hash of the root key the session was initialized from
+    pub root_key_hash_hex: String,
+    /// Hash of the current sending chain key
+    pub sending_chain_key_hash_hex: String,
+    /// Hash of the current receiving chain key, None if there is no receiving chain
+    pub receiving_chain_key_hash_hex: Option<String>,
+    /// Number of messages sent on the current sending chain
+    pub sending_message_number: u64,
+    /// Number of keys derived on the current receiving chain
+    pub receiving_message_number: u64,
+    /// Number of message keys held for skipped messages
+    pub skipped_key_count: usize,
+}
 
 /// Double Ratchet for forward secrecy and break-in recovery
 /// 
@@ -17,11 +180,60 @@ pub struct DoubleRatchet {
     /// Receiving chain - ratchets forward when receiving DH keys
     receiving_chain: Option<Chain>,
     /// Current DH key pair for DH ratchet
-    dh_key_pair: EphemeralSecret,
+    dh_key_pair: StaticSecret,
+    /// Public half of `dh_key_pair`, cached so sends skip the scalar multiplication
+    dh_public: PublicKey,
     /// Remote DH public key
     remote_dh_public: Option<PublicKey>,
+    /// Remote DH public keys of earlier ratchet steps, oldest first
+    retired_remote_dh_publics: VecDeque<[u8; 32]>,
+    /// Header message number just before the receiving chain's first key
+    receiving_chain_start: u64,
+    /// Set for a responder whose DH key the initiator already knows; the first
+    /// received message then performs a DH ratchet instead of using the initial chain
+    ratchet_on_first_receive: bool,
     /// Message number for sending
     sending_message_number: u64,
+    /// Messages sent under our previous DH key, reported as `previous_chain_length`
+    previous_sending_chain_length: u32,
+    /// Whether this side was the X3DH initiator
+    is_initiator: bool,
+    /// Whether a DH ratchet step has run at least once
+    has_ratcheted: bool,
+    /// Whether a sending chain has ever been derived from a DH step; until
+    /// then both peers share the root key and a key exchange may start one
+    sending_ratchet_started: bool,
+    /// Set by `close`; a closed ratchet refuses to encrypt or decrypt
+    closed: bool,
+    /// Per-chain message cap applied to every chain this ratchet creates, if
+    /// one was configured (otherwise chains stop at `MAX_CHAIN_MESSAGES`)
+    chain_message_limit: Option<u32>,
+    /// Whether 33-byte type-prefixed DH keys (Signal encoding) are accepted
+    accept_prefixed_keys: bool,
+    /// Maximum number of message keys skipped in one receiving chain gap
+    max_skip: u64,
+    /// Recent plaintexts served again for duplicate envelopes (None when disabled)
+    decrypt_cache: Option<DecryptCache>,
+    /// (key, nonce) pairs sealed under on the current sending chain, checked
+    /// in debug builds only; not persisted by `to_state`
+    #[cfg(debug_assertions)]
+    nonce_guard: crate::aead::NonceReuseGuard,
+    /// Application context binding every key derivation (empty by default)
+    context: Vec<u8>,
+    /// Suite this ratchet was configured with (default unless set by the builder)
+    suite: SuiteDescriptor,
+    /// AEAD for message bodies, resolved from `suite`
+    cipher: MessageCipher,
+    /// Message keys derived for skipped messages, keyed by (remote DH public, message number)
+    skipped_message_keys: HashMap<([u8; 32], u64), MessageKeys>,
+    /// Sending keys derived ahead of time by `precompute_send_keys`, oldest first
+    precomputed_send_keys: VecDeque<(u64, MessageKeys)>,
+    /// Every message number in `1..=received_through` has been decrypted
+    received_through: u64,
+    /// Message numbers above `received_through + 1` decrypted out of order
+    received_out_of_order: BTreeSet<u64>,
+    /// Current root key, advanced by every DH ratchet step
+    root_key: [u8; 32],
 }
 
 impl DoubleRatchet {
@@ -34,14 +246,34 @@ impl DoubleRatchet {
     /// Note: In Double Ratchet, after X3DH:
     /// - Initiator (Alice): sending_chain = derive(root, "sending"), receiving_chain = derive(root, "receiving")
     /// - Responder (Bob): sending_chain = derive(root, "receiving"), receiving_chain = derive(root, "sending")
+    /// 
     /// This ensures Alice's sending matches Bob's receiving and vice versa.
     pub fn from_shared_secret(shared_secret: &[u8; 32], is_initiator: bool) -> Result<Self> {
+        Self::from_shared_secret_with_context(shared_secret, is_initiator, &[])
+    }
+
+    /// Create a new Double Ratchet whose key schedule is bound to an application context
+    /// 
+    /// The context is used as HKDF salt for the root-to-chain derivation and for
+    /// every chain derivation, so ratchets seeded with the same shared secret
+    /// but different contexts derive unrelated keys. Both parties must use the
+    /// same context.
+    /// 
+    /// # Arguments
+    /// * `shared_secret` - 32-byte shared secret from X3DH handshake
+    /// * `is_initiator` - true if this is the X3DH initiator (Alice), false if responder (Bob)
+    /// * `context` - Application context (empty matches `from_shared_secret`)
+    pub fn from_shared_secret_with_context(
+        shared_secret: &[u8; 32],
+        is_initiator: bool,
+        context: &[u8],
+    ) -> Result<Self> {
         // Derive root key and chain keys from shared secret
         let root_key = shared_secret;
         
         // Derive both chain keys
-        let sending_chain_key_derived = Self::derive_chain_key(root_key, b"sending")?;
-        let receiving_chain_key_derived = Self::derive_chain_key(root_key, b"receiving")?;
+        let sending_chain_key_derived = Self::derive_chain_key(context, root_key, b"sending")?;
+        let receiving_chain_key_derived = Self::derive_chain_key(context, root_key, b"receiving")?;
         
         // Swap chains for responder so they match initiator's setup
         // Alice (initiator): sending_chain = "sending", receiving_chain = "receiving"
@@ -58,17 +290,309 @@ impl DoubleRatchet {
         };
         
         // Generate initial DH key pair
-        let dh_key_pair = EphemeralSecret::random_from_rng(OsRng);
+        let dh_key_pair = StaticSecret::random_from_rng(OsRng);
         
         Ok(Self {
-            sending_chain: Chain::new(sending_chain_key),
-            receiving_chain: Some(Chain::new(receiving_chain_key)),
+            sending_chain: Chain::with_context(sending_chain_key, MAX_CHAIN_MESSAGES, context),
+            receiving_chain: Some(Chain::with_context(receiving_chain_key, MAX_CHAIN_MESSAGES, context)),
+            dh_public: PublicKey::from(&dh_key_pair),
             dh_key_pair,
             remote_dh_public: None,
+            retired_remote_dh_publics: VecDeque::new(),
+            receiving_chain_start: 0,
+            ratchet_on_first_receive: false,
             sending_message_number: 0,
+            previous_sending_chain_length: 0,
+            is_initiator,
+            has_ratcheted: false,
+            sending_ratchet_started: false,
+            closed: false,
+            chain_message_limit: None,
+            accept_prefixed_keys: false,
+            max_skip: MAX_SKIP,
+            decrypt_cache: None,
+            #[cfg(debug_assertions)]
+            nonce_guard: Default::default(),
+            context: context.to_vec(),
+            suite: SuiteDescriptor::default_suite(),
+            cipher: MessageCipher::Aes256Gcm,
+            skipped_message_keys: HashMap::new(),
+            precomputed_send_keys: VecDeque::new(),
+            received_through: 0,
+            received_out_of_order: BTreeSet::new(),
+            root_key: Self::derive_chain_key(context, root_key, b"root")?,
         })
     }
 
+    /// Create the initiator's ratchet, seeded with the responder's signed prekey
+    /// 
+    /// Bob's signed prekey doubles as his initial DH ratchet key (as in the
+    /// Signal spec): Alice's first sending chain is derived from
+    /// DH(her initial DH key, Bob's signed prekey), so her very first message
+    /// already runs through the DH ratchet. Messages Bob sends before he has
+    /// heard from Alice use the initial receiving chain of `from_shared_secret`.
+    /// 
+    /// # Arguments
+    /// * `shared_secret` - 32-byte shared secret from X3DH handshake
+    /// * `remote_ratchet_public` - Bob's signed prekey public key
+    pub fn from_x3dh_initiator(shared_secret: &[u8; 32], remote_ratchet_public: &[u8; 32]) -> Result<Self> {
+        let mut ratchet = Self::from_shared_secret(shared_secret, true)?;
+        ratchet.set_initial_remote_dh(*remote_ratchet_public)?;
+        Ok(ratchet)
+    }
+
+    /// Create the responder's ratchet, using the signed prekey as initial DH key
+    /// 
+    /// Counterpart of `from_x3dh_initiator`. The first message received from
+    /// Alice performs a DH ratchet against the signed prekey, after which Bob
+    /// sends under a fresh DH key.
+    /// 
+    /// # Arguments
+    /// * `shared_secret` - 32-byte shared secret from X3DH handshake
+    /// * `signed_prekey_private` - Bob's signed prekey private key
+    pub fn from_x3dh_responder(shared_secret: &[u8; 32], signed_prekey_private: &StaticSecret) -> Result<Self> {
+        let mut ratchet = Self::from_shared_secret(shared_secret, false)?;
+        ratchet.seed_initial_dh_key(signed_prekey_private);
+        Ok(ratchet)
+    }
+
+    /// Use a key the initiator knows the public half of as our first DH key
+    /// 
+    /// The first message received then performs a DH ratchet against it,
+    /// as for a responder created with `from_x3dh_responder`.
+    pub(crate) fn seed_initial_dh_key(&mut self, private_key: &StaticSecret) {
+        self.dh_key_pair = private_key.clone();
+        self.dh_public = PublicKey::from(&self.dh_key_pair);
+        self.ratchet_on_first_receive = true;
+    }
+
+    /// Prime the remote DH public key before the first message
+    /// 
+    /// For protocols where the responder's first DH key is known in advance
+    /// (e.g. from its prekey bundle). Derives the sending chain by a DH
+    /// ratchet step against `public`, matching the receiving chain of a
+    /// responder created with `from_x3dh_responder` for the same key.
+    /// 
+    /// # Arguments
+    /// * `public` - The peer's initial DH public key
+    /// 
+    /// # Returns
+    /// Ok(()), or `StateError` once the ratchet has sent, received or been closed
+    pub fn set_initial_remote_dh(&mut self, public: [u8; 32]) -> Result<()> {
+        self.ensure_open()?;
+        if self.remote_dh_public.is_some() || self.sending_message_number > 0 {
+            return Err(E2EEError::StateError(
+                "Initial remote DH key must be set before the first message".to_string(),
+            ));
+        }
+        
+        self.remote_dh_public = Some(PublicKey::from(public));
+        self.ratchet_sending_chain()
+    }
+
+    /// Set the per-chain message cap
+    /// 
+    /// The cap is opt-in: without one a chain is only bounded by
+    /// `MAX_CHAIN_MESSAGES`. It applies to the current sending and receiving
+    /// chains and to every chain created by later DH ratchets. Once a chain
+    /// reaches the cap it refuses to derive further keys, so a DH ratchet
+    /// becomes mandatory. The last message of a capped sending chain carries
+    /// `rekey_requested`, asking the peer to send us a new DH key; any reply
+    /// from the peer starts a fresh sending chain.
+    /// 
+    /// # Arguments
+    /// * `limit` - Maximum number of messages per chain
+    pub fn set_chain_message_limit(&mut self, limit: u32) {
+        self.chain_message_limit = Some(limit);
+        self.sending_chain.set_message_limit(limit);
+        if let Some(receiving_chain) = self.receiving_chain.as_mut() {
+            receiving_chain.set_message_limit(limit);
+        }
+    }
+
+    /// Get the per-chain message cap (`MAX_CHAIN_MESSAGES` if none is configured)
+    pub fn chain_message_limit(&self) -> u32 {
+        self.chain_message_limit.unwrap_or(MAX_CHAIN_MESSAGES)
+    }
+
+    /// Get the per-chain message cap, or None if none was configured
+    pub fn configured_chain_message_limit(&self) -> Option<u32> {
+        self.chain_message_limit
+    }
+
+    /// Accept 33-byte `0x05`-prefixed DH keys in incoming headers
+    /// 
+    /// Compatibility switch for peers that serialize X25519 keys the way
+    /// Signal does. Raw 32-byte keys are always accepted; outgoing headers
+    /// keep the raw form.
+    /// 
+    /// # Arguments
+    /// * `accept` - true to also accept the type-prefixed encoding
+    pub fn set_accept_prefixed_keys(&mut self, accept: bool) {
+        self.accept_prefixed_keys = accept;
+    }
+
+    /// Check whether 33-byte type-prefixed DH keys are accepted
+    pub fn accepts_prefixed_keys(&self) -> bool {
+        self.accept_prefixed_keys
+    }
+
+    /// Set how many message keys may be skipped in one receiving chain gap
+    /// 
+    /// Defaults to `MAX_SKIP`. Incoming messages further ahead of the chain
+    /// than this are rejected instead of deriving and storing the keys in
+    /// between.
+    /// 
+    /// # Arguments
+    /// * `max_skip` - Maximum number of skipped keys per gap
+    pub fn set_max_skip(&mut self, max_skip: u64) {
+        self.max_skip = max_skip;
+    }
+
+    /// Get the maximum number of message keys skipped in one receiving chain gap
+    pub fn max_skip(&self) -> u64 {
+        self.max_skip
+    }
+
+    /// Serve duplicate deliveries of recently decrypted envelopes from a cache
+    /// 
+    /// With at-least-once delivery the same envelope can arrive twice. While
+    /// enabled, decrypting a byte-identical copy of one of the last `window`
+    /// decrypted envelopes returns the same result again instead of failing.
+    /// Cached plaintexts stay in memory only (they are not part of
+    /// `to_state`) and are zeroized when evicted or when the ratchet closes.
+    /// 
+    /// # Arguments
+    /// * `window` - Number of recent messages to remember (0 disables the cache)
+    pub fn enable_idempotent_decrypt(&mut self, window: usize) {
+        self.decrypt_cache = (window > 0).then(|| DecryptCache::new(window));
+    }
+
+    /// Get the application context the key schedule is bound to (empty if none)
+    pub fn context(&self) -> &[u8] {
+        &self.context
+    }
+
+    /// Get the suite this ratchet was configured with
+    pub(crate) fn suite(&self) -> &SuiteDescriptor {
+        &self.suite
+    }
+
+    /// Protect message bodies with `suite`; both peers must use the same one
+    /// 
+    /// # Returns
+    /// `ProtocolError` if this build does not support the suite
+    pub(crate) fn set_suite(&mut self, suite: SuiteDescriptor) -> Result<()> {
+        self.cipher = suite.message_cipher()?;
+        self.suite = suite;
+        Ok(())
+    }
+
+    /// Number of messages that can still be sent before a DH ratchet is forced
+    /// 
+    /// # Returns
+    /// Remaining sends on the current sending chain under the per-chain cap
+    /// (0 means the next send fails until a DH ratchet), or None when no cap
+    /// has been set with `set_chain_message_limit`
+    pub fn messages_until_rekey(&self) -> Option<u64> {
+        self.chain_message_limit?;
+        Some(self.remaining_sends())
+    }
+
+    /// Sends left on the current sending chain, counting precomputed keys
+    fn remaining_sends(&self) -> u64 {
+        let used = self.sending_chain.message_number();
+        let remaining = self.sending_chain.message_limit().saturating_sub(used) as u64;
+        remaining + self.precomputed_send_keys.len() as u64
+    }
+
+    /// Derive the next `n` sending keys ahead of time
+    /// 
+    /// Ratchets the sending chain forward now so that the following sends take
+    /// their key from the cache instead of running HKDF inline. Envelopes are
+    /// identical to those produced without precomputation. The cache is
+    /// zeroized on close/drop and discarded by a DH ratchet.
+    /// 
+    /// # Arguments
+    /// * `n` - Number of additional keys to derive
+    /// 
+    /// # Returns
+    /// Ok(()), `ProtocolError` if more than `MAX_SKIP` keys would be cached, or
+    /// if the chain's message cap is reached (keys derived before that point
+    /// stay cached)
+    pub fn precompute_send_keys(&mut self, n: usize) -> Result<()> {
+        self.ensure_open()?;
+        // Discarded keys become skipped messages for the peer, which accepts at most MAX_SKIP
+        if (self.precomputed_send_keys.len() + n) as u64 > MAX_SKIP {
+            return Err(E2EEError::ProtocolError(format!(
+                "Too many precomputed send keys: {} (max {})",
+                self.precomputed_send_keys.len() + n,
+                MAX_SKIP
+            )));
+        }
+        for _ in 0..n {
+            let message_number = self.sending_message_number
+                .checked_add(self.precomputed_send_keys.len() as u64 + 1)
+                .ok_or_else(Self::counter_overflow)?;
+            let message_keys = self.sending_chain.ratchet_forward()?;
+            self.precomputed_send_keys.push_back((message_number, message_keys));
+        }
+        Ok(())
+    }
+
+    /// Number of precomputed sending keys not used yet
+    pub fn precomputed_send_key_count(&self) -> usize {
+        self.precomputed_send_keys.len()
+    }
+
+    /// Error for a sending counter that cannot be incremented any further
+    fn counter_overflow() -> E2EEError {
+        E2EEError::StateError("message counter overflow, rekey required".to_string())
+    }
+
+    /// Length of the current sending chain, as reported in `previous_chain_length`
+    /// 
+    /// Precomputed keys count as sent, since the ratchet step discards them.
+    /// The header field is 32 bits, so a longer chain is an error rather than
+    /// a saturated length the peer would misread.
+    fn sending_chain_length(&self) -> Result<u32> {
+        self.sending_message_number
+            .checked_add(self.precomputed_send_keys.len() as u64)
+            .and_then(|length| u32::try_from(length).ok())
+            .ok_or_else(Self::counter_overflow)
+    }
+
+    /// Number of the next message to send, checked before any key is consumed
+    /// 
+    /// A wrapped counter would reuse nonces, so overflow is an error instead.
+    fn next_sending_message_number(&self) -> Result<u64> {
+        self.sending_message_number.checked_add(1).ok_or_else(Self::counter_overflow)
+    }
+
+    /// Take the keys for the next send, from the cache if one was precomputed
+    fn next_sending_keys(&mut self) -> Result<MessageKeys> {
+        match self.precomputed_send_keys.pop_front() {
+            Some((message_number, message_keys)) => {
+                debug_assert_eq!(message_number, self.sending_message_number + 1);
+                Ok(message_keys)
+            }
+            None => self.sending_chain.ratchet_forward(),
+        }
+    }
+
+    /// Zeroize and drop the precomputed sending keys
+    /// 
+    /// The chain has already moved past them, so the message numbers are
+    /// consumed; the peer stores their keys as skipped.
+    fn discard_precomputed_send_keys(&mut self) {
+        for (_, (encryption_key, auth_key)) in self.precomputed_send_keys.iter_mut() {
+            encryption_key.zeroize();
+            auth_key.zeroize();
+        }
+        self.sending_message_number += self.precomputed_send_keys.len() as u64;
+        self.precomputed_send_keys.clear();
+    }
+
     /// Encrypt a plaintext message into a MessageEnvelope
     /// 
     /// # Arguments
@@ -77,63 +601,295 @@ impl DoubleRatchet {
     /// # Returns
     /// MessageEnvelope containing encrypted message and metadata
     pub fn encrypt_envelope(&mut self, plaintext: &[u8]) -> Result<MessageEnvelope> {
-        // Ratchet sending chain forward to get message key
-        let (message_key, _) = self.sending_chain.ratchet_forward()?;
+        self.encrypt_envelope_with_metadata(plaintext, HeaderMetadata::default())
+    }
+
+    /// Encrypt a plaintext message with an authenticated sent timestamp
+    /// 
+    /// The timestamp travels in the clear in the header but is bound into the
+    /// AEAD associated data, so any tampering makes decryption fail.
+    /// 
+    /// # Arguments
+    /// * `plaintext` - Plaintext message to encrypt
+    /// * `sent_at` - Sender's timestamp (seconds since Unix epoch)
+    /// 
+    /// # Returns
+    /// MessageEnvelope containing encrypted message and metadata
+    pub fn encrypt_envelope_at(&mut self, plaintext: &[u8], sent_at: u64) -> Result<MessageEnvelope> {
+        self.encrypt_envelope_with_metadata(plaintext, HeaderMetadata { sent_at: Some(sent_at), ..Default::default() })
+    }
+
+    /// Encrypt a plaintext message padded to hide its length
+    /// 
+    /// The plaintext is length-prefixed and zero-padded to the next multiple of
+    /// `PADDING_BUCKET` bytes before encryption, so the padding is authenticated.
+    /// The header's `padded` flag tells the receiver to strip it.
+    /// 
+    /// # Arguments
+    /// * `plaintext` - Plaintext message to encrypt
+    /// 
+    /// # Returns
+    /// MessageEnvelope containing encrypted message and metadata
+    pub fn encrypt_envelope_padded(&mut self, plaintext: &[u8]) -> Result<MessageEnvelope> {
+        let padded = pad(plaintext, PADDING_BUCKET)?;
+        self.encrypt_envelope_with_metadata(&padded, HeaderMetadata { padded: true, ..Default::default() })
+    }
+
+    /// Encrypt a plaintext message addressed to one of the recipient's devices
+    /// 
+    /// The device id travels in the header for relay routing and is bound into
+    /// the AEAD associated data, so it cannot be changed in transit.
+    /// 
+    /// # Arguments
+    /// * `plaintext` - Plaintext message to encrypt
+    /// * `device_id` - Recipient device (registration) id
+    /// 
+    /// # Returns
+    /// MessageEnvelope containing encrypted message and metadata
+    pub fn encrypt_envelope_for_device(&mut self, plaintext: &[u8], device_id: u32) -> Result<MessageEnvelope> {
+        self.encrypt_envelope_with_metadata(
+            plaintext,
+            HeaderMetadata { recipient_device_id: Some(device_id), ..Default::default() },
+        )
+    }
+
+    /// Encrypt a receipt acknowledging a message received from the peer
+    /// 
+    /// The receipt is an envelope with an empty body whose header names the
+    /// acknowledged message number. The number is bound into the AEAD
+    /// associated data, so it cannot be changed in transit.
+    /// 
+    /// # Arguments
+    /// * `for_message_number` - Number of the peer message being acknowledged
+    /// 
+    /// # Returns
+    /// Receipt envelope, or `ProtocolError` if that message was never received
+    pub fn encrypt_receipt(&mut self, for_message_number: u64) -> Result<MessageEnvelope> {
+        if !self.has_received(for_message_number) {
+            return Err(E2EEError::ProtocolError(format!(
+                "Cannot acknowledge message {}: it was never received",
+                for_message_number
+            )));
+        }
+        self.encrypt_envelope_with_metadata(
+            &[],
+            HeaderMetadata { receipt_for: Some(for_message_number), ..Default::default() },
+        )
+    }
+
+    /// Encrypt a key exchange announcing our DH public key, to rekey without a data message
+    /// 
+    /// The envelope has type `KeyExchange` and an empty authenticated body.
+    /// If this side has never taken a DH sending step, one is taken first
+    /// (a fresh DH key pair and sending chain); otherwise the current key is
+    /// announced. A peer seeing a DH key it does not know yet performs a DH
+    /// ratchet, so later data messages in both directions use new chains.
+    /// Only one side should start the first key exchange: two concurrent ones
+    /// from the initial state derive different root keys.
+    /// 
+    /// # Returns
+    /// Key exchange envelope, or `StateError` if no message from the peer has
+    /// been received yet (its DH key is unknown)
+    pub fn encrypt_key_exchange(&mut self) -> Result<MessageEnvelope> {
+        self.ensure_open()?;
+        if !self.sending_ratchet_started {
+            self.ratchet_sending_chain()?;
+        }
+        self.encrypt_envelope_with_metadata(&[], HeaderMetadata { key_exchange: true, ..Default::default() })
+    }
+
+    /// Associated data of an envelope: its header's, plus the key exchange marker
+    /// 
+    /// Binding the type keeps an empty data message or receipt from being
+    /// relabelled as a key exchange in transit, and vice versa.
+    fn envelope_associated_data(envelope: &MessageEnvelope) -> Vec<u8> {
+        let mut aad = envelope.header.associated_data();
+        if envelope.message_type == MessageType::KeyExchange {
+            aad.extend_from_slice(b"key_exchange");
+        }
+        aad
+    }
+
+    /// Encrypt a plaintext message, binding optional header metadata into the AEAD
+    fn encrypt_envelope_with_metadata(&mut self, plaintext: &[u8], metadata: HeaderMetadata) -> Result<MessageEnvelope> {
+        self.ensure_open()?;
         
-        // Increment sending message number (must be done before encryption to use correct nonce)
-        self.sending_message_number += 1;
-        let message_number = self.sending_message_number;
+        // Checked first, so an overflowing counter consumes no key
+        let message_number = self.next_sending_message_number()?;
         
-        // Encrypt plaintext with message key using AES-256-GCM with message-number-based nonce
-        let ciphertext = Self::encrypt_with_key(&message_key, plaintext, message_number)?;
+        // Ratchet sending chain forward (or take a precomputed key) to get message key
+        let message_keys = self.next_sending_keys()?;
+        
+        // Increment sending message number (must be done before encryption to use correct nonce)
+        self.sending_message_number = message_number;
         
         // Get DH public key for header
-        let dh_public = PublicKey::from(&self.dh_key_pair);
-        let dh_public_hex = hex::encode(dh_public.as_bytes());
+        let dh_public_hex = hex::encode(self.dh_public.as_bytes());
         
-        // Create message envelope
-        let envelope = MessageEnvelope::regular(
-            ciphertext,
+        // Create message envelope (header first, it feeds the associated data)
+        let mut envelope = MessageEnvelope::regular(
+            Vec::new(),
             dh_public_hex,
-            0, // previous_chain_length (simplified for now)
+            self.previous_sending_chain_length,
             message_number,
         );
+        envelope.header.sent_at = metadata.sent_at;
+        envelope.header.padded = metadata.padded;
+        envelope.header.recipient_device_id = metadata.recipient_device_id;
+        envelope.header.receipt_for = metadata.receipt_for;
+        if metadata.key_exchange {
+            envelope.message_type = MessageType::KeyExchange;
+        }
+        // Until the first DH ratchet, tell the peer which role we think we have
+        envelope.header.role_hint = (!self.has_ratcheted).then_some(self.role());
+        // The chain's last message asks the peer for the DH key we need to continue
+        envelope.header.rekey_requested = self.remaining_sends() == 0;
+        
+        // Debug builds refuse to seal twice under the same key and nonce
+        #[cfg(debug_assertions)]
+        self.nonce_guard.record(&message_keys.0, &Self::derive_nonce(&message_keys.1, message_number)?)?;
+        
+        // Encrypt plaintext with message key using AES-256-GCM with message-number-based nonce
+        let aad = Self::envelope_associated_data(&envelope);
+        envelope.ciphertext = Self::encrypt_with_key(self.cipher, &message_keys, plaintext, message_number, &aad)?;
         
         Ok(envelope)
     }
 
+    /// Advance the sending chain without producing an envelope
+    /// 
+    /// Burns the next message key, e.g. to keep counters in sync with a peer
+    /// around a specially handled control message. The receiver stores the
+    /// matching key as skipped when the next real envelope arrives.
+    /// 
+    /// # Returns
+    /// The message number that was skipped
+    pub fn skip_send(&mut self) -> Result<u64> {
+        self.ensure_open()?;
+        let message_number = self.next_sending_message_number()?;
+        let (mut encryption_key, mut auth_key) = self.next_sending_keys()?;
+        encryption_key.zeroize();
+        auth_key.zeroize();
+        self.sending_message_number = message_number;
+        Ok(message_number)
+    }
+
     /// Decrypt a MessageEnvelope to plaintext
     /// 
+    /// The envelope is only read: its ciphertext is copied before being
+    /// opened, so after a failure the same envelope can be retried or handed
+    /// to another session unchanged.
+    /// 
     /// # Arguments
     /// * `envelope` - MessageEnvelope containing encrypted message
     /// 
     /// # Returns
     /// Decrypted plaintext message
     pub fn decrypt_envelope(&mut self, envelope: &MessageEnvelope) -> Result<Vec<u8>> {
+        Ok(self.decrypt_envelope_full(envelope)?.plaintext)
+    }
+
+    /// Decrypt a stream of envelopes, e.g. offline history being caught up
+    /// 
+    /// Envelopes are decrypted lazily in the order given. Small gaps and
+    /// reordering are handled through the skipped-key store, and a failure
+    /// only affects its own item. Holding `&mut self` for the whole stream
+    /// lets callers take the session lock once instead of per message.
+    /// 
+    /// # Arguments
+    /// * `envelopes` - Envelopes to decrypt
+    /// 
+    /// # Returns
+    /// Iterator yielding one plaintext (or error) per envelope, in input order
+    pub fn decrypt_ordered<'a, I>(&'a mut self, envelopes: I) -> impl Iterator<Item = Result<Vec<u8>>> + 'a
+    where
+        I: IntoIterator<Item = MessageEnvelope>,
+        I::IntoIter: 'a,
+    {
+        envelopes.into_iter().map(move |envelope| self.decrypt_envelope(&envelope))
+    }
+
+    /// Decrypt a MessageEnvelope and report where its message key came from
+    /// 
+    /// A message served from the skipped-key store arrived after a later one,
+    /// so counting `DecryptSource::SkippedStore` results measures reordering
+    /// on the network.
+    /// 
+    /// # Arguments
+    /// * `envelope` - MessageEnvelope containing encrypted message
+    /// 
+    /// # Returns
+    /// Decrypted plaintext and its `DecryptSource`
+    pub fn decrypt_envelope_with_meta(&mut self, envelope: &MessageEnvelope) -> Result<(Vec<u8>, DecryptSource)> {
+        let decrypted = self.decrypt_envelope_full(envelope)?;
+        Ok((decrypted.plaintext, decrypted.source))
+    }
+
+    /// Decrypt a MessageEnvelope and return the plaintext with its verified metadata
+    /// 
+    /// # Arguments
+    /// * `envelope` - MessageEnvelope containing encrypted message
+    /// 
+    /// # Returns
+    /// DecryptedMessage with the plaintext, authenticated timestamp and message number
+    pub fn decrypt_envelope_full(&mut self, envelope: &MessageEnvelope) -> Result<DecryptedMessage> {
+        self.ensure_open()?;
+        validate_envelope_consistency(envelope)?;
+        
         // Parse DH public key from envelope
-        let dh_public_hex = &envelope.header.dh_public_key;
-        let dh_public_bytes = hex::decode(dh_public_hex)
-            .map_err(|e| E2EEError::SerializationError(format!("Failed to decode DH public key: {}", e)))?;
+        let dh_pub_bytes = parse_curve_point_hex(&envelope.header.dh_public_key, self.accept_prefixed_keys)?;
+        let dh_public = PublicKey::from(dh_pub_bytes);
         
-        if dh_public_bytes.len() != 32 {
-            return Err(E2EEError::ProtocolError(
-                format!("Invalid DH public key length: expected 32, got {}", dh_public_bytes.len())
-            ));
+        // A duplicate of an envelope decrypted recently gets the same result again
+        let aad = Self::envelope_associated_data(envelope);
+        let cache_digest = self.decrypt_cache.as_ref().map(|_| DecryptCache::digest(&aad, &envelope.ciphertext));
+        if let (Some(cache), Some(digest)) = (self.decrypt_cache.as_ref(), cache_digest.as_ref()) {
+            if let Some(mut decrypted) = cache.get(&dh_pub_bytes, envelope.header.message_number, digest) {
+                decrypted.source = DecryptSource::DuplicateCache;
+                return Ok(decrypted);
+            }
         }
         
-        let mut dh_pub_bytes = [0u8; 32];
-        dh_pub_bytes.copy_from_slice(&dh_public_bytes);
-        let dh_public = PublicKey::from(dh_pub_bytes);
+        // A legitimate peer never sends our own DH public key back to us
+        if self.is_own_dh_public(&dh_public) {
+            return Err(E2EEError::ProtocolError("reflected DH key".to_string()));
+        }
+        
+        // Both sides claiming the same role means one was built with the wrong
+        // `is_initiator`: their sending chain is ours, so the AEAD would only
+        // fail with a generic error. Report the likely cause instead.
+        if envelope.header.role_hint == Some(self.role()) {
+            return Err(E2EEError::ProtocolError(format!(
+                "likely role misconfiguration: both peers {}? (role mismatch)",
+                if self.is_initiator { "initiator" } else { "responder" }
+            )));
+        }
+        
+        // Get message number from envelope for key lookup and nonce generation
+        let message_number = envelope.header.message_number;
+        
+        // Keys stored for a skipped message may belong to any earlier DH ratchet
+        // step, so they take precedence over the current chain
+        let has_stored_keys = self.skipped_message_keys.contains_key(&(dh_pub_bytes, message_number));
+        
+        // A key we already ratcheted away from must not start another ratchet
+        if !has_stored_keys && self.retired_remote_dh_publics.contains(&dh_pub_bytes) {
+            return Err(E2EEError::ProtocolError(format!(
+                "Message {} from an earlier DH ratchet step was already received or its key was discarded",
+                message_number
+            )));
+        }
         
         // Check if this is a new DH public key (different from what we've seen before)
         // If remote_dh_public is None, this is the first message, use initial receiving chain
         // If remote_dh_public is Some but different, perform DH ratchet
-        let should_perform_dh_ratchet = match self.remote_dh_public {
+        let should_perform_dh_ratchet = !has_stored_keys && match self.remote_dh_public {
             None => {
-                // First message: store the DH public key but don't perform ratchet yet
-                // Use initial receiving chain which matches sender's sending chain
-                self.remote_dh_public = Some(dh_public);
-                false
+                // First message: a responder seeded with its signed prekey ratchets onto the
+                // initiator's DH key, and a key exchange announces a key that already went
+                // through a DH step. Otherwise use initial receiving chain which matches
+                // sender's sending chain; the DH public key is stored once the message authenticates
+                self.ratchet_on_first_receive || envelope.message_type == MessageType::KeyExchange
             }
             Some(ref existing) if existing != &dh_public => {
                 // New DH key: perform DH ratchet to update receiving chain
@@ -145,160 +901,907 @@ impl DoubleRatchet {
             }
         };
         
-        if should_perform_dh_ratchet {
-            self.perform_dh_ratchet(dh_public)?;
+        // Get message keys, either from the skipped-key store or from a staged
+        // copy of the receiving state (DH ratchet included) that is committed
+        // only once the envelope authenticates
+        let (staged, message_keys) = match self.skipped_message_keys.get(&(dh_pub_bytes, message_number)) {
+            Some(message_keys) => {
+                let mut staged = StagedReceive::default();
+                staged.used_stored_key = Some((dh_pub_bytes, message_number));
+                (staged, *message_keys)
+            }
+            None => self.stage_receive(dh_public, should_perform_dh_ratchet, envelope)?,
+        };
+        
+        // Decrypt ciphertext with message key using message-number-based nonce;
+        // on failure the staged changes are dropped and the store keeps its keys
+        let plaintext = Self::decrypt_with_key(self.cipher, &message_keys, &envelope.ciphertext, message_number, &aad)?;
+        
+        // A receipt can only acknowledge a message we actually sent; checked
+        // before committing so a rejected receipt consumes no key
+        if let Some(receipt_for) = envelope.header.receipt_for {
+            if receipt_for == 0 || receipt_for > self.sending_message_number {
+                return Err(E2EEError::ProtocolError(format!(
+                    "Receipt for message {} that was never sent",
+                    receipt_for
+                )));
+            }
+        }
+        
+        // Padding is inside the AEAD, so it is only stripped once authenticated,
+        // and before committing so malformed padding consumes no key
+        let plaintext = if envelope.header.padded {
+            unpad(&plaintext)?
+        } else {
+            plaintext
+        };
+        
+        self.commit_receive(staged)?;
+        self.record_received(message_number);
+        
+        if self.remote_dh_public.is_none() {
+            self.remote_dh_public = Some(dh_public);
         }
         
-        // Get receiving chain (should always be Some at this point)
-        let receiving_chain = self.receiving_chain.as_mut()
-            .ok_or_else(|| E2EEError::StateError("No receiving chain available".to_string()))?;
+        let decrypted = DecryptedMessage {
+            plaintext,
+            sent_at: envelope.header.sent_at,
+            message_number,
+            receipt_for: envelope.header.receipt_for,
+            rekey_requested: envelope.header.rekey_requested,
+            source: if has_stored_keys { DecryptSource::SkippedStore } else { DecryptSource::Live },
+        };
+        if let (Some(cache), Some(digest)) = (self.decrypt_cache.as_mut(), cache_digest) {
+            cache.insert(dh_pub_bytes, digest, decrypted.clone());
+        }
         
-        // Ratchet receiving chain forward to get message key
-        let (message_key, _) = receiving_chain.ratchet_forward()?;
+        Ok(decrypted)
+    }
+
+    /// Close the ratchet and zeroize its key material in place
+    /// 
+    /// Wipes the chain keys, every stored skipped message key and the DH
+    /// private key. Afterwards every encrypt or decrypt call fails with
+    /// `StateError("session closed")`. Closing twice is a no-op.
+    pub fn close(&mut self) {
+        self.wipe_keys();
+        // Replacing the DH secret drops (and zeroizes) the old one
+        self.dh_key_pair = StaticSecret::from([0u8; 32]);
+        self.remote_dh_public = None;
+        self.closed = true;
+    }
+
+    /// Snapshot the complete ratchet state for persistence
+    /// 
+    /// The snapshot holds every secret of the session; see `RatchetState`.
+    /// 
+    /// # Returns
+    /// RatchetState, or `StateError` if the ratchet has been closed
+    pub fn to_state(&self) -> Result<RatchetState> {
+        self.ensure_open()?;
+        
+        let dh_private_bytes = Zeroizing::new(self.dh_key_pair.to_bytes());
+        let dh_private_hex = hex::encode(&dh_private_bytes[..]);
+        
+        Ok(RatchetState {
+            version: SESSION_STATE_VERSION,
+            is_initiator: self.is_initiator,
+            sending_chain: Self::chain_state(&self.sending_chain),
+            receiving_chain: self.receiving_chain.as_ref().map(Self::chain_state),
+            dh_private_hex,
+            remote_dh_public_hex: self.remote_dh_public.map(|key| hex::encode(key.as_bytes())),
+            retired_remote_dh_public_hex: self.retired_remote_dh_publics.iter().map(hex::encode).collect(),
+            receiving_chain_start: self.receiving_chain_start,
+            ratchet_on_first_receive: self.ratchet_on_first_receive,
+            sending_message_number: self.sending_message_number,
+            previous_sending_chain_length: self.previous_sending_chain_length,
+            has_ratcheted: self.has_ratcheted,
+            sending_ratchet_started: self.sending_ratchet_started,
+            chain_message_limit: self.chain_message_limit,
+            accept_prefixed_keys: self.accept_prefixed_keys,
+            max_skip: self.max_skip,
+            context_hex: hex::encode(&self.context),
+            suite: self.suite.clone(),
+            skipped_message_keys: self.skipped_message_keys
+                .iter()
+                .map(|((remote_dh_public, message_number), keys)| {
+                    Self::stored_keys(Some(remote_dh_public), *message_number, keys)
+                })
+                .collect(),
+            precomputed_send_keys: self.precomputed_send_keys
+                .iter()
+                .map(|(message_number, keys)| Self::stored_keys(None, *message_number, keys))
+                .collect(),
+            received_through: self.received_through,
+            received_out_of_order: self.received_out_of_order.iter().copied().collect(),
+            root_key_hex: hex::encode(self.root_key),
+        })
+    }
+
+    /// Restore a ratchet from a snapshot taken with `to_state`
+    /// 
+    /// # Arguments
+    /// * `state` - Saved ratchet state
+    /// 
+    /// # Returns
+    /// DoubleRatchet continuing exactly where the snapshot was taken, or
+    /// `ProtocolError` / `SerializationError` for an incompatible or corrupt state
+    pub fn from_state(state: &RatchetState) -> Result<Self> {
+        if state.version != SESSION_STATE_VERSION {
+            return Err(E2EEError::ProtocolError(format!(
+                "Unsupported session state version {} (expected {})",
+                state.version, SESSION_STATE_VERSION
+            )));
+        }
+        
+        let context = hex::decode(&state.context_hex)
+            .map_err(|e| E2EEError::SerializationError(format!("Failed to decode hex: {}", e)))?;
+        
+        let cipher = state.suite.message_cipher()?;
+        
+        let dh_private_bytes = Zeroizing::new(parse_hex_32(&state.dh_private_hex)?);
+        let dh_key_pair = StaticSecret::from(*dh_private_bytes);
+        
+        let remote_dh_public = state.remote_dh_public_hex
+            .as_deref()
+            .map(|key_hex| parse_hex_32(key_hex).map(PublicKey::from))
+            .transpose()?;
+        
+        let mut skipped_message_keys = HashMap::new();
+        for stored in &state.skipped_message_keys {
+            let remote_dh_public_hex = stored.remote_dh_public_hex.as_deref()
+                .ok_or_else(|| E2EEError::SerializationError("Skipped key without a DH public key".to_string()))?;
+            skipped_message_keys.insert(
+                (parse_hex_32(remote_dh_public_hex)?, stored.message_number),
+                Self::restore_keys(stored)?,
+            );
+        }
+        
+        let retired_remote_dh_publics = state.retired_remote_dh_public_hex
+            .iter()
+            .map(|key_hex| parse_hex_32(key_hex))
+            .collect::<Result<VecDeque<_>>>()?;
+        
+        let mut precomputed_send_keys = VecDeque::new();
+        for stored in &state.precomputed_send_keys {
+            precomputed_send_keys.push_back((stored.message_number, Self::restore_keys(stored)?));
+        }
+        
+        Ok(Self {
+            sending_chain: Self::restore_chain(&state.sending_chain, &context)?,
+            receiving_chain: state.receiving_chain
+                .as_ref()
+                .map(|chain| Self::restore_chain(chain, &context))
+                .transpose()?,
+            dh_public: PublicKey::from(&dh_key_pair),
+            dh_key_pair,
+            remote_dh_public,
+            retired_remote_dh_publics,
+            receiving_chain_start: state.receiving_chain_start,
+            ratchet_on_first_receive: state.ratchet_on_first_receive,
+            sending_message_number: state.sending_message_number,
+            previous_sending_chain_length: state.previous_sending_chain_length,
+            is_initiator: state.is_initiator,
+            has_ratcheted: state.has_ratcheted,
+            sending_ratchet_started: state.sending_ratchet_started,
+            closed: false,
+            // Snapshots from before the cap was optional wrote the ceiling for "no cap"
+            chain_message_limit: state.chain_message_limit.filter(|&limit| limit != MAX_CHAIN_MESSAGES),
+            accept_prefixed_keys: state.accept_prefixed_keys,
+            max_skip: state.max_skip,
+            decrypt_cache: None,
+            #[cfg(debug_assertions)]
+            nonce_guard: Default::default(),
+            context,
+            suite: state.suite.clone(),
+            cipher,
+            skipped_message_keys,
+            precomputed_send_keys,
+            received_through: state.received_through,
+            received_out_of_order: state.received_out_of_order.iter().copied().collect(),
+            root_key: parse_hex_32(&state.root_key_hex)?,
+        })
+    }
+
+    fn chain_state(chain: &Chain) -> ChainState {
+        ChainState {
+            chain_key_hex: hex::encode(chain.chain_key()),
+            message_number: chain.message_number(),
+            message_limit: chain.message_limit(),
+        }
+    }
+
+    fn restore_chain(state: &ChainState, context: &[u8]) -> Result<Chain> {
+        Ok(Chain::from_parts(
+            parse_hex_32(&state.chain_key_hex)?,
+            state.message_number,
+            state.message_limit,
+            context,
+        ))
+    }
+
+    fn stored_keys(remote_dh_public: Option<&[u8; 32]>, message_number: u64, keys: &MessageKeys) -> StoredMessageKeys {
+        StoredMessageKeys {
+            remote_dh_public_hex: remote_dh_public.map(hex::encode),
+            message_number,
+            encryption_key_hex: hex::encode(keys.0),
+            auth_key_hex: hex::encode(keys.1),
+        }
+    }
+
+    fn restore_keys(stored: &StoredMessageKeys) -> Result<MessageKeys> {
+        Ok((parse_hex_32(&stored.encryption_key_hex)?, parse_hex_32(&stored.auth_key_hex)?))
+    }
+
+    /// Check whether the ratchet has been closed
+    pub fn is_closed(&self) -> bool {
+        self.closed
+    }
+
+    /// Check that no key material is left, as after `close`
+    /// 
+    /// True once the root key, the chain keys and the DH private key are all
+    /// zero and no skipped or precomputed message key or cached plaintext is held.
+    pub fn is_wiped(&self) -> bool {
+        let dh_private_bytes = Zeroizing::new(self.dh_key_pair.to_bytes());
+        let dh_private_wiped = *dh_private_bytes == [0u8; 32];
+        
+        dh_private_wiped
+            && self.root_key == [0u8; 32]
+            && self.sending_chain.chain_key() == &[0u8; 32]
+            && self.receiving_chain.iter().all(|chain| chain.chain_key() == &[0u8; 32])
+            && self.skipped_message_keys.is_empty()
+            && self.precomputed_send_keys.is_empty()
+            && self.decrypt_cache.is_none()
+    }
+
+    /// Fail with `StateError` if the ratchet has been closed
+    fn ensure_open(&self) -> Result<()> {
+        if self.closed {
+            return Err(E2EEError::StateError("session closed".to_string()));
+        }
+        Ok(())
+    }
+
+    /// Zeroize chain keys, skipped message keys and cached plaintexts
+    fn wipe_keys(&mut self) {
+        self.sending_chain.wipe();
+        if let Some(receiving_chain) = self.receiving_chain.as_mut() {
+            receiving_chain.wipe();
+        }
+        for (encryption_key, auth_key) in self.skipped_message_keys.values_mut() {
+            encryption_key.zeroize();
+            auth_key.zeroize();
+        }
+        self.skipped_message_keys.clear();
+        self.discard_precomputed_send_keys();
+        self.root_key.zeroize();
+        // Dropping the cache zeroizes its plaintexts
+        self.decrypt_cache = None;
+    }
+
+    /// Check whether this ratchet was built for the X3DH initiator
+    pub fn is_initiator(&self) -> bool {
+        self.is_initiator
+    }
+
+    /// Role hint this side sends (`ROLE_INITIATOR` or `ROLE_RESPONDER`)
+    fn role(&self) -> u8 {
+        if self.is_initiator { ROLE_INITIATOR } else { ROLE_RESPONDER }
+    }
+
+    /// Check whether a DH ratchet step has run at least once
+    /// 
+    /// False while the session is one-directional (only the initial chains
+    /// have been used); true once a message carrying a new DH public key from
+    /// the peer has been received, or once a responder created with
+    /// `from_x3dh_responder` has received its first message. Sending steps do
+    /// not count: neither the one an initiator created with `from_x3dh_initiator`
+    /// runs at setup nor the one `encrypt_key_exchange` may take.
+    pub fn has_ratcheted(&self) -> bool {
+        self.has_ratcheted
+    }
+
+    /// Export the non-secret ratchet state for comparison with the peer
+    /// 
+    /// # Returns
+    /// PublicRatchetState with both DH public keys and the message counters
+    pub fn public_state(&self) -> PublicRatchetState {
+        PublicRatchetState {
+            our_dh_public: hex::encode(self.dh_public.as_bytes()),
+            remote_dh_public: self.remote_dh_public.map(|key| hex::encode(key.as_bytes())),
+            sending_number: self.sending_message_number,
+            receiving_number: self.received_out_of_order
+                .last()
+                .copied()
+                .unwrap_or(self.received_through),
+        }
+    }
+
+    /// Get the message numbers decrypted so far, in ascending order
+    pub fn received_numbers(&self) -> Vec<u64> {
+        (1..=self.received_through)
+            .chain(self.received_out_of_order.iter().copied())
+            .collect()
+    }
+
+    /// Check whether a message number has been decrypted
+    /// 
+    /// # Arguments
+    /// * `message_number` - Header message number (starting at 1)
+    pub fn has_received(&self, message_number: u64) -> bool {
+        (message_number >= 1 && message_number <= self.received_through)
+            || self.received_out_of_order.contains(&message_number)
+    }
+
+    /// Get the message numbers lower than `n` that have not been decrypted yet
+    /// 
+    /// Message numbers start at 1. Useful to drive retransmission requests.
+    /// 
+    /// # Arguments
+    /// * `n` - Exclusive upper bound
+    pub fn missing_before(&self, n: u64) -> Vec<u64> {
+        (self.received_through + 1..n)
+            .filter(|number| !self.received_out_of_order.contains(number))
+            .collect()
+    }
+
+    /// Count the chain steps decrypting an envelope would skip, without taking them
+    /// 
+    /// Covers the rest of the old receiving chain when the envelope starts a
+    /// new DH ratchet step, plus the gap in the chain the message belongs to.
+    /// The ratchet is not modified. The count is not capped at `MAX_SKIP`, so
+    /// apps can apply a stricter limit of their own before decrypting.
+    /// 
+    /// # Arguments
+    /// * `envelope` - MessageEnvelope about to be decrypted
+    /// 
+    /// # Returns
+    /// Number of message keys that would be derived and stored as skipped
+    /// (0 for the next expected message or one with stored keys), or
+    /// `ProtocolError` for a message that can no longer be decrypted
+    pub fn skip_cost(&self, envelope: &MessageEnvelope) -> Result<u64> {
+        self.ensure_open()?;
+        let dh_pub_bytes = parse_curve_point_hex(&envelope.header.dh_public_key, self.accept_prefixed_keys)?;
+        let dh_public = PublicKey::from(dh_pub_bytes);
+        let message_number = envelope.header.message_number;
+        
+        if self.skipped_message_keys.contains_key(&(dh_pub_bytes, message_number)) {
+            return Ok(0);
+        }
+        if self.retired_remote_dh_publics.contains(&dh_pub_bytes) {
+            return Err(E2EEError::ProtocolError(format!(
+                "Message {} from an earlier DH ratchet step was already received or its key was discarded",
+                message_number
+            )));
+        }
+        
+        // Header message numbers start at 1, chain positions at 0
+        let current_next = self.receiving_chain.as_ref()
+            .map(|chain| self.receiving_chain_start + chain.message_number() as u64 + 1);
+        let is_new_dh_public = match self.remote_dh_public {
+            Some(ref existing) => existing != &dh_public,
+            None => self.ratchet_on_first_receive || envelope.message_type == MessageType::KeyExchange,
+        };
+        
+        // Mirror decrypt_envelope_full: a new remote DH key first finishes the old chain
+        let (old_chain_steps, next_message_number) = if is_new_dh_public {
+            let previous_chain_length = envelope.header.previous_chain_length as u64;
+            let old_chain_steps = match (self.remote_dh_public, current_next) {
+                (Some(_), Some(next)) if previous_chain_length >= next => previous_chain_length - next + 1,
+                _ => 0,
+            };
+            (old_chain_steps, previous_chain_length + 1)
+        } else {
+            let next = current_next
+                .ok_or_else(|| E2EEError::StateError("No receiving chain available".to_string()))?;
+            (0, next)
+        };
+        
+        if message_number < next_message_number {
+            return Err(E2EEError::ProtocolError(format!(
+                "Message {} was already received or its key was discarded",
+                message_number
+            )));
+        }
         
-        // Get message number from envelope for nonce generation
+        Ok(old_chain_steps + (message_number - next_message_number))
+    }
+
+    /// Recompute the nonce, tag and message key used to decrypt an envelope
+    /// 
+    /// Debugging aid for cross-implementation failures. Works on copies of the
+    /// chain state, so the ratchet is left untouched and the envelope can
+    /// still be decrypted afterwards. Only available with `test-support`.
+    /// 
+    /// # Arguments
+    /// * `envelope` - MessageEnvelope to inspect
+    /// 
+    /// # Returns
+    /// EnvelopeInspection with the values `decrypt_envelope` would use
+    #[cfg(feature = "test-support")]
+    pub fn inspect_envelope(&self, envelope: &MessageEnvelope) -> Result<EnvelopeInspection> {
+        let dh_pub_bytes = parse_curve_point_hex(&envelope.header.dh_public_key, self.accept_prefixed_keys)?;
+        let dh_public = PublicKey::from(dh_pub_bytes);
         let message_number = envelope.header.message_number;
         
-        // Decrypt ciphertext with message key using message-number-based nonce
-        let plaintext = Self::decrypt_with_key(&message_key, &envelope.ciphertext, message_number)?;
+        let tag_len = crate::aead::TAG_LEN;
+        if envelope.ciphertext.len() < tag_len {
+            return Err(E2EEError::ProtocolError("Ciphertext shorter than AEAD tag".to_string()));
+        }
         
-        Ok(plaintext)
+        let (message_key, auth_key) = match self.skipped_message_keys.get(&(dh_pub_bytes, message_number)) {
+            Some(message_keys) => *message_keys,
+            None => {
+                // Mirror decrypt_envelope_full: a new remote DH key starts a fresh chain
+                if self.retired_remote_dh_publics.contains(&dh_pub_bytes) {
+                    return Err(E2EEError::ProtocolError(format!(
+                        "Message {} from an earlier DH ratchet step was already received or its key was discarded",
+                        message_number
+                    )));
+                }
+                let is_new_dh_public = match self.remote_dh_public {
+                    Some(ref existing) => existing != &dh_public,
+                    None => self.ratchet_on_first_receive || envelope.message_type == MessageType::KeyExchange,
+                };
+                let (chain_key, next_message_number) = if is_new_dh_public {
+                    let mut dh_output = self.dh(&dh_public);
+                    let (_, chain_key) = Self::kdf_root(&self.root_key, &dh_output, &self.context)?;
+                    dh_output.zeroize();
+                    (chain_key, envelope.header.previous_chain_length as u64 + 1)
+                } else {
+                    let receiving_chain = self.receiving_chain.as_ref()
+                        .ok_or_else(|| E2EEError::StateError("No receiving chain available".to_string()))?;
+                    (
+                        *receiving_chain.chain_key(),
+                        self.receiving_chain_start + receiving_chain.message_number() as u64 + 1,
+                    )
+                };
+                
+                if message_number < next_message_number {
+                    return Err(E2EEError::ProtocolError(format!(
+                        "Message {} was already received or its key was discarded",
+                        message_number
+                    )));
+                }
+                
+                let mut chain = Chain::with_context(chain_key, MAX_CHAIN_MESSAGES, &self.context);
+                let mut message_keys = chain.ratchet_forward()?;
+                for _ in next_message_number..message_number {
+                    message_keys = chain.ratchet_forward()?;
+                }
+                message_keys
+            }
+        };
+        
+        let (ciphertext, tag) = envelope.ciphertext.split_at(envelope.ciphertext.len() - tag_len);
+        Ok(EnvelopeInspection {
+            derived_nonce_hex: hex::encode(Self::derive_nonce(&auth_key, message_number)?),
+            ciphertext_len: ciphertext.len(),
+            tag_hex: hex::encode(tag),
+            expected_message_key_hex: hex::encode(message_key),
+        })
+    }
+
+    /// SHA-256 hash of the current sending chain key
+    /// 
+    /// Lets test harnesses compare chain state across ratchets without exposing
+    /// the key itself. Only available with `test-support`.
+    #[cfg(feature = "test-support")]
+    pub fn sending_chain_key_hash(&self) -> [u8; 32] {
+        Self::chain_key_hash(self.sending_chain.chain_key())
+    }
+
+    /// Set the number of the last message sent, e.g. to test counter exhaustion
+    /// 
+    /// Only the counter moves, not the chain. Only available with `test-support`.
+    #[cfg(feature = "test-support")]
+    pub fn pin_sending_message_number(&mut self, message_number: u64) {
+        self.sending_message_number = message_number;
     }
 
-    /// Perform DH ratchet when receiving a new DH public key
+    /// Save the sending chain position, for `rewind_sending_chain`
     /// 
-    /// This updates the receiving chain and generates a new DH key pair.
-    fn perform_dh_ratchet(&mut self, remote_dh_public: PublicKey) -> Result<()> {
-        // Extract DH key pair bytes before consuming it
-        let dh_key_pair_bytes = unsafe {
-            std::mem::transmute_copy::<EphemeralSecret, [u8; 32]>(&self.dh_key_pair)
+    /// Only available with `test-support`.
+    #[cfg(feature = "test-support")]
+    pub fn sending_chain_checkpoint(&self) -> SendingChainCheckpoint {
+        SendingChainCheckpoint {
+            chain_key: *self.sending_chain.chain_key(),
+            chain_message_number: self.sending_chain.message_number(),
+            sending_message_number: self.sending_message_number,
+        }
+    }
+
+    /// Move the sending chain back to a checkpoint, as a buggy state restore would
+    /// 
+    /// The next messages then reuse message keys and nonces, which lets tests
+    /// exercise the debug-build nonce reuse guard. Only available with `test-support`.
+    #[cfg(feature = "test-support")]
+    pub fn rewind_sending_chain(&mut self, checkpoint: &SendingChainCheckpoint) {
+        self.discard_precomputed_send_keys();
+        self.sending_chain = Chain::from_parts(
+            checkpoint.chain_key,
+            checkpoint.chain_message_number,
+            self.chain_message_limit(),
+            &self.context,
+        );
+        self.sending_message_number = checkpoint.sending_message_number;
+    }
+
+    /// Export the raw root key, for auditors reproducing the key schedule
+    /// 
+    /// DANGER: this compromises forward secrecy. Together with the DH outputs
+    /// of later ratchet steps, the root key yields every chain key derived
+    /// after it. Never export it from a session that carries real traffic.
+    /// Only available with `dangerous-export`.
+    #[cfg(feature = "dangerous-export")]
+    pub fn export_root_key(&self) -> [u8; 32] {
+        self.root_key
+    }
+
+    /// SHA-256 hash of the current receiving chain key
+    /// 
+    /// All zeroes if there is no receiving chain. Only available with `test-support`.
+    #[cfg(feature = "test-support")]
+    pub fn receiving_chain_key_hash(&self) -> [u8; 32] {
+        self.receiving_chain
+            .as_ref()
+            .map_or([0u8; 32], |chain| Self::chain_key_hash(chain.chain_key()))
+    }
+
+    /// Hashed snapshot of the ratchet state
+    /// 
+    /// Two instances that should be in sync (e.g. a session and its restored
+    /// copy) produce equal reports; the first differing field shows where they
+    /// diverged. Only available with `test-support`.
+    /// 
+    /// # Returns
+    /// AuditReport with key hashes, chain positions and the skipped-key count
+    #[cfg(feature = "test-support")]
+    pub fn audit_state(&self) -> AuditReport {
+        AuditReport {
+            root_key_hash_hex: hex::encode(Self::chain_key_hash(&self.root_key)),
+            sending_chain_key_hash_hex: hex::encode(self.sending_chain_key_hash()),
+            receiving_chain_key_hash_hex: self.receiving_chain
+                .as_ref()
+                .map(|chain| hex::encode(Self::chain_key_hash(chain.chain_key()))),
+            sending_message_number: self.sending_message_number,
+            receiving_message_number: self.receiving_chain
+                .as_ref()
+                .map_or(0, |chain| chain.message_number() as u64),
+            skipped_key_count: self.skipped_message_keys.len(),
+        }
+    }
+
+    #[cfg(feature = "test-support")]
+    fn chain_key_hash(chain_key: &[u8; 32]) -> [u8; 32] {
+        let digest = ring::digest::digest(&ring::digest::SHA256, chain_key);
+        let mut hash = [0u8; 32];
+        hash.copy_from_slice(digest.as_ref());
+        hash
+    }
+
+    /// Check whether a DH public key is our current ratchet public key
+    fn is_own_dh_public(&self, dh_public: &PublicKey) -> bool {
+        self.dh_public.as_bytes() == dh_public.as_bytes()
+    }
+
+    /// Derive the message keys for an envelope on copies of the receiving state
+    /// 
+    /// For a new remote DH key this stages the DH ratchet: the rest of the
+    /// old receiving chain up to `previous_chain_length` (at most `max_skip`
+    /// keys) is kept as skipped, and a new receiving chain is derived from
+    /// DH(our key, their new key) mixed into the root key. The chain is then
+    /// advanced to the message, keeping the keys of any messages skipped on
+    /// the way (at most `max_skip`). Nothing is applied to the ratchet; see
+    /// `commit_receive`.
+    fn stage_receive(
+        &self,
+        dh_public: PublicKey,
+        should_perform_dh_ratchet: bool,
+        envelope: &MessageEnvelope,
+    ) -> Result<(StagedReceive, MessageKeys)> {
+        let mut staged = StagedReceive::default();
+        
+        let (mut receiving_chain, receiving_chain_start) = if should_perform_dh_ratchet {
+            let previous_chain_length = envelope.header.previous_chain_length as u64;
+            self.stage_old_receiving_chain(previous_chain_length, &mut staged.skipped_keys)?;
+            
+            let mut dh_output = self.dh(&dh_public);
+            let derived = Self::kdf_root(&self.root_key, &dh_output, &self.context);
+            dh_output.zeroize();
+            let (root_key, chain_key) = derived?;
+            
+            // Message numbers continue across chains; theirs resumes after the old chain
+            staged.dh_ratchet = Some(StagedDhRatchet {
+                remote_dh_public: dh_public,
+                root_key,
+                receiving_chain_start: previous_chain_length,
+            });
+            (Chain::with_context(chain_key, self.chain_message_limit(), &self.context), previous_chain_length)
+        } else {
+            let receiving_chain = self.receiving_chain.as_ref()
+                .ok_or_else(|| E2EEError::StateError("No receiving chain available".to_string()))?;
+            (receiving_chain.duplicate(), self.receiving_chain_start)
         };
-        let dh_key_pair_for_dh = unsafe {
-            std::mem::transmute::<[u8; 32], EphemeralSecret>(dh_key_pair_bytes)
+        
+        // Header message numbers start at 1, chain positions at 0
+        let message_number = envelope.header.message_number;
+        let next_message_number = receiving_chain_start + receiving_chain.message_number() as u64 + 1;
+        if message_number < next_message_number {
+            return Err(E2EEError::ProtocolError(format!(
+                "Message {} was already received or its key was discarded",
+                message_number
+            )));
+        }
+        
+        let skip = message_number - next_message_number;
+        if skip > self.max_skip {
+            return Err(E2EEError::ProtocolError(format!(
+                "Too many skipped messages: {} (max {})",
+                skip, self.max_skip
+            )));
+        }
+        
+        for skipped_number in next_message_number..message_number {
+            let skipped_keys = receiving_chain.ratchet_forward()?;
+            staged.skipped_keys.push(((*dh_public.as_bytes(), skipped_number), skipped_keys));
+        }
+        
+        let message_keys = receiving_chain.ratchet_forward()?;
+        staged.receiving_chain = Some(receiving_chain);
+        Ok((staged, message_keys))
+    }
+
+    /// Apply the changes staged for an envelope that authenticated
+    /// 
+    /// A staged DH ratchet also starts a new sending chain under a fresh DH
+    /// key pair, retiring the previous remote DH key.
+    fn commit_receive(&mut self, mut staged: StagedReceive) -> Result<()> {
+        // Fail before anything is applied if the sending chain cannot be replaced
+        if staged.dh_ratchet.is_some() {
+            self.sending_chain_length()?;
+        }
+        if let Some(index) = staged.used_stored_key.take() {
+            if let Some((mut encryption_key, mut auth_key)) = self.skipped_message_keys.remove(&index) {
+                encryption_key.zeroize();
+                auth_key.zeroize();
+            }
+        }
+        for (index, message_keys) in staged.skipped_keys.drain(..) {
+            self.skipped_message_keys.insert(index, message_keys);
+        }
+        if let Some(receiving_chain) = staged.receiving_chain.take() {
+            self.receiving_chain = Some(receiving_chain);
+        }
+        
+        let Some(dh_ratchet) = staged.dh_ratchet.take() else {
+            return Ok(());
         };
+        self.root_key.zeroize();
+        self.root_key = dh_ratchet.root_key;
+        self.receiving_chain_start = dh_ratchet.receiving_chain_start;
         
-        // Calculate shared secret from DH(our_dh_private, remote_dh_public)
-        let dh_shared_secret = dh_key_pair_for_dh.diffie_hellman(&remote_dh_public);
-        let dh_shared_bytes = *dh_shared_secret.as_bytes();
+        // Update remote DH public key, remembering the old one
+        if let Some(old_remote) = self.remote_dh_public.replace(dh_ratchet.remote_dh_public) {
+            if self.retired_remote_dh_publics.len() == MAX_RETIRED_DH_KEYS {
+                self.retired_remote_dh_publics.pop_front();
+            }
+            self.retired_remote_dh_publics.push_back(*old_remote.as_bytes());
+        }
+        self.ratchet_on_first_receive = false;
+        self.has_ratcheted = true;
         
-        // Derive new receiving chain key from DH shared secret
-        let new_receiving_chain_key = Self::derive_chain_key(&dh_shared_bytes, b"receiving")?;
-        self.receiving_chain = Some(Chain::new(new_receiving_chain_key));
+        self.ratchet_sending_chain()
+    }
+
+    /// Record a successfully decrypted message number
+    fn record_received(&mut self, message_number: u64) {
+        if message_number == self.received_through + 1 {
+            self.received_through = message_number;
+            // Fold in any out-of-order numbers that are now contiguous
+            while self.received_out_of_order.remove(&(self.received_through + 1)) {
+                self.received_through += 1;
+            }
+        } else if message_number > self.received_through {
+            self.received_out_of_order.insert(message_number);
+        }
+    }
+
+    /// Start a new sending chain under a fresh DH key pair
+    /// 
+    /// Messages sent so far went out under the old key and are reported to the
+    /// peer as `previous_chain_length`.
+    fn ratchet_sending_chain(&mut self) -> Result<()> {
+        let remote_dh_public = self.remote_dh_public
+            .ok_or_else(|| E2EEError::StateError("No remote DH public key".to_string()))?;
+        let previous_sending_chain_length = self.sending_chain_length()?;
         
-        // Generate new DH key pair for next ratchet
-        self.dh_key_pair = EphemeralSecret::random_from_rng(OsRng);
+        self.dh_key_pair = StaticSecret::random_from_rng(OsRng);
+        self.dh_public = PublicKey::from(&self.dh_key_pair);
+        self.discard_precomputed_send_keys();
+        self.previous_sending_chain_length = previous_sending_chain_length;
         
-        // Update remote DH public key
-        self.remote_dh_public = Some(remote_dh_public);
+        let mut dh_output = self.dh(&remote_dh_public);
+        let new_sending_chain_key = self.advance_root_key(&dh_output);
+        dh_output.zeroize();
+        self.sending_chain = Chain::with_context(new_sending_chain_key?, self.chain_message_limit(), &self.context);
+        self.sending_ratchet_started = true;
+        // Keys of the new chain never repeat earlier ones, so the old pairs can go
+        #[cfg(debug_assertions)]
+        self.nonce_guard.clear();
         
         Ok(())
     }
 
-    /// Derive chain key from input key material
-    fn derive_chain_key(ikm: &[u8], label: &[u8]) -> Result<[u8; 32]> {
-        let salt = ring::hkdf::Salt::new(ring::hkdf::HKDF_SHA256, &[]);
-        let prk = salt.extract(ikm);
+    /// Collect the keys of the old receiving chain up to message `until`, to keep as skipped
+    /// 
+    /// Works on a copy of the chain: the old chain is replaced by the DH ratchet anyway.
+    fn stage_old_receiving_chain(&self, until: u64, skipped_keys: &mut Vec<(([u8; 32], u64), MessageKeys)>) -> Result<()> {
+        let (Some(old_remote), Some(receiving_chain)) = (self.remote_dh_public, self.receiving_chain.as_ref()) else {
+            return Ok(());
+        };
         
-        // Create array reference to avoid temporary value issue
-        let label_array = [label];
-        let okm = prk.expand(&label_array, ring::hkdf::HKDF_SHA256)
-            .map_err(|e| E2EEError::CryptoError(format!("HKDF expand failed: {}", e)))?;
+        // Header message numbers start at 1, chain positions at 0
+        let next_message_number = self.receiving_chain_start + receiving_chain.message_number() as u64 + 1;
+        if until < next_message_number {
+            return Ok(());
+        }
         
-        let mut chain_key = [0u8; 32];
-        okm.fill(&mut chain_key)
-            .map_err(|e| E2EEError::CryptoError(format!("HKDF fill failed: {}", e)))?;
+        let skip = until - next_message_number + 1;
+        if skip > self.max_skip {
+            return Err(E2EEError::ProtocolError(format!(
+                "Too many skipped messages: {} (max {})",
+                skip, self.max_skip
+            )));
+        }
         
+        let mut old_chain = receiving_chain.duplicate();
+        for skipped_number in next_message_number..=until {
+            let message_keys = old_chain.ratchet_forward()?;
+            skipped_keys.push(((*old_remote.as_bytes(), skipped_number), message_keys));
+        }
+        
+        Ok(())
+    }
+
+    /// DH(our current DH private key, remote DH public key)
+    fn dh(&self, remote_dh_public: &PublicKey) -> [u8; 32] {
+        self.dh_key_pair.diffie_hellman(remote_dh_public).to_bytes()
+    }
+
+    /// Mix a DH output into the root key, returning the new chain key
+    fn advance_root_key(&mut self, dh_output: &[u8; 32]) -> Result<[u8; 32]> {
+        let (root_key, chain_key) = Self::kdf_root(&self.root_key, dh_output, &self.context)?;
+        self.root_key.zeroize();
+        self.root_key = root_key;
         Ok(chain_key)
     }
 
-    /// Encrypt plaintext with message key using AES-256-GCM
+    /// Root KDF: HKDF(salt = root key, ikm = DH output) split into (root key, chain key)
+    fn kdf_root(root_key: &[u8; 32], dh_output: &[u8; 32], context: &[u8]) -> Result<([u8; 32], [u8; 32])> {
+        let info = [b"ratchet".as_slice(), context].concat();
+        let mut output = crate::kdf::hkdf_expand(dh_output, root_key, &info, 64)?;
+        
+        let mut new_root_key = [0u8; 32];
+        let mut chain_key = [0u8; 32];
+        new_root_key.copy_from_slice(&output[..32]);
+        chain_key.copy_from_slice(&output[32..]);
+        output.zeroize();
+        
+        Ok((new_root_key, chain_key))
+    }
+
+    /// Derive chain key from input key material, salted with the application context
+    pub(crate) fn derive_chain_key(context: &[u8], ikm: &[u8], label: &[u8]) -> Result<[u8; 32]> {
+        crate::kdf::hkdf_32(ikm, context, label)
+    }
+
+    /// Encrypt plaintext with message key using the suite's AEAD
     /// 
     /// Uses message number to derive a unique nonce for each message.
-    /// The nonce is derived using HKDF from the message key and message number.
+    /// The nonce is derived from the auth key and message number, so the
+    /// encryption key is used for nothing but the AEAD.
     /// 
     /// # Arguments
-    /// * `key` - Message key (32 bytes)
+    /// * `cipher` - AES-256-GCM or XChaCha20-Poly1305
+    /// * `keys` - Message keys `(encryption_key, auth_key)`
     /// * `plaintext` - Plaintext to encrypt
     /// * `message_number` - Message number in the chain (for nonce generation)
-    fn encrypt_with_key(key: &[u8; 32], plaintext: &[u8], message_number: u64) -> Result<Vec<u8>> {
-        // Create unbound key
-        let unbound_key = UnboundKey::new(&AES_256_GCM, key)
-            .map_err(|e| E2EEError::CryptoError(format!("Failed to create key: {}", e)))?;
-        
-        // Create less safe key (for deterministic nonce usage)
-        let less_safe_key = LessSafeKey::new(unbound_key);
+    /// * `aad` - Associated data authenticated alongside the ciphertext
+    pub(crate) fn encrypt_with_key(cipher: MessageCipher, keys: &MessageKeys, plaintext: &[u8], message_number: u64, aad: &[u8]) -> Result<Vec<u8>> {
+        let (encryption_key, auth_key) = keys;
         
-        // Derive nonce from message key and message number using HKDF
+        // Derive nonce from auth key and message number
         // This ensures each message has a unique nonce
-        let nonce_bytes = Self::derive_nonce(key, message_number)?;
-        let nonce = Nonce::assume_unique_for_key(nonce_bytes);
-        
-        // Encrypt
-        let mut ciphertext = plaintext.to_vec();
-        less_safe_key.seal_in_place_append_tag(nonce, Aad::empty(), &mut ciphertext)
-            .map_err(|e| E2EEError::CryptoError(format!("Encryption failed: {}", e)))?;
-        
-        Ok(ciphertext)
+        match cipher {
+            MessageCipher::Aes256Gcm => {
+                let nonce = Self::derive_nonce(auth_key, message_number)?;
+                crate::aead::seal(encryption_key, &nonce, aad, plaintext)
+            }
+            MessageCipher::XChaCha20Poly1305 => {
+                let nonce = Self::derive_xnonce(auth_key, message_number);
+                crate::aead::seal_xchacha(encryption_key, &nonce, aad, plaintext)
+            }
+        }
     }
 
-    /// Decrypt ciphertext with message key using AES-256-GCM
+    /// Decrypt ciphertext with message key using the suite's AEAD
     /// 
     /// Uses message number to derive the same nonce that was used during encryption.
-    /// The nonce is derived using HKDF from the message key and message number.
+    /// The nonce is derived from the auth key and message number.
     /// 
     /// # Arguments
-    /// * `key` - Message key (32 bytes)
+    /// * `cipher` - AEAD the message was sealed with
+    /// * `keys` - Message keys `(encryption_key, auth_key)`
     /// * `ciphertext` - Ciphertext to decrypt
     /// * `message_number` - Message number in the chain (must match encryption)
-    fn decrypt_with_key(key: &[u8; 32], ciphertext: &[u8], message_number: u64) -> Result<Vec<u8>> {
-        // Create unbound key
-        let unbound_key = UnboundKey::new(&AES_256_GCM, key)
-            .map_err(|e| E2EEError::CryptoError(format!("Failed to create key: {}", e)))?;
-        
-        // Create less safe key (for deterministic nonce usage)
-        let less_safe_key = LessSafeKey::new(unbound_key);
+    /// * `aad` - Associated data (must match encryption)
+    pub(crate) fn decrypt_with_key(cipher: MessageCipher, keys: &MessageKeys, ciphertext: &[u8], message_number: u64, aad: &[u8]) -> Result<Vec<u8>> {
+        let (encryption_key, auth_key) = keys;
         
-        // Derive nonce from message key and message number using HKDF
+        // Derive nonce from auth key and message number
         // Must match the nonce used during encryption
-        let nonce_bytes = Self::derive_nonce(key, message_number)?;
-        let nonce = Nonce::assume_unique_for_key(nonce_bytes);
-        
-        // Decrypt
-        let mut plaintext = ciphertext.to_vec();
-        let plaintext_len = less_safe_key.open_in_place(nonce, Aad::empty(), &mut plaintext)
-            .map_err(|e| E2EEError::CryptoError(format!("Decryption failed: {}", e)))?
-            .len();
-        
-        plaintext.truncate(plaintext_len);
-        Ok(plaintext)
+        match cipher {
+            MessageCipher::Aes256Gcm => {
+                let nonce = Self::derive_nonce(auth_key, message_number)?;
+                crate::aead::open(encryption_key, &nonce, aad, ciphertext)
+            }
+            MessageCipher::XChaCha20Poly1305 => {
+                let nonce = Self::derive_xnonce(auth_key, message_number);
+                crate::aead::open_xchacha(encryption_key, &nonce, aad, ciphertext)
+            }
+        }
     }
 
-    /// Derive nonce from message key and message number using HMAC-SHA256
+    /// Derive nonce from auth key and message number using HMAC-SHA256
     /// 
     /// This ensures each message has a unique, deterministic nonce.
-    /// The nonce is derived using HMAC-SHA256 from the message key and message number.
-    /// This is secure because each message uses a different message key (from chain ratchet).
+    /// The nonce is derived using HMAC-SHA256 from the auth key and message number.
+    /// This is secure because each message uses a different auth key (from chain ratchet).
     /// 
     /// # Arguments
-    /// * `message_key` - Message key (32 bytes)
+    /// * `auth_key` - Message auth key (32 bytes)
     /// * `message_number` - Message number in the chain
     /// 
     /// # Returns
-    /// 12-byte nonce for AES-GCM
-    fn derive_nonce(message_key: &[u8; 32], message_number: u64) -> Result<[u8; 12]> {
-        // Encode message number as bytes (little-endian, 8 bytes)
-        let message_number_bytes = message_number.to_le_bytes();
-        
-        // Use HMAC-SHA256 to derive nonce from message key and message number
-        // This is secure and deterministic: same key + same number = same nonce
-        let key = hmac::Key::new(hmac::HMAC_SHA256, message_key);
-        let tag = hmac::sign(&key, &message_number_bytes);
-        
+    /// 12-byte nonce for AES-GCM: the first 12 bytes of
+    /// HMAC-SHA256(auth_key, message_number as 8 bytes in `NONCE_MESSAGE_NUMBER_ENDIAN` order)
+    pub fn derive_nonce(auth_key: &[u8; 32], message_number: u64) -> Result<[u8; 12]> {
         // Take first 12 bytes from HMAC output for nonce (HMAC-SHA256 produces 32 bytes)
+        let tag = Self::nonce_tag(auth_key, message_number);
         let mut nonce = [0u8; 12];
         nonce.copy_from_slice(&tag.as_ref()[..12]);
         
         Ok(nonce)
     }
+
+    /// Derive the 24-byte XChaCha20-Poly1305 nonce: the first 24 bytes of the
+    /// same HMAC as `derive_nonce`
+    fn derive_xnonce(auth_key: &[u8; 32], message_number: u64) -> [u8; crate::aead::XNONCE_LEN] {
+        let tag = Self::nonce_tag(auth_key, message_number);
+        let mut nonce = [0u8; crate::aead::XNONCE_LEN];
+        nonce.copy_from_slice(&tag.as_ref()[..crate::aead::XNONCE_LEN]);
+        nonce
+    }
+
+    /// HMAC-SHA256(auth_key, message_number as 8 bytes in `NONCE_MESSAGE_NUMBER_ENDIAN` order)
+    fn nonce_tag(auth_key: &[u8; 32], message_number: u64) -> hmac::Tag {
+        // Encode message number as 8 bytes in the wire byte order
+        let message_number_bytes = match NONCE_MESSAGE_NUMBER_ENDIAN {
+            Endian::Little => message_number.to_le_bytes(),
+            Endian::Big => message_number.to_be_bytes(),
+        };
+        
+        // Use HMAC-SHA256 to derive nonce from message key and message number
+        // This is secure and deterministic: same key + same number = same nonce
+        let key = hmac::Key::new(hmac::HMAC_SHA256, auth_key);
+        hmac::sign(&key, &message_number_bytes)
+    }
 }
 
+impl Drop for DoubleRatchet {
+    fn drop(&mut self) {
+        self.wipe_keys();
+    }
+}
@@ -1,6 +1,18 @@
+pub mod builder;
 pub mod chain;
+mod decrypt_cache;
 pub mod double_ratchet;
+pub mod one_way;
+pub mod state;
+pub mod suite;
 
-pub use chain::Chain;
-pub use double_ratchet::DoubleRatchet;
+pub use builder::{DoubleRatchetBuilder, RatchetConfig};
+pub use chain::{Chain, MessageKeys, MAX_CHAIN_MESSAGES};
+pub use double_ratchet::{DecryptSource, DecryptedMessage, DoubleRatchet, Endian, MAX_SKIP, NONCE_MESSAGE_NUMBER_ENDIAN};
+pub use one_way::{OneWayReceiver, OneWaySender};
+pub use state::{PublicRatchetState, RatchetState, SESSION_STATE_VERSION};
+pub use suite::{supported_suites, SuiteDescriptor};
 
+
+#[cfg(feature = "test-support")]
+pub use double_ratchet::{AuditReport, EnvelopeInspection, SendingChainCheckpoint};
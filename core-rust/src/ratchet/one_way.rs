@@ -0,0 +1,174 @@
+use crate::error::{E2EEError, Result};
+use crate::message::{validate_envelope_consistency, MessageEnvelope};
+use crate::ratchet::chain::{Chain, MessageKeys, MAX_CHAIN_MESSAGES};
+use crate::ratchet::double_ratchet::{DoubleRatchet, MAX_SKIP};
+use crate::ratchet::suite::MessageCipher;
+use std::collections::HashMap;
+use zeroize::Zeroize;
+
+/// HKDF label of the single chain shared by a one-way sender and receiver
+const ONE_WAY_CHAIN_LABEL: &[u8] = b"one-way";
+
+/// Derive the one-way chain from a shared secret
+fn one_way_chain(shared_secret: &[u8; 32]) -> Result<Chain> {
+    let chain_key = DoubleRatchet::derive_chain_key(&[], shared_secret, ONE_WAY_CHAIN_LABEL)?;
+    Ok(Chain::with_context(chain_key, MAX_CHAIN_MESSAGES, &[]))
+}
+
+/// Sending half of a one-way ratchet, for channels where the receiver never replies
+/// 
+/// Only the symmetric chain ratchets: every message key is derived from the
+/// previous chain key and then forgotten, which gives forward secrecy but no
+/// break-in recovery. There is no DH key pair, and envelopes carry no DH
+/// public key. Created with `DoubleRatchet::one_way_sender`.
+pub struct OneWaySender {
+    chain: Chain,
+    message_number: u64,
+}
+
+/// Receiving half of a one-way ratchet (see `OneWaySender`)
+/// 
+/// Created with `DoubleRatchet::one_way_receiver`.
+pub struct OneWayReceiver {
+    chain: Chain,
+    /// Message keys derived for skipped messages, keyed by message number
+    skipped_message_keys: HashMap<u64, MessageKeys>,
+}
+
+impl DoubleRatchet {
+    /// Create the sending half of a one-way ratchet
+    /// 
+    /// # Arguments
+    /// * `shared_secret` - 32-byte secret shared with the receiver
+    /// 
+    /// # Returns
+    /// OneWaySender, the counterpart of `one_way_receiver` with the same secret
+    pub fn one_way_sender(shared_secret: &[u8; 32]) -> Result<OneWaySender> {
+        Ok(OneWaySender {
+            chain: one_way_chain(shared_secret)?,
+            message_number: 0,
+        })
+    }
+
+    /// Create the receiving half of a one-way ratchet
+    /// 
+    /// # Arguments
+    /// * `shared_secret` - 32-byte secret shared with the sender
+    /// 
+    /// # Returns
+    /// OneWayReceiver, the counterpart of `one_way_sender` with the same secret
+    pub fn one_way_receiver(shared_secret: &[u8; 32]) -> Result<OneWayReceiver> {
+        Ok(OneWayReceiver {
+            chain: one_way_chain(shared_secret)?,
+            skipped_message_keys: HashMap::new(),
+        })
+    }
+}
+
+impl OneWaySender {
+    /// Encrypt a plaintext message into a MessageEnvelope without a DH key
+    /// 
+    /// # Arguments
+    /// * `plaintext` - Plaintext message to encrypt
+    /// 
+    /// # Returns
+    /// MessageEnvelope whose header has an empty `dh_public_key`
+    pub fn encrypt_envelope(&mut self, plaintext: &[u8]) -> Result<MessageEnvelope> {
+        let message_number = self.message_number
+            .checked_add(1)
+            .ok_or_else(|| E2EEError::StateError("message counter overflow, rekey required".to_string()))?;
+        let message_keys = self.chain.ratchet_forward()?;
+        self.message_number = message_number;
+        
+        let mut envelope = MessageEnvelope::regular(Vec::new(), String::new(), 0, self.message_number);
+        let aad = envelope.header.associated_data();
+        envelope.ciphertext = DoubleRatchet::encrypt_with_key(MessageCipher::Aes256Gcm, &message_keys, plaintext, self.message_number, &aad)?;
+        
+        Ok(envelope)
+    }
+
+    /// Number of the last message sent (0 if none)
+    pub fn message_number(&self) -> u64 {
+        self.message_number
+    }
+}
+
+impl OneWayReceiver {
+    /// Decrypt an envelope produced by the matching `OneWaySender`
+    /// 
+    /// Messages may arrive out of order: keys of skipped messages are kept
+    /// (at most `MAX_SKIP` per gap) until their message arrives. Every key is
+    /// discarded once used.
+    /// 
+    /// # Arguments
+    /// * `envelope` - MessageEnvelope from the one-way sender
+    /// 
+    /// # Returns
+    /// Decrypted plaintext, or `ProtocolError` for an envelope carrying a DH
+    /// key, a message already received, or too large a gap
+    pub fn decrypt_envelope(&mut self, envelope: &MessageEnvelope) -> Result<Vec<u8>> {
+        validate_envelope_consistency(envelope)?;
+        if !envelope.header.dh_public_key.is_empty() {
+            return Err(E2EEError::ProtocolError(
+                "One-way envelope must not carry a DH public key".to_string(),
+            ));
+        }
+        
+        let message_number = envelope.header.message_number;
+        let message_keys = match self.skipped_message_keys.remove(&message_number) {
+            Some(message_keys) => message_keys,
+            None => self.advance_to(message_number)?,
+        };
+        
+        let aad = envelope.header.associated_data();
+        match DoubleRatchet::decrypt_with_key(MessageCipher::Aes256Gcm, &message_keys, &envelope.ciphertext, message_number, &aad) {
+            Ok(plaintext) => Ok(plaintext),
+            Err(e) => {
+                // Keep the keys so the genuine message can still be decrypted later
+                self.skipped_message_keys.insert(message_number, message_keys);
+                Err(e)
+            }
+        }
+    }
+
+    /// Ratchet the chain up to `message_number`, storing the keys skipped on the way
+    fn advance_to(&mut self, message_number: u64) -> Result<MessageKeys> {
+        // Header message numbers start at 1, chain positions at 0
+        let next_message_number = self.chain.message_number() as u64 + 1;
+        if message_number < next_message_number {
+            return Err(E2EEError::ProtocolError(format!(
+                "Message {} was already received or its key was discarded",
+                message_number
+            )));
+        }
+        
+        let skip = message_number - next_message_number;
+        if skip > MAX_SKIP {
+            return Err(E2EEError::ProtocolError(format!(
+                "Too many skipped messages: {} (max {})",
+                skip, MAX_SKIP
+            )));
+        }
+        
+        for skipped_number in next_message_number..message_number {
+            let skipped_keys = self.chain.ratchet_forward()?;
+            self.skipped_message_keys.insert(skipped_number, skipped_keys);
+        }
+        
+        self.chain.ratchet_forward()
+    }
+
+    /// Number of message keys held for skipped messages
+    pub fn skipped_key_count(&self) -> usize {
+        self.skipped_message_keys.len()
+    }
+}
+
+impl Drop for OneWayReceiver {
+    fn drop(&mut self) {
+        for (encryption_key, auth_key) in self.skipped_message_keys.values_mut() {
+            encryption_key.zeroize();
+            auth_key.zeroize();
+        }
+    }
+}
@@ -0,0 +1,173 @@
+use crate::error::{E2EEError, Result};
+use crate::ratchet::suite::SuiteDescriptor;
+use base64::{engine::general_purpose, Engine as _};
+use serde::{Deserialize, Serialize};
+use zeroize::Zeroize;
+
+/// Version of the persisted session state format
+/// 
+/// Bump whenever the layout of saved ratchet state changes, so apps can refuse
+/// to load sessions written by an incompatible build.
+pub const SESSION_STATE_VERSION: u32 = 4;
+
+/// Non-secret view of a ratchet, for peers comparing state over a secure channel
+/// 
+/// One peer's `sending_number` should equal the other's `receiving_number`
+/// once all messages have been delivered; a mismatch points at a desync or
+/// lost messages. Produced by `DoubleRatchet::public_state`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PublicRatchetState {
+    /// Our current DH ratchet public key (hex)
+    pub our_dh_public: String,
+    /// Peer's current DH ratchet public key (hex), None before the first message
+    pub remote_dh_public: Option<String>,
+    /// Number of the last message we sent (0 if none)
+    pub sending_number: u64,
+    /// Highest message number we have decrypted (0 if none)
+    pub receiving_number: u64,
+}
+
+/// Serialized position of a sending or receiving chain
+#[derive(Serialize, Deserialize)]
+pub(crate) struct ChainState {
+    /// Current chain key (hex)
+    pub(crate) chain_key_hex: String,
+    /// Number of keys derived so far
+    pub(crate) message_number: u32,
+    /// Maximum number of keys the chain may derive
+    pub(crate) message_limit: u32,
+}
+
+/// Serialized message keys held outside a chain
+#[derive(Serialize, Deserialize)]
+pub(crate) struct StoredMessageKeys {
+    /// Remote DH public key (hex) the keys belong to; None for our own sending keys
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) remote_dh_public_hex: Option<String>,
+    /// Header message number the keys decrypt
+    pub(crate) message_number: u64,
+    /// AEAD encryption key (hex)
+    pub(crate) encryption_key_hex: String,
+    /// Nonce/auth key (hex)
+    pub(crate) auth_key_hex: String,
+}
+
+/// Complete, restorable snapshot of a `DoubleRatchet`
+/// 
+/// Contains every secret of the session (root and chain keys, DH private key,
+/// skipped message keys): persist it only in secure storage. Secret fields are
+/// zeroized when the snapshot is dropped. Produced by
+/// `DoubleRatchet::to_state` and restored with `DoubleRatchet::from_state`.
+#[derive(Serialize, Deserialize)]
+pub struct RatchetState {
+    /// Format version (`SESSION_STATE_VERSION`)
+    pub(crate) version: u32,
+    pub(crate) is_initiator: bool,
+    pub(crate) sending_chain: ChainState,
+    pub(crate) receiving_chain: Option<ChainState>,
+    /// Current DH private key (hex)
+    pub(crate) dh_private_hex: String,
+    pub(crate) remote_dh_public_hex: Option<String>,
+    /// Remote DH public keys of earlier ratchet steps (hex), oldest first
+    #[serde(default)]
+    pub(crate) retired_remote_dh_public_hex: Vec<String>,
+    pub(crate) receiving_chain_start: u64,
+    pub(crate) ratchet_on_first_receive: bool,
+    pub(crate) sending_message_number: u64,
+    pub(crate) previous_sending_chain_length: u32,
+    pub(crate) has_ratcheted: bool,
+    pub(crate) sending_ratchet_started: bool,
+    /// Configured per-chain cap; null if none (older snapshots store
+    /// `MAX_CHAIN_MESSAGES` for that case)
+    pub(crate) chain_message_limit: Option<u32>,
+    pub(crate) accept_prefixed_keys: bool,
+    pub(crate) max_skip: u64,
+    /// Application context (hex)
+    pub(crate) context_hex: String,
+    /// Suite the ratchet was configured with (older snapshots: the default)
+    #[serde(default = "SuiteDescriptor::default_suite")]
+    pub(crate) suite: SuiteDescriptor,
+    pub(crate) skipped_message_keys: Vec<StoredMessageKeys>,
+    pub(crate) precomputed_send_keys: Vec<StoredMessageKeys>,
+    pub(crate) received_through: u64,
+    pub(crate) received_out_of_order: Vec<u64>,
+    /// Current root key (hex)
+    pub(crate) root_key_hex: String,
+}
+
+impl RatchetState {
+    /// Get the format version of this snapshot
+    pub fn version(&self) -> u32 {
+        self.version
+    }
+
+    /// Check whether the snapshot was taken from the X3DH initiator's ratchet
+    pub fn is_initiator(&self) -> bool {
+        self.is_initiator
+    }
+
+    /// Get the next message number the restored ratchet will send
+    pub fn next_message_number(&self) -> u64 {
+        self.sending_message_number + 1
+    }
+
+    /// Serialize the snapshot to a base64 string
+    /// 
+    /// # Returns
+    /// Base64-encoded JSON string
+    pub fn to_base64(&self) -> Result<String> {
+        let mut json = serde_json::to_string(self)
+            .map_err(|e| E2EEError::SerializationError(format!("Failed to serialize ratchet state: {}", e)))?;
+
+        let b64 = general_purpose::STANDARD.encode(json.as_bytes());
+        json.zeroize();
+        Ok(b64)
+    }
+
+    /// Deserialize a snapshot from a base64 string
+    /// 
+    /// # Arguments
+    /// * `b64` - Base64-encoded JSON string produced by `to_base64`
+    /// 
+    /// # Returns
+    /// RatchetState, `SerializationError` for malformed input, or
+    /// `ProtocolError` for a state written with another format version
+    pub fn from_base64(b64: &str) -> Result<Self> {
+        let mut json_bytes = general_purpose::STANDARD.decode(b64.trim())
+            .map_err(|e| E2EEError::SerializationError(format!("Failed to decode base64: {}", e)))?;
+
+        let state: Result<Self> = serde_json::from_slice(&json_bytes)
+            .map_err(|e| E2EEError::SerializationError(format!("Failed to deserialize ratchet state: {}", e)));
+        json_bytes.zeroize();
+        let state = state?;
+
+        if state.version != SESSION_STATE_VERSION {
+            return Err(E2EEError::ProtocolError(format!(
+                "Unsupported session state version {} (expected {})",
+                state.version, SESSION_STATE_VERSION
+            )));
+        }
+
+        Ok(state)
+    }
+}
+
+impl Drop for StoredMessageKeys {
+    fn drop(&mut self) {
+        self.encryption_key_hex.zeroize();
+        self.auth_key_hex.zeroize();
+    }
+}
+
+impl Drop for ChainState {
+    fn drop(&mut self) {
+        self.chain_key_hex.zeroize();
+    }
+}
+
+impl Drop for RatchetState {
+    fn drop(&mut self) {
+        self.dh_private_hex.zeroize();
+        self.root_key_hex.zeroize();
+    }
+}
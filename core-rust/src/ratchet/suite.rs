@@ -0,0 +1,82 @@
+use crate::error::{E2EEError, Result};
+use crate::ratchet::double_ratchet::DoubleRatchet;
+use serde::{Deserialize, Serialize};
+
+/// AEAD used for message bodies by default
+pub const CIPHER_AES_256_GCM: &str = "AES-256-GCM";
+
+/// Alternative AEAD for message bodies, with a 24-byte nonce
+pub const CIPHER_XCHACHA20_POLY1305: &str = "XChaCha20-Poly1305";
+
+/// KDF used for the root and chain keys
+pub const KDF_HKDF_SHA256: &str = "HKDF-SHA256";
+
+/// Algorithms a ratchet uses to protect messages
+/// 
+/// Clients compare descriptors to negotiate a suite with a peer, and support
+/// can read them from a session to diagnose mismatches.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SuiteDescriptor {
+    /// AEAD cipher for message bodies (e.g. "AES-256-GCM")
+    pub cipher: String,
+    /// KDF for root and chain keys (e.g. "HKDF-SHA256")
+    pub kdf: String,
+    /// Whether message headers are encrypted
+    pub header_encryption: bool,
+}
+
+impl SuiteDescriptor {
+    /// The suite ratchets use unless configured otherwise: AES-256-GCM with
+    /// HKDF-SHA256, plaintext headers
+    pub fn default_suite() -> Self {
+        Self {
+            cipher: CIPHER_AES_256_GCM.to_string(),
+            kdf: KDF_HKDF_SHA256.to_string(),
+            header_encryption: false,
+        }
+    }
+
+    /// XChaCha20-Poly1305 with HKDF-SHA256, plaintext headers
+    pub fn xchacha20_poly1305() -> Self {
+        Self {
+            cipher: CIPHER_XCHACHA20_POLY1305.to_string(),
+            ..Self::default_suite()
+        }
+    }
+
+    /// Resolve the AEAD for message bodies
+    /// 
+    /// # Returns
+    /// MessageCipher, or `ProtocolError` if this build does not support the suite
+    pub(crate) fn message_cipher(&self) -> Result<MessageCipher> {
+        if !supported_suites().contains(self) {
+            return Err(E2EEError::ProtocolError(format!(
+                "Unsupported suite: cipher {}, kdf {}, header encryption {}",
+                self.cipher, self.kdf, self.header_encryption
+            )));
+        }
+        match self.cipher.as_str() {
+            CIPHER_XCHACHA20_POLY1305 => Ok(MessageCipher::XChaCha20Poly1305),
+            _ => Ok(MessageCipher::Aes256Gcm),
+        }
+    }
+}
+
+/// AEAD that seals message bodies, resolved from a `SuiteDescriptor`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum MessageCipher {
+    Aes256Gcm,
+    XChaCha20Poly1305,
+}
+
+/// List the suites this build supports, default first
+pub fn supported_suites() -> Vec<SuiteDescriptor> {
+    vec![SuiteDescriptor::default_suite(), SuiteDescriptor::xchacha20_poly1305()]
+}
+
+impl DoubleRatchet {
+    /// Get the suite this ratchet protects messages with
+    pub fn current_suite(&self) -> SuiteDescriptor {
+        self.suite().clone()
+    }
+}
@@ -1,5 +1,5 @@
 use crate::error::{E2EEError, Result};
-use x25519_dalek::{EphemeralSecret, PublicKey};
+use x25519_dalek::{PublicKey, StaticSecret};
 
 /// Calculate shared secret for X3DH protocol
 /// 
@@ -10,8 +10,7 @@ use x25519_dalek::{EphemeralSecret, PublicKey};
 /// - DH3 = ECDH(EK, SPKB)
 /// - DH4 = ECDH(EK, OPKB) [if available]
 /// 
-/// This function accepts pre-computed DH values to avoid ownership issues
-/// with EphemeralSecret which cannot be cloned.
+/// This function accepts pre-computed DH values (see `perform_dh`).
 pub fn calculate_shared_secret_from_dh(
     dh1: &[u8; 32],
     dh2: &[u8; 32],
@@ -37,35 +36,109 @@ pub fn calculate_shared_secret_from_dh(
     Ok(shared_secret)
 }
 
+/// Calculate the transcript hash of an X3DH handshake
+/// 
+/// SHA-256 over a domain label and the ordered public inputs:
+/// IKA || IKB || SPKB || EK || OPKB. A presence byte precedes OPKB so a
+/// handshake without a one-time prekey cannot collide with one that has it.
+/// Both sides compute the same value; apps can compare it out-of-band or
+/// bind it into the first message for channel binding.
+pub fn calculate_transcript_hash(
+    identity_a: &[u8; 32],
+    identity_b: &[u8; 32],
+    signed_prekey_b: &[u8; 32],
+    ephemeral_a: &[u8; 32],
+    one_time_prekey_b: Option<&[u8; 32]>,
+) -> [u8; 32] {
+    let mut context = ring::digest::Context::new(&ring::digest::SHA256);
+    context.update(b"X3DH-transcript");
+    context.update(identity_a);
+    context.update(identity_b);
+    context.update(signed_prekey_b);
+    context.update(ephemeral_a);
+    match one_time_prekey_b {
+        Some(opk) => {
+            context.update(&[1u8]);
+            context.update(opk);
+        }
+        None => context.update(&[0u8]),
+    }
+    
+    let mut transcript_hash = [0u8; 32];
+    transcript_hash.copy_from_slice(context.finish().as_ref());
+    transcript_hash
+}
+
+/// Calculate the key-confirmation MAC of an X3DH handshake
+/// 
+/// HMAC-SHA256 keyed with the shared secret over a domain label and the
+/// transcript hash (empty for resumed sessions). The initiator sends it with
+/// the first message; the responder recomputes it before decrypting, so a
+/// mismatched shared secret is reported as such instead of a decrypt failure.
+pub fn calculate_key_confirmation(shared_secret: &[u8; 32], transcript_hash: Option<&[u8; 32]>) -> [u8; 32] {
+    let tag = ring::hmac::sign(
+        &ring::hmac::Key::new(ring::hmac::HMAC_SHA256, shared_secret),
+        &key_confirmation_input(transcript_hash),
+    );
+    
+    let mut mac = [0u8; 32];
+    mac.copy_from_slice(tag.as_ref());
+    mac
+}
+
+/// Verify a key-confirmation MAC in constant time
+/// 
+/// # Returns
+/// Ok(()) if `mac` matches, `E2EEError::KeyConfirmationFailed` otherwise
+pub fn verify_key_confirmation(
+    shared_secret: &[u8; 32],
+    transcript_hash: Option<&[u8; 32]>,
+    mac: &[u8; 32],
+) -> Result<()> {
+    ring::hmac::verify(
+        &ring::hmac::Key::new(ring::hmac::HMAC_SHA256, shared_secret),
+        &key_confirmation_input(transcript_hash),
+        mac,
+    )
+    .map_err(|_| E2EEError::KeyConfirmationFailed)
+}
+
+fn key_confirmation_input(transcript_hash: Option<&[u8; 32]>) -> Vec<u8> {
+    let mut input = b"X3DH-key-confirmation".to_vec();
+    if let Some(hash) = transcript_hash {
+        input.extend_from_slice(hash);
+    }
+    input
+}
+
 /// Perform ECDH key exchange
 /// 
 /// Returns the shared secret from ECDH(private, public)
 /// 
-/// Note: This function consumes the private key because EphemeralSecret
-/// doesn't implement Copy.
-pub fn perform_dh(private: EphemeralSecret, public: &PublicKey) -> Result<[u8; 32]> {
-    // Perform ECDH using x25519
-    // diffie_hellman consumes the private key
-    let shared_secret = private.diffie_hellman(public);
-    Ok(*shared_secret.as_bytes())
+/// Borrows the private key, so the same StaticSecret can take part in
+/// several DH computations (DH1..DH4) without being reconstructed.
+pub fn perform_dh(private: &StaticSecret, public: &PublicKey) -> [u8; 32] {
+    *private.diffie_hellman(public).as_bytes()
+}
+
+/// Perform X25519 on raw key bytes
+/// 
+/// Pure entrypoint for known-answer tests (e.g. RFC 7748 vectors): the scalar
+/// is clamped as usual, exactly as in `perform_dh`.
+/// 
+/// # Arguments
+/// * `private_bytes` - Private scalar bytes
+/// * `public_bytes` - Peer public key (u-coordinate) bytes
+/// 
+/// # Returns
+/// The 32-byte X25519 output
+pub fn dh_from_bytes(private_bytes: &[u8; 32], public_bytes: &[u8; 32]) -> [u8; 32] {
+    perform_dh(&StaticSecret::from(*private_bytes), &PublicKey::from(*public_bytes))
 }
 
 /// Derive shared secret using HKDF-SHA256
 /// 
 /// Uses HKDF with empty salt and info to derive 32-byte key
 fn derive_shared_secret(ikm: &[u8]) -> Result<[u8; 32]> {
-    let salt = ring::hkdf::Salt::new(ring::hkdf::HKDF_SHA256, &[]);
-    
-    // Extract PRK
-    let prk = salt.extract(ikm);
-    
-    // Expand to 32 bytes
-    let okm = prk.expand(&[], ring::hkdf::HKDF_SHA256)
-        .map_err(|e| E2EEError::CryptoError(format!("HKDF expand failed: {}", e)))?;
-    
-    let mut shared_secret = [0u8; 32];
-    okm.fill(&mut shared_secret)
-        .map_err(|e| E2EEError::CryptoError(format!("HKDF fill failed: {}", e)))?;
-    
-    Ok(shared_secret)
+    crate::kdf::hkdf_32(ikm, &[], &[])
 }
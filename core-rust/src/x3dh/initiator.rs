@@ -1,15 +1,108 @@
+use crate::encoding::parse_hex_32;
 use crate::error::{E2EEError, Result};
 use crate::keys::{IdentityKeyPair, PreKeyBundle};
-use crate::x3dh::handshake::{calculate_shared_secret_from_dh, perform_dh};
+use crate::ratchet::DoubleRatchet;
+use crate::x3dh::handshake::{
+    calculate_key_confirmation, calculate_shared_secret_from_dh, calculate_transcript_hash, perform_dh,
+};
+use crate::x3dh::resumption::{derive_resumed_secret, resumption_id, ticket_resumption_id};
+use parking_lot::Mutex;
 use rand::rngs::OsRng;
-use x25519_dalek::{EphemeralSecret, PublicKey};
+use rand::{CryptoRng, RngCore};
+use serde::{Deserialize, Serialize};
+use subtle::ConstantTimeEq;
+use std::collections::HashMap;
+use x25519_dalek::{PublicKey, StaticSecret};
+use zeroize::{Zeroize, Zeroizing};
 
 /// Result of X3DH initiation
+/// 
+/// The shared secret is not exposed: turn the result into a Double Ratchet
+/// with `into_ratchet`, or compare it with `matches`.
 pub struct X3DHResult {
     /// The shared secret derived from X3DH handshake
-    pub shared_secret: [u8; 32],
+    pub(crate) shared_secret: [u8; 32],
     /// Ephemeral public key as hex string
     pub ephemeral_public_key_hex: String,
+    /// Hash of the handshake's public inputs (None for resumed sessions)
+    pub transcript_hash: Option<[u8; 32]>,
+    /// Bob's signed prekey, his initial Double Ratchet key
+    pub ratchet_public_key: Option<[u8; 32]>,
+}
+
+impl X3DHResult {
+    /// Key-confirmation MAC to send with the first (prekey) message
+    /// 
+    /// # Returns
+    /// HMAC-SHA256(shared_secret, transcript_hash), checked by the responder
+    /// with `X3DHResponseResult::verify_key_confirmation`
+    pub fn key_confirmation(&self) -> [u8; 32] {
+        calculate_key_confirmation(&self.shared_secret, self.transcript_hash.as_ref())
+    }
+
+    /// Compare the shared secret with another in constant time
+    /// 
+    /// For diagnostics such as self-checking a handshake in a test network;
+    /// not a security boundary (use `key_confirmation` between peers).
+    pub fn matches(&self, other: &[u8; 32]) -> bool {
+        self.shared_secret.ct_eq(other).into()
+    }
+
+    /// Build the initiator's Double Ratchet, consuming the result
+    /// 
+    /// Seeded with Bob's signed prekey (`DoubleRatchet::from_x3dh_initiator`)
+    /// when the handshake provided one, otherwise built with
+    /// `DoubleRatchet::from_shared_secret`.
+    /// 
+    /// # Returns
+    /// DoubleRatchet for the initiator
+    pub fn into_ratchet(self) -> Result<DoubleRatchet> {
+        match self.ratchet_public_key {
+            Some(ref ratchet_public_key) => DoubleRatchet::from_x3dh_initiator(&self.shared_secret, ratchet_public_key),
+            None => DoubleRatchet::from_shared_secret(&self.shared_secret, true),
+        }
+    }
+
+    /// Get the raw shared secret
+    /// 
+    /// For test vectors only; app code should never handle the root secret.
+    /// Only available with `test-vectors`.
+    #[cfg(feature = "test-vectors")]
+    pub fn shared_secret_bytes(&self) -> [u8; 32] {
+        self.shared_secret
+    }
+}
+
+/// Ephemeral key generated ahead of an X3DH initiation
+/// 
+/// Created by `X3DHInitiator::prepare` before the responder's bundle is
+/// known (e.g. while composing offline) and consumed by `finish`. Serializes
+/// so it survives an app restart; it holds a private key, so store it like one.
+#[derive(Serialize, Deserialize)]
+pub struct PendingInitiation {
+    ephemeral_private: [u8; 32],
+}
+
+impl PendingInitiation {
+    /// Ephemeral public key that `finish` will send, as hex string
+    pub fn ephemeral_public_key_hex(&self) -> String {
+        let ephemeral_public = PublicKey::from(&StaticSecret::from(self.ephemeral_private));
+        hex::encode(ephemeral_public.as_bytes())
+    }
+}
+
+impl Drop for PendingInitiation {
+    fn drop(&mut self) {
+        self.ephemeral_private.zeroize();
+    }
+}
+
+/// What `X3DHInitiator::resume` needs of a session it started
+#[derive(Clone)]
+struct ResumableSession {
+    shared_secret: Zeroizing<[u8; 32]>,
+    /// Responder's signed prekey, reused as its first DH ratchet key
+    ratchet_public_key: [u8; 32],
 }
 
 /// X3DH Initiator (Alice side)
@@ -17,12 +110,20 @@ pub struct X3DHResult {
 /// Handles the initiator side of the X3DH key agreement protocol.
 pub struct X3DHInitiator {
     identity_pair: IdentityKeyPair,
+    /// Ephemeral keys used by `initiate_idempotent`, keyed by bundle
+    ephemeral_cache: Mutex<HashMap<String, StaticSecret>>,
+    /// Sessions started here, keyed by resumption id, for `resume`
+    resumption_secrets: Mutex<HashMap<[u8; 32], ResumableSession>>,
 }
 
 impl X3DHInitiator {
     /// Create a new X3DH initiator
     pub fn new(identity_pair: IdentityKeyPair) -> Self {
-        Self { identity_pair }
+        Self {
+            identity_pair,
+            ephemeral_cache: Mutex::new(HashMap::new()),
+            resumption_secrets: Mutex::new(HashMap::new()),
+        }
     }
 
     /// Initiate X3DH handshake with a prekey bundle
@@ -31,22 +132,82 @@ impl X3DHInitiator {
     /// * `bundle` - Prekey bundle from Bob containing identity, signed prekey, and optional one-time prekey
     /// 
     /// # Returns
-    /// X3DHResult containing the shared secret and ephemeral public key
+    /// X3DHResult containing the shared secret, ephemeral public key and transcript hash
     pub fn initiate(&self, bundle: &PreKeyBundle) -> Result<X3DHResult> {
-        // Parse Bob's identity public key from hex
-        let identity_b_hex = bundle.identity_public_hex();
-        let identity_b_bytes = hex::decode(identity_b_hex)
-            .map_err(|e| E2EEError::SerializationError(format!("Failed to decode identity public key: {}", e)))?;
+        // Generate ephemeral key (EK)
+        // StaticSecret so the same ephemeral can be borrowed for DH2..DH4
+        self.initiate_with_ephemeral(bundle, &StaticSecret::random_from_rng(OsRng))
+    }
+
+    /// Initiate X3DH handshake with an ephemeral key drawn from the given RNG
+    /// 
+    /// With a seeded RNG the whole handshake is reproducible, so tests can pin
+    /// the key schedule against golden vectors. Production code uses `initiate`.
+    /// 
+    /// # Arguments
+    /// * `bundle` - Prekey bundle from Bob
+    /// * `rng` - Cryptographically secure RNG
+    /// 
+    /// # Returns
+    /// X3DHResult containing the shared secret, ephemeral public key and transcript hash
+    pub fn initiate_with_rng<R: RngCore + CryptoRng>(&self, bundle: &PreKeyBundle, rng: &mut R) -> Result<X3DHResult> {
+        self.initiate_with_ephemeral(bundle, &StaticSecret::random_from_rng(rng))
+    }
+
+    /// Initiate X3DH handshake, reusing the ephemeral key of an earlier call with the same bundle
+    /// 
+    /// A retry after a network error then reproduces the same ephemeral public
+    /// key and shared secret, so the responder sees a single handshake. The
+    /// ephemeral is cached per bundle (identity, signed prekey and one-time
+    /// prekey) for the lifetime of this initiator; `initiate` never uses it.
+    /// 
+    /// # Arguments
+    /// * `bundle` - Prekey bundle from Bob
+    /// 
+    /// # Returns
+    /// X3DHResult identical to the previous idempotent result for this bundle
+    pub fn initiate_idempotent(&self, bundle: &PreKeyBundle) -> Result<X3DHResult> {
+        let cache_key = format!(
+            "{}:{}:{}",
+            bundle.identity_public_hex(),
+            bundle.signed_prekey().public_key_hex(),
+            bundle.one_time_prekey().map(|otp| otp.public_key_hex()).unwrap_or_default(),
+        );
+        let ephemeral_private = self.ephemeral_cache
+            .lock()
+            .entry(cache_key)
+            .or_insert_with(|| StaticSecret::random_from_rng(OsRng))
+            .clone();
         
-        if identity_b_bytes.len() != 32 {
-            return Err(E2EEError::ProtocolError(
-                format!("Invalid identity public key length: expected 32, got {}", identity_b_bytes.len())
-            ));
+        self.initiate_with_ephemeral(bundle, &ephemeral_private)
+    }
+
+    /// Generate the ephemeral key of a future initiation
+    /// 
+    /// # Returns
+    /// PendingInitiation to pass to `finish` once the bundle is available
+    pub fn prepare(&self) -> PendingInitiation {
+        PendingInitiation {
+            ephemeral_private: StaticSecret::random_from_rng(OsRng).to_bytes(),
         }
-        
-        let mut identity_b_pub_bytes = [0u8; 32];
-        identity_b_pub_bytes.copy_from_slice(&identity_b_bytes);
-        let identity_b_public = PublicKey::from(identity_b_pub_bytes);
+    }
+
+    /// Complete an initiation started with `prepare`
+    /// 
+    /// # Arguments
+    /// * `pending` - Ephemeral key from `prepare` (consumed, so it is used once)
+    /// * `bundle` - Prekey bundle from Bob
+    /// 
+    /// # Returns
+    /// X3DHResult exactly as `initiate` would produce with that ephemeral key
+    pub fn finish(&self, pending: PendingInitiation, bundle: &PreKeyBundle) -> Result<X3DHResult> {
+        self.initiate_with_ephemeral(bundle, &StaticSecret::from(pending.ephemeral_private))
+    }
+
+    /// Run the initiator side of X3DH with the given ephemeral key
+    fn initiate_with_ephemeral(&self, bundle: &PreKeyBundle, ephemeral_private: &StaticSecret) -> Result<X3DHResult> {
+        // Parse Bob's identity public key from hex
+        let identity_b_public = PublicKey::from(parse_hex_32(bundle.identity_public_hex())?);
         
         // Parse signed prekey public key
         let signed_prekey = bundle.signed_prekey();
@@ -56,44 +217,21 @@ impl X3DHInitiator {
         let one_time_prekey_public = bundle.one_time_prekey()
             .map(|otp| otp.public_key());
         
-        // Generate ephemeral key (EK)
-        let ephemeral_private = EphemeralSecret::random_from_rng(OsRng);
-        let ephemeral_public = PublicKey::from(&ephemeral_private);
+        let ephemeral_public = PublicKey::from(ephemeral_private);
         let ephemeral_public_hex = hex::encode(ephemeral_public.as_bytes());
         
         // Calculate DH1 = ECDH(IKA, SPKB)
-        // Get identity private key as EphemeralSecret (can be used multiple times)
-        let identity_a_private = self.identity_pair.private_key_as_ephemeral();
-        let dh1 = perform_dh(identity_a_private, &signed_prekey_public)?;
+        let identity_a_private = self.identity_pair.private_key_as_static();
+        let dh1 = perform_dh(&identity_a_private, signed_prekey_public);
         
         // Calculate DH2 = ECDH(EK, IKB)
-        // We need to clone ephemeral_private for multiple uses
-        // Since EphemeralSecret doesn't implement Clone, we need to extract bytes first
-        let ephemeral_private_bytes = unsafe {
-            std::mem::transmute_copy::<EphemeralSecret, [u8; 32]>(&ephemeral_private)
-        };
-        
-        // Create new EphemeralSecret for DH2
-        let ephemeral_private_for_dh2 = unsafe {
-            std::mem::transmute::<[u8; 32], EphemeralSecret>(ephemeral_private_bytes)
-        };
-        let dh2 = perform_dh(ephemeral_private_for_dh2, &identity_b_public)?;
+        let dh2 = perform_dh(ephemeral_private, &identity_b_public);
         
         // Calculate DH3 = ECDH(EK, SPKB)
-        let ephemeral_private_for_dh3 = unsafe {
-            std::mem::transmute::<[u8; 32], EphemeralSecret>(ephemeral_private_bytes)
-        };
-        let dh3 = perform_dh(ephemeral_private_for_dh3, &signed_prekey_public)?;
+        let dh3 = perform_dh(ephemeral_private, signed_prekey_public);
         
         // Calculate DH4 = ECDH(EK, OPKB) if available
-        let dh4 = if let Some(opkb) = one_time_prekey_public.as_ref() {
-            let ephemeral_private_for_dh4 = unsafe {
-                std::mem::transmute::<[u8; 32], EphemeralSecret>(ephemeral_private_bytes)
-            };
-            Some(perform_dh(ephemeral_private_for_dh4, opkb)?)
-        } else {
-            None
-        };
+        let dh4 = one_time_prekey_public.map(|opkb| perform_dh(ephemeral_private, opkb));
         
         // Calculate shared secret from DH values
         let shared_secret = calculate_shared_secret_from_dh(
@@ -103,9 +241,74 @@ impl X3DHInitiator {
             dh4.as_ref(),
         )?;
         
+        self.remember_for_resumption(&shared_secret, signed_prekey_public.as_bytes())?;
+        
+        let transcript_hash = calculate_transcript_hash(
+            &self.identity_pair.public_key_bytes(),
+            identity_b_public.as_bytes(),
+            signed_prekey_public.as_bytes(),
+            ephemeral_public.as_bytes(),
+            one_time_prekey_public.map(|opk| opk.as_bytes()),
+        );
+        
         Ok(X3DHResult {
             shared_secret,
             ephemeral_public_key_hex: ephemeral_public_hex,
+            transcript_hash: Some(transcript_hash),
+            ratchet_public_key: Some(*signed_prekey_public.as_bytes()),
+        })
+    }
+
+    /// Resume a previous session from a resumption ticket (0-RTT)
+    /// 
+    /// Skips a full X3DH: the new shared secret is derived from the original
+    /// session's shared secret, the ticket and a fresh ephemeral key's DH
+    /// with Bob's signed prekey, so it never equals the original and differs
+    /// on every call. The original secret never leaves the crate: this
+    /// initiator remembers the secret of every session it started (resumed
+    /// ones included) and finds it through the resumption id the ticket
+    /// carries. Send the ticket to the responder together with our identity
+    /// public key and `ephemeral_public_key_hex` so it can derive the same secret.
+    /// 
+    /// # Arguments
+    /// * `ticket` - Ticket issued by the responder
+    /// 
+    /// # Returns
+    /// X3DHResult with the resumed shared secret, or `KeyNotFound` if the
+    /// ticket belongs to a session this initiator did not start (e.g. one
+    /// started before an app restart). The ratchet is seeded with Bob's
+    /// signed prekey as after a full handshake; there is no transcript hash.
+    pub fn resume(&self, ticket: &[u8]) -> Result<X3DHResult> {
+        let id = ticket_resumption_id(ticket)?;
+        let ResumableSession { shared_secret: original_shared_secret, ratchet_public_key } = self.resumption_secrets
+            .lock()
+            .get(&id)
+            .cloned()
+            .ok_or_else(|| E2EEError::KeyNotFound("No session to resume for this ticket".to_string()))?;
+        
+        let ephemeral_private = StaticSecret::random_from_rng(OsRng);
+        let ephemeral_public = PublicKey::from(&ephemeral_private);
+        let mut dh = perform_dh(&ephemeral_private, &PublicKey::from(ratchet_public_key));
+        let shared_secret = derive_resumed_secret(&original_shared_secret, ticket, ephemeral_public.as_bytes(), &dh);
+        dh.zeroize();
+        let shared_secret = shared_secret?;
+        self.remember_for_resumption(&shared_secret, &ratchet_public_key)?;
+        
+        Ok(X3DHResult {
+            shared_secret,
+            ephemeral_public_key_hex: hex::encode(ephemeral_public.as_bytes()),
+            transcript_hash: None,
+            ratchet_public_key: Some(ratchet_public_key),
         })
     }
+
+    /// Keep a session's shared secret so a ticket for it can be resumed later
+    fn remember_for_resumption(&self, shared_secret: &[u8; 32], ratchet_public_key: &[u8; 32]) -> Result<()> {
+        let id = resumption_id(shared_secret)?;
+        self.resumption_secrets.lock().insert(id, ResumableSession {
+            shared_secret: Zeroizing::new(*shared_secret),
+            ratchet_public_key: *ratchet_public_key,
+        });
+        Ok(())
+    }
 }
@@ -1,8 +1,19 @@
 pub mod handshake;
 pub mod initiator;
+pub mod replay;
 pub mod responder;
+pub mod resumption;
+#[cfg(feature = "test-support")]
+pub mod session_pair;
 
-pub use handshake::{calculate_shared_secret_from_dh, perform_dh};
-pub use initiator::{X3DHInitiator, X3DHResult};
+pub use handshake::{
+    calculate_key_confirmation, calculate_shared_secret_from_dh, calculate_transcript_hash, dh_from_bytes,
+    perform_dh, verify_key_confirmation,
+};
+pub use initiator::{PendingInitiation, X3DHInitiator, X3DHResult};
+pub use replay::{InitiationReplayGuard, SeenInitiations, DEFAULT_REPLAY_CAPACITY};
 pub use responder::{X3DHResponder, X3DHResponseResult};
 
+#[cfg(feature = "test-support")]
+pub use session_pair::establish_session_pair;
+
@@ -0,0 +1,84 @@
+use parking_lot::Mutex;
+use std::collections::{HashSet, VecDeque};
+
+/// Default number of initiations `SeenInitiations` remembers
+pub const DEFAULT_REPLAY_CAPACITY: usize = 4096;
+
+/// Record of X3DH initiations a responder has already answered
+/// 
+/// Without a one-time prekey nothing is consumed by a handshake, so a relay
+/// replaying Alice's initiation would make Bob derive the same session again.
+/// Implementations may be backed by persistent storage to survive restarts.
+pub trait InitiationReplayGuard: Send + Sync {
+    /// Record an initiation, reporting whether it is new
+    /// 
+    /// # Arguments
+    /// * `identity_a` - Initiator's identity public key
+    /// * `ephemeral` - Initiator's ephemeral public key
+    /// 
+    /// # Returns
+    /// true the first time a pair is seen, false for a repeat
+    fn record(&self, identity_a: &[u8; 32], ephemeral: &[u8; 32]) -> bool;
+}
+
+/// In-memory replay guard remembering the most recent initiations
+/// 
+/// Once `capacity` pairs are stored the oldest is forgotten, so a replay
+/// older than that goes undetected; size it to the expected handshake rate.
+pub struct SeenInitiations {
+    capacity: usize,
+    seen: Mutex<SeenPairs>,
+}
+
+/// Remembered (identity, ephemeral) pairs, with their insertion order for eviction
+#[derive(Default)]
+struct SeenPairs {
+    set: HashSet<[u8; 64]>,
+    order: VecDeque<[u8; 64]>,
+}
+
+impl SeenInitiations {
+    /// Create a guard remembering up to `capacity` initiations (at least 1)
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity: capacity.max(1),
+            seen: Mutex::new(SeenPairs::default()),
+        }
+    }
+
+    /// Number of initiations currently remembered
+    pub fn len(&self) -> usize {
+        self.seen.lock().set.len()
+    }
+
+    /// Check whether no initiation is remembered
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl Default for SeenInitiations {
+    fn default() -> Self {
+        Self::new(DEFAULT_REPLAY_CAPACITY)
+    }
+}
+
+impl InitiationReplayGuard for SeenInitiations {
+    fn record(&self, identity_a: &[u8; 32], ephemeral: &[u8; 32]) -> bool {
+        let mut pair = [0u8; 64];
+        pair[..32].copy_from_slice(identity_a);
+        pair[32..].copy_from_slice(ephemeral);
+        
+        let mut seen = self.seen.lock();
+        if !seen.set.insert(pair) {
+            return false;
+        }
+        seen.order.push_back(pair);
+        if seen.order.len() > self.capacity {
+            if let Some(oldest) = seen.order.pop_front() {
+                seen.set.remove(&oldest);
+            }
+        }
+        true
+    }
+}
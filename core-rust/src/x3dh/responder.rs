@@ -1,12 +1,75 @@
+use crate::clock::{Clock, SystemClock};
+use crate::encoding::parse_hex_32;
 use crate::error::{E2EEError, Result};
 use crate::keys::{IdentityKeyPair, SignedPreKeyPair};
-use crate::x3dh::handshake::{calculate_shared_secret_from_dh, perform_dh};
-use x25519_dalek::{EphemeralSecret, PublicKey};
+use crate::ratchet::DoubleRatchet;
+use crate::x3dh::handshake::{
+    calculate_shared_secret_from_dh, calculate_transcript_hash, perform_dh, verify_key_confirmation,
+};
+use crate::x3dh::replay::InitiationReplayGuard;
+use crate::x3dh::resumption::{derive_resumed_secret, open_ticket, seal_ticket, ticket_digest};
+use std::sync::Arc;
+use subtle::ConstantTimeEq;
+use x25519_dalek::{PublicKey, StaticSecret};
+use zeroize::{Zeroize, Zeroizing};
 
 /// Result of X3DH response
+/// 
+/// The shared secret is not exposed: turn the result into a Double Ratchet
+/// with `into_ratchet`, or compare it with `matches`.
 pub struct X3DHResponseResult {
     /// The shared secret derived from X3DH handshake
-    pub shared_secret: [u8; 32],
+    pub(crate) shared_secret: [u8; 32],
+    /// Hash of the handshake's public inputs (None for resumed sessions)
+    pub transcript_hash: Option<[u8; 32]>,
+    /// Alice's identity public key the secret was derived against (lowercase hex)
+    pub peer_identity_hex: String,
+    /// Our signed prekey, the initial Double Ratchet key
+    pub ratchet_private_key: Option<StaticSecret>,
+}
+
+impl X3DHResponseResult {
+    /// Verify the initiator's key-confirmation MAC before decrypting its first message
+    /// 
+    /// # Arguments
+    /// * `mac` - MAC from `X3DHResult::key_confirmation`
+    /// 
+    /// # Returns
+    /// Ok(()) if both sides derived the same shared secret and transcript,
+    /// `E2EEError::KeyConfirmationFailed` otherwise
+    pub fn verify_key_confirmation(&self, mac: &[u8; 32]) -> Result<()> {
+        verify_key_confirmation(&self.shared_secret, self.transcript_hash.as_ref(), mac)
+    }
+
+    /// Compare the shared secret with another in constant time
+    /// 
+    /// For diagnostics such as self-checking a handshake in a test network;
+    /// not a security boundary (use `verify_key_confirmation` between peers).
+    pub fn matches(&self, other: &[u8; 32]) -> bool {
+        self.shared_secret.ct_eq(other).into()
+    }
+
+    /// Build the responder's Double Ratchet, consuming the result
+    /// 
+    /// Counterpart of `X3DHResult::into_ratchet`.
+    /// 
+    /// # Returns
+    /// DoubleRatchet for the responder
+    pub fn into_ratchet(self) -> Result<DoubleRatchet> {
+        match self.ratchet_private_key {
+            Some(ref ratchet_private_key) => DoubleRatchet::from_x3dh_responder(&self.shared_secret, ratchet_private_key),
+            None => DoubleRatchet::from_shared_secret(&self.shared_secret, false),
+        }
+    }
+
+    /// Get the raw shared secret
+    /// 
+    /// For test vectors only; app code should never handle the root secret.
+    /// Only available with `test-vectors`.
+    #[cfg(feature = "test-vectors")]
+    pub fn shared_secret_bytes(&self) -> [u8; 32] {
+        self.shared_secret
+    }
 }
 
 /// X3DH Responder (Bob side)
@@ -15,9 +78,13 @@ pub struct X3DHResponseResult {
 pub struct X3DHResponder {
     identity_pair: IdentityKeyPair,
     signed_prekey_pair: SignedPreKeyPair,
-    one_time_prekey_private: Option<EphemeralSecret>,
+    one_time_prekey_private: Option<StaticSecret>,
     one_time_prekey_public: Option<PublicKey>,
     one_time_prekey_id: Option<u32>,
+    /// Time source for resumption ticket expiry
+    clock: Arc<dyn Clock>,
+    /// Initiations already answered, when replay detection is enabled
+    replay_guard: Option<Arc<dyn InitiationReplayGuard>>,
 }
 
 impl X3DHResponder {
@@ -33,16 +100,37 @@ impl X3DHResponder {
             one_time_prekey_private: None,
             one_time_prekey_public: None,
             one_time_prekey_id: None,
+            clock: Arc::new(SystemClock),
+            replay_guard: None,
         }
     }
 
+    /// Replace the clock used for resumption ticket expiry (`SystemClock` by default)
+    /// 
+    /// # Arguments
+    /// * `clock` - Time source, e.g. a `MockClock` in tests
+    pub fn set_clock(&mut self, clock: Arc<dyn Clock>) {
+        self.clock = clock;
+    }
+
+    /// Reject initiations that were already answered (off by default)
+    /// 
+    /// Share one guard between every responder of the same identity, e.g. a
+    /// `SeenInitiations`, since each handshake usually gets a fresh responder.
+    /// 
+    /// # Arguments
+    /// * `guard` - Record of answered initiations
+    pub fn set_replay_guard(&mut self, guard: Arc<dyn InitiationReplayGuard>) {
+        self.replay_guard = Some(guard);
+    }
+
     /// Set the one-time prekey for this responder
     /// 
     /// # Arguments
     /// * `key_id` - One-time prekey ID
     /// * `private_key` - One-time prekey private key
     /// * `public_key` - One-time prekey public key
-    pub fn set_one_time_prekey(&mut self, key_id: u32, private_key: EphemeralSecret, public_key: PublicKey) {
+    pub fn set_one_time_prekey(&mut self, key_id: u32, private_key: StaticSecret, public_key: PublicKey) {
         self.one_time_prekey_private = Some(private_key);
         self.one_time_prekey_public = Some(public_key);
         self.one_time_prekey_id = Some(key_id);
@@ -55,68 +143,37 @@ impl X3DHResponder {
     /// * `ephemeral_public_key_hex` - Alice's ephemeral public key as hex string
     /// 
     /// # Returns
-    /// X3DHResponseResult containing the shared secret
+    /// X3DHResponseResult containing the shared secret, transcript hash and
+    /// Alice's identity, or `ProtocolError("replayed initiation")` if a replay
+    /// guard is set and has seen this identity and ephemeral key before
     pub fn respond(&self, identity_a_hex: &str, ephemeral_public_key_hex: &str) -> Result<X3DHResponseResult> {
         // Parse Alice's identity public key from hex
-        let identity_a_bytes = hex::decode(identity_a_hex)
-            .map_err(|e| E2EEError::SerializationError(format!("Failed to decode identity public key: {}", e)))?;
-        
-        if identity_a_bytes.len() != 32 {
-            return Err(E2EEError::ProtocolError(
-                format!("Invalid identity public key length: expected 32, got {}", identity_a_bytes.len())
-            ));
-        }
-        
-        let mut identity_a_pub_bytes = [0u8; 32];
-        identity_a_pub_bytes.copy_from_slice(&identity_a_bytes);
-        let identity_a_public = PublicKey::from(identity_a_pub_bytes);
+        let identity_a_public = PublicKey::from(parse_hex_32(identity_a_hex)?);
         
         // Parse Alice's ephemeral public key from hex
-        let ephemeral_bytes = hex::decode(ephemeral_public_key_hex)
-            .map_err(|e| E2EEError::SerializationError(format!("Failed to decode ephemeral public key: {}", e)))?;
-        
-        if ephemeral_bytes.len() != 32 {
-            return Err(E2EEError::ProtocolError(
-                format!("Invalid ephemeral public key length: expected 32, got {}", ephemeral_bytes.len())
-            ));
-        }
-        
-        let mut ephemeral_pub_bytes = [0u8; 32];
-        ephemeral_pub_bytes.copy_from_slice(&ephemeral_bytes);
-        let ephemeral_public = PublicKey::from(ephemeral_pub_bytes);
+        let ephemeral_public = PublicKey::from(parse_hex_32(ephemeral_public_key_hex)?);
         
         // Calculate DH1 = ECDH(IKA, SPKB)
         // From initiator: DH1 = ECDH(IKA_private, SPKB_public)
         // From responder: DH1 = ECDH(SPKB_private, IKA_public)
         // These are equal due to ECDH commutativity
         let signed_prekey_b_private = self.signed_prekey_pair.private_key();
-        let dh1 = perform_dh(signed_prekey_b_private, &identity_a_public)?;
+        let dh1 = perform_dh(&signed_prekey_b_private, &identity_a_public);
         
         // Calculate DH2 = ECDH(EK, IKB)
         // From responder perspective: ECDH(IKB_private, EK_public)
-        let identity_b_private_for_dh2 = self.identity_pair.private_key_as_ephemeral();
-        let dh2 = perform_dh(identity_b_private_for_dh2, &ephemeral_public)?;
+        let identity_b_private = self.identity_pair.private_key_as_static();
+        let dh2 = perform_dh(&identity_b_private, &ephemeral_public);
         
         // Calculate DH3 = ECDH(EK, SPKB)
         // From responder perspective: ECDH(SPKB_private, EK_public)
-        let signed_prekey_b_private_for_dh3 = self.signed_prekey_pair.private_key();
-        let dh3 = perform_dh(signed_prekey_b_private_for_dh3, &ephemeral_public)?;
+        let dh3 = perform_dh(&signed_prekey_b_private, &ephemeral_public);
         
         // Calculate DH4 = ECDH(EK, OPKB) if available
-        let dh4 = if let Some(ref opk_private) = self.one_time_prekey_private {
-            // From responder perspective: ECDH(OPKB_private, EK_public)
-            // Note: opk_private is owned, so we need to clone it for reuse
-            // But EphemeralSecret doesn't implement Clone, so we extract bytes
-            let opk_private_bytes = unsafe {
-                std::mem::transmute_copy::<EphemeralSecret, [u8; 32]>(opk_private)
-            };
-            let opk_private_for_dh4 = unsafe {
-                std::mem::transmute::<[u8; 32], EphemeralSecret>(opk_private_bytes)
-            };
-            Some(perform_dh(opk_private_for_dh4, &ephemeral_public)?)
-        } else {
-            None
-        };
+        // From responder perspective: ECDH(OPKB_private, EK_public)
+        let dh4 = self.one_time_prekey_private
+            .as_ref()
+            .map(|opk_private| perform_dh(opk_private, &ephemeral_public));
         
         // Calculate shared secret from DH values
         let shared_secret = calculate_shared_secret_from_dh(
@@ -126,9 +183,128 @@ impl X3DHResponder {
             dh4.as_ref(),
         )?;
         
+        let transcript_hash = calculate_transcript_hash(
+            identity_a_public.as_bytes(),
+            &self.identity_pair.public_key_bytes(),
+            &self.signed_prekey_pair.public_key_bytes(),
+            ephemeral_public.as_bytes(),
+            self.one_time_prekey_public.as_ref().map(|opk| opk.as_bytes()),
+        );
+        
+        // Recorded only once the handshake succeeded, so a failed attempt can be retried
+        if let Some(ref guard) = self.replay_guard {
+            if !guard.record(identity_a_public.as_bytes(), ephemeral_public.as_bytes()) {
+                return Err(E2EEError::ProtocolError("replayed initiation".to_string()));
+            }
+        }
+        
         Ok(X3DHResponseResult {
             shared_secret,
+            transcript_hash: Some(transcript_hash),
+            peer_identity_hex: hex::encode(identity_a_public.as_bytes()),
+            ratchet_private_key: Some(signed_prekey_b_private),
         })
     }
-}
 
+    /// Respond to X3DH handshake initiation and build the responder's Double Ratchet
+    /// 
+    /// Shorthand for `respond` followed by `X3DHResponseResult::into_ratchet`:
+    /// the ratchet is seeded with the signed prekey, matching the ratchet the
+    /// initiator builds with `X3DHResult::into_ratchet`.
+    /// 
+    /// # Arguments
+    /// * `identity_a_hex` - Alice's identity public key as hex string
+    /// * `ephemeral_public_key_hex` - Alice's ephemeral public key as hex string
+    /// 
+    /// # Returns
+    /// Responder DoubleRatchet, ready to decrypt Alice's first message
+    pub fn respond_into_ratchet(&self, identity_a_hex: &str, ephemeral_public_key_hex: &str) -> Result<DoubleRatchet> {
+        self.respond(identity_a_hex, ephemeral_public_key_hex)?.into_ratchet()
+    }
+
+    /// Issue a resumption ticket for a session established with `respond`
+    /// 
+    /// The ticket wraps the session's shared secret under a server-held key,
+    /// bound to both identities and valid until `expires_at`. Issue it before
+    /// turning the result into a ratchet.
+    /// 
+    /// # Arguments
+    /// * `ticket_key` - Server-held ticket encryption key
+    /// * `result` - Result of `respond` (or of an accepted ticket) for the session
+    /// * `expires_at` - Expiry as seconds since Unix epoch
+    /// 
+    /// # Returns
+    /// Opaque ticket bytes to hand to the initiator
+    pub fn issue_resumption_ticket(
+        &self,
+        ticket_key: &[u8; 32],
+        result: &X3DHResponseResult,
+        expires_at: u64,
+    ) -> Result<Vec<u8>> {
+        let identity_a = parse_hex_32(&result.peer_identity_hex)?;
+        seal_ticket(
+            ticket_key,
+            &result.shared_secret,
+            &identity_a,
+            &self.identity_pair.public_key_bytes(),
+            expires_at,
+        )
+    }
+
+    /// Accept a resumption ticket presented by the initiator
+    /// 
+    /// Each ticket is accepted once: it is recorded in the replay guard set
+    /// with `set_replay_guard`, which is required here. The responder must
+    /// still hold the signed prekey of the original handshake, since
+    /// Alice's ephemeral key is combined with it.
+    /// 
+    /// # Arguments
+    /// * `ticket_key` - Server-held ticket encryption key
+    /// * `ticket` - Ticket presented by Alice
+    /// * `identity_a_hex` - Alice's identity public key as hex string
+    /// * `ephemeral_public_key_hex` - Alice's ephemeral public key from `resume`
+    /// 
+    /// # Returns
+    /// X3DHResponseResult with the resumed shared secret, `StateError` without
+    /// a replay guard, `ProtocolError` for a ticket already accepted, or an
+    /// error if the ticket is tampered, bound to other identities or expired
+    /// according to the responder's clock
+    pub fn accept_resumption_ticket(
+        &self,
+        ticket_key: &[u8; 32],
+        ticket: &[u8],
+        identity_a_hex: &str,
+        ephemeral_public_key_hex: &str,
+    ) -> Result<X3DHResponseResult> {
+        let guard = self.replay_guard.as_ref().ok_or_else(|| {
+            E2EEError::StateError("Resumption tickets require a replay guard".to_string())
+        })?;
+        let identity_a = parse_hex_32(identity_a_hex)?;
+        let ephemeral_public = PublicKey::from(parse_hex_32(ephemeral_public_key_hex)?);
+        let original_shared_secret = Zeroizing::new(open_ticket(
+            ticket_key,
+            ticket,
+            &identity_a,
+            &self.identity_pair.public_key_bytes(),
+            self.clock.now_secs(),
+        )?);
+        
+        let signed_prekey_private = self.signed_prekey_pair.private_key();
+        let mut dh = perform_dh(&signed_prekey_private, &ephemeral_public);
+        let shared_secret = derive_resumed_secret(&original_shared_secret, ticket, ephemeral_public.as_bytes(), &dh);
+        dh.zeroize();
+        let shared_secret = shared_secret?;
+        
+        // Recorded only once the ticket opened, so a failed attempt can be retried
+        if !guard.record(&identity_a, &ticket_digest(ticket)) {
+            return Err(E2EEError::ProtocolError("replayed resumption ticket".to_string()));
+        }
+        
+        Ok(X3DHResponseResult {
+            shared_secret,
+            transcript_hash: None,
+            peer_identity_hex: hex::encode(identity_a),
+            ratchet_private_key: Some(signed_prekey_private),
+        })
+    }
+}
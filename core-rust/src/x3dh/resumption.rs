@@ -0,0 +1,167 @@
+use crate::error::{E2EEError, Result};
+use rand::rngs::OsRng;
+use rand::RngCore;
+use crate::aead::NONCE_LEN;
+use zeroize::{Zeroize, Zeroizing};
+
+/// Domain separation label for resumption tickets
+const TICKET_LABEL: &[u8] = b"e2ee-resumption-ticket";
+
+/// Length of the sealed ticket payload: shared secret (32) || expires_at (8)
+const TICKET_PAYLOAD_LEN: usize = 40;
+
+/// Length of the resumption id prefixed to every ticket
+const RESUMPTION_ID_LEN: usize = 32;
+
+/// HKDF info prefix for the root key of a resumed session
+const RESUMPTION_INFO: &[u8] = b"resumption";
+
+/// Derive the public id naming a session in its resumption tickets
+/// 
+/// One-way in the shared secret, so it can travel in the clear: the
+/// initiator uses it to find the secret of the session a ticket resumes.
+pub(crate) fn resumption_id(shared_secret: &[u8; 32]) -> Result<[u8; 32]> {
+    crate::kdf::hkdf_32(shared_secret, TICKET_LABEL, b"resumption-id")
+}
+
+/// Read the resumption id of a ticket without opening it
+/// 
+/// # Returns
+/// The id, or `ProtocolError` if the ticket is too short to carry one
+pub(crate) fn ticket_resumption_id(ticket: &[u8]) -> Result<[u8; 32]> {
+    if ticket.len() <= RESUMPTION_ID_LEN + NONCE_LEN {
+        return Err(E2EEError::ProtocolError("Resumption ticket too short".to_string()));
+    }
+    let mut id = [0u8; RESUMPTION_ID_LEN];
+    id.copy_from_slice(&ticket[..RESUMPTION_ID_LEN]);
+    Ok(id)
+}
+
+/// Seal a resumption ticket
+/// 
+/// Ticket layout: resumption id (32) || nonce (12) ||
+/// AES-256-GCM(ticket_key, shared_secret || expires_at). The resumption id and
+/// both identity keys are bound as associated data, so a ticket presented for
+/// a different pair of identities fails to open.
+/// 
+/// # Arguments
+/// * `ticket_key` - Server-held ticket encryption key
+/// * `shared_secret` - Root key of the session being resumed
+/// * `initiator_identity` - Initiator's identity public key
+/// * `responder_identity` - Responder's identity public key
+/// * `expires_at` - Expiry as seconds since Unix epoch
+pub(crate) fn seal_ticket(
+    ticket_key: &[u8; 32],
+    shared_secret: &[u8; 32],
+    initiator_identity: &[u8; 32],
+    responder_identity: &[u8; 32],
+    expires_at: u64,
+) -> Result<Vec<u8>> {
+    let mut nonce_bytes = [0u8; NONCE_LEN];
+    OsRng.fill_bytes(&mut nonce_bytes);
+
+    let mut payload = Vec::with_capacity(TICKET_PAYLOAD_LEN);
+    payload.extend_from_slice(shared_secret);
+    payload.extend_from_slice(&expires_at.to_be_bytes());
+
+    let id = resumption_id(shared_secret)?;
+    let aad = ticket_aad(&id, initiator_identity, responder_identity);
+    let sealed = crate::aead::seal(ticket_key, &nonce_bytes, &aad, &payload);
+    payload.zeroize();
+    let sealed = sealed
+        .map_err(|e| E2EEError::CryptoError(format!("Failed to seal resumption ticket: {}", e)))?;
+
+    let mut ticket = id.to_vec();
+    ticket.extend_from_slice(&nonce_bytes);
+    ticket.extend_from_slice(&sealed);
+    Ok(ticket)
+}
+
+/// Open a resumption ticket and check its expiry
+/// 
+/// A ticket is valid strictly before `expires_at`: at `now_secs == expires_at`
+/// it has expired.
+/// 
+/// # Arguments
+/// * `now_secs` - Current time as seconds since Unix epoch
+/// 
+/// # Returns
+/// The root key of the original session, or an error if the ticket was
+/// tampered with, bound to other identities, or has expired
+pub(crate) fn open_ticket(
+    ticket_key: &[u8; 32],
+    ticket: &[u8],
+    initiator_identity: &[u8; 32],
+    responder_identity: &[u8; 32],
+    now_secs: u64,
+) -> Result<[u8; 32]> {
+    let id = ticket_resumption_id(ticket)?;
+    let (nonce_bytes, sealed) = ticket[RESUMPTION_ID_LEN..].split_at(NONCE_LEN);
+    let nonce: [u8; NONCE_LEN] = nonce_bytes.try_into().expect("split at NONCE_LEN");
+
+    let aad = ticket_aad(&id, initiator_identity, responder_identity);
+    let payload = crate::aead::open(ticket_key, &nonce, &aad, sealed)
+        .map_err(|e| E2EEError::CryptoError(format!("Invalid resumption ticket: {}", e)))?;
+
+    if payload.len() != TICKET_PAYLOAD_LEN {
+        return Err(E2EEError::ProtocolError("Invalid resumption ticket payload".to_string()));
+    }
+
+    let mut expires_at_bytes = [0u8; 8];
+    expires_at_bytes.copy_from_slice(&payload[32..]);
+    if u64::from_be_bytes(expires_at_bytes) <= now_secs {
+        return Err(E2EEError::ProtocolError("Resumption ticket expired".to_string()));
+    }
+
+    let mut shared_secret = [0u8; 32];
+    shared_secret.copy_from_slice(&payload[..32]);
+    Ok(shared_secret)
+}
+
+/// Derive the root key of a resumed session
+/// 
+/// Never reuses the original root key directly. The initiator's fresh
+/// ephemeral key and its DH with the responder's signed prekey are mixed in,
+/// so every resumption yields a new key, even from the same ticket, and the
+/// ticket key alone does not reveal it. HKDF-SHA256 with
+/// ikm = shared_secret || DH, salt = ticket, info = "resumption" || ephemeral.
+/// 
+/// # Arguments
+/// * `shared_secret` - Shared secret of the session being resumed
+/// * `ticket` - Ticket presented for the resumption
+/// * `ephemeral_public` - Initiator's ephemeral public key for this resumption
+/// * `dh_output` - DH(initiator ephemeral, responder signed prekey)
+pub(crate) fn derive_resumed_secret(
+    shared_secret: &[u8; 32],
+    ticket: &[u8],
+    ephemeral_public: &[u8; 32],
+    dh_output: &[u8; 32],
+) -> Result<[u8; 32]> {
+    let mut ikm = Zeroizing::new([0u8; 64]);
+    ikm[..32].copy_from_slice(shared_secret);
+    ikm[32..].copy_from_slice(dh_output);
+    
+    let mut info = Vec::with_capacity(RESUMPTION_INFO.len() + 32);
+    info.extend_from_slice(RESUMPTION_INFO);
+    info.extend_from_slice(ephemeral_public);
+    crate::kdf::hkdf_32(&ikm[..], ticket, &info)
+}
+
+/// Digest naming a ticket in an `InitiationReplayGuard`, so it is accepted once
+/// 
+/// Recorded in place of the ephemeral key, next to the initiator's identity.
+pub(crate) fn ticket_digest(ticket: &[u8]) -> [u8; 32] {
+    let digest = ring::digest::digest(&ring::digest::SHA256, ticket);
+    let mut out = [0u8; 32];
+    out.copy_from_slice(digest.as_ref());
+    out
+}
+
+fn ticket_aad(id: &[u8; 32], initiator_identity: &[u8; 32], responder_identity: &[u8; 32]) -> Vec<u8> {
+    let mut aad = Vec::with_capacity(TICKET_LABEL.len() + 96);
+    aad.extend_from_slice(TICKET_LABEL);
+    aad.extend_from_slice(id);
+    aad.extend_from_slice(initiator_identity);
+    aad.extend_from_slice(responder_identity);
+    aad
+}
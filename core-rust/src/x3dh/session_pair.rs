@@ -0,0 +1,55 @@
+//! Deterministic X3DH session setup for integration tests (`test-support` only)
+
+use crate::error::Result;
+use crate::keys::prekey::{OneTimePreKey, OneTimePreKeyPair, SignedPreKey, SignedPreKeyPair};
+use crate::keys::{IdentityKeyPair, PreKeyBundle};
+use crate::ratchet::DoubleRatchet;
+use crate::x3dh::{X3DHInitiator, X3DHResponder};
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+/// Run a full X3DH handshake between two fresh parties and build their ratchets
+/// 
+/// Identities, prekeys (signed and one-time) and Alice's ephemeral key all
+/// come from an RNG seeded with `seed`, so the same seed always yields the
+/// same identities and shared secret. The ratchets still draw their own DH
+/// keys from the OS RNG.
+/// 
+/// # Arguments
+/// * `seed` - Seed for every key generated during the handshake
+/// 
+/// # Returns
+/// (Alice, Bob) ratchets, with Alice as the X3DH initiator: she can send
+/// straight away and Bob can reply once her first message arrives
+/// 
+/// # Panics
+/// If the handshake fails, which only a bug in the library can cause
+pub fn establish_session_pair(seed: u64) -> (DoubleRatchet, DoubleRatchet) {
+    try_establish_session_pair(seed).expect("Failed to establish session pair")
+}
+
+fn try_establish_session_pair(seed: u64) -> Result<(DoubleRatchet, DoubleRatchet)> {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let alice_identity = IdentityKeyPair::generate_with_rng(&mut rng);
+    let bob_identity = IdentityKeyPair::generate_with_rng(&mut rng);
+    let signed_prekey = SignedPreKeyPair::generate_with_rng(1, &bob_identity, &mut rng)?;
+    let one_time_prekey = OneTimePreKeyPair::generate_with_rng(1, &mut rng)?;
+    
+    let bundle = PreKeyBundle::new(
+        bob_identity.public_key_hex(),
+        bob_identity.verifying_key(),
+        SignedPreKey::from(&signed_prekey),
+        Some(OneTimePreKey::from(&one_time_prekey)),
+    );
+    let alice_result = X3DHInitiator::new(alice_identity.clone()).initiate_with_rng(&bundle, &mut rng)?;
+    
+    let mut responder = X3DHResponder::new(bob_identity, signed_prekey);
+    responder.set_one_time_prekey(
+        one_time_prekey.key_id(),
+        one_time_prekey.private_key().clone(),
+        *one_time_prekey.public_key(),
+    );
+    let bob_result = responder.respond(&alice_identity.public_key_hex(), &alice_result.ephemeral_public_key_hex)?;
+    
+    Ok((alice_result.into_ratchet()?, bob_result.into_ratchet()?))
+}
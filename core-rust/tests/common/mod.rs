@@ -0,0 +1,41 @@
+//! Helper dùng chung cho các integration test
+
+#![allow(dead_code)]
+
+use e2ee_core::ffi::api::{
+    create_session_initiator_with_ephemeral, create_session_responder, generate_identity_key_pair,
+    generate_prekey_bundle,
+};
+use e2ee_core::ratchet::DoubleRatchet;
+
+/// Create a matching (initiator, responder) Double Ratchet pair from a fixed shared secret
+pub fn ratchet_pair(shared_secret: [u8; 32]) -> (DoubleRatchet, DoubleRatchet) {
+    let alice_dr = DoubleRatchet::from_shared_secret(&shared_secret, true)
+        .expect("Failed to create Alice's Double Ratchet");
+    let bob_dr = DoubleRatchet::from_shared_secret(&shared_secret, false)
+        .expect("Failed to create Bob's Double Ratchet");
+    (alice_dr, bob_dr)
+}
+
+/// Establish an (Alice, Bob) pair of registered FFI sessions
+/// 
+/// Prekey ids go into process-wide stores, so every test must use its own ids.
+pub fn establish_ffi_sessions(signed_prekey_id: u32, one_time_prekey_id: Option<u32>) -> (String, String) {
+    let alice_identity_json = generate_identity_key_pair();
+    let bob_identity_json = generate_identity_key_pair();
+    let bundle_json = generate_prekey_bundle(bob_identity_json.clone(), signed_prekey_id, one_time_prekey_id);
+
+    let init_json = create_session_initiator_with_ephemeral(alice_identity_json, bundle_json);
+    let init: serde_json::Value = serde_json::from_str(&init_json).expect("Invalid initiator JSON");
+    let alice_session = init["session_id"].as_str().expect("Missing session_id").to_string();
+    let bob_session = create_session_responder(
+        bob_identity_json,
+        signed_prekey_id,
+        one_time_prekey_id,
+        init["alice_identity_hex"].as_str().expect("Missing identity").to_string(),
+        init["alice_ephemeral_public_key_hex"].as_str().expect("Missing ephemeral").to_string(),
+    );
+    assert!(!bob_session.starts_with("Error"), "Responder failed: {}", bob_session);
+
+    (alice_session, bob_session)
+}
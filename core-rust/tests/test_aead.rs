@@ -0,0 +1,140 @@
+//! Test module AEAD (AES-256-GCM, XChaCha20-Poly1305) với known-answer vectors từ đặc tả
+
+use e2ee_core::aead::{open, open_xchacha, seal, seal_xchacha, TAG_LEN};
+use e2ee_core::error::E2EEError;
+
+/// AES-256 test cases 13-16 of McGrew & Viega, "The Galois/Counter Mode of
+/// Operation (GCM)", the vectors NIST SP 800-38D validation builds on
+struct KnownAnswer {
+    name: &'static str,
+    key: &'static str,
+    nonce: &'static str,
+    aad: &'static str,
+    plaintext: &'static str,
+    ciphertext_and_tag: &'static str,
+}
+
+const KNOWN_ANSWERS: &[KnownAnswer] = &[
+    KnownAnswer {
+        name: "test case 13 (empty plaintext)",
+        key: "0000000000000000000000000000000000000000000000000000000000000000",
+        nonce: "000000000000000000000000",
+        aad: "",
+        plaintext: "",
+        ciphertext_and_tag: "530f8afbc74536b9a963b4f1c4cb738b",
+    },
+    KnownAnswer {
+        name: "test case 14 (one zero block)",
+        key: "0000000000000000000000000000000000000000000000000000000000000000",
+        nonce: "000000000000000000000000",
+        aad: "",
+        plaintext: "00000000000000000000000000000000",
+        ciphertext_and_tag: "cea7403d4d606b6e074ec5d3baf39d18d0d1c8a799996bf0265b98b5d48ab919",
+    },
+    KnownAnswer {
+        name: "test case 15 (four blocks)",
+        key: "feffe9928665731c6d6a8f9467308308feffe9928665731c6d6a8f9467308308",
+        nonce: "cafebabefacedbaddecaf888",
+        aad: "",
+        plaintext: "d9313225f88406e5a55909c5aff5269a86a7a9531534f7da2e4c303d8a318a72\
+                    1c3c0c95956809532fcf0e2449a6b525b16aedf5aa0de657ba637b391aafd255",
+        ciphertext_and_tag: "522dc1f099567d07f47f37a32a84427d643a8cdcbfe5c0c97598a2bd2555d1aa\
+                             8cb08e48590dbb3da7b08b1056828838c5f61e6393ba7a0abcc9f662898015ad\
+                             b094dac5d93471bdec1a502270e3cc6c",
+    },
+    KnownAnswer {
+        name: "test case 16 (partial block, with AAD)",
+        key: "feffe9928665731c6d6a8f9467308308feffe9928665731c6d6a8f9467308308",
+        nonce: "cafebabefacedbaddecaf888",
+        aad: "feedfacedeadbeeffeedfacedeadbeefabaddad2",
+        plaintext: "d9313225f88406e5a55909c5aff5269a86a7a9531534f7da2e4c303d8a318a72\
+                    1c3c0c95956809532fcf0e2449a6b525b16aedf5aa0de657ba637b39",
+        ciphertext_and_tag: "522dc1f099567d07f47f37a32a84427d643a8cdcbfe5c0c97598a2bd2555d1aa\
+                             8cb08e48590dbb3da7b08b1056828838c5f61e6393ba7a0abcc9f662\
+                             76fc6ece0f4e1768cddf8853bb2d551b",
+    },
+];
+
+fn unhex(s: &str) -> Vec<u8> {
+    let compact: String = s.chars().filter(|c| !c.is_whitespace()).collect();
+    hex::decode(compact).expect("Invalid hex in test vector")
+}
+
+#[test]
+fn test_aes_256_gcm_known_answers() {
+    println!("\n=== Test: AES-256-GCM Known Answers ===\n");
+
+    for vector in KNOWN_ANSWERS {
+        let key: [u8; 32] = unhex(vector.key).try_into().unwrap();
+        let nonce: [u8; 12] = unhex(vector.nonce).try_into().unwrap();
+        let aad = unhex(vector.aad);
+        let plaintext = unhex(vector.plaintext);
+        let expected = unhex(vector.ciphertext_and_tag);
+
+        let sealed = seal(&key, &nonce, &aad, &plaintext).expect("Failed to seal");
+        assert_eq!(hex::encode(&sealed), hex::encode(&expected), "{}", vector.name);
+        assert_eq!(sealed.len(), plaintext.len() + TAG_LEN);
+        assert_eq!(open(&key, &nonce, &aad, &expected).expect("Failed to open"), plaintext, "{}", vector.name);
+        println!("  ✓ {}", vector.name);
+    }
+}
+
+#[test]
+fn test_aead_open_rejects_tampering() {
+    println!("\n=== Test: AEAD Tamper Detection ===\n");
+
+    let key = [7u8; 32];
+    let nonce = [9u8; 12];
+    let sealed = seal(&key, &nonce, b"header", b"payload").expect("Failed to seal");
+
+    let mut flipped = sealed.clone();
+    flipped[0] ^= 1;
+    let mut other_key = key;
+    other_key[0] ^= 1;
+    let mut other_nonce = nonce;
+    other_nonce[0] ^= 1;
+    let attempts = [
+        open(&key, &nonce, b"header", &flipped),
+        open(&key, &nonce, b"other header", &sealed),
+        open(&other_key, &nonce, b"header", &sealed),
+        open(&key, &other_nonce, b"header", &sealed),
+        open(&key, &nonce, b"header", &sealed[..TAG_LEN - 1]),
+    ];
+    for attempt in attempts {
+        match attempt {
+            Err(E2EEError::CryptoError(msg)) => assert!(msg.starts_with("Decryption failed")),
+            other => panic!("Expected CryptoError, got {:?}", other),
+        }
+    }
+    println!("  ✓ Modified ciphertext, AAD, key or nonce and truncation all fail");
+}
+
+#[test]
+fn test_xchacha20_poly1305_known_answer() {
+    println!("\n=== Test: XChaCha20-Poly1305 Known Answer ===\n");
+
+    // draft-irtf-cfrg-xchacha-03, appendix A.3.1
+    let key: [u8; 32] = unhex("808182838485868788898a8b8c8d8e8f909192939495969798999a9b9c9d9e9f").try_into().unwrap();
+    let nonce: [u8; 24] = unhex("404142434445464748494a4b4c4d4e4f5051525354555657").try_into().unwrap();
+    let aad = unhex("50515253c0c1c2c3c4c5c6c7");
+    let plaintext = b"Ladies and Gentlemen of the class of '99: If I could offer you only one tip \
+                      for the future, sunscreen would be it.";
+    let expected = unhex(
+        "bd6d179d3e83d43b9576579493c0e939572a1700252bfaccbed2902c21396cbb\
+         731c7f1b0b4aa6440bf3a82f4eda7e39ae64c6708c54c216cb96b72e1213b452\
+         2f8c9ba40db5d945b11b69b982c1bb9e3f3fac2bc369488f76b2383565d3fff9\
+         21f9664c97637da9768812f615c68b13b52e\
+         c0875924c1c7987947deafd8780acf49",
+    );
+
+    let sealed = seal_xchacha(&key, &nonce, &aad, plaintext).expect("Failed to seal");
+    assert_eq!(hex::encode(&sealed), hex::encode(&expected));
+    assert_eq!(open_xchacha(&key, &nonce, &aad, &expected).expect("Failed to open"), plaintext.to_vec());
+    println!("  ✓ Matches the draft's test vector");
+
+    let mut flipped = sealed.clone();
+    flipped[0] ^= 1;
+    assert!(matches!(open_xchacha(&key, &nonce, &aad, &flipped), Err(E2EEError::CryptoError(_))));
+    assert!(matches!(open_xchacha(&key, &nonce, b"other", &sealed), Err(E2EEError::CryptoError(_))));
+    println!("  ✓ Modified ciphertext or AAD fails");
+}
@@ -0,0 +1,43 @@
+//! Test báo cáo kiểm tra trạng thái ratchet (chỉ có với feature test-support)
+
+#![cfg(feature = "test-support")]
+
+use e2ee_core::ratchet::DoubleRatchet;
+
+#[test]
+fn test_audit_reports_match_then_diverge() {
+    println!("\n=== Test: Ratchet Audit Report ===\n");
+
+    let shared_secret = [28u8; 32];
+    let mut replica_a = DoubleRatchet::from_shared_secret(&shared_secret, true)
+        .expect("Failed to create first replica");
+    let mut replica_b = DoubleRatchet::from_shared_secret(&shared_secret, true)
+        .expect("Failed to create second replica");
+    let mut bob_dr = DoubleRatchet::from_shared_secret(&shared_secret, false)
+        .expect("Failed to create Bob's Double Ratchet");
+
+    let envelopes: Vec<_> = (0..3)
+        .map(|i| bob_dr.encrypt_envelope(format!("msg {}", i).as_bytes()).expect("Failed to encrypt"))
+        .collect();
+
+    // Both replicas see the same messages, with message 2 still missing
+    for envelope in [&envelopes[0], &envelopes[2]] {
+        replica_a.decrypt_envelope(envelope).expect("Failed to decrypt");
+        replica_b.decrypt_envelope(envelope).expect("Failed to decrypt");
+    }
+
+    let report = replica_a.audit_state();
+    assert_eq!(report, replica_b.audit_state());
+    assert_eq!(report.receiving_message_number, 3);
+    assert_eq!(report.skipped_key_count, 1);
+    assert_ne!(report.root_key_hash_hex, hex::encode(shared_secret));
+    println!("  ✓ Replicas that processed the same messages report identical state");
+
+    replica_a.encrypt_envelope(b"extra").expect("Failed to encrypt");
+    let diverged = replica_a.audit_state();
+    assert_ne!(diverged, replica_b.audit_state());
+    assert_ne!(diverged.sending_chain_key_hash_hex, report.sending_chain_key_hash_hex);
+    assert_eq!(diverged.sending_message_number, report.sending_message_number + 1);
+    assert_eq!(diverged.receiving_chain_key_hash_hex, report.receiving_chain_key_hash_hex);
+    println!("  ✓ One extra send shows up as a sending-chain divergence");
+}
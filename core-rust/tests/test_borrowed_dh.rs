@@ -0,0 +1,49 @@
+//! Test perform_dh mượn StaticSecret cho kết quả giống cách transmute EphemeralSecret cũ
+
+use e2ee_core::x3dh::{calculate_shared_secret_from_dh, perform_dh};
+use x25519_dalek::{EphemeralSecret, PublicKey, StaticSecret};
+
+/// DH the way the handshake used to: rebuild a consumable EphemeralSecret from bytes
+fn transmute_dh(private_bytes: [u8; 32], public: &PublicKey) -> [u8; 32] {
+    let private = unsafe { std::mem::transmute::<[u8; 32], EphemeralSecret>(private_bytes) };
+    *private.diffie_hellman(public).as_bytes()
+}
+
+#[test]
+fn test_borrowed_dh_matches_transmute_based_dh() {
+    println!("\n=== Test: Borrowed DH ===\n");
+
+    let identity_a = [0x11u8; 32];
+    let ephemeral_a = [0x22u8; 32];
+    let identity_b = [0x33u8; 32];
+    let signed_prekey_b = [0x44u8; 32];
+    let one_time_prekey_b = [0x55u8; 32];
+    let public = |bytes: [u8; 32]| PublicKey::from(&StaticSecret::from(bytes));
+
+    let identity_a_static = StaticSecret::from(identity_a);
+    let ephemeral_static = StaticSecret::from(ephemeral_a);
+
+    let dh1 = perform_dh(&identity_a_static, &public(signed_prekey_b));
+    let dh2 = perform_dh(&ephemeral_static, &public(identity_b));
+    let dh3 = perform_dh(&ephemeral_static, &public(signed_prekey_b));
+    let dh4 = perform_dh(&ephemeral_static, &public(one_time_prekey_b));
+
+    assert_eq!(dh1, transmute_dh(identity_a, &public(signed_prekey_b)));
+    assert_eq!(dh2, transmute_dh(ephemeral_a, &public(identity_b)));
+    assert_eq!(dh3, transmute_dh(ephemeral_a, &public(signed_prekey_b)));
+    assert_eq!(dh4, transmute_dh(ephemeral_a, &public(one_time_prekey_b)));
+    println!("  ✓ DH1..DH4 match the transmute-based outputs");
+
+    // The responder side borrows its own secrets and lands on the same shared secret
+    let signed_prekey_static = StaticSecret::from(signed_prekey_b);
+    let responder_dh = [
+        perform_dh(&signed_prekey_static, &public(identity_a)),
+        perform_dh(&StaticSecret::from(identity_b), &public(ephemeral_a)),
+        perform_dh(&signed_prekey_static, &public(ephemeral_a)),
+        perform_dh(&StaticSecret::from(one_time_prekey_b), &public(ephemeral_a)),
+    ];
+    assert_eq!(
+        calculate_shared_secret_from_dh(&dh1, &dh2, &dh3, Some(&dh4)).unwrap(),
+        calculate_shared_secret_from_dh(&responder_dh[0], &responder_dh[1], &responder_dh[2], Some(&responder_dh[3])).unwrap()
+    );
+}
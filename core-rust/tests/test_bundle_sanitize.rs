@@ -0,0 +1,35 @@
+//! Test làm sạch (sanitize) prekey bundle trước khi công bố lại
+
+use e2ee_core::ffi::api::{generate_identity_key_pair, generate_prekey_bundle};
+use e2ee_core::ffi::PreKeyBundleJSON;
+
+#[test]
+fn test_sanitized_bundle_roundtrip_and_tampering() {
+    println!("\n=== Test: Sanitized Prekey Bundle ===\n");
+
+    let bundle_json = generate_prekey_bundle(generate_identity_key_pair(), 1331, Some(1332));
+    let bundle: PreKeyBundleJSON = serde_json::from_str(&bundle_json).expect("Invalid bundle JSON");
+
+    let sanitized = bundle.sanitized().expect("Valid bundle must sanitize");
+    assert_eq!(sanitized, bundle);
+    println!("  ✓ Valid bundle sanitizes to an equal bundle");
+
+    // Stray fields and uppercase hex are dropped / normalized
+    let mut value: serde_json::Value = serde_json::from_str(&bundle_json).unwrap();
+    value["signed_prekey"]["private_key_hex"] = serde_json::json!("00".repeat(32));
+    value["identity_public_hex"] = serde_json::json!(bundle.identity_public_hex.to_uppercase());
+    let noisy: PreKeyBundleJSON = serde_json::from_value(value).expect("Invalid bundle JSON");
+    let cleaned = noisy.sanitized().expect("Noisy bundle must sanitize");
+    assert_eq!(cleaned, bundle);
+    assert!(!serde_json::to_string(&cleaned).unwrap().contains("private_key"));
+    println!("  ✓ Extra fields are stripped and hex is normalized");
+
+    // Swapping the signed prekey breaks its signature
+    let mut tampered = bundle.clone();
+    let other: PreKeyBundleJSON = serde_json::from_str(
+        &generate_prekey_bundle(generate_identity_key_pair(), 1333, None),
+    ).unwrap();
+    tampered.signed_prekey.public_key_hex = other.signed_prekey.public_key_hex;
+    assert!(tampered.sanitized().is_err(), "Tampered bundle must not sanitize");
+    println!("  ✓ Tampered bundle is rejected");
+}
@@ -0,0 +1,85 @@
+//! Test xác minh hàng loạt chữ ký prekey bundle (dành cho key server)
+
+use e2ee_core::error::E2EEError;
+use e2ee_core::ffi::api::{generate_identity_key_pair, generate_prekey_bundle, verify_bundles_json};
+use e2ee_core::keys::prekey::{SignedPreKey, SignedPreKeyPair};
+use e2ee_core::keys::{verify_bundles, verify_bundles_batch, IdentityKeyPair, PreKeyBundle};
+use ed25519_dalek::{Signer, SigningKey, Verifier};
+
+fn signed_bundle(identity: &IdentityKeyPair) -> PreKeyBundle {
+    let signed_prekey = SignedPreKeyPair::generate(1, identity).expect("Failed to generate signed prekey");
+    PreKeyBundle::new(
+        identity.public_key_hex(),
+        identity.verifying_key(),
+        SignedPreKey::from(&signed_prekey),
+        None,
+    )
+}
+
+/// Bundle whose signed prekey was signed by a different identity
+fn forged_bundle() -> PreKeyBundle {
+    let victim = IdentityKeyPair::generate();
+    let attacker = IdentityKeyPair::generate();
+    let forged = SignedPreKey::from(&SignedPreKeyPair::generate(1, &attacker).expect("Failed to generate"));
+    PreKeyBundle::new(victim.public_key_hex(), victim.verifying_key(), forged, None)
+}
+
+#[test]
+fn test_batch_reports_exactly_the_bad_bundle() {
+    println!("\n=== Test: Batch Bundle Verification ===\n");
+
+    let bad_index = 37;
+    let bundles: Vec<PreKeyBundle> = (0..50)
+        .map(|i| if i == bad_index { forged_bundle() } else { signed_bundle(&IdentityKeyPair::generate()) })
+        .collect();
+
+    let batch = verify_bundles_batch(&bundles);
+    let invalid: Vec<usize> = (0..batch.len()).filter(|&i| !batch[i]).collect();
+    assert_eq!(invalid, vec![bad_index]);
+
+    let serial = verify_bundles(&bundles);
+    for (i, result) in serial.iter().enumerate() {
+        assert_eq!(result.is_ok(), i != bad_index);
+    }
+    println!("  ✓ Only bundle {} reported invalid by both paths", bad_index);
+}
+
+#[test]
+fn test_verify_bundles_json_ffi() {
+    let good = generate_prekey_bundle(generate_identity_key_pair(), 1251, None);
+    let mut tampered: serde_json::Value = serde_json::from_str(&good).expect("Invalid bundle JSON");
+    let other = generate_prekey_bundle(generate_identity_key_pair(), 1252, None);
+    let other: serde_json::Value = serde_json::from_str(&other).expect("Invalid bundle JSON");
+    tampered["signed_prekey"]["signature_hex"] = other["signed_prekey"]["signature_hex"].clone();
+
+    let results = verify_bundles_json(vec![good, tampered.to_string(), "not json".to_string()]);
+    assert_eq!(results, vec![true, false, false]);
+}
+
+#[test]
+fn test_signed_prekey_equal_to_identity_rejected() {
+    println!("\n=== Test: Signed PreKey Equal To Identity ===\n");
+
+    // Identity whose signing key is known, so the degenerate prekey carries a valid signature
+    let ed25519_seed = [0x97u8; 32];
+    let identity = IdentityKeyPair::from_ed25519_seed_and_x25519(ed25519_seed, [0x98u8; 32]).unwrap();
+    let signature = SigningKey::from_bytes(&ed25519_seed).sign(&identity.public_key_bytes());
+    let degenerate = PreKeyBundle::new(
+        identity.public_key_hex(),
+        identity.verifying_key(),
+        SignedPreKey::from_components(*identity.public_key(), signature, 1),
+        None,
+    );
+    assert!(identity.verifying_key().verify(&identity.public_key_bytes(), &signature).is_ok());
+
+    match degenerate.verify_signature() {
+        Err(E2EEError::ProtocolError(msg)) => assert_eq!(msg, "signed prekey must differ from identity key"),
+        other => panic!("Expected ProtocolError for a degenerate bundle, got {:?}", other),
+    }
+    let normal = signed_bundle(&identity);
+    assert!(normal.verify_signature().unwrap());
+    println!("  ✓ Degenerate bundle rejected, normal bundle accepted");
+
+    assert_eq!(verify_bundles_batch(&[normal, degenerate]), vec![true, false]);
+    println!("  ✓ Batch verification rejects the degenerate bundle too");
+}
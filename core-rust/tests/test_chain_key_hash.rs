@@ -0,0 +1,22 @@
+//! Test so sánh chain key giữa hai ratchet qua hash (chỉ có với feature test-support)
+
+#![cfg(feature = "test-support")]
+
+mod common;
+
+use common::ratchet_pair;
+
+#[test]
+fn test_chain_key_hashes_match_after_setup() {
+    println!("\n=== Test: Chain Key Hashes ===\n");
+
+    let (mut alice_dr, bob_dr) = ratchet_pair([17u8; 32]);
+    assert_eq!(alice_dr.sending_chain_key_hash(), bob_dr.receiving_chain_key_hash());
+    assert_eq!(alice_dr.receiving_chain_key_hash(), bob_dr.sending_chain_key_hash());
+    assert_ne!(alice_dr.sending_chain_key_hash(), alice_dr.receiving_chain_key_hash());
+    println!("  ✓ Alice's sending chain matches Bob's receiving chain");
+
+    // The hash tracks the chain as it ratchets forward
+    alice_dr.encrypt_envelope(b"advance").expect("Failed to encrypt");
+    assert_ne!(alice_dr.sending_chain_key_hash(), bob_dr.receiving_chain_key_hash());
+}
@@ -0,0 +1,129 @@
+//! Test giới hạn số tin nhắn trên mỗi chain (bắt buộc DH ratchet khi vượt giới hạn)
+
+use e2ee_core::error::E2EEError;
+use e2ee_core::ffi::api::session_messages_until_rekey;
+use e2ee_core::ratchet::{Chain, DoubleRatchet, MAX_CHAIN_MESSAGES};
+
+#[test]
+fn test_chain_refuses_keys_past_limit() {
+    let mut chain = Chain::with_message_limit([7u8; 32], 3);
+    assert_eq!(chain.message_limit(), 3);
+
+    for _ in 0..3 {
+        chain.ratchet_forward().expect("Chain should derive keys below the limit");
+    }
+    assert!(chain.is_exhausted(), "Chain should be exhausted at the limit");
+
+    match chain.ratchet_forward() {
+        Err(E2EEError::StateError(msg)) => assert!(msg.contains("DH ratchet required")),
+        other => panic!("Expected StateError, got {:?}", other.map(|_| ())),
+    }
+    assert_eq!(chain.message_number(), 3, "Exhausted chain must not advance");
+}
+
+#[test]
+fn test_default_chain_limit() {
+    let chain = Chain::new([7u8; 32]);
+    assert_eq!(chain.message_limit(), MAX_CHAIN_MESSAGES);
+    assert!(!chain.is_exhausted());
+
+    // The cap is opt-in: an unconfigured ratchet is only bounded by the counter
+    let ratchet = DoubleRatchet::from_shared_secret(&[7u8; 32], true).unwrap();
+    assert_eq!(ratchet.chain_message_limit(), MAX_CHAIN_MESSAGES);
+}
+
+#[test]
+fn test_ratchet_requires_rekey_after_cap() {
+    println!("\n=== Test: Chain Message Cap Forces Rekey ===\n");
+
+    let shared_secret = [42u8; 32];
+    let mut alice_dr = DoubleRatchet::from_shared_secret(&shared_secret, true)
+        .expect("Failed to create Alice's Double Ratchet");
+    let mut bob_dr = DoubleRatchet::from_shared_secret(&shared_secret, false)
+        .expect("Failed to create Bob's Double Ratchet");
+
+    alice_dr.set_chain_message_limit(2);
+    assert_eq!(alice_dr.chain_message_limit(), 2);
+
+    for i in 1..=2 {
+        let msg = format!("Message {}", i).into_bytes();
+        let env = alice_dr.encrypt_envelope(&msg).expect("Failed to encrypt below cap");
+        let dec = bob_dr.decrypt_envelope(&env).expect("Failed to decrypt below cap");
+        assert_eq!(dec, msg);
+    }
+    println!("  ✓ Messages below the cap encrypt normally");
+
+    // The third message would exceed the cap: a DH ratchet is mandatory instead
+    match alice_dr.encrypt_envelope(b"Message 3") {
+        Err(E2EEError::StateError(msg)) => assert!(msg.contains("DH ratchet required")),
+        other => panic!("Expected StateError, got {:?}", other),
+    }
+    println!("  ✓ Encrypting past the cap requires a rekey");
+}
+
+#[test]
+fn test_last_message_of_capped_chain_requests_rekey() {
+    println!("\n=== Test: Capped Chain Requests Rekey ===\n");
+
+    let mut alice_dr = DoubleRatchet::from_shared_secret(&[44u8; 32], true)
+        .expect("Failed to create Alice's Double Ratchet");
+    let mut bob_dr = DoubleRatchet::from_shared_secret(&[44u8; 32], false)
+        .expect("Failed to create Bob's Double Ratchet");
+    alice_dr.set_chain_message_limit(3);
+
+    for i in 1..=3u64 {
+        let env = alice_dr.encrypt_envelope(b"capped").expect("Failed to encrypt below cap");
+        assert_eq!(env.header.rekey_requested, i == 3);
+        let decrypted = bob_dr.decrypt_envelope_full(&env).expect("Failed to decrypt below cap");
+        assert_eq!(decrypted.rekey_requested, i == 3);
+    }
+    assert!(alice_dr.encrypt_envelope(b"blocked").is_err());
+    println!("  ✓ Only the last message the chain can carry asks for a rekey");
+
+    // Any reply brings Alice a new DH key; her next chain starts from scratch
+    let reply = bob_dr.encrypt_key_exchange().expect("Failed to encrypt key exchange");
+    alice_dr.decrypt_envelope(&reply).expect("Failed to decrypt key exchange");
+    assert_eq!(alice_dr.messages_until_rekey(), Some(3));
+    let env = alice_dr.encrypt_envelope(b"after rekey").expect("Failed to encrypt after rekey");
+    assert!(!env.header.rekey_requested);
+    assert_eq!(bob_dr.decrypt_envelope(&env).expect("Failed to decrypt after rekey"), b"after rekey".to_vec());
+    println!("  ✓ The peer's reply unblocks the sender on a fresh chain");
+
+    let mut forged = alice_dr.encrypt_envelope(b"flag is authenticated").unwrap();
+    forged.header.rekey_requested = true;
+    assert!(bob_dr.decrypt_envelope(&forged).is_err());
+    println!("  ✓ The rekey request is bound into the associated data");
+}
+
+#[test]
+fn test_messages_until_rekey_counts_down() {
+    println!("\n=== Test: Messages Until Rekey ===\n");
+
+    let mut alice_dr = DoubleRatchet::from_shared_secret(&[43u8; 32], true)
+        .expect("Failed to create Alice's Double Ratchet");
+    assert_eq!(alice_dr.messages_until_rekey(), None, "No cap means no forced rekey");
+
+    alice_dr.set_chain_message_limit(5);
+    for remaining in (1..=5).rev() {
+        assert_eq!(alice_dr.messages_until_rekey(), Some(remaining));
+        alice_dr.encrypt_envelope(b"tick").expect("Failed to encrypt below cap");
+    }
+    assert_eq!(alice_dr.messages_until_rekey(), Some(0));
+    assert!(alice_dr.encrypt_envelope(b"one too many").is_err(), "Send at 0 must require a rekey");
+    println!("  ✓ Counter goes 5 → 0 and the next send is refused");
+
+    let restored = DoubleRatchet::from_state(&alice_dr.to_state().unwrap()).unwrap();
+    assert_eq!(restored.messages_until_rekey(), Some(0));
+    let unconfigured = DoubleRatchet::from_shared_secret(&[43u8; 32], false).unwrap();
+    let restored = DoubleRatchet::from_state(&unconfigured.to_state().unwrap()).unwrap();
+    assert_eq!(restored.messages_until_rekey(), None);
+    println!("  ✓ Whether a cap was configured survives a save and restore");
+
+    // A cap is reported whenever one was configured, even at the counter ceiling
+    let mut bob_dr = DoubleRatchet::from_shared_secret(&[43u8; 32], false).unwrap();
+    bob_dr.set_chain_message_limit(MAX_CHAIN_MESSAGES);
+    assert_eq!(bob_dr.messages_until_rekey(), Some(MAX_CHAIN_MESSAGES as u64));
+    println!("  ✓ An explicit cap of MAX_CHAIN_MESSAGES is not mistaken for none");
+
+    assert_eq!(session_messages_until_rekey("missing-session-145".to_string()), -1);
+}
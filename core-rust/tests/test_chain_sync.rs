@@ -2,9 +2,8 @@
 
 use e2ee_core::keys::{IdentityKeyPair, PreKeyBundle};
 use e2ee_core::keys::prekey::{OneTimePreKey, OneTimePreKeyPair, SignedPreKey, SignedPreKeyPair};
-use e2ee_core::ratchet::DoubleRatchet;
 use e2ee_core::x3dh::{X3DHInitiator, X3DHResponder};
-use x25519_dalek::{EphemeralSecret, PublicKey};
+use x25519_dalek::{PublicKey, StaticSecret};
 
 #[test]
 fn test_chain_key_synchronization() {
@@ -16,7 +15,8 @@ fn test_chain_key_synchronization() {
     
     let bob_signed_prekey = SignedPreKeyPair::generate(1, &bob_identity)
         .expect("Failed to generate signed prekey");
-    let bob_one_time_prekey = OneTimePreKeyPair::generate(1);
+    let bob_one_time_prekey = OneTimePreKeyPair::generate(1)
+        .expect("Failed to generate one-time prekey");
     
     let prekey_bundle = PreKeyBundle::new(
         bob_identity.public_key_hex(),
@@ -30,12 +30,8 @@ fn test_chain_key_synchronization() {
         .expect("Failed to initiate X3DH");
     
     let bob_one_time_private_ref = bob_one_time_prekey.private_key();
-    let bob_one_time_private_bytes = unsafe {
-        std::mem::transmute_copy::<EphemeralSecret, [u8; 32]>(bob_one_time_private_ref)
-    };
-    let bob_one_time_private = unsafe {
-        std::mem::transmute::<[u8; 32], EphemeralSecret>(bob_one_time_private_bytes)
-    };
+    let bob_one_time_private_bytes = bob_one_time_private_ref.to_bytes();
+    let bob_one_time_private = StaticSecret::from(bob_one_time_private_bytes);
     let bob_one_time_public = PublicKey::from(&bob_one_time_private);
     
     let mut bob = X3DHResponder::new(bob_identity.clone(), bob_signed_prekey.clone());
@@ -47,17 +43,16 @@ fn test_chain_key_synchronization() {
     ).expect("Failed to respond to X3DH");
     
     // Verify shared secrets match
-    assert_eq!(
-        alice_result.shared_secret,
-        bob_result.shared_secret,
+    assert!(
+        bob_result.verify_key_confirmation(&alice_result.key_confirmation()).is_ok(),
         "Shared secrets must match"
     );
     println!("✓ Shared secrets match");
 
     // Create Double Ratchet instances
-    let mut alice_dr = DoubleRatchet::from_shared_secret(&alice_result.shared_secret, true)
+    let mut alice_dr = alice_result.into_ratchet()
         .expect("Failed to create Alice's Double Ratchet");
-    let mut bob_dr = DoubleRatchet::from_shared_secret(&bob_result.shared_secret, false)
+    let mut bob_dr = bob_result.into_ratchet()
         .expect("Failed to create Bob's Double Ratchet");
 
     // Test: Encrypt and decrypt first message
@@ -0,0 +1,43 @@
+//! Test định dạng identity rút gọn (chỉ lưu hai private key)
+
+use e2ee_core::error::E2EEError;
+use e2ee_core::ffi::{CompactIdentityBytes, IdentityKeyPairBytes};
+use e2ee_core::keys::IdentityKeyPair;
+
+#[test]
+fn test_compact_identity_roundtrip() {
+    println!("\n=== Test: Compact Identity Bytes ===\n");
+
+    let identity = IdentityKeyPair::generate();
+    let full = IdentityKeyPairBytes::from_identity_key_pair(&identity);
+
+    let compact = full.to_compact().expect("Valid identity must compact");
+    let json = serde_json::to_string(&compact).expect("Failed to serialize");
+    assert!(!json.contains("public"));
+    let compact: CompactIdentityBytes = serde_json::from_str(&json).expect("Failed to deserialize");
+
+    let restored = compact.to_identity_key_pair().expect("Failed to restore identity");
+    assert_eq!(restored.public_key_bytes(), identity.public_key_bytes());
+    assert_eq!(restored.verifying_key(), identity.verifying_key());
+    println!("  ✓ Compact form restores the same public keys");
+
+    let expanded = compact.to_full().expect("Failed to expand");
+    assert_eq!(expanded.x25519_public_key, full.x25519_public_key);
+    assert_eq!(expanded.ed25519_public_key, full.ed25519_public_key);
+    assert!(expanded.to_identity_key_pair().is_ok());
+    println!("  ✓ Expanded full form still validates");
+}
+
+#[test]
+fn test_compact_rejects_inconsistent_or_malformed_input() {
+    // A full form whose stored public does not match is not silently repaired
+    let mut mismatched = IdentityKeyPairBytes::from_identity_key_pair(&IdentityKeyPair::generate());
+    mismatched.x25519_public_key = IdentityKeyPair::generate().public_key_bytes().to_vec();
+    assert!(matches!(mismatched.to_compact(), Err(E2EEError::SerializationError(_))));
+
+    let short = CompactIdentityBytes { x25519_private_key: vec![1u8; 31], ed25519_private_key: vec![1u8; 32] };
+    assert!(matches!(short.to_identity_key_pair(), Err(E2EEError::SerializationError(_))));
+
+    let weak = CompactIdentityBytes { x25519_private_key: vec![1u8; 32], ed25519_private_key: vec![0u8; 32] };
+    assert!(matches!(weak.to_identity_key_pair(), Err(E2EEError::KeyGenerationError(_))));
+}
@@ -0,0 +1,70 @@
+//! Test truy cập đồng thời vào session registry và prekey store
+
+mod common;
+
+use common::establish_ffi_sessions;
+use e2ee_core::ffi::api::{decrypt_message, encrypt_message};
+use e2ee_core::ffi::{Session, SessionRegistry};
+use std::sync::Arc;
+use std::thread;
+
+#[test]
+fn test_registry_many_readers_few_writers() {
+    println!("\n=== Test: Concurrent Session Registry ===\n");
+
+    let registry = Arc::new(SessionRegistry::new());
+    let writers: Vec<_> = (0..2)
+        .map(|w| {
+            let registry = Arc::clone(&registry);
+            thread::spawn(move || {
+                for i in 0..50 {
+                    let id = format!("writer-{}-{}", w, i);
+                    let session = Session::from_shared_secret([i as u8; 32], w == 0, id.clone())
+                        .expect("Failed to create session");
+                    registry.register(id, Arc::new(session));
+                }
+            })
+        })
+        .collect();
+    let readers: Vec<_> = (0..8)
+        .map(|_| {
+            let registry = Arc::clone(&registry);
+            thread::spawn(move || {
+                for i in 0..200 {
+                    let id = format!("writer-{}-{}", i % 2, i % 50);
+                    if let Some(session) = registry.get(&id) {
+                        assert_eq!(session.id(), &id);
+                    }
+                }
+            })
+        })
+        .collect();
+
+    for handle in writers.into_iter().chain(readers) {
+        handle.join().expect("Thread panicked");
+    }
+
+    for w in 0..2 {
+        for i in 0..50 {
+            assert!(registry.contains(&format!("writer-{}-{}", w, i)), "Lost update");
+        }
+    }
+    println!("  ✓ All 100 registrations visible after concurrent access");
+}
+
+#[test]
+fn test_prekey_stores_under_concurrent_handshakes() {
+    let handles: Vec<_> = (0..8u32)
+        .map(|i| {
+            thread::spawn(move || {
+                let (alice_session, bob_session) = establish_ffi_sessions(1131 + i * 2, Some(1132 + i * 2));
+                let envelope = encrypt_message(alice_session, b"concurrent".to_vec());
+                assert_eq!(decrypt_message(bob_session, envelope), b"concurrent".to_vec());
+            })
+        })
+        .collect();
+
+    for handle in handles {
+        handle.join().expect("Handshake thread panicked");
+    }
+}
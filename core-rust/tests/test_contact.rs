@@ -0,0 +1,53 @@
+//! Test lưu prekey bundle đã xác minh thành contact
+
+use e2ee_core::clock::MockClock;
+use e2ee_core::ffi::api::{generate_identity_key_pair, generate_prekey_bundle, get_public_key_hex_from_json, save_contact};
+use e2ee_core::keys::prekey::{SignedPreKey, SignedPreKeyPair};
+use e2ee_core::keys::{Contact, IdentityKeyPair, PreKeyBundle};
+
+#[test]
+fn test_contact_from_verified_bundle() {
+    println!("\n=== Test: Contact From Bundle ===\n");
+
+    let identity = IdentityKeyPair::generate();
+    let signed_prekey = SignedPreKeyPair::generate(1, &identity).expect("Failed to generate signed prekey");
+    let bundle = PreKeyBundle::new(
+        identity.public_key_hex(),
+        identity.verifying_key(),
+        SignedPreKey::from(&signed_prekey),
+        None,
+    );
+
+    let contact = Contact::from_verified_bundle_with_clock(&bundle, &MockClock::new(1_700_000_000))
+        .expect("Valid bundle must become a contact");
+    assert_eq!(contact.identity_hex(), identity.public_key_hex());
+    assert_eq!(contact.ed25519_verifying_key().unwrap(), identity.verifying_key());
+    assert_eq!(contact.verified_at(), 1_700_000_000);
+    println!("  ✓ Contact carries the bundle's identity keys");
+
+    // Bundle claiming another identity's verifying key fails verification
+    let forged = PreKeyBundle::new(
+        identity.public_key_hex(),
+        IdentityKeyPair::generate().verifying_key(),
+        SignedPreKey::from(&signed_prekey),
+        None,
+    );
+    assert!(Contact::from_verified_bundle(&forged).is_err());
+    println!("  ✓ Invalid signature cannot become a contact");
+}
+
+#[test]
+fn test_save_contact_ffi() {
+    let identity_json = generate_identity_key_pair();
+    let bundle_json = generate_prekey_bundle(identity_json.clone(), 1581, None);
+
+    let contact: Contact = serde_json::from_str(&save_contact(bundle_json.clone())).expect("Invalid contact JSON");
+    assert_eq!(contact.identity_hex(), get_public_key_hex_from_json(identity_json));
+
+    let mut tampered: serde_json::Value = serde_json::from_str(&bundle_json).unwrap();
+    let other: serde_json::Value = serde_json::from_str(
+        &generate_prekey_bundle(generate_identity_key_pair(), 1582, None),
+    ).unwrap();
+    tampered["signed_prekey"]["signature_hex"] = other["signed_prekey"]["signature_hex"].clone();
+    assert!(save_contact(tampered.to_string()).starts_with("Error"));
+}
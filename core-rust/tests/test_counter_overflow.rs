@@ -0,0 +1,59 @@
+//! Test tràn bộ đếm message: encrypt báo lỗi thay vì wrap về 0 (chỉ với test-support)
+
+#![cfg(feature = "test-support")]
+
+mod common;
+
+use common::ratchet_pair;
+use e2ee_core::error::E2EEError;
+use e2ee_core::x3dh::establish_session_pair;
+
+#[test]
+fn test_sending_counter_overflow_errors_instead_of_wrapping() {
+    println!("\n=== Test: Message Counter Overflow ===\n");
+
+    let (mut alice_dr, _bob_dr) = ratchet_pair([0x85; 32]);
+    alice_dr.pin_sending_message_number(u64::MAX - 1);
+    let last = alice_dr.encrypt_envelope(b"last one").expect("u64::MAX itself is still usable");
+    assert_eq!(last.header.message_number, u64::MAX);
+    println!("  ✓ Message number u64::MAX is sent");
+
+    let chain_before = alice_dr.sending_chain_key_hash();
+    for _ in 0..2 {
+        match alice_dr.encrypt_envelope(b"wraps?") {
+            Err(E2EEError::StateError(msg)) => assert_eq!(msg, "message counter overflow, rekey required"),
+            other => panic!("Expected counter overflow, got {:?}", other.map(|e| e.header.message_number)),
+        }
+    }
+    assert!(matches!(alice_dr.skip_send(), Err(E2EEError::StateError(_))));
+    assert!(matches!(alice_dr.precompute_send_keys(1), Err(E2EEError::StateError(_))));
+    assert_eq!(alice_dr.public_state().sending_number, u64::MAX);
+    assert_eq!(alice_dr.sending_chain_key_hash(), chain_before);
+    println!("  ✓ Next encrypt errors without wrapping or consuming a key");
+}
+
+#[test]
+fn test_previous_chain_length_overflow_errors_instead_of_saturating() {
+    println!("\n=== Test: Previous Chain Length Overflow ===\n");
+
+    let (mut alice_dr, mut bob_dr) = establish_session_pair(0x128);
+    let first = alice_dr.encrypt_envelope(b"hello").expect("Failed to encrypt");
+    bob_dr.decrypt_envelope(&first).expect("Failed to decrypt");
+    let reply = bob_dr.encrypt_envelope(b"reply").expect("Failed to encrypt reply");
+
+    // More sends than the 32-bit previous_chain_length header field can report
+    alice_dr.pin_sending_message_number(u64::from(u32::MAX) + 1);
+    let state_before = alice_dr.public_state();
+    let chain_before = alice_dr.sending_chain_key_hash();
+    match alice_dr.decrypt_envelope(&reply) {
+        Err(E2EEError::StateError(msg)) => assert_eq!(msg, "message counter overflow, rekey required"),
+        other => panic!("Expected counter overflow, got {:?}", other),
+    }
+    assert_eq!(alice_dr.public_state(), state_before);
+    assert_eq!(alice_dr.sending_chain_key_hash(), chain_before);
+    println!("  ✓ DH ratchet refused without touching the state");
+
+    alice_dr.pin_sending_message_number(u64::from(u32::MAX));
+    assert_eq!(alice_dr.decrypt_envelope(&reply).expect("Failed to decrypt reply"), b"reply");
+    println!("  ✓ A chain of exactly u32::MAX messages still ratchets");
+}
@@ -0,0 +1,69 @@
+//! Test phân tích khóa công khai X25519 dạng 33 byte có tiền tố 0x05 (tương thích Signal)
+
+mod common;
+
+use common::ratchet_pair;
+use e2ee_core::encoding::{parse_curve_point, parse_curve_point_hex, CURVE_POINT_TYPE_DJB};
+use e2ee_core::ffi::api::{generate_identity_key_pair, generate_prekey_bundle};
+use e2ee_core::ffi::PreKeyBundleJSON;
+
+#[test]
+fn test_prefixed_and_raw_points_parse_identically() {
+    println!("\n=== Test: Type-Prefixed Curve Points ===\n");
+
+    let raw: Vec<u8> = (1..=32).collect();
+    let mut prefixed = vec![CURVE_POINT_TYPE_DJB];
+    prefixed.extend_from_slice(&raw);
+
+    assert_eq!(parse_curve_point(&raw).unwrap(), parse_curve_point(&prefixed).unwrap());
+    assert_eq!(parse_curve_point(&prefixed).unwrap().to_vec(), raw);
+    println!("  ✓ 33-byte 0x05 key and 32-byte key parse to the same point");
+
+    let mut unknown = prefixed.clone();
+    unknown[0] = 0x06;
+    assert!(parse_curve_point(&unknown).is_err());
+    assert!(parse_curve_point(&raw[..31]).is_err());
+    println!("  ✓ Unknown prefix byte and wrong length are rejected");
+
+    // Hex form only accepts the prefix when asked to
+    assert!(parse_curve_point_hex(&hex::encode(&prefixed), false).is_err());
+    assert_eq!(
+        parse_curve_point_hex(&hex::encode(&prefixed), true).unwrap(),
+        parse_curve_point_hex(&hex::encode(&raw), false).unwrap()
+    );
+    println!("  ✓ Hex parsing honors the compatibility flag");
+}
+
+#[test]
+fn test_ratchet_accepts_prefixed_dh_key_when_enabled() {
+    let (mut alice_dr, mut bob_dr) = ratchet_pair([27u8; 32]);
+
+    let mut envelope = alice_dr.encrypt_envelope(b"interop").expect("Failed to encrypt");
+    envelope.header.dh_public_key = format!("05{}", envelope.header.dh_public_key);
+
+    assert!(!bob_dr.accepts_prefixed_keys());
+    assert!(bob_dr.decrypt_envelope(&envelope).is_err(), "Prefixed key must be rejected by default");
+
+    bob_dr.set_accept_prefixed_keys(true);
+    assert_eq!(bob_dr.decrypt_envelope(&envelope).expect("Failed to decrypt"), b"interop".to_vec());
+    println!("  ✓ Session compatibility flag enables prefixed DH keys");
+}
+
+#[test]
+fn test_bundle_with_prefixed_keys() {
+    let bundle_json = generate_prekey_bundle(generate_identity_key_pair(), 1481, Some(1482));
+    let bundle: PreKeyBundleJSON = serde_json::from_str(&bundle_json).expect("Invalid bundle JSON");
+
+    let mut signal_style = bundle.clone();
+    signal_style.identity_public_hex = format!("05{}", bundle.identity_public_hex);
+    signal_style.signed_prekey.public_key_hex = format!("05{}", bundle.signed_prekey.public_key_hex);
+    if let Some(otp) = signal_style.one_time_prekey.as_mut() {
+        otp.public_key_hex = format!("05{}", otp.public_key_hex);
+    }
+
+    assert!(signal_style.to_prekey_bundle().is_err());
+    let parsed = signal_style.to_prekey_bundle_compat(true).expect("Prefixed bundle must parse");
+    assert!(parsed.verify_signature().expect("Signature check failed"));
+    assert_eq!(parsed.identity_public_hex(), bundle.identity_public_hex);
+    println!("  ✓ Prefixed bundle keys are normalized and the signature still verifies");
+}
@@ -0,0 +1,52 @@
+//! Test giải mã một luồng envelope bằng iterator (decrypt_ordered)
+
+mod common;
+
+use e2ee_core::message::MessageEnvelope;
+
+#[test]
+fn test_decrypt_ordered_stream_with_reordering() {
+    println!("\n=== Test: Decrypt Ordered Stream ===\n");
+
+    let (mut alice_dr, mut bob_dr) = common::ratchet_pair([21u8; 32]);
+
+    let plaintexts: Vec<Vec<u8>> = (0..20).map(|i| format!("history {}", i).into_bytes()).collect();
+    let mut envelopes: Vec<MessageEnvelope> = plaintexts
+        .iter()
+        .map(|p| alice_dr.encrypt_envelope(p).expect("Failed to encrypt"))
+        .collect();
+
+    // Two pairs arrive swapped
+    envelopes.swap(3, 4);
+    envelopes.swap(12, 15);
+    let arrival: Vec<u64> = envelopes.iter().map(|e| e.header.message_number).collect();
+
+    let results: Vec<_> = bob_dr.decrypt_ordered(envelopes).collect();
+    assert_eq!(results.len(), 20);
+
+    // Put each plaintext back at its send position
+    let mut in_send_order = vec![Vec::new(); 20];
+    for (message_number, result) in arrival.iter().zip(results) {
+        in_send_order[(*message_number - 1) as usize] = result.expect("Failed to decrypt");
+    }
+    assert_eq!(in_send_order, plaintexts);
+    println!("  ✓ 20 envelopes (2 pairs out of order) decrypted in send order");
+}
+
+#[test]
+fn test_decrypt_ordered_failure_is_per_item() {
+    println!("\n=== Test: Decrypt Ordered Per-Item Errors ===\n");
+
+    let (mut alice_dr, mut bob_dr) = common::ratchet_pair([22u8; 32]);
+
+    let mut envelopes: Vec<MessageEnvelope> = (0..3)
+        .map(|i| alice_dr.encrypt_envelope(format!("m{}", i).as_bytes()).expect("Failed to encrypt"))
+        .collect();
+    envelopes[1].ciphertext[0] ^= 0x01;
+
+    let results: Vec<_> = bob_dr.decrypt_ordered(envelopes).collect();
+    assert_eq!(results[0].as_ref().unwrap(), b"m0");
+    assert!(results[1].is_err(), "Tampered envelope must fail");
+    assert_eq!(results[2].as_ref().unwrap(), b"m2");
+    println!("  ✓ A tampered envelope fails without stopping the stream");
+}
@@ -0,0 +1,40 @@
+//! Test giải mã không làm thay đổi envelope, để có thể thử lại hoặc chuyển tiếp
+
+mod common;
+
+use common::ratchet_pair;
+use e2ee_core::ratchet::DoubleRatchet;
+
+#[test]
+fn test_decrypt_leaves_envelope_unchanged() {
+    println!("\n=== Test: Decrypt Leaves Envelope Unchanged ===\n");
+
+    let (mut alice_dr, mut bob_dr) = ratchet_pair([0x96; 32]);
+    let envelope = alice_dr.encrypt_envelope(b"retry me").unwrap();
+    let original = envelope.clone();
+
+    // Wrong session: the decrypt fails and the envelope stays intact
+    let mut stranger = DoubleRatchet::from_shared_secret(&[0x97; 32], false).unwrap();
+    assert!(stranger.decrypt_envelope(&envelope).is_err());
+    assert_eq!(envelope, original);
+    assert_eq!(envelope.ciphertext, original.ciphertext);
+    println!("  ✓ Failed decrypt leaves the envelope byte-identical");
+
+    // The same envelope can then be routed to the right session
+    assert_eq!(bob_dr.decrypt_envelope(&envelope).unwrap(), b"retry me".to_vec());
+    assert_eq!(envelope, original);
+    println!("  ✓ Successful decrypt leaves the envelope byte-identical");
+
+    // A tampered envelope is rejected without being touched either
+    let next = alice_dr.encrypt_envelope(b"second").unwrap();
+    let mut tampered = next.clone();
+    let last = tampered.ciphertext.len() - 1;
+    tampered.ciphertext[last] ^= 0x01;
+    let tampered_before = tampered.clone();
+    assert!(bob_dr.decrypt_envelope(&tampered).is_err());
+    assert_eq!(tampered, tampered_before);
+    assert_eq!(bob_dr.decrypt_envelope(&next).unwrap(), b"second".to_vec());
+    assert!(bob_dr.decrypt_envelope_full(&envelope).is_err());
+    assert_eq!(envelope, original);
+    println!("  ✓ Tampered and replayed envelopes are rejected unchanged");
+}
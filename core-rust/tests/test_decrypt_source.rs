@@ -0,0 +1,36 @@
+//! Test nguồn khóa khi giải mã: chain đang chạy (Live) hay kho khóa bị bỏ qua (SkippedStore)
+
+mod common;
+
+use common::ratchet_pair;
+use e2ee_core::ratchet::DecryptSource;
+
+#[test]
+fn test_decrypt_source_live_vs_skipped_store() {
+    println!("\n=== Test: Decrypt Source ===\n");
+
+    let (mut alice_dr, mut bob_dr) = ratchet_pair([0x93; 32]);
+    let first = alice_dr.encrypt_envelope(b"first").unwrap();
+    let second = alice_dr.encrypt_envelope(b"second").unwrap();
+    let third = alice_dr.encrypt_envelope(b"third").unwrap();
+
+    let (plaintext, source) = bob_dr.decrypt_envelope_with_meta(&first).unwrap();
+    assert_eq!(plaintext, b"first".to_vec());
+    assert_eq!(source, DecryptSource::Live);
+    println!("  ✓ In-order message reports Live");
+
+    let (plaintext, source) = bob_dr.decrypt_envelope_with_meta(&third).unwrap();
+    assert_eq!(plaintext, b"third".to_vec());
+    assert_eq!(source, DecryptSource::Live);
+    println!("  ✓ Message ahead of the chain reports Live");
+
+    let (plaintext, source) = bob_dr.decrypt_envelope_with_meta(&second).unwrap();
+    assert_eq!(plaintext, b"second".to_vec());
+    assert_eq!(source, DecryptSource::SkippedStore);
+    println!("  ✓ Out-of-order message reports SkippedStore");
+
+    let reply = bob_dr.encrypt_envelope(b"reply").unwrap();
+    let decrypted = alice_dr.decrypt_envelope_full(&reply).unwrap();
+    assert_eq!(decrypted.source, DecryptSource::Live);
+    println!("  ✓ decrypt_envelope_full carries the source too");
+}
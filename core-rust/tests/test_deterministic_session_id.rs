@@ -0,0 +1,88 @@
+//! Test session id xác định: hai bên suy ra cùng một id từ handshake
+
+use e2ee_core::ffi::api::{
+    close_session, create_session_initiator_deterministic, create_session_responder,
+    create_session_responder_deterministic, decrypt_message, encrypt_message, generate_identity_key_pair,
+    generate_prekey_bundle, session_routing_id,
+};
+use e2ee_core::ffi::{deterministic_session_id, Session, SessionRegistry};
+use std::sync::Arc;
+use std::thread;
+
+#[test]
+fn test_both_sides_derive_the_same_session_id() {
+    println!("\n=== Test: Deterministic Session Id ===\n");
+
+    let bob_identity_json = generate_identity_key_pair();
+    let bundle_json = generate_prekey_bundle(bob_identity_json.clone(), 1941, None);
+    let init_json = create_session_initiator_deterministic(generate_identity_key_pair(), bundle_json);
+    let init: serde_json::Value = serde_json::from_str(&init_json).expect("Invalid initiator JSON");
+    let alice_session = init["session_id"].as_str().expect("Missing session_id").to_string();
+    let alice_identity_hex = init["alice_identity_hex"].as_str().unwrap().to_string();
+    let alice_ephemeral_hex = init["alice_ephemeral_public_key_hex"].as_str().unwrap().to_string();
+    let routing_id = session_routing_id(alice_session.clone());
+    assert_eq!(alice_session, deterministic_session_id(&routing_id).unwrap());
+    assert_eq!(uuid::Uuid::parse_str(&alice_session).unwrap().get_version_num(), 8);
+    println!("  ✓ Initiator registered under the id derived from the routing id");
+
+    // Both ends live in this process, so the derived id is already taken here
+    let respond = || {
+        create_session_responder_deterministic(
+            bob_identity_json.clone(),
+            1941,
+            None,
+            alice_identity_hex.clone(),
+            alice_ephemeral_hex.clone(),
+        )
+    };
+    assert!(respond().starts_with("Error:"));
+    let envelope = encrypt_message(alice_session.clone(), b"still here".to_vec());
+    assert!(!envelope.is_empty());
+    println!("  ✓ A second session under the same id is refused, the first is kept");
+
+    let random_bob = create_session_responder(
+        bob_identity_json.clone(),
+        1941,
+        None,
+        alice_identity_hex.clone(),
+        alice_ephemeral_hex.clone(),
+    );
+    assert_ne!(random_bob, alice_session);
+    let envelope = encrypt_message(alice_session.clone(), b"hello".to_vec());
+    assert_eq!(decrypt_message(random_bob.clone(), envelope), b"hello".to_vec());
+    close_session(random_bob);
+
+    close_session(alice_session.clone());
+    let bob_session = respond();
+    assert_eq!(bob_session, alice_session);
+    assert_eq!(session_routing_id(bob_session), routing_id);
+    println!("  ✓ Responder derives the same session id as the initiator");
+}
+
+#[test]
+fn test_racing_registrations_under_one_id() {
+    println!("\n=== Test: Racing Registrations Under One Id ===\n");
+
+    let registry = Arc::new(SessionRegistry::new());
+    let id = deterministic_session_id("shared-routing-id").unwrap();
+    let handles: Vec<_> = (0..8u8)
+        .map(|i| {
+            let registry = Arc::clone(&registry);
+            let id = id.clone();
+            thread::spawn(move || {
+                let session = Session::from_shared_secret([i; 32], true, id.clone())
+                    .expect("Failed to create session");
+                registry.try_register(id, Arc::new(session)).is_ok()
+            })
+        })
+        .collect();
+
+    let winners = handles
+        .into_iter()
+        .map(|handle| handle.join().expect("Thread panicked"))
+        .filter(|won| *won)
+        .count();
+    assert_eq!(winners, 1);
+    assert!(registry.contains(&id));
+    println!("  ✓ Exactly one registration wins, the rest are refused");
+}
@@ -0,0 +1,239 @@
+//! Test DH ratchet hai chiều với khóa DH ban đầu lấy từ signed prekey của Bob
+
+mod common;
+
+use common::establish_ffi_sessions;
+use e2ee_core::ffi::api::{decrypt_message, encrypt_message, session_has_ratcheted};
+use e2ee_core::keys::prekey::{SignedPreKey, SignedPreKeyPair};
+use e2ee_core::keys::{IdentityKeyPair, PreKeyBundle};
+use e2ee_core::ffi::Session;
+use e2ee_core::ratchet::DoubleRatchet;
+use e2ee_core::x3dh::{X3DHInitiator, X3DHResponder, X3DHResponseResult, X3DHResult};
+use x25519_dalek::{PublicKey, StaticSecret};
+
+/// Run X3DH between fresh identities, returning Alice's identity hex and both results
+fn x3dh_handshake() -> (String, X3DHResult, X3DHResponseResult) {
+    let (alice_hex, alice_result, bob) = x3dh_initiation();
+    let bob_result = bob
+        .respond(&alice_hex, &alice_result.ephemeral_public_key_hex)
+        .expect("Failed to respond to X3DH");
+    (alice_hex, alice_result, bob_result)
+}
+
+/// Run Alice's side of X3DH, returning Bob's responder unanswered
+fn x3dh_initiation() -> (String, X3DHResult, X3DHResponder) {
+    let alice_identity = IdentityKeyPair::generate();
+    let bob_identity = IdentityKeyPair::generate();
+    let bob_signed_prekey = SignedPreKeyPair::generate(1, &bob_identity)
+        .expect("Failed to generate signed prekey");
+    let bundle = PreKeyBundle::new(
+        bob_identity.public_key_hex(),
+        bob_identity.verifying_key(),
+        SignedPreKey::from(&bob_signed_prekey),
+        None,
+    );
+
+    let alice_result = X3DHInitiator::new(alice_identity.clone())
+        .initiate(&bundle)
+        .expect("Failed to initiate X3DH");
+    let bob = X3DHResponder::new(bob_identity, bob_signed_prekey);
+
+    (alice_identity.public_key_hex(), alice_result, bob)
+}
+
+#[test]
+fn test_bidirectional_dh_ratchet_stays_in_sync() {
+    println!("\n=== Test: Bidirectional DH Ratchet ===\n");
+
+    let (alice_hex, alice_result, bob_result) = x3dh_handshake();
+    let alice = Session::from_x3dh_result(&alice_result, "alice".to_string()).expect("Failed to create session");
+    let bob = Session::from_x3dh_response(&bob_result, &alice_hex, "bob".to_string())
+        .expect("Failed to create session");
+
+    // Bob may talk first on the initial chain, before any DH ratchet
+    let early = bob.encrypt(b"bob first").expect("Failed to encrypt");
+    assert_eq!(alice.decrypt(&early).expect("Failed to decrypt"), b"bob first".to_vec());
+
+    let mut previous_alice_key = String::new();
+    let mut previous_bob_key = String::new();
+    for round in 0..4 {
+        let from_alice: Vec<_> = (0..3)
+            .map(|i| alice.encrypt(format!("alice {} {}", round, i).as_bytes()).expect("Failed to encrypt"))
+            .collect();
+        // Deliver the last one first: out-of-order delivery within a chain
+        for envelope in from_alice.iter().rev() {
+            bob.decrypt(envelope).expect("Bob failed to decrypt");
+        }
+
+        let from_bob: Vec<_> = (0..2)
+            .map(|i| bob.encrypt(format!("bob {} {}", round, i).as_bytes()).expect("Failed to encrypt"))
+            .collect();
+        for (i, envelope) in from_bob.iter().enumerate() {
+            let plaintext = alice.decrypt(envelope).expect("Alice failed to decrypt");
+            assert_eq!(plaintext, format!("bob {} {}", round, i).into_bytes());
+        }
+
+        // Every turn of the conversation moves both parties to a new DH key
+        assert_ne!(from_alice[0].header.dh_public_key, previous_alice_key);
+        assert_ne!(from_bob[0].header.dh_public_key, previous_bob_key);
+        previous_alice_key = from_alice[0].header.dh_public_key.clone();
+        previous_bob_key = from_bob[0].header.dh_public_key.clone();
+    }
+    println!("  ✓ Four round trips with fresh DH keys each turn stay in sync");
+}
+
+#[test]
+fn test_initiator_first_message_uses_signed_prekey_ratchet() {
+    println!("\n=== Test: Initial DH Ratchet Key ===\n");
+
+    let (alice_hex, alice_result, bob_responder) = x3dh_initiation();
+    let signed_prekey_hex = hex::encode(alice_result.ratchet_public_key.expect("Missing ratchet key"));
+    let respond = || {
+        bob_responder
+            .respond(&alice_hex, &alice_result.ephemeral_public_key_hex)
+            .expect("Failed to respond to X3DH")
+    };
+    let bob_result = respond();
+    // Without its ratchet key the result builds a ratchet that ignores the signed prekey
+    let mut unseeded_result = respond();
+    unseeded_result.ratchet_private_key = None;
+    let mut unseeded_bob = unseeded_result.into_ratchet().expect("Failed to create Double Ratchet");
+    let mut alice = alice_result.into_ratchet().expect("Failed to create Alice's Double Ratchet");
+    let mut bob = bob_result.into_ratchet().expect("Failed to create Bob's Double Ratchet");
+
+    // Alice's first chain comes from DH(her key, Bob's signed prekey): a responder
+    // that ignores the signed prekey cannot read it
+    let first = alice.encrypt_envelope(b"hello bob").expect("Failed to encrypt");
+    let second = alice.encrypt_envelope(b"still first chain").expect("Failed to encrypt");
+    assert_eq!(first.header.dh_public_key, second.header.dh_public_key);
+    assert_ne!(first.header.dh_public_key, signed_prekey_hex);
+    assert!(unseeded_bob.decrypt_envelope(&first).is_err());
+    assert_eq!(bob.decrypt_envelope(&first).expect("Failed to decrypt"), b"hello bob".to_vec());
+    assert_eq!(bob.decrypt_envelope(&second).expect("Failed to decrypt"), b"still first chain".to_vec());
+    println!("  ✓ Alice's first message is keyed by Bob's signed prekey");
+
+    // Alternate several times; each turn is a new DH ratchet on both sides
+    let mut last_bob_key = signed_prekey_hex;
+    for round in 0..3 {
+        let reply = bob.encrypt_envelope(format!("bob {}", round).as_bytes()).expect("Failed to encrypt");
+        assert_ne!(reply.header.dh_public_key, last_bob_key);
+        last_bob_key = reply.header.dh_public_key.clone();
+        assert_eq!(alice.decrypt_envelope(&reply).expect("Failed to decrypt"), format!("bob {}", round).into_bytes());
+
+        let next = alice.encrypt_envelope(format!("alice {}", round).as_bytes()).expect("Failed to encrypt");
+        assert_eq!(bob.decrypt_envelope(&next).expect("Failed to decrypt"), format!("alice {}", round).into_bytes());
+    }
+    println!("  ✓ Bidirectional flow survives repeated DH ratchets");
+}
+
+#[test]
+fn test_late_message_from_previous_chain_after_ratchet() {
+    let (alice_session, bob_session) = establish_ffi_sessions(1591, None);
+
+    let first = encrypt_message(alice_session.clone(), b"one".to_vec());
+    let late = encrypt_message(alice_session.clone(), b"two".to_vec());
+    assert_eq!(decrypt_message(bob_session.clone(), first), b"one".to_vec());
+
+    // Bob answers under a new DH key; Alice ratchets on receipt
+    let reply = encrypt_message(bob_session.clone(), b"reply".to_vec());
+    assert_eq!(decrypt_message(alice_session.clone(), reply), b"reply".to_vec());
+    assert!(session_has_ratcheted(alice_session.clone()));
+    assert!(session_has_ratcheted(bob_session.clone()));
+
+    // Alice's next message is on a new chain; her older one still decrypts
+    let after = encrypt_message(alice_session, b"three".to_vec());
+    assert_eq!(decrypt_message(bob_session.clone(), after), b"three".to_vec());
+    assert_eq!(decrypt_message(bob_session, late), b"two".to_vec());
+}
+
+#[test]
+fn test_set_initial_remote_dh_matches_responder() {
+    println!("\n=== Test: Initial Remote DH ===\n");
+
+    let shared_secret = [61u8; 32];
+    let bob_ratchet_key = StaticSecret::from([62u8; 32]);
+    let bob_ratchet_public = *PublicKey::from(&bob_ratchet_key).as_bytes();
+
+    let mut alice = DoubleRatchet::from_shared_secret(&shared_secret, true).expect("Failed to create Double Ratchet");
+    alice.set_initial_remote_dh(bob_ratchet_public).expect("Failed to set initial remote DH");
+    let mut bob = DoubleRatchet::from_x3dh_responder(&shared_secret, &bob_ratchet_key)
+        .expect("Failed to create Double Ratchet");
+
+    let envelope = alice.encrypt_envelope(b"primed").expect("Failed to encrypt");
+    assert_eq!(bob.decrypt_envelope(&envelope).expect("Failed to decrypt"), b"primed".to_vec());
+    println!("  ✓ Primed sending chain matches the responder's receiving chain");
+
+    // Too late once a message has gone out
+    assert!(alice.set_initial_remote_dh(bob_ratchet_public).is_err());
+    println!("  ✓ Priming after the first message is refused");
+}
+
+#[test]
+fn test_delayed_message_with_old_dh_key_does_not_ratchet_back() {
+    println!("\n=== Test: Delayed Message From Old DH Epoch ===\n");
+
+    let (_, alice_result, bob_result) = x3dh_handshake();
+    let mut alice = alice_result.into_ratchet().expect("Failed to create Alice's Double Ratchet");
+    let mut bob = bob_result.into_ratchet().expect("Failed to create Bob's Double Ratchet");
+
+    // Two messages under Alice's key A; the second one is held back by the network
+    let first = alice.encrypt_envelope(b"a1").expect("Failed to encrypt");
+    let delayed = alice.encrypt_envelope(b"a2").expect("Failed to encrypt");
+    assert_eq!(bob.decrypt_envelope(&first).expect("Failed to decrypt"), b"a1".to_vec());
+
+    // Alice ratchets to key B on Bob's reply, and Bob processes a B-message
+    let reply = bob.encrypt_envelope(b"reply").expect("Failed to encrypt");
+    alice.decrypt_envelope(&reply).expect("Failed to decrypt");
+    let on_b = alice.encrypt_envelope(b"b1").expect("Failed to encrypt");
+    assert_ne!(on_b.header.dh_public_key, delayed.header.dh_public_key);
+    assert_eq!(bob.decrypt_envelope(&on_b).expect("Failed to decrypt"), b"b1".to_vec());
+    let bob_state = bob.public_state();
+    assert_eq!(bob_state.remote_dh_public.as_deref(), Some(on_b.header.dh_public_key.as_str()));
+    println!("  ✓ Bob ratcheted onto Alice's key B");
+
+    // The A-message decrypts from the stored keys of its epoch, without a backward ratchet
+    assert_eq!(bob.decrypt_envelope(&delayed).expect("Failed to decrypt delayed message"), b"a2".to_vec());
+    assert_eq!(bob.public_state(), bob_state);
+    println!("  ✓ Delayed A-message decrypted from stored keys, state unchanged");
+
+    // A replay of it is rejected without ratcheting back to A either
+    assert!(bob.decrypt_envelope(&delayed).is_err());
+    assert_eq!(bob.public_state(), bob_state);
+    let next = alice.encrypt_envelope(b"b2").expect("Failed to encrypt");
+    assert_eq!(bob.decrypt_envelope(&next).expect("Failed to decrypt"), b"b2".to_vec());
+    println!("  ✓ Replayed A-message rejected, chain B continues");
+}
+
+#[test]
+fn test_respond_into_ratchet_interoperates_with_initiator() {
+    println!("\n=== Test: Respond Into Ratchet ===\n");
+
+    let alice_identity = IdentityKeyPair::generate();
+    let bob_identity = IdentityKeyPair::generate();
+    let bob_signed_prekey = SignedPreKeyPair::generate(1, &bob_identity).expect("Failed to generate signed prekey");
+    let bundle = PreKeyBundle::new(
+        bob_identity.public_key_hex(),
+        bob_identity.verifying_key(),
+        SignedPreKey::from(&bob_signed_prekey),
+        None,
+    );
+    let alice_result = X3DHInitiator::new(alice_identity.clone()).initiate(&bundle).expect("Failed to initiate X3DH");
+    let mut bob = X3DHResponder::new(bob_identity, bob_signed_prekey)
+        .respond_into_ratchet(&alice_identity.public_key_hex(), &alice_result.ephemeral_public_key_hex)
+        .expect("Failed to build Bob's Double Ratchet");
+    let mut alice = alice_result.into_ratchet().expect("Failed to create Alice's Double Ratchet");
+    println!("  ✓ Responder ratchet built in one call");
+
+    for round in 0..3 {
+        for i in 0..2 {
+            let text = format!("alice {}.{}", round, i);
+            let envelope = alice.encrypt_envelope(text.as_bytes()).expect("Failed to encrypt");
+            assert_eq!(bob.decrypt_envelope(&envelope).expect("Failed to decrypt"), text.into_bytes());
+        }
+        let text = format!("bob {}", round);
+        let reply = bob.encrypt_envelope(text.as_bytes()).expect("Failed to encrypt");
+        assert_eq!(alice.decrypt_envelope(&reply).expect("Failed to decrypt"), text.into_bytes());
+    }
+    assert!(alice.has_ratcheted() && bob.has_ratcheted());
+    println!("  ✓ Interoperates with the initiator's ratchet across several DH ratchets");
+}
@@ -0,0 +1,27 @@
+//! Test đầu vào base64 rỗng hoặc chỉ có khoảng trắng khi giải mã
+
+mod common;
+
+use common::establish_ffi_sessions;
+use e2ee_core::error::E2EEError;
+use e2ee_core::ffi::api::decrypt_message;
+use e2ee_core::message::MessageEnvelope;
+
+#[test]
+fn test_empty_input_reports_empty_envelope() {
+    println!("\n=== Test: Empty Envelope Input ===\n");
+
+    for input in ["", " ", "\n", "\t \r\n"] {
+        match MessageEnvelope::from_base64(input) {
+            Err(E2EEError::SerializationError(msg)) => assert_eq!(msg, "empty envelope", "Input {:?}", input),
+            Err(e) => panic!("Expected empty envelope error for {:?}, got {}", input, e),
+            Ok(_) => panic!("Empty input {:?} must not parse", input),
+        }
+    }
+    println!("  ✓ Empty and whitespace-only input give a specific error");
+
+    let (_alice_session, bob_session) = establish_ffi_sessions(1421, None);
+    let output = String::from_utf8(decrypt_message(bob_session, " \n".to_string())).unwrap();
+    assert_eq!(output, "Error: Failed to parse envelope: Serialization error: empty envelope");
+    println!("  ✓ FFI reports the empty envelope clearly");
+}
@@ -12,9 +12,8 @@
 use e2ee_core::keys::{IdentityKeyPair, PreKeyBundle};
 use e2ee_core::keys::prekey::{OneTimePreKey, OneTimePreKeyPair, SignedPreKey, SignedPreKeyPair};
 use e2ee_core::message::MessageEnvelope;
-use e2ee_core::ratchet::DoubleRatchet;
 use e2ee_core::x3dh::{X3DHInitiator, X3DHResponder};
-use x25519_dalek::{EphemeralSecret, PublicKey};
+use x25519_dalek::{PublicKey, StaticSecret};
 
 #[test]
 fn test_full_encrypt_decrypt_flow() {
@@ -42,7 +41,8 @@ fn test_full_encrypt_decrypt_flow() {
     println!("\nStep 2: Bob generates prekeys...");
     let bob_signed_prekey = SignedPreKeyPair::generate(1, &bob_identity)
         .expect("Failed to generate signed prekey");
-    let bob_one_time_prekey = OneTimePreKeyPair::generate(1);
+    let bob_one_time_prekey = OneTimePreKeyPair::generate(1)
+        .expect("Failed to generate one-time prekey");
     
     println!("  Signed prekey ID: {}", bob_signed_prekey.key_id());
     println!("  One-time prekey ID: {}", bob_one_time_prekey.key_id());
@@ -82,12 +82,8 @@ fn test_full_encrypt_decrypt_flow() {
     
     // Bob needs to provide the one-time prekey private key
     let bob_one_time_private_ref = bob_one_time_prekey.private_key();
-    let bob_one_time_private_bytes = unsafe {
-        std::mem::transmute_copy::<EphemeralSecret, [u8; 32]>(bob_one_time_private_ref)
-    };
-    let bob_one_time_private = unsafe {
-        std::mem::transmute::<[u8; 32], EphemeralSecret>(bob_one_time_private_bytes)
-    };
+    let bob_one_time_private_bytes = bob_one_time_private_ref.to_bytes();
+    let bob_one_time_private = StaticSecret::from(bob_one_time_private_bytes);
     let bob_one_time_public = PublicKey::from(&bob_one_time_private);
     
     let mut bob = X3DHResponder::new(bob_identity.clone(), bob_signed_prekey.clone());
@@ -101,9 +97,8 @@ fn test_full_encrypt_decrypt_flow() {
     println!("  Bob derived shared secret (32 bytes)");
     
     // Verify both sides have the same shared secret
-    assert_eq!(
-        alice_result.shared_secret,
-        bob_result.shared_secret,
+    assert!(
+        bob_result.verify_key_confirmation(&alice_result.key_confirmation()).is_ok(),
         "Shared secrets should match"
     );
     println!("  ✓ Shared secrets match!");
@@ -113,9 +108,9 @@ fn test_full_encrypt_decrypt_flow() {
     // ============================================================
     println!("\nStep 6: Initializing Double Ratchet...");
     // Alice is the initiator, Bob is the responder
-    let mut alice_dr = DoubleRatchet::from_shared_secret(&alice_result.shared_secret, true)
+    let mut alice_dr = alice_result.into_ratchet()
         .expect("Failed to create Alice's Double Ratchet");
-    let mut bob_dr = DoubleRatchet::from_shared_secret(&bob_result.shared_secret, false)
+    let mut bob_dr = bob_result.into_ratchet()
         .expect("Failed to create Bob's Double Ratchet");
     
     println!("  Alice Double Ratchet initialized (initiator)");
@@ -267,7 +262,8 @@ fn test_message_number_synchronization() {
     
     let bob_signed_prekey = SignedPreKeyPair::generate(1, &bob_identity)
         .expect("Failed to generate signed prekey");
-    let bob_one_time_prekey = OneTimePreKeyPair::generate(1);
+    let bob_one_time_prekey = OneTimePreKeyPair::generate(1)
+        .expect("Failed to generate one-time prekey");
     
     let prekey_bundle = PreKeyBundle::new(
         bob_identity.public_key_hex(),
@@ -281,12 +277,8 @@ fn test_message_number_synchronization() {
         .expect("Failed to initiate X3DH");
     
     let bob_one_time_private_ref = bob_one_time_prekey.private_key();
-    let bob_one_time_private_bytes = unsafe {
-        std::mem::transmute_copy::<EphemeralSecret, [u8; 32]>(bob_one_time_private_ref)
-    };
-    let bob_one_time_private = unsafe {
-        std::mem::transmute::<[u8; 32], EphemeralSecret>(bob_one_time_private_bytes)
-    };
+    let bob_one_time_private_bytes = bob_one_time_private_ref.to_bytes();
+    let bob_one_time_private = StaticSecret::from(bob_one_time_private_bytes);
     let bob_one_time_public = PublicKey::from(&bob_one_time_private);
     
     let mut bob = X3DHResponder::new(bob_identity.clone(), bob_signed_prekey.clone());
@@ -297,9 +289,9 @@ fn test_message_number_synchronization() {
         &alice_result.ephemeral_public_key_hex,
     ).expect("Failed to respond to X3DH");
     
-    let mut alice_dr = DoubleRatchet::from_shared_secret(&alice_result.shared_secret, true)
+    let mut alice_dr = alice_result.into_ratchet()
         .expect("Failed to create Alice's Double Ratchet");
-    let mut bob_dr = DoubleRatchet::from_shared_secret(&bob_result.shared_secret, false)
+    let mut bob_dr = bob_result.into_ratchet()
         .expect("Failed to create Bob's Double Ratchet");
 
     // Test: Verify message numbers increment correctly
@@ -347,7 +339,8 @@ fn test_dh_ratchet_after_multiple_messages() {
     
     let bob_signed_prekey = SignedPreKeyPair::generate(1, &bob_identity)
         .expect("Failed to generate signed prekey");
-    let bob_one_time_prekey = OneTimePreKeyPair::generate(1);
+    let bob_one_time_prekey = OneTimePreKeyPair::generate(1)
+        .expect("Failed to generate one-time prekey");
     
     let prekey_bundle = PreKeyBundle::new(
         bob_identity.public_key_hex(),
@@ -361,12 +354,8 @@ fn test_dh_ratchet_after_multiple_messages() {
         .expect("Failed to initiate X3DH");
     
     let bob_one_time_private_ref = bob_one_time_prekey.private_key();
-    let bob_one_time_private_bytes = unsafe {
-        std::mem::transmute_copy::<EphemeralSecret, [u8; 32]>(bob_one_time_private_ref)
-    };
-    let bob_one_time_private = unsafe {
-        std::mem::transmute::<[u8; 32], EphemeralSecret>(bob_one_time_private_bytes)
-    };
+    let bob_one_time_private_bytes = bob_one_time_private_ref.to_bytes();
+    let bob_one_time_private = StaticSecret::from(bob_one_time_private_bytes);
     let bob_one_time_public = PublicKey::from(&bob_one_time_private);
     
     let mut bob = X3DHResponder::new(bob_identity.clone(), bob_signed_prekey.clone());
@@ -377,9 +366,9 @@ fn test_dh_ratchet_after_multiple_messages() {
         &alice_result.ephemeral_public_key_hex,
     ).expect("Failed to respond to X3DH");
     
-    let mut alice_dr = DoubleRatchet::from_shared_secret(&alice_result.shared_secret, true)
+    let mut alice_dr = alice_result.into_ratchet()
         .expect("Failed to create Alice's Double Ratchet");
-    let mut bob_dr = DoubleRatchet::from_shared_secret(&bob_result.shared_secret, false)
+    let mut bob_dr = bob_result.into_ratchet()
         .expect("Failed to create Bob's Double Ratchet");
 
     // Test: Send multiple messages, then trigger DH ratchet by Bob sending back
@@ -439,7 +428,8 @@ fn test_serialization_roundtrip() {
     
     let bob_signed_prekey = SignedPreKeyPair::generate(1, &bob_identity)
         .expect("Failed to generate signed prekey");
-    let bob_one_time_prekey = OneTimePreKeyPair::generate(1);
+    let bob_one_time_prekey = OneTimePreKeyPair::generate(1)
+        .expect("Failed to generate one-time prekey");
     
     let prekey_bundle = PreKeyBundle::new(
         bob_identity.public_key_hex(),
@@ -453,12 +443,8 @@ fn test_serialization_roundtrip() {
         .expect("Failed to initiate X3DH");
     
     let bob_one_time_private_ref = bob_one_time_prekey.private_key();
-    let bob_one_time_private_bytes = unsafe {
-        std::mem::transmute_copy::<EphemeralSecret, [u8; 32]>(bob_one_time_private_ref)
-    };
-    let bob_one_time_private = unsafe {
-        std::mem::transmute::<[u8; 32], EphemeralSecret>(bob_one_time_private_bytes)
-    };
+    let bob_one_time_private_bytes = bob_one_time_private_ref.to_bytes();
+    let bob_one_time_private = StaticSecret::from(bob_one_time_private_bytes);
     let bob_one_time_public = PublicKey::from(&bob_one_time_private);
     
     let mut bob = X3DHResponder::new(bob_identity.clone(), bob_signed_prekey.clone());
@@ -469,9 +455,9 @@ fn test_serialization_roundtrip() {
         &alice_result.ephemeral_public_key_hex,
     ).expect("Failed to respond to X3DH");
     
-    let mut alice_dr = DoubleRatchet::from_shared_secret(&alice_result.shared_secret, true)
+    let mut alice_dr = alice_result.into_ratchet()
         .expect("Failed to create Alice's Double Ratchet");
-    let mut bob_dr = DoubleRatchet::from_shared_secret(&bob_result.shared_secret, false)
+    let mut bob_dr = bob_result.into_ratchet()
         .expect("Failed to create Bob's Double Ratchet");
 
     // Test: Encrypt, serialize, deserialize, decrypt
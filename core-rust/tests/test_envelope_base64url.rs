@@ -0,0 +1,50 @@
+//! Test envelope mã hoá base64 URL-safe (cho transport web) và base64 chuẩn
+
+mod common;
+
+use base64::{engine::general_purpose, Engine as _};
+use common::ratchet_pair;
+use e2ee_core::error::E2EEError;
+use e2ee_core::message::MessageEnvelope;
+
+#[test]
+fn test_url_safe_and_standard_envelopes_decode() {
+    println!("\n=== Test: Base64 URL-Safe Envelopes ===\n");
+
+    let (mut alice_dr, mut bob_dr) = ratchet_pair([0xa0; 32]);
+    let envelope = alice_dr.encrypt_envelope(b"over a web transport").unwrap();
+
+    let url = envelope.to_base64_url().unwrap();
+    assert!(!url.contains(['+', '/', '=']), "Not URL-safe: {}", url);
+    let decoded = MessageEnvelope::from_base64(&url).unwrap();
+    assert_eq!(decoded, envelope);
+    println!("  ✓ to_base64_url output round-trips through from_base64");
+
+    let decoded_len = url.len() * 3 / 4;
+    assert_eq!(MessageEnvelope::from_base64_bounded(&url, decoded_len).unwrap(), envelope);
+    assert!(matches!(
+        MessageEnvelope::from_base64_bounded(&url, decoded_len - 1),
+        Err(E2EEError::ProtocolError(_))
+    ));
+    assert_eq!(MessageEnvelope::from_base64_bounded(&padded_url_of(&envelope), decoded_len).unwrap(), envelope);
+    println!("  ✓ to_base64_url output round-trips through from_base64_bounded, bound enforced");
+
+    let padded_url = padded_url_of(&envelope);
+    assert_eq!(MessageEnvelope::from_base64(&padded_url).unwrap(), envelope);
+    println!("  ✓ Padded URL-safe encoding is accepted too");
+
+    let standard = envelope.to_base64().unwrap();
+    assert_eq!(MessageEnvelope::from_base64(&standard).unwrap(), envelope);
+    assert_eq!(bob_dr.decrypt_envelope(&decoded).unwrap(), b"over a web transport".to_vec());
+    println!("  ✓ Standard encoding still works and the envelope decrypts");
+
+    match MessageEnvelope::from_base64("not base64 at all!") {
+        Err(E2EEError::SerializationError(msg)) => assert!(msg.starts_with("Failed to decode base64"), "{}", msg),
+        other => panic!("Expected SerializationError, got {:?}", other),
+    }
+    println!("  ✓ Input valid in neither alphabet is still rejected");
+}
+
+fn padded_url_of(envelope: &MessageEnvelope) -> String {
+    general_purpose::URL_SAFE.encode(serde_json::to_vec(envelope).unwrap())
+}
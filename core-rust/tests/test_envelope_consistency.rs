@@ -0,0 +1,41 @@
+//! Test từ chối envelope có version/type/flag không nhất quán
+
+mod common;
+
+use common::ratchet_pair;
+use e2ee_core::error::E2EEError;
+use e2ee_core::message::{validate_envelope_consistency, MessageEnvelope, MessageType};
+
+fn assert_protocol_error(result: Result<Vec<u8>, E2EEError>, what: &str) {
+    match result {
+        Err(E2EEError::ProtocolError(_)) => println!("  ✓ {} rejected", what),
+        Err(e) => panic!("{}: expected ProtocolError before any crypto, got {}", what, e),
+        Ok(_) => panic!("{} must be rejected", what),
+    }
+}
+
+#[test]
+fn test_inconsistent_envelopes_are_rejected_before_decryption() {
+    println!("\n=== Test: Envelope Consistency ===\n");
+
+    let (mut alice_dr, mut bob_dr) = ratchet_pair([23u8; 32]);
+    let envelope = alice_dr.encrypt_envelope(b"consistent").expect("Failed to encrypt");
+    validate_envelope_consistency(&envelope).expect("Fresh envelope must be consistent");
+
+    // Padded flag on an unpadded version-1 ciphertext
+    let mut padded_claim = envelope.clone();
+    padded_claim.header.padded = true;
+    assert_protocol_error(bob_dr.decrypt_envelope(&padded_claim), "Padded flag on unpadded body");
+
+    let mut future_version = envelope.clone();
+    future_version.version = MessageEnvelope::VERSION + 1;
+    assert_protocol_error(bob_dr.decrypt_envelope(&future_version), "Unknown version");
+
+    let mut key_exchange = envelope.clone();
+    key_exchange.message_type = MessageType::KeyExchange;
+    assert_protocol_error(bob_dr.decrypt_envelope(&key_exchange), "Key exchange type");
+
+    // Rejections left the ratchet untouched
+    assert_eq!(bob_dr.decrypt_envelope(&envelope).expect("Failed to decrypt"), b"consistent".to_vec());
+    println!("  ✓ Original envelope still decrypts");
+}
@@ -0,0 +1,82 @@
+//! Test giới hạn kích thước envelope khi giải mã
+
+mod common;
+
+use common::{establish_ffi_sessions, ratchet_pair};
+use e2ee_core::error::E2EEError;
+use e2ee_core::ffi::api::{decrypt_message, encrypt_message};
+use e2ee_core::message::{MessageEnvelope, MAX_ENVELOPE_BYTES};
+
+#[test]
+fn test_oversized_envelope_is_rejected() {
+    println!("\n=== Test: Envelope Size Limit ===\n");
+
+    let (mut alice_dr, mut bob_dr) = ratchet_pair([24u8; 32]);
+    let envelope = alice_dr.encrypt_envelope(&[0x61u8; 1024]).expect("Failed to encrypt");
+    let b64 = envelope.to_base64().expect("Failed to serialize");
+
+    let parsed = MessageEnvelope::from_base64_limited(&b64, MAX_ENVELOPE_BYTES).expect("Normal envelope must pass");
+    assert_eq!(bob_dr.decrypt_envelope(&parsed).expect("Failed to decrypt"), vec![0x61u8; 1024]);
+    println!("  ✓ Normal envelope passes the default cap");
+
+    // A 1 KiB ciphertext is over a 512-byte cap, both as JSON and as ciphertext
+    match MessageEnvelope::from_base64_limited(&b64, 512) {
+        Err(E2EEError::ProtocolError(msg)) => assert_eq!(msg, "envelope too large"),
+        Err(e) => panic!("Expected ProtocolError, got {}", e),
+        Ok(_) => panic!("Oversized envelope must be rejected"),
+    }
+
+    // Junk that would decode past the cap is refused on length alone
+    let junk = "!".repeat((MAX_ENVELOPE_BYTES / 3 + 1) * 4);
+    match MessageEnvelope::from_base64_limited(&junk, MAX_ENVELOPE_BYTES) {
+        Err(E2EEError::ProtocolError(msg)) => assert_eq!(msg, "envelope too large"),
+        Err(e) => panic!("Expected size rejection before decoding, got {}", e),
+        Ok(_) => panic!("Oversized blob must be rejected"),
+    }
+    println!("  ✓ Oversized input rejected before decoding");
+}
+
+#[test]
+fn test_ffi_decrypt_enforces_default_cap() {
+    let (alice_session, bob_session) = establish_ffi_sessions(1391, None);
+
+    let b64 = encrypt_message(alice_session, b"small".to_vec());
+    assert_eq!(decrypt_message(bob_session.clone(), b64), b"small".to_vec());
+
+    let junk = "A".repeat((MAX_ENVELOPE_BYTES / 3 + 1) * 4);
+    let output = String::from_utf8(decrypt_message(bob_session, junk)).unwrap();
+    assert!(output.contains("envelope too large"), "Unexpected output: {}", output);
+}
+
+#[test]
+fn test_bounded_decode_rejects_oversized_base64() {
+    println!("\n=== Test: Bounded Base64 Decode ===\n");
+
+    let (mut alice_dr, mut bob_dr) = ratchet_pair([25u8; 32]);
+    let envelope = alice_dr.encrypt_envelope(&[0x62u8; 256]).expect("Failed to encrypt");
+    let b64 = envelope.to_base64().expect("Failed to serialize");
+    let decoded_len = b64.len() / 4 * 3 - b64.matches('=').count();
+
+    let parsed = MessageEnvelope::from_base64_bounded(&b64, decoded_len).expect("Envelope at the bound must pass");
+    assert_eq!(bob_dr.decrypt_envelope(&parsed).expect("Failed to decrypt"), vec![0x62u8; 256]);
+    println!("  ✓ Envelope decoding to exactly the bound passes");
+
+    match MessageEnvelope::from_base64_bounded(&b64, decoded_len - 1) {
+        Err(E2EEError::ProtocolError(msg)) => assert!(msg.contains("decoded size exceeds"), "Unexpected error: {}", msg),
+        Err(e) => panic!("Expected ProtocolError, got {}", e),
+        Ok(_) => panic!("Envelope over the bound must be rejected"),
+    }
+    println!("  ✓ One byte over the bound is rejected while decoding");
+
+    // Valid base64 of non-JSON data is rejected at the decode stage, before any parsing
+    let junk = "QUFB".repeat(1024 * 1024);
+    match MessageEnvelope::from_base64_bounded(&junk, 4096) {
+        Err(E2EEError::ProtocolError(msg)) => assert!(msg.contains("decoded size exceeds 4096"), "Unexpected error: {}", msg),
+        other => panic!("Expected ProtocolError, got {:?}", other.err()),
+    }
+    assert!(matches!(
+        MessageEnvelope::from_base64_bounded("!!!!", 4096),
+        Err(E2EEError::SerializationError(_))
+    ));
+    println!("  ✓ Large base64 junk is cut off at the bound, invalid base64 still reported");
+}
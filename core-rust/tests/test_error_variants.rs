@@ -0,0 +1,59 @@
+//! Test các error variant chuyên biệt (SessionNotFound, KeyNotFound)
+
+use e2ee_core::error::E2EEError;
+use e2ee_core::ffi::api::{
+    create_session_responder, encrypt_message, generate_identity_key_pair,
+};
+use e2ee_core::ffi::{Session, SessionRegistry};
+use std::sync::Arc;
+
+#[test]
+fn test_lookup_missing_session_surfaces_session_not_found() {
+    let registry = SessionRegistry::new();
+    let missing_id = "missing-session-1234".to_string();
+
+    match registry.try_get(&missing_id) {
+        Err(E2EEError::SessionNotFound(id)) => assert_eq!(id, missing_id),
+        Err(e) => panic!("Expected SessionNotFound, got {}", e),
+        Ok(_) => panic!("Lookup of a missing session must fail"),
+    }
+    assert!(matches!(registry.try_remove(&missing_id), Err(E2EEError::SessionNotFound(_))));
+
+    // The FFI stringifies the typed error, keeping the id
+    let output = encrypt_message(missing_id.clone(), b"hello".to_vec());
+    assert_eq!(output, format!("Error: Session not found: {}", missing_id));
+}
+
+#[test]
+fn test_missing_prekey_surfaces_key_not_found() {
+    let bob_identity_json = generate_identity_key_pair();
+    let output = create_session_responder(
+        bob_identity_json,
+        1091,
+        None,
+        "00".repeat(32),
+        "00".repeat(32),
+    );
+    assert_eq!(output, "Error: Key not found: signed prekey id 1091");
+}
+
+#[test]
+fn test_try_methods_report_registry_failures() {
+    println!("\n=== Test: Fallible Session Registry Methods ===\n");
+
+    let registry = SessionRegistry::new();
+    let id = "try-register-157".to_string();
+    let session = Arc::new(Session::from_shared_secret([0x57; 32], true, id.clone()).unwrap());
+    registry.try_register(id.clone(), Arc::clone(&session)).expect("First registration must succeed");
+    match registry.try_register(id.clone(), Arc::clone(&session)) {
+        Err(E2EEError::StateError(msg)) => assert!(msg.contains("already registered"), "{}", msg),
+        other => panic!("Expected StateError, got {:?}", other.err()),
+    }
+    println!("  ✓ try_register refuses an id that is taken");
+
+    assert!(Arc::ptr_eq(&registry.try_get(&id).unwrap(), &session));
+    assert!(Arc::ptr_eq(&registry.try_remove(&id).unwrap(), &session));
+    assert!(matches!(registry.try_get(&id), Err(E2EEError::SessionNotFound(_))));
+    assert!(matches!(registry.try_remove(&id), Err(E2EEError::SessionNotFound(_))));
+    println!("  ✓ try_get and try_remove return the session, then SessionNotFound");
+}
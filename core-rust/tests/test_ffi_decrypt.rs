@@ -0,0 +1,53 @@
+//! Test FFI decrypt_message_full trả về plaintext và metadata dạng JSON
+
+mod common;
+
+use base64::{engine::general_purpose, Engine as _};
+use common::establish_ffi_sessions;
+use e2ee_core::ffi::api::{decrypt_message_full, encrypt_message};
+use e2ee_core::message::MessageEnvelope;
+
+#[test]
+fn test_decrypt_message_full_valid_envelope() {
+    let (alice_session, bob_session) = establish_ffi_sessions(1081, Some(1082));
+
+    let first = encrypt_message(alice_session.clone(), b"first".to_vec());
+    let second = encrypt_message(alice_session.clone(), b"second".to_vec());
+
+    let result: serde_json::Value =
+        serde_json::from_str(&decrypt_message_full(bob_session.clone(), first)).expect("Invalid JSON");
+    assert_eq!(result["ok"], true);
+    assert_eq!(result["message_number"], 1);
+
+    let result: serde_json::Value =
+        serde_json::from_str(&decrypt_message_full(bob_session, second)).expect("Invalid JSON");
+    assert_eq!(result["ok"], true);
+    assert_eq!(result["message_number"], 2);
+    assert!(result["error"].is_null());
+
+    let plaintext = general_purpose::STANDARD
+        .decode(result["plaintext_base64"].as_str().expect("Missing plaintext"))
+        .expect("Invalid base64 plaintext");
+    assert_eq!(plaintext, b"second".to_vec());
+}
+
+#[test]
+fn test_decrypt_message_full_corrupt_envelope() {
+    let (alice_session, bob_session) = establish_ffi_sessions(1083, None);
+
+    let envelope_b64 = encrypt_message(alice_session, b"will be corrupted".to_vec());
+    let mut envelope = MessageEnvelope::from_base64(&envelope_b64).expect("Failed to parse envelope");
+    envelope.ciphertext[0] ^= 0xff;
+    let corrupt = envelope.to_base64().expect("Failed to serialize");
+
+    let result: serde_json::Value =
+        serde_json::from_str(&decrypt_message_full(bob_session.clone(), corrupt)).expect("Invalid JSON");
+    assert_eq!(result["ok"], false);
+    assert!(result["plaintext_base64"].is_null());
+    assert!(result["error"].as_str().expect("Missing error").contains("Decryption failed"));
+
+    let result: serde_json::Value =
+        serde_json::from_str(&decrypt_message_full(bob_session, "not base64!".to_string())).expect("Invalid JSON");
+    assert_eq!(result["ok"], false);
+    assert!(result["error"].is_string());
+}
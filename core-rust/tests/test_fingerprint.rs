@@ -0,0 +1,30 @@
+//! Test fingerprint hiển thị của identity (hex viết hoa, chia nhóm)
+
+use e2ee_core::ffi::api;
+use e2ee_core::keys::{identity_fingerprint_display, IdentityKeyPair};
+
+#[test]
+fn test_fingerprint_is_deterministic_and_grouped() {
+    println!("\n=== Test: Identity Fingerprint ===\n");
+
+    let alice = IdentityKeyPair::generate();
+    let bob = IdentityKeyPair::generate();
+    let fingerprint = identity_fingerprint_display(&alice.public_key_hex()).expect("Failed to fingerprint");
+
+    let groups: Vec<&str> = fingerprint.split(' ').collect();
+    assert_eq!(groups.len(), 8);
+    assert!(groups.iter().all(|g| g.len() == 8 && g.chars().all(|c| c.is_ascii_digit() || c.is_ascii_uppercase())));
+    println!("  ✓ 8 groups of 8 uppercase hex characters: {}", fingerprint);
+
+    assert_eq!(identity_fingerprint_display(&alice.public_key_hex()).unwrap(), fingerprint);
+    assert_eq!(identity_fingerprint_display(&alice.public_key_hex().to_uppercase()).unwrap(), fingerprint);
+    assert_eq!(api::identity_fingerprint_display(alice.public_key_hex()), fingerprint);
+    println!("  ✓ Same identity always yields the same fingerprint");
+
+    assert_ne!(identity_fingerprint_display(&bob.public_key_hex()).unwrap(), fingerprint);
+    println!("  ✓ Different identities differ");
+
+    assert!(identity_fingerprint_display("abcd").is_err());
+    assert!(api::identity_fingerprint_display("zz".repeat(32)).starts_with("Error:"));
+    println!("  ✓ Malformed identity hex is rejected");
+}
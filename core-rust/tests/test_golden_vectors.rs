@@ -0,0 +1,49 @@
+//! Test golden vector cho toàn bộ luồng X3DH → Double Ratchet với RNG cố định
+
+mod common;
+
+use e2ee_core::keys::prekey::{SignedPreKey, SignedPreKeyPair};
+use e2ee_core::keys::{IdentityKeyPair, PreKeyBundle};
+use e2ee_core::x3dh::{X3DHInitiator, X3DHResponder};
+use rand_chacha::rand_core::SeedableRng;
+use rand_chacha::ChaCha20Rng;
+
+#[test]
+fn test_key_schedule_matches_golden_vectors() {
+    println!("\n=== Test: Golden Vectors ===\n");
+
+    let mut alice_rng = ChaCha20Rng::from_seed([0xA1; 32]);
+    let mut bob_rng = ChaCha20Rng::from_seed([0xB0; 32]);
+
+    let alice_identity = IdentityKeyPair::generate_with_rng(&mut alice_rng);
+    let bob_identity = IdentityKeyPair::generate_with_rng(&mut bob_rng);
+    let bob_signed_prekey = SignedPreKeyPair::generate_with_rng(1, &bob_identity, &mut bob_rng)
+        .expect("Failed to generate signed prekey");
+    let bundle = PreKeyBundle::new(
+        bob_identity.public_key_hex(),
+        bob_identity.verifying_key(),
+        SignedPreKey::from(&bob_signed_prekey),
+        None,
+    );
+
+    let alice_result = X3DHInitiator::new(alice_identity.clone())
+        .initiate_with_rng(&bundle, &mut alice_rng)
+        .expect("Failed to initiate X3DH");
+    let bob_result = X3DHResponder::new(bob_identity, bob_signed_prekey)
+        .respond(&alice_identity.public_key_hex(), &alice_result.ephemeral_public_key_hex)
+        .expect("Failed to respond to X3DH");
+    let expected_secret: [u8; 32] = hex::decode("3033d7ae81d2efbe1a84f34ecca760ec8839c75061902d92e4f5bcf79325b5cb")
+        .unwrap()
+        .try_into()
+        .unwrap();
+    assert!(alice_result.matches(&expected_secret));
+    assert!(bob_result.matches(&expected_secret));
+    println!("  ✓ Shared secret matches the committed vector");
+
+    let (mut alice_dr, mut bob_dr) = common::ratchet_pair(expected_secret);
+    let envelope = alice_dr.encrypt_envelope(b"golden vector").expect("Failed to encrypt");
+    println!("  first ciphertext: {}", hex::encode(&envelope.ciphertext));
+    assert_eq!(hex::encode(&envelope.ciphertext), "4c286b52194aca8f8c0ce119ce47c1f7a74f4ebffc12c927e956c037ef");
+    assert_eq!(bob_dr.decrypt_envelope(&envelope).expect("Failed to decrypt"), b"golden vector".to_vec());
+    println!("  ✓ First ciphertext matches the committed vector");
+}
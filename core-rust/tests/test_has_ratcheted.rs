@@ -0,0 +1,61 @@
+//! Test trạng thái DH ratchet của session (một chiều hay hai chiều)
+
+mod common;
+
+use common::{establish_ffi_sessions, ratchet_pair};
+use e2ee_core::ffi::api::{encrypt_message, session_has_ratcheted};
+
+#[test]
+fn test_has_ratcheted_after_new_peer_dh_key() {
+    println!("\n=== Test: First DH Ratchet ===\n");
+
+    let (mut alice_dr, mut bob_dr) = ratchet_pair([25u8; 32]);
+    assert!(!alice_dr.has_ratcheted());
+    assert!(!bob_dr.has_ratcheted());
+
+    // Sending alone never ratchets
+    let first = alice_dr.encrypt_envelope(b"hello").expect("Failed to encrypt");
+    assert!(!alice_dr.has_ratcheted());
+
+    // The first DH key seen from the peer seeds the receiving chain without a ratchet
+    bob_dr.decrypt_envelope(&first).expect("Failed to decrypt");
+    assert!(!bob_dr.has_ratcheted());
+    println!("  ✓ Still one-directional after setup, send and first receive");
+
+    // A message carrying a different DH public key triggers the DH ratchet
+    let reply = bob_dr.encrypt_envelope(b"reply").expect("Failed to encrypt");
+    alice_dr.decrypt_envelope(&reply).expect("Failed to decrypt reply");
+    let rekeyed = alice_dr.encrypt_key_exchange().expect("Failed to encrypt key exchange");
+    assert_ne!(rekeyed.header.dh_public_key, first.header.dh_public_key);
+    bob_dr.decrypt_envelope(&rekeyed).expect("Failed to decrypt key exchange");
+    assert!(bob_dr.has_ratcheted());
+
+    println!("  ✓ Ratcheted after receiving a new DH key");
+}
+
+#[test]
+fn test_sending_key_exchange_does_not_count_as_ratchet() {
+    let (mut alice_dr, mut bob_dr) = ratchet_pair([26u8; 32]);
+    let first = alice_dr.encrypt_envelope(b"hello").expect("Failed to encrypt");
+    bob_dr.decrypt_envelope(&first).expect("Failed to decrypt");
+
+    // Bob's key exchange takes a sending step under a fresh DH key, but
+    // nothing has been received under a new peer key yet
+    let rekeyed = bob_dr.encrypt_key_exchange().expect("Failed to encrypt key exchange");
+    assert!(!bob_dr.has_ratcheted());
+    println!("  ✓ Sending a key exchange leaves has_ratcheted false");
+
+    alice_dr.decrypt_envelope(&rekeyed).expect("Failed to decrypt key exchange");
+    assert!(alice_dr.has_ratcheted());
+    println!("  ✓ The receiver of the key exchange has ratcheted");
+}
+
+#[test]
+fn test_session_has_ratcheted_ffi() {
+    let (alice_session, bob_session) = establish_ffi_sessions(1241, None);
+    let _ = encrypt_message(alice_session.clone(), b"one way".to_vec());
+
+    assert!(!session_has_ratcheted(alice_session));
+    assert!(!session_has_ratcheted(bob_session));
+    assert!(!session_has_ratcheted("unknown".to_string()));
+}
@@ -0,0 +1,44 @@
+//! Test phân tích chuỗi hex từ dữ liệu không tin cậy (không được panic)
+
+use e2ee_core::encoding::{parse_hex_32, parse_hex_64};
+use e2ee_core::error::E2EEError;
+use e2ee_core::ffi::api::{generate_identity_key_pair, generate_prekey_bundle};
+use e2ee_core::ffi::PreKeyBundleJSON;
+
+#[test]
+fn test_parse_hex_32_valid() {
+    let hex_str = "ab".repeat(32);
+    assert_eq!(parse_hex_32(&hex_str).expect("Valid hex rejected"), [0xab; 32]);
+    assert_eq!(parse_hex_64(&"cd".repeat(64)).expect("Valid hex rejected"), [0xcd; 64]);
+}
+
+#[test]
+fn test_parse_hex_32_rejects_malformed_input() {
+    let odd_length = "a".repeat(63);
+    let non_hex = "zz".repeat(32);
+    let too_short = "ab".repeat(31);
+    let too_long = "ab".repeat(33);
+
+    for input in [odd_length.as_str(), non_hex.as_str(), too_short.as_str(), too_long.as_str(), ""] {
+        match parse_hex_32(input) {
+            Err(E2EEError::SerializationError(_)) => {}
+            other => panic!("Expected SerializationError for {:?}, got {:?}", input, other),
+        }
+    }
+}
+
+#[test]
+fn test_bundle_with_malformed_one_time_prekey_hex() {
+    let bob_identity_json = generate_identity_key_pair();
+    let bundle_json = generate_prekey_bundle(bob_identity_json, 1061, Some(1062));
+    let mut bundle: PreKeyBundleJSON = serde_json::from_str(&bundle_json).expect("Invalid bundle JSON");
+
+    bundle.one_time_prekey.as_mut().expect("Bundle has no one-time prekey").public_key_hex =
+        "not-hex!".to_string();
+
+    match bundle.to_prekey_bundle() {
+        Err(E2EEError::SerializationError(_)) => {}
+        Err(e) => panic!("Expected SerializationError, got {}", e),
+        Ok(_) => panic!("Malformed one-time prekey hex must be rejected"),
+    }
+}
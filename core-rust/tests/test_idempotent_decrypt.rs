@@ -0,0 +1,85 @@
+//! Test giải mã idempotent: envelope bị gửi lại trả về cùng plaintext thay vì lỗi
+
+mod common;
+
+use common::{establish_ffi_sessions, ratchet_pair};
+use e2ee_core::ffi::api::{decrypt_message, encrypt_message, session_enable_idempotent_decrypt, session_stats};
+use e2ee_core::message::MessageEnvelope;
+use e2ee_core::ratchet::DecryptSource;
+
+fn is_error(output: &[u8]) -> bool {
+    output.starts_with(b"Error:")
+}
+
+#[test]
+fn test_duplicate_delivery_with_and_without_idempotent_decrypt() {
+    println!("\n=== Test: Idempotent Decrypt ===\n");
+
+    let (alice_session, bob_session) = establish_ffi_sessions(2011, Some(2012));
+    let envelope = encrypt_message(alice_session.clone(), b"delivered twice".to_vec());
+    assert_eq!(decrypt_message(bob_session.clone(), envelope.clone()), b"delivered twice".to_vec());
+    assert!(is_error(&decrypt_message(bob_session.clone(), envelope)));
+    println!("  ✓ Without the cache a duplicate delivery fails");
+
+    assert!(session_enable_idempotent_decrypt(bob_session.clone(), 4));
+    let envelope = encrypt_message(alice_session.clone(), b"at least once".to_vec());
+    let first = decrypt_message(bob_session.clone(), envelope.clone());
+    assert_eq!(first, b"at least once".to_vec());
+    assert_eq!(decrypt_message(bob_session.clone(), envelope.clone()), first);
+    assert_eq!(decrypt_message(bob_session.clone(), envelope), first);
+    println!("  ✓ With the cache duplicates return identical plaintext");
+
+    assert!(!session_enable_idempotent_decrypt("unknown".to_string(), 4));
+}
+
+#[test]
+fn test_duplicates_are_not_counted_as_received() {
+    println!("\n=== Test: Idempotent Decrypt Counters ===\n");
+
+    let (alice_session, bob_session) = establish_ffi_sessions(2013, None);
+    assert!(session_enable_idempotent_decrypt(bob_session.clone(), 4));
+    let envelope = encrypt_message(alice_session, b"counted once".to_vec());
+    assert_eq!(decrypt_message(bob_session.clone(), envelope.clone()), b"counted once".to_vec());
+    assert_eq!(decrypt_message(bob_session.clone(), envelope), b"counted once".to_vec());
+
+    let stats: serde_json::Value = serde_json::from_str(&session_stats(bob_session)).unwrap();
+    assert_eq!(stats["messages_received"], 1);
+    println!("  ✓ The same envelope decrypted twice counts as one received message");
+
+    let (mut alice_dr, mut bob_dr) = ratchet_pair([0xa2; 32]);
+    bob_dr.enable_idempotent_decrypt(1);
+    let m1 = alice_dr.encrypt_envelope(b"one").unwrap();
+    assert_eq!(bob_dr.decrypt_envelope_full(&m1).unwrap().source, DecryptSource::Live);
+    assert_eq!(bob_dr.decrypt_envelope_full(&m1).unwrap().source, DecryptSource::DuplicateCache);
+    println!("  ✓ Cache hits are reported as DecryptSource::DuplicateCache");
+}
+
+#[test]
+fn test_idempotent_decrypt_only_serves_identical_envelopes() {
+    println!("\n=== Test: Idempotent Decrypt Window ===\n");
+
+    let (mut alice_dr, mut bob_dr) = ratchet_pair([0xa1; 32]);
+    bob_dr.enable_idempotent_decrypt(1);
+    let m1 = alice_dr.encrypt_envelope(b"one").unwrap();
+    let m2 = alice_dr.encrypt_envelope(b"two").unwrap();
+    assert_eq!(bob_dr.decrypt_envelope(&m1).unwrap(), b"one".to_vec());
+
+    // Same DH key and message number but a different ciphertext is not a duplicate
+    let mut forged: MessageEnvelope = m1.clone();
+    forged.ciphertext[0] ^= 0x01;
+    assert!(bob_dr.decrypt_envelope(&forged).is_err());
+    assert_eq!(bob_dr.decrypt_envelope(&m1).unwrap(), b"one".to_vec());
+    println!("  ✓ Only a byte-identical envelope is served from the cache");
+
+    assert_eq!(bob_dr.decrypt_envelope(&m2).unwrap(), b"two".to_vec());
+    assert!(bob_dr.decrypt_envelope(&m1).is_err());
+    assert_eq!(bob_dr.decrypt_envelope(&m2).unwrap(), b"two".to_vec());
+    println!("  ✓ Entries beyond the window are evicted");
+
+    bob_dr.enable_idempotent_decrypt(0);
+    assert!(bob_dr.decrypt_envelope(&m2).is_err());
+    bob_dr.enable_idempotent_decrypt(2);
+    bob_dr.close();
+    assert!(bob_dr.is_wiped());
+    println!("  ✓ Window 0 disables the cache and close drops it");
+}
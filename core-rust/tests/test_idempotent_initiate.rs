@@ -0,0 +1,44 @@
+//! Test initiate_idempotent: thử lại X3DH với cùng bundle cho cùng kết quả
+
+use e2ee_core::keys::prekey::{SignedPreKey, SignedPreKeyPair};
+use e2ee_core::keys::{IdentityKeyPair, PreKeyBundle};
+use e2ee_core::x3dh::X3DHInitiator;
+
+fn bundle_for(identity: &IdentityKeyPair) -> PreKeyBundle {
+    let signed_prekey = SignedPreKeyPair::generate(1, identity).expect("Failed to generate signed prekey");
+    PreKeyBundle::new(
+        identity.public_key_hex(),
+        identity.verifying_key(),
+        SignedPreKey::from(&signed_prekey),
+        None,
+    )
+}
+
+#[test]
+fn test_idempotent_initiate_reproduces_handshake() {
+    println!("\n=== Test: Idempotent X3DH Initiation ===\n");
+
+    let alice = X3DHInitiator::new(IdentityKeyPair::generate());
+    let bob_identity = IdentityKeyPair::generate();
+    let bundle = bundle_for(&bob_identity);
+
+    let first = alice.initiate_idempotent(&bundle).expect("Failed to initiate");
+    let retry = alice.initiate_idempotent(&bundle).expect("Failed to initiate");
+    assert_eq!(first.ephemeral_public_key_hex, retry.ephemeral_public_key_hex);
+    assert_eq!(first.key_confirmation(), retry.key_confirmation());
+    assert_eq!(first.transcript_hash, retry.transcript_hash);
+    println!("  ✓ Retry reproduces ephemeral key and shared secret");
+
+    let plain_a = alice.initiate(&bundle).expect("Failed to initiate");
+    let plain_b = alice.initiate(&bundle).expect("Failed to initiate");
+    assert_ne!(plain_a.ephemeral_public_key_hex, plain_b.ephemeral_public_key_hex);
+    assert_ne!(plain_a.key_confirmation(), plain_b.key_confirmation());
+    assert_ne!(plain_a.ephemeral_public_key_hex, first.ephemeral_public_key_hex);
+    println!("  ✓ Default initiate still uses a fresh ephemeral");
+
+    // A different bundle gets its own ephemeral
+    let other = alice
+        .initiate_idempotent(&bundle_for(&IdentityKeyPair::generate()))
+        .expect("Failed to initiate");
+    assert_ne!(other.ephemeral_public_key_hex, first.ephemeral_public_key_hex);
+}
@@ -0,0 +1,49 @@
+//! Test import identity từ Ed25519 seed và X25519 scalar có sẵn (migrate từ app khác)
+
+use e2ee_core::error::E2EEError;
+use e2ee_core::keys::prekey::{SignedPreKey, SignedPreKeyPair};
+use e2ee_core::keys::{IdentityKeyPair, PreKeyBundle};
+use e2ee_core::x3dh::{X3DHInitiator, X3DHResponder};
+use ed25519_dalek::SigningKey;
+use x25519_dalek::{PublicKey, StaticSecret};
+
+#[test]
+fn test_identity_from_existing_seed_and_scalar() {
+    println!("\n=== Test: Import Identity From Seed ===\n");
+
+    let ed25519_seed = [0x21u8; 32];
+    let x25519_scalar = [0x42u8; 32];
+    let bob_identity = IdentityKeyPair::from_ed25519_seed_and_x25519(ed25519_seed, x25519_scalar)
+        .expect("Failed to import identity");
+
+    assert_eq!(bob_identity.verifying_key(), SigningKey::from_bytes(&ed25519_seed).verifying_key());
+    assert_eq!(bob_identity.public_key_bytes(), *PublicKey::from(&StaticSecret::from(x25519_scalar)).as_bytes());
+    println!("  ✓ Public keys match the ones derived from the provided material");
+
+    // A handshake against the imported identity only works if DH uses the provided scalar
+    let signed_prekey = SignedPreKeyPair::generate(1, &bob_identity).expect("Failed to generate signed prekey");
+    let bundle = PreKeyBundle::new(
+        bob_identity.public_key_hex(),
+        bob_identity.verifying_key(),
+        SignedPreKey::from(&signed_prekey),
+        None,
+    );
+    assert!(bundle.verify_signature().unwrap());
+    let alice_identity = IdentityKeyPair::generate();
+    let alice_result = X3DHInitiator::new(alice_identity.clone()).initiate(&bundle).expect("Failed to initiate X3DH");
+    let bob_result = X3DHResponder::new(bob_identity, signed_prekey)
+        .respond(&alice_identity.public_key_hex(), &alice_result.ephemeral_public_key_hex)
+        .expect("Failed to respond to X3DH");
+    assert!(bob_result.verify_key_confirmation(&alice_result.key_confirmation()).is_ok());
+    println!("  ✓ Signatures and X3DH work with the imported identity");
+
+    assert!(matches!(
+        IdentityKeyPair::from_ed25519_seed_and_x25519([0u8; 32], x25519_scalar),
+        Err(E2EEError::KeyGenerationError(_))
+    ));
+    assert!(matches!(
+        IdentityKeyPair::from_ed25519_seed_and_x25519(ed25519_seed, [0u8; 32]),
+        Err(E2EEError::KeyGenerationError(_))
+    ));
+    println!("  ✓ Weak seed or scalar is rejected");
+}
@@ -0,0 +1,42 @@
+//! Test import session từ state đã lưu, với kiểm tra vai trò (initiator/responder)
+
+mod common;
+
+use common::establish_ffi_sessions;
+use e2ee_core::ffi::api::{decrypt_message, encrypt_message, encrypt_message_with_state, import_session, session_routing_id};
+
+#[test]
+fn test_import_session_checks_asserted_role() {
+    println!("\n=== Test: Import Session Role Guard ===\n");
+
+    let (alice_session, bob_session) = establish_ffi_sessions(1881, Some(1882));
+    let result: serde_json::Value =
+        serde_json::from_str(&encrypt_message_with_state(alice_session.clone(), b"hello".to_vec())).unwrap();
+    assert_eq!(result["ok"], true);
+    let state_base64 = result["state_base64"].as_str().unwrap().to_string();
+    let routing_id = session_routing_id(alice_session);
+    assert_eq!(
+        decrypt_message(bob_session.clone(), result["envelope_base64"].as_str().unwrap().to_string()),
+        b"hello".to_vec()
+    );
+
+    let wrong_role = import_session(state_base64.clone(), routing_id.clone(), Some(false));
+    assert!(wrong_role.starts_with("Error:"), "Wrong role must fail: {}", wrong_role);
+    assert!(wrong_role.contains("role mismatch"), "Error must name the cause: {}", wrong_role);
+    assert!(wrong_role.contains("belongs to the initiator, expected the responder"), "{}", wrong_role);
+    println!("  ✓ Importing the initiator's state as responder fails clearly");
+
+    let restored = import_session(state_base64.clone(), routing_id.clone(), Some(true));
+    assert!(!restored.starts_with("Error"), "Correct role must import: {}", restored);
+    assert_eq!(session_routing_id(restored.clone()), routing_id);
+    let envelope = encrypt_message(restored.clone(), b"after import".to_vec());
+    assert_eq!(decrypt_message(bob_session.clone(), envelope), b"after import".to_vec());
+    let reply = encrypt_message(bob_session, b"reply".to_vec());
+    assert_eq!(decrypt_message(restored, reply), b"reply".to_vec());
+    println!("  ✓ Importing with the right role succeeds and decrypts");
+
+    let unchecked = import_session(state_base64, routing_id, None);
+    assert!(!unchecked.starts_with("Error"), "Import without a role must work: {}", unchecked);
+    assert!(import_session("not a state".to_string(), String::new(), None).starts_with("Error:"));
+    println!("  ✓ No asserted role skips the check, malformed state is rejected");
+}
@@ -0,0 +1,46 @@
+//! Test công cụ debug inspect_envelope (chỉ có với feature test-support)
+
+#![cfg(feature = "test-support")]
+
+mod common;
+
+use common::ratchet_pair;
+use ring::aead::{Aad, LessSafeKey, Nonce, UnboundKey, AES_256_GCM};
+
+#[test]
+fn test_inspected_nonce_matches_decryption() {
+    println!("\n=== Test: Envelope Inspection ===\n");
+
+    let (mut alice_dr, mut bob_dr) = ratchet_pair([13u8; 32]);
+    let first = alice_dr.encrypt_envelope(b"first").expect("Failed to encrypt");
+    let second = alice_dr
+        .encrypt_envelope_at(b"second", 1_700_000_000)
+        .expect("Failed to encrypt");
+
+    // Inspect the second envelope while the first is still pending
+    let inspection = bob_dr.inspect_envelope(&second).expect("Failed to inspect");
+    assert_eq!(inspection.ciphertext_len, b"second".len());
+    assert_eq!(inspection.tag_hex, hex::encode(&second.ciphertext[b"second".len()..]));
+
+    // Decrypting by hand with the reported key and nonce must succeed
+    let key_bytes = hex::decode(&inspection.expected_message_key_hex).expect("Invalid key hex");
+    let nonce_bytes = hex::decode(&inspection.derived_nonce_hex).expect("Invalid nonce hex");
+    let key = LessSafeKey::new(UnboundKey::new(&AES_256_GCM, &key_bytes).expect("Invalid key"));
+    let nonce = Nonce::try_assume_unique_for_key(&nonce_bytes).expect("Invalid nonce");
+    let mut buffer = second.ciphertext.clone();
+    let opened = key
+        .open_in_place(nonce, Aad::from(second.header.associated_data()), &mut buffer)
+        .expect("Reported nonce/key must open the envelope");
+    assert_eq!(opened, b"second");
+    println!("  ✓ Reported nonce and key open the envelope");
+
+    // Inspection leaves the ratchet untouched
+    assert_eq!(bob_dr.decrypt_envelope(&first).expect("Failed to decrypt"), b"first".to_vec());
+    assert_eq!(
+        bob_dr.inspect_envelope(&second).expect("Failed to inspect"),
+        inspection,
+        "Skipped-key lookup must report the same values"
+    );
+    assert_eq!(bob_dr.decrypt_envelope(&second).expect("Failed to decrypt"), b"second".to_vec());
+    println!("  ✓ Inspection does not consume the chain");
+}
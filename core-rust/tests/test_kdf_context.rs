@@ -0,0 +1,34 @@
+//! Test context ứng dụng tách biệt key schedule giữa các ứng dụng
+
+mod common;
+
+use common::ratchet_pair;
+use e2ee_core::ratchet::DoubleRatchet;
+
+#[test]
+fn test_different_contexts_cannot_decrypt_each_other() {
+    println!("\n=== Test: Application Context Separation ===\n");
+
+    let secret = [19u8; 32];
+    let mut alice_dr = DoubleRatchet::from_shared_secret_with_context(&secret, true, b"app-one")
+        .expect("Failed to create Alice's Double Ratchet");
+    let mut bob_same = DoubleRatchet::from_shared_secret_with_context(&secret, false, b"app-one")
+        .expect("Failed to create Bob's Double Ratchet");
+    let mut bob_other = DoubleRatchet::from_shared_secret_with_context(&secret, false, b"app-two")
+        .expect("Failed to create Bob's Double Ratchet");
+
+    let envelope = alice_dr.encrypt_envelope(b"context bound").expect("Failed to encrypt");
+    assert!(bob_other.decrypt_envelope(&envelope).is_err(), "Different context must not decrypt");
+    assert_eq!(bob_same.decrypt_envelope(&envelope).expect("Failed to decrypt"), b"context bound".to_vec());
+    println!("  ✓ Only the matching context decrypts");
+}
+
+#[test]
+fn test_empty_context_matches_default_key_schedule() {
+    let (mut alice_dr, _) = ratchet_pair([19u8; 32]);
+    let mut bob_dr = DoubleRatchet::from_shared_secret_with_context(&[19u8; 32], false, b"")
+        .expect("Failed to create Bob's Double Ratchet");
+
+    let envelope = alice_dr.encrypt_envelope(b"default").expect("Failed to encrypt");
+    assert_eq!(bob_dr.decrypt_envelope(&envelope).expect("Failed to decrypt"), b"default".to_vec());
+}
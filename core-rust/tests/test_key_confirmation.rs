@@ -0,0 +1,59 @@
+//! Test MAC xác nhận khóa (key confirmation) cho tin nhắn đầu tiên
+
+use e2ee_core::error::E2EEError;
+use e2ee_core::keys::{IdentityKeyPair, PreKeyBundle};
+use e2ee_core::keys::prekey::{SignedPreKey, SignedPreKeyPair};
+use e2ee_core::x3dh::{X3DHInitiator, X3DHResponder};
+
+#[test]
+fn test_key_confirmation_detects_mismatched_shared_secret() {
+    println!("\n=== Test: Key Confirmation ===\n");
+
+    let alice_identity = IdentityKeyPair::generate();
+    let bob_identity = IdentityKeyPair::generate();
+    let bob_signed_prekey = SignedPreKeyPair::generate(1, &bob_identity)
+        .expect("Failed to generate signed prekey");
+
+    let prekey_bundle = PreKeyBundle::new(
+        bob_identity.public_key_hex(),
+        bob_identity.verifying_key(),
+        SignedPreKey::from(&bob_signed_prekey),
+        None,
+    );
+
+    let alice = X3DHInitiator::new(alice_identity.clone());
+    let alice_result = alice.initiate(&prekey_bundle).expect("Failed to initiate X3DH");
+    let mac = alice_result.key_confirmation();
+
+    let bob = X3DHResponder::new(bob_identity.clone(), bob_signed_prekey);
+    let bob_result = bob
+        .respond(&alice_identity.public_key_hex(), &alice_result.ephemeral_public_key_hex)
+        .expect("Failed to respond to X3DH");
+    bob_result.verify_key_confirmation(&mac).expect("Matching handshake must confirm");
+    println!("  ✓ Matching handshake passes key confirmation");
+
+    // Bob answers with a different signed prekey than the one Alice used,
+    // as after a prekey swap: the DH still succeeds but the secrets differ
+    let other_signed_prekey = SignedPreKeyPair::generate(2, &bob_identity)
+        .expect("Failed to generate signed prekey");
+    let swapped_bob = X3DHResponder::new(bob_identity, other_signed_prekey);
+    let swapped_result = swapped_bob
+        .respond(&alice_identity.public_key_hex(), &alice_result.ephemeral_public_key_hex)
+        .expect("Failed to respond to X3DH");
+
+    match swapped_result.verify_key_confirmation(&mac) {
+        Err(E2EEError::KeyConfirmationFailed) => {}
+        Err(e) => panic!("Expected KeyConfirmationFailed, got {}", e),
+        Ok(()) => panic!("Mismatched shared secret must fail key confirmation"),
+    }
+    println!("  ✓ Mismatched shared secret fails with KeyConfirmationFailed");
+
+    // A corrupted MAC is rejected as well
+    let mut corrupted = mac;
+    corrupted[0] ^= 0x01;
+    assert!(matches!(
+        bob_result.verify_key_confirmation(&corrupted),
+        Err(E2EEError::KeyConfirmationFailed)
+    ));
+    println!("  ✓ Corrupted MAC is rejected");
+}
@@ -0,0 +1,77 @@
+//! Test envelope KeyExchange: rekey tường minh không cần data message
+
+mod common;
+
+use common::ratchet_pair;
+use e2ee_core::error::E2EEError;
+use e2ee_core::message::MessageType;
+use e2ee_core::ratchet::DoubleRatchet;
+
+#[test]
+fn test_key_exchange_triggers_dh_ratchet() {
+    println!("\n=== Test: Key Exchange Triggers DH Ratchet ===\n");
+
+    let (mut alice_dr, mut bob_dr) = ratchet_pair([0x75; 32]);
+    let first = alice_dr.encrypt_envelope(b"hello").unwrap();
+    assert_eq!(bob_dr.decrypt_envelope(&first).unwrap(), b"hello".to_vec());
+    let alice_key_before = alice_dr.public_state().our_dh_public;
+    let bob_key_before = bob_dr.public_state().our_dh_public;
+
+    let key_exchange = bob_dr.encrypt_key_exchange().unwrap();
+    assert_eq!(key_exchange.message_type, MessageType::KeyExchange);
+    assert_ne!(key_exchange.header.dh_public_key, bob_key_before);
+    println!("  ✓ Key exchange announces a fresh DH key");
+
+    assert!(!alice_dr.has_ratcheted());
+    assert!(alice_dr.decrypt_envelope(&key_exchange).unwrap().is_empty());
+    assert!(alice_dr.has_ratcheted());
+    let alice_state = alice_dr.public_state();
+    assert_eq!(alice_state.remote_dh_public.as_deref(), Some(key_exchange.header.dh_public_key.as_str()));
+    assert_ne!(alice_state.our_dh_public, alice_key_before);
+    println!("  ✓ Receiver performed a DH ratchet onto the announced key");
+
+    // Data messages now run on the new chains, in both directions
+    let data = alice_dr.encrypt_envelope(b"on the new chain").unwrap();
+    assert_eq!(data.header.dh_public_key, alice_state.our_dh_public);
+    assert_eq!(bob_dr.decrypt_envelope(&data).unwrap(), b"on the new chain".to_vec());
+    let reply = bob_dr.encrypt_envelope(b"reply").unwrap();
+    assert_ne!(reply.header.dh_public_key, key_exchange.header.dh_public_key);
+    assert_eq!(alice_dr.decrypt_envelope(&reply).unwrap(), b"reply".to_vec());
+    println!("  ✓ Subsequent data messages use the new chains");
+
+    // A later key exchange announces the current key without another forced step
+    let again = alice_dr.encrypt_key_exchange().unwrap();
+    assert_eq!(again.header.dh_public_key, alice_dr.public_state().our_dh_public);
+    assert!(bob_dr.decrypt_envelope(&again).unwrap().is_empty());
+    println!("  ✓ Repeated key exchanges stay in sync");
+}
+
+#[test]
+fn test_key_exchange_type_is_authenticated() {
+    println!("\n=== Test: Key Exchange Type Is Authenticated ===\n");
+
+    let (mut alice_dr, mut bob_dr) = ratchet_pair([0x76; 32]);
+    match alice_dr.encrypt_key_exchange() {
+        Err(E2EEError::StateError(_)) => {}
+        other => panic!("Expected StateError before hearing from the peer, got {:?}", other),
+    }
+    println!("  ✓ Key exchange needs the peer's DH key first");
+
+    let first = alice_dr.encrypt_envelope(b"hello").unwrap();
+    bob_dr.decrypt_envelope(&first).unwrap();
+    let key_exchange = bob_dr.encrypt_key_exchange().unwrap();
+
+    // A failed decrypt can burn chain positions, so try the forgery on a copy
+    let mut alice_copy = DoubleRatchet::from_state(&alice_dr.to_state().unwrap()).unwrap();
+    let mut relabelled = key_exchange.clone();
+    relabelled.message_type = MessageType::Regular;
+    assert!(alice_copy.decrypt_envelope(&relabelled).is_err());
+
+    let mut with_body = key_exchange.clone();
+    with_body.ciphertext.insert(0, 0);
+    assert!(matches!(alice_dr.decrypt_envelope(&with_body), Err(E2EEError::ProtocolError(_))));
+    println!("  ✓ Relabelled or non-empty key exchanges are rejected");
+
+    assert!(alice_dr.decrypt_envelope(&key_exchange).unwrap().is_empty());
+    println!("  ✓ Genuine key exchange still applies afterwards");
+}
@@ -0,0 +1,37 @@
+//! Test giới hạn key id của prekey (0 được dành riêng, tối đa 2^24)
+
+use e2ee_core::error::E2EEError;
+use e2ee_core::ffi::api::{generate_identity_key_pair, generate_prekey_bundle};
+use e2ee_core::keys::{IdentityKeyPair, OneTimePreKeyPair, SignedPreKeyPair, MAX_KEY_ID};
+
+#[test]
+fn test_key_id_zero_and_out_of_range_rejected() {
+    println!("\n=== Test: Prekey Id Range ===\n");
+
+    let identity = IdentityKeyPair::generate();
+    for invalid in [0, MAX_KEY_ID + 1, u32::MAX] {
+        assert!(matches!(
+            SignedPreKeyPair::generate(invalid, &identity),
+            Err(E2EEError::KeyGenerationError(_))
+        ));
+        assert!(matches!(
+            OneTimePreKeyPair::generate(invalid),
+            Err(E2EEError::KeyGenerationError(_))
+        ));
+    }
+    println!("  ✓ Id 0 and ids above 2^24 are rejected");
+
+    for valid in [1, 1641, MAX_KEY_ID] {
+        let signed_prekey = SignedPreKeyPair::generate(valid, &identity).expect("Valid id must be accepted");
+        assert_eq!(signed_prekey.key_id(), valid);
+        let one_time_prekey = OneTimePreKeyPair::generate(valid).expect("Valid id must be accepted");
+        assert_eq!(one_time_prekey.key_id(), valid);
+    }
+    println!("  ✓ Ids 1..=2^24 are accepted");
+}
+
+#[test]
+fn test_bundle_with_reserved_one_time_id_fails() {
+    let bundle_json = generate_prekey_bundle(generate_identity_key_pair(), 1642, Some(0));
+    assert!(bundle_json.contains("error"), "Unexpected bundle: {}", bundle_json);
+}
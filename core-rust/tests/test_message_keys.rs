@@ -0,0 +1,33 @@
+//! Test tách khóa mã hóa và khóa xác thực cho mỗi tin nhắn
+
+mod common;
+
+use common::ratchet_pair;
+use e2ee_core::ratchet::Chain;
+
+#[test]
+fn test_encryption_and_auth_keys_are_distinct() {
+    println!("\n=== Test: Separate Message Keys ===\n");
+
+    let mut chain = Chain::new([25u8; 32]);
+    let (first_encryption, first_auth) = chain.ratchet_forward().expect("Failed to ratchet");
+    let (second_encryption, second_auth) = chain.ratchet_forward().expect("Failed to ratchet");
+
+    assert_ne!(first_encryption, first_auth);
+    assert_ne!(second_encryption, second_auth);
+    assert_ne!(first_encryption, second_encryption);
+    assert_ne!(first_auth, second_auth);
+    println!("  ✓ Encryption and auth keys differ, and change every message");
+
+    // Same chain key, same pair
+    let (replayed_encryption, replayed_auth) = Chain::new([25u8; 32]).ratchet_forward().expect("Failed to ratchet");
+    assert_eq!((replayed_encryption, replayed_auth), (first_encryption, first_auth));
+
+    let (mut alice_dr, mut bob_dr) = ratchet_pair([25u8; 32]);
+    for i in 0..3 {
+        let plaintext = format!("message {}", i).into_bytes();
+        let envelope = alice_dr.encrypt_envelope(&plaintext).expect("Failed to encrypt");
+        assert_eq!(bob_dr.decrypt_envelope(&envelope).expect("Failed to decrypt"), plaintext);
+    }
+    println!("  ✓ Encryption/decryption still round-trips");
+}
@@ -0,0 +1,28 @@
+//! Test vector cố định cho nonce suy ra từ số thứ tự tin nhắn (thứ tự byte little-endian)
+
+use e2ee_core::ratchet::{DoubleRatchet, Endian, NONCE_MESSAGE_NUMBER_ENDIAN};
+
+#[test]
+fn test_nonce_known_answer() {
+    println!("\n=== Test: Nonce Derivation KAT ===\n");
+
+    assert_eq!(NONCE_MESSAGE_NUMBER_ENDIAN, Endian::Little);
+
+    // HMAC-SHA256(0x42 * 32, 01 00 00 00 00 00 00 00)[..12]
+    let auth_key = [0x42u8; 32];
+    let nonce = DoubleRatchet::derive_nonce(&auth_key, 1).expect("Failed to derive nonce");
+    assert_eq!(hex::encode(nonce), "ace4d74c3c6973e132c39a04");
+    println!("  ✓ Nonce for message 1 matches the known-answer vector");
+
+    // The big-endian encoding would give HMAC(.., 00 .. 00 01) instead
+    assert_ne!(hex::encode(nonce), "d5863dc95b5dc081c06eb7c5");
+    println!("  ✓ Big-endian encoding yields a different nonce");
+}
+
+#[test]
+fn test_nonce_differs_per_message_number() {
+    let auth_key = [0x42u8; 32];
+    let first = DoubleRatchet::derive_nonce(&auth_key, 1).expect("Failed to derive nonce");
+    let second = DoubleRatchet::derive_nonce(&auth_key, 2).expect("Failed to derive nonce");
+    assert_ne!(first, second);
+}
@@ -0,0 +1,53 @@
+//! Test phát hiện dùng lại cặp (key, nonce) của AEAD trong bản build debug
+#![cfg(all(feature = "test-support", debug_assertions))]
+
+mod common;
+
+use common::ratchet_pair;
+use e2ee_core::error::E2EEError;
+
+#[test]
+fn test_forced_nonce_reuse_is_caught() {
+    println!("\n=== Test: Nonce Reuse Guard ===\n");
+
+    let (mut alice_dr, mut bob_dr) = ratchet_pair([0xa2; 32]);
+    let checkpoint = alice_dr.sending_chain_checkpoint();
+    let first = alice_dr.encrypt_envelope(b"first").unwrap();
+    assert_eq!(bob_dr.decrypt_envelope(&first).unwrap(), b"first".to_vec());
+
+    // A rewound sending chain derives the same key and nonce again
+    alice_dr.rewind_sending_chain(&checkpoint);
+    match alice_dr.encrypt_envelope(b"second") {
+        Err(E2EEError::CryptoError(msg)) => assert_eq!(msg, "AEAD key/nonce reuse detected"),
+        other => panic!("Expected CryptoError for nonce reuse, got {:?}", other),
+    }
+    println!("  ✓ Sealing twice under the same key and nonce is refused");
+
+    // Fresh positions of the chain still seal normally
+    let next = alice_dr.encrypt_envelope(b"next").unwrap();
+    assert_eq!(next.header.message_number, 2);
+    assert_eq!(bob_dr.decrypt_envelope(&next).unwrap(), b"next".to_vec());
+    println!("  ✓ Later messages on new keys are unaffected");
+}
+
+#[test]
+fn test_guard_follows_the_current_sending_chain() {
+    println!("\n=== Test: Nonce Reuse Guard Across DH Ratchets ===\n");
+
+    let (mut alice_dr, mut bob_dr) = ratchet_pair([0xa3; 32]);
+    let first = alice_dr.encrypt_envelope(b"first").unwrap();
+    bob_dr.decrypt_envelope(&first).unwrap();
+    let reply = bob_dr.encrypt_envelope(b"reply").unwrap();
+    alice_dr.decrypt_envelope(&reply).unwrap();
+    println!("  ✓ Alice moved to a new sending chain");
+
+    // The guard was reset for the new chain and still catches reuse on it
+    let checkpoint = alice_dr.sending_chain_checkpoint();
+    alice_dr.encrypt_envelope(b"on the new chain").unwrap();
+    alice_dr.rewind_sending_chain(&checkpoint);
+    assert!(matches!(
+        alice_dr.encrypt_envelope(b"again"),
+        Err(E2EEError::CryptoError(_))
+    ));
+    println!("  ✓ Reuse on the new chain is refused");
+}
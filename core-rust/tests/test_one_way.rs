@@ -0,0 +1,66 @@
+//! Test ratchet một chiều (không DH) cho kênh broadcast/notification
+
+use e2ee_core::error::E2EEError;
+use e2ee_core::message::MessageEnvelope;
+use e2ee_core::ratchet::DoubleRatchet;
+
+#[test]
+fn test_one_way_sender_pushes_many_messages() {
+    println!("\n=== Test: One-Way Ratchet Pushes 1000 Messages ===\n");
+
+    let shared_secret = [0x1f; 32];
+    let mut sender = DoubleRatchet::one_way_sender(&shared_secret).unwrap();
+    let mut receiver = DoubleRatchet::one_way_receiver(&shared_secret).unwrap();
+
+    let mut first = None;
+    for i in 0..1000 {
+        let plaintext = format!("notification {}", i);
+        let envelope = sender.encrypt_envelope(plaintext.as_bytes()).unwrap();
+        assert!(envelope.header.dh_public_key.is_empty());
+        let json = serde_json::to_value(&envelope).unwrap();
+        assert!(json["header"].get("dh_public_key").is_none(), "Envelope carries a DH key: {}", json);
+
+        let b64 = envelope.to_base64().unwrap();
+        let parsed = MessageEnvelope::from_base64(&b64).unwrap();
+        assert_eq!(receiver.decrypt_envelope(&parsed).unwrap(), plaintext.into_bytes());
+        if first.is_none() {
+            first = Some(envelope);
+        }
+    }
+    assert_eq!(sender.message_number(), 1000);
+    println!("  ✓ 1000 messages delivered, none carrying a DH key");
+
+    // Forward secrecy: a used key is gone, so an old envelope no longer decrypts
+    match receiver.decrypt_envelope(&first.unwrap()) {
+        Err(E2EEError::ProtocolError(_)) => {}
+        other => panic!("Expected protocol error for a replayed message, got {:?}", other),
+    }
+    assert_eq!(receiver.skipped_key_count(), 0);
+    println!("  ✓ Used message keys are discarded");
+}
+
+#[test]
+fn test_one_way_out_of_order_and_mismatch() {
+    println!("\n=== Test: One-Way Ratchet Out Of Order ===\n");
+
+    let mut sender = DoubleRatchet::one_way_sender(&[0x2a; 32]).unwrap();
+    let mut receiver = DoubleRatchet::one_way_receiver(&[0x2a; 32]).unwrap();
+    let envelopes: Vec<_> = (0..3).map(|i| sender.encrypt_envelope(&[i]).unwrap()).collect();
+
+    assert_eq!(receiver.decrypt_envelope(&envelopes[2]).unwrap(), vec![2]);
+    assert_eq!(receiver.decrypt_envelope(&envelopes[0]).unwrap(), vec![0]);
+    assert_eq!(receiver.decrypt_envelope(&envelopes[1]).unwrap(), vec![1]);
+    println!("  ✓ Out-of-order messages decrypt from skipped keys");
+
+    // A regular Double Ratchet envelope is refused
+    let mut alice = DoubleRatchet::from_shared_secret(&[0x2a; 32], true).unwrap();
+    let regular = alice.encrypt_envelope(b"two-way").unwrap();
+    assert!(matches!(receiver.decrypt_envelope(&regular), Err(E2EEError::ProtocolError(_))));
+    println!("  ✓ Envelope with a DH key is rejected");
+
+    // A different secret yields a different chain
+    let mut stranger = DoubleRatchet::one_way_receiver(&[0x2b; 32]).unwrap();
+    let envelope = sender.encrypt_envelope(b"secret").unwrap();
+    assert!(stranger.decrypt_envelope(&envelope).is_err());
+    println!("  ✓ Receiver with another secret cannot decrypt");
+}
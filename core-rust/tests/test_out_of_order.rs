@@ -0,0 +1,288 @@
+//! Test nhận tin nhắn không theo thứ tự và báo cáo các tin nhắn bị thiếu
+
+mod common;
+
+use common::{establish_ffi_sessions, ratchet_pair};
+use e2ee_core::ffi::api::{decrypt_message, encrypt_message, session_has_received, session_missing_messages};
+use e2ee_core::ratchet::{DecryptSource, DoubleRatchet, MAX_SKIP};
+use x25519_dalek::{PublicKey, StaticSecret};
+
+#[test]
+fn test_missing_messages_reported_and_filled() {
+    println!("\n=== Test: Sequence Gap Reporting ===\n");
+
+    let (mut alice_dr, mut bob_dr) = ratchet_pair([9u8; 32]);
+    let envelopes: Vec<_> = (1..=4)
+        .map(|i| {
+            alice_dr
+                .encrypt_envelope(format!("Message {}", i).as_bytes())
+                .expect("Failed to encrypt")
+        })
+        .collect();
+
+    for index in [0, 1, 3] {
+        let dec = bob_dr.decrypt_envelope(&envelopes[index]).expect("Failed to decrypt");
+        assert_eq!(dec, format!("Message {}", index + 1).into_bytes());
+    }
+    assert_eq!(bob_dr.received_numbers(), vec![1, 2, 4]);
+    assert_eq!(bob_dr.missing_before(5), vec![3]);
+    println!("  ✓ Gap at message 3 reported");
+
+    let dec3 = bob_dr.decrypt_envelope(&envelopes[2]).expect("Failed to decrypt late message");
+    assert_eq!(dec3, b"Message 3".to_vec());
+    assert_eq!(bob_dr.received_numbers(), vec![1, 2, 3, 4]);
+    assert!(bob_dr.missing_before(5).is_empty());
+    println!("  ✓ Late message decrypted and gap closed");
+}
+
+#[test]
+fn test_replayed_message_rejected() {
+    let (mut alice_dr, mut bob_dr) = ratchet_pair([9u8; 32]);
+    let env = alice_dr.encrypt_envelope(b"once").expect("Failed to encrypt");
+
+    bob_dr.decrypt_envelope(&env).expect("Failed to decrypt");
+    assert!(bob_dr.decrypt_envelope(&env).is_err(), "Replay must be rejected");
+}
+
+#[test]
+fn test_skip_beyond_max_rejected() {
+    let (mut alice_dr, mut bob_dr) = ratchet_pair([9u8; 32]);
+    let mut env = alice_dr.encrypt_envelope(b"far ahead").expect("Failed to encrypt");
+    env.header.message_number = MAX_SKIP + 2;
+
+    assert!(bob_dr.decrypt_envelope(&env).is_err());
+    assert!(bob_dr.received_numbers().is_empty());
+}
+
+#[test]
+fn test_session_missing_messages_ffi() {
+    let (alice_session, bob_session) = establish_ffi_sessions(1051, Some(1052));
+
+    let envelopes: Vec<String> = (1..=3)
+        .map(|i| encrypt_message(alice_session.clone(), format!("m{}", i).into_bytes()))
+        .collect();
+    assert_eq!(decrypt_message(bob_session.clone(), envelopes[2].clone()), b"m3".to_vec());
+
+    assert_eq!(session_missing_messages(bob_session.clone(), 4), vec![1, 2]);
+    assert!(session_missing_messages("unknown".to_string(), 4).is_empty());
+}
+
+#[test]
+fn test_header_number_selects_key_after_lost_message() {
+    let (mut alice_dr, mut bob_dr) = ratchet_pair([21u8; 32]);
+    let m1 = alice_dr.encrypt_envelope(b"one").expect("Failed to encrypt");
+    let _lost = alice_dr.encrypt_envelope(b"two").expect("Failed to encrypt");
+    let m3 = alice_dr.encrypt_envelope(b"three").expect("Failed to encrypt");
+
+    assert_eq!(bob_dr.decrypt_envelope(&m1).expect("Failed to decrypt"), b"one".to_vec());
+    // Chain is at position 1 but the header says 3: the key must come from the header number
+    let dec3 = bob_dr.decrypt_envelope_full(&m3).expect("Failed to decrypt after lost message");
+    assert_eq!(dec3.plaintext, b"three".to_vec());
+    assert_eq!(dec3.message_number, 3);
+    assert_eq!(bob_dr.missing_before(4), vec![2]);
+}
+
+#[test]
+fn test_skip_send_burns_message_numbers() {
+    let (mut alice_dr, mut bob_dr) = ratchet_pair([23u8; 32]);
+    assert_eq!(alice_dr.skip_send().expect("Failed to skip"), 1);
+    assert_eq!(alice_dr.skip_send().expect("Failed to skip"), 2);
+
+    let envelope = alice_dr.encrypt_envelope(b"after skips").expect("Failed to encrypt");
+    assert_eq!(envelope.header.message_number, 3);
+    assert_eq!(bob_dr.decrypt_envelope(&envelope).expect("Failed to decrypt"), b"after skips".to_vec());
+    assert_eq!(bob_dr.missing_before(4), vec![1, 2]);
+}
+
+#[test]
+fn test_late_message_after_reply_decrypts() {
+    let (mut alice_dr, mut bob_dr) = ratchet_pair([29u8; 32]);
+    let sent: Vec<_> = (1..=3)
+        .map(|i| alice_dr.encrypt_envelope(format!("a{}", i).as_bytes()).expect("Failed to encrypt"))
+        .collect();
+
+    bob_dr.decrypt_envelope(&sent[0]).expect("Failed to decrypt");
+    bob_dr.decrypt_envelope(&sent[2]).expect("Failed to decrypt");
+
+    let reply = bob_dr.encrypt_envelope(b"reply").expect("Failed to encrypt");
+    assert_eq!(alice_dr.decrypt_envelope(&reply).expect("Failed to decrypt"), b"reply".to_vec());
+
+    assert_eq!(bob_dr.decrypt_envelope(&sent[1]).expect("Failed to decrypt late message"), b"a2".to_vec());
+}
+
+/// Create an (Alice, Bob) pair whose first message already runs a DH ratchet on Bob
+fn x3dh_ratchet_pair(seed: u8) -> (DoubleRatchet, DoubleRatchet) {
+    let bob_ratchet_key = StaticSecret::from([seed; 32]);
+    let alice_dr = DoubleRatchet::from_x3dh_initiator(&[seed + 1; 32], PublicKey::from(&bob_ratchet_key).as_bytes())
+        .expect("Failed to create Alice's Double Ratchet");
+    let bob_dr = DoubleRatchet::from_x3dh_responder(&[seed + 1; 32], &bob_ratchet_key)
+        .expect("Failed to create Bob's Double Ratchet");
+    (alice_dr, bob_dr)
+}
+
+#[test]
+fn test_previous_chain_length_fills_old_chain_on_dh_ratchet() {
+    let (mut alice_dr, mut bob_dr) = x3dh_ratchet_pair(27);
+    let sent: Vec<_> = (1..=3)
+        .map(|i| alice_dr.encrypt_envelope(format!("a{}", i).as_bytes()).expect("Failed to encrypt"))
+        .collect();
+    bob_dr.decrypt_envelope(&sent[0]).expect("Failed to decrypt");
+    let reply = bob_dr.encrypt_envelope(b"reply").expect("Failed to encrypt");
+    alice_dr.decrypt_envelope(&reply).expect("Failed to decrypt reply");
+
+    // Alice's next message is under a new DH key and announces pn = 3
+    let rekeyed = alice_dr.encrypt_envelope(b"new chain").expect("Failed to encrypt");
+    assert_eq!(rekeyed.header.previous_chain_length, 3);
+    assert_eq!(bob_dr.decrypt_envelope(&rekeyed).expect("Failed to decrypt"), b"new chain".to_vec());
+
+    // Messages 2 and 3 of the old chain come from the skipped-key store
+    assert_eq!(bob_dr.decrypt_envelope(&sent[2]).expect("Failed to decrypt"), b"a3".to_vec());
+    assert_eq!(bob_dr.decrypt_envelope(&sent[1]).expect("Failed to decrypt"), b"a2".to_vec());
+}
+
+#[test]
+fn test_forged_new_dh_envelope_leaves_ratchet_untouched() {
+    println!("\n=== Test: Forged DH Ratchet Envelope ===\n");
+
+    let (mut alice_dr, mut bob_dr) = x3dh_ratchet_pair(25);
+    let first = alice_dr.encrypt_envelope(b"first").expect("Failed to encrypt");
+    bob_dr.decrypt_envelope(&first).expect("Failed to decrypt");
+    let reply = bob_dr.encrypt_envelope(b"reply").expect("Failed to encrypt");
+    alice_dr.decrypt_envelope(&reply).expect("Failed to decrypt reply");
+    let before = bob_dr.public_state();
+
+    // Random DH key and a large pn: without staging this would fill the store,
+    // advance the root key and retire Alice's genuine key before the AEAD fails
+    let mut forger = DoubleRatchet::from_shared_secret(&[26u8; 32], true)
+        .expect("Failed to create Double Ratchet");
+    let mut forged = forger.encrypt_envelope(b"forged").expect("Failed to encrypt");
+    forged.header.previous_chain_length = 50;
+    forged.header.role_hint = None;
+    assert!(bob_dr.decrypt_envelope(&forged).is_err());
+    assert_eq!(bob_dr.public_state(), before);
+    println!("  ✓ Forged envelope rejected without moving the ratchet");
+
+    let next = alice_dr.encrypt_envelope(b"next").expect("Failed to encrypt");
+    assert_eq!(bob_dr.decrypt_envelope(&next).expect("Failed to decrypt"), b"next".to_vec());
+    let answer = bob_dr.encrypt_envelope(b"answer").expect("Failed to encrypt");
+    assert_eq!(alice_dr.decrypt_envelope(&answer).expect("Failed to decrypt"), b"answer".to_vec());
+    println!("  ✓ Session keeps working in both directions");
+}
+
+#[test]
+fn test_interleaved_messages_from_three_dh_epochs() {
+    println!("\n=== Test: Out-of-Order Across DH Epochs ===\n");
+
+    let (mut alice_dr, mut bob_dr) = x3dh_ratchet_pair(31);
+
+    // Each epoch: Alice sends three messages, Bob reads the first and replies,
+    // which moves Alice to a new DH key for the next epoch
+    let mut epochs = Vec::new();
+    for epoch in 0..3 {
+        let sent: Vec<_> = (1..=3)
+            .map(|i| alice_dr.encrypt_envelope(format!("e{} m{}", epoch, i).as_bytes()).expect("Failed to encrypt"))
+            .collect();
+        if epoch < 2 {
+            bob_dr.decrypt_envelope(&sent[0]).expect("Failed to decrypt");
+            let reply = bob_dr.encrypt_envelope(b"ack").expect("Failed to encrypt");
+            alice_dr.decrypt_envelope(&reply).expect("Failed to decrypt reply");
+        }
+        epochs.push(sent);
+    }
+    assert_ne!(epochs[0][0].header.dh_public_key, epochs[1][0].header.dh_public_key);
+    assert_ne!(epochs[1][0].header.dh_public_key, epochs[2][0].header.dh_public_key);
+
+    // Newest epoch first, then the rest interleaved across all three DH keys
+    for (epoch, i) in [(2, 2), (0, 2), (2, 0), (1, 1), (0, 1), (2, 1), (1, 2)] {
+        let plaintext = bob_dr.decrypt_envelope(&epochs[epoch][i]).expect("Failed to decrypt");
+        assert_eq!(plaintext, format!("e{} m{}", epoch, i + 1).into_bytes());
+    }
+    println!("  ✓ Messages from three DH epochs decrypt in any order");
+
+    // A replay from an old epoch is refused without disturbing the session
+    assert!(bob_dr.decrypt_envelope(&epochs[0][1]).is_err());
+    let next = alice_dr.encrypt_envelope(b"after replay").expect("Failed to encrypt");
+    assert_eq!(bob_dr.decrypt_envelope(&next).expect("Failed to decrypt"), b"after replay".to_vec());
+    println!("  ✓ Replayed old-epoch message does not trigger a DH ratchet");
+}
+
+/// Skipped-key store entries of a ratchet, as sorted (DH key, message number) pairs
+fn stored_keys(dr: &DoubleRatchet) -> Vec<(String, u64)> {
+    let state = serde_json::to_value(dr.to_state().expect("Failed to snapshot")).unwrap();
+    let mut keys: Vec<(String, u64)> = state["skipped_message_keys"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|stored| {
+            (
+                stored["remote_dh_public_hex"].as_str().unwrap().to_string(),
+                stored["message_number"].as_u64().unwrap(),
+            )
+        })
+        .collect();
+    keys.sort();
+    keys
+}
+
+#[test]
+fn test_tampered_envelope_for_retired_key_leaves_store_unchanged() {
+    println!("\n=== Test: Tampered Envelope For Retired Key ===\n");
+
+    let (mut alice_dr, mut bob_dr) = x3dh_ratchet_pair(23);
+    let sent: Vec<_> = (1..=3)
+        .map(|i| alice_dr.encrypt_envelope(format!("a{}", i).as_bytes()).expect("Failed to encrypt"))
+        .collect();
+    bob_dr.decrypt_envelope(&sent[0]).expect("Failed to decrypt");
+    let reply = bob_dr.encrypt_envelope(b"reply").expect("Failed to encrypt");
+    alice_dr.decrypt_envelope(&reply).expect("Failed to decrypt reply");
+    let rekeyed = alice_dr.encrypt_envelope(b"new chain").expect("Failed to encrypt");
+    bob_dr.decrypt_envelope(&rekeyed).expect("Failed to decrypt");
+
+    // Alice's first DH key is retired now; messages 2 and 3 wait in the store
+    let retired_key = sent[1].header.dh_public_key.clone();
+    let before = stored_keys(&bob_dr);
+    assert_eq!(before, vec![(retired_key.clone(), 2), (retired_key.clone(), 3)]);
+
+    let mut tampered = sent[1].clone();
+    let last = tampered.ciphertext.len() - 1;
+    tampered.ciphertext[last] ^= 0x01;
+    assert!(bob_dr.decrypt_envelope(&tampered).is_err());
+    assert_eq!(stored_keys(&bob_dr), before);
+    println!("  ✓ Tampered copy of a stored message leaves its key in place");
+
+    let mut unknown_number = sent[1].clone();
+    unknown_number.header.message_number = 9;
+    assert!(bob_dr.decrypt_envelope(&unknown_number).is_err());
+    assert_eq!(stored_keys(&bob_dr), before);
+    println!("  ✓ Retired key with an unknown message number changes nothing");
+
+    let (plaintext, source) = bob_dr.decrypt_envelope_with_meta(&sent[1]).expect("Failed to decrypt");
+    assert_eq!(plaintext, b"a2".to_vec());
+    assert_eq!(source, DecryptSource::SkippedStore);
+    assert_eq!(stored_keys(&bob_dr), vec![(retired_key, 3)]);
+    println!("  ✓ Genuine message still decrypts and consumes its key");
+}
+
+#[test]
+fn test_has_received_tracks_decrypted_numbers() {
+    println!("\n=== Test: Has Received ===\n");
+
+    let (alice_session, bob_session) = establish_ffi_sessions(1951, Some(1952));
+    let envelopes: Vec<String> = (1..=3)
+        .map(|i| encrypt_message(alice_session.clone(), format!("m{}", i).into_bytes()))
+        .collect();
+    assert_eq!(decrypt_message(bob_session.clone(), envelopes[0].clone()), b"m1".to_vec());
+    assert_eq!(decrypt_message(bob_session.clone(), envelopes[2].clone()), b"m3".to_vec());
+
+    assert!(session_has_received(bob_session.clone(), 1));
+    assert!(session_has_received(bob_session.clone(), 3));
+    assert!(!session_has_received(bob_session.clone(), 2));
+    assert!(!session_has_received(bob_session.clone(), 0));
+    assert!(!session_has_received(bob_session.clone(), 4));
+    println!("  ✓ Messages 1 and 3 received, 2 still missing");
+
+    assert_eq!(decrypt_message(bob_session.clone(), envelopes[1].clone()), b"m2".to_vec());
+    assert!(session_has_received(bob_session, 2));
+    assert!(!session_has_received("unknown".to_string(), 1));
+    println!("  ✓ Late message is recorded, unknown session reports false");
+}
@@ -0,0 +1,54 @@
+//! Test serialize prekey bundle với kích thước cố định (có/không có one-time prekey)
+
+use e2ee_core::error::E2EEError;
+use e2ee_core::keys::prekey::{OneTimePreKey, OneTimePreKeyPair, SignedPreKey, SignedPreKeyPair};
+use e2ee_core::keys::{IdentityKeyPair, PreKeyBundle};
+
+fn bundle(identity: &IdentityKeyPair, one_time_prekey: Option<&OneTimePreKeyPair>) -> PreKeyBundle {
+    let signed_prekey = SignedPreKeyPair::generate(5, identity).expect("Failed to generate signed prekey");
+    PreKeyBundle::new(
+        identity.public_key_hex(),
+        identity.verifying_key(),
+        SignedPreKey::from(&signed_prekey),
+        one_time_prekey.map(OneTimePreKey::from),
+    )
+}
+
+#[test]
+fn test_padded_bundles_have_the_same_size() {
+    println!("\n=== Test: Padded PreKey Bundle ===\n");
+
+    let identity = IdentityKeyPair::generate();
+    let one_time_prekey = OneTimePreKeyPair::generate(9).expect("Failed to generate one-time prekey");
+    let with_otp = bundle(&identity, Some(&one_time_prekey));
+    let without_otp = bundle(&identity, None);
+
+    let with_bytes = with_otp.to_bytes_padded().expect("Failed to serialize");
+    let without_bytes = without_otp.to_bytes_padded().expect("Failed to serialize");
+    assert_eq!(with_bytes.len(), PreKeyBundle::PADDED_LEN);
+    assert_eq!(without_bytes.len(), with_bytes.len());
+    println!("  ✓ Bundles with and without a one-time prekey are {} bytes", with_bytes.len());
+
+    let parsed = PreKeyBundle::from_bytes_padded(&with_bytes).expect("Failed to parse");
+    assert!(parsed.verify_signature().unwrap());
+    assert_eq!(parsed.identity_public_hex(), with_otp.identity_public_hex());
+    assert_eq!(parsed.signed_prekey().key_id(), 5);
+    assert_eq!(parsed.signed_prekey().public_key_hex(), with_otp.signed_prekey().public_key_hex());
+    let parsed_otp = parsed.one_time_prekey().expect("One-time prekey must survive");
+    assert_eq!(parsed_otp.key_id(), 9);
+    assert_eq!(parsed_otp.public_key_hex(), one_time_prekey.public_key_hex());
+
+    let parsed = PreKeyBundle::from_bytes_padded(&without_bytes).expect("Failed to parse");
+    assert!(parsed.verify_signature().unwrap());
+    assert!(parsed.one_time_prekey().is_none());
+    println!("  ✓ Both round-trip, the empty slot reads back as no one-time prekey");
+
+    let mut bad_flag = without_bytes.clone();
+    bad_flag[PreKeyBundle::PADDED_LEN - 37] = 2;
+    let mut dirty_slot = without_bytes.clone();
+    dirty_slot[PreKeyBundle::PADDED_LEN - 1] = 1;
+    for bytes in [&bad_flag[..], &dirty_slot[..], &with_bytes[..PreKeyBundle::PADDED_LEN - 1]] {
+        assert!(matches!(PreKeyBundle::from_bytes_padded(bytes), Err(E2EEError::SerializationError(_))));
+    }
+    println!("  ✓ Unknown flag, non-zero empty slot and wrong length are rejected");
+}
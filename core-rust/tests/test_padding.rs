@@ -0,0 +1,52 @@
+//! Test padding plaintext để che giấu độ dài tin nhắn
+
+mod common;
+
+use common::ratchet_pair;
+use e2ee_core::message::padding::{pad, unpad};
+use e2ee_core::message::PADDING_BUCKET;
+
+#[test]
+fn test_padded_messages_share_ciphertext_length() {
+    println!("\n=== Test: Length-Hiding Padding ===\n");
+
+    let (mut alice_dr, mut bob_dr) = ratchet_pair([15u8; 32]);
+    let short = vec![0x41u8; 10];
+    let long = vec![0x42u8; 200];
+
+    let short_env = alice_dr.encrypt_envelope_padded(&short).expect("Failed to encrypt");
+    let long_env = alice_dr.encrypt_envelope_padded(&long).expect("Failed to encrypt");
+    assert!(short_env.header.padded);
+    assert_eq!(short_env.ciphertext.len(), long_env.ciphertext.len());
+    assert_eq!(short_env.ciphertext.len(), PADDING_BUCKET + 16);
+    println!("  ✓ 10-byte and 200-byte messages both encrypt to {} bytes", short_env.ciphertext.len());
+
+    assert_eq!(bob_dr.decrypt_envelope(&short_env).expect("Failed to decrypt"), short);
+    assert_eq!(bob_dr.decrypt_envelope(&long_env).expect("Failed to decrypt"), long);
+    println!("  ✓ Padding stripped to the exact original");
+}
+
+#[test]
+fn test_padded_flag_is_authenticated() {
+    let (mut alice_dr, mut bob_dr) = ratchet_pair([15u8; 32]);
+    let envelope = alice_dr.encrypt_envelope_padded(b"hidden length").expect("Failed to encrypt");
+
+    let mut stripped = envelope.clone();
+    stripped.header.padded = false;
+    assert!(bob_dr.decrypt_envelope(&stripped).is_err(), "Clearing the flag must fail");
+
+    assert_eq!(bob_dr.decrypt_envelope(&envelope).expect("Failed to decrypt"), b"hidden length".to_vec());
+}
+
+#[test]
+fn test_pad_bucket_boundaries() {
+    // Length prefix takes 4 bytes, so 252 bytes fill exactly one bucket
+    assert_eq!(pad(&[1u8; 252], PADDING_BUCKET).unwrap().len(), PADDING_BUCKET);
+    assert_eq!(pad(&[1u8; 253], PADDING_BUCKET).unwrap().len(), 2 * PADDING_BUCKET);
+    assert_eq!(unpad(&pad(b"", PADDING_BUCKET).unwrap()).unwrap(), Vec::<u8>::new());
+    assert!(pad(b"x", 0).is_err());
+
+    let mut bogus = pad(b"abc", PADDING_BUCKET).unwrap();
+    bogus[..4].copy_from_slice(&1000u32.to_be_bytes());
+    assert!(unpad(&bogus).is_err());
+}
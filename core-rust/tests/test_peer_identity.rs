@@ -0,0 +1,52 @@
+//! Test session phía responder ghi nhận danh tính của peer
+
+use e2ee_core::error::E2EEError;
+use e2ee_core::ffi::Session;
+use e2ee_core::keys::prekey::{SignedPreKey, SignedPreKeyPair};
+use e2ee_core::keys::{IdentityKeyPair, PreKeyBundle};
+use e2ee_core::x3dh::{X3DHInitiator, X3DHResponder};
+
+#[test]
+fn test_responder_session_reports_peer_identity() {
+    println!("\n=== Test: Responder Peer Identity ===\n");
+
+    let alice_identity = IdentityKeyPair::generate();
+    let bob_identity = IdentityKeyPair::generate();
+    let bob_signed_prekey = SignedPreKeyPair::generate(1, &bob_identity)
+        .expect("Failed to generate signed prekey");
+    let bundle = PreKeyBundle::new(
+        bob_identity.public_key_hex(),
+        bob_identity.verifying_key(),
+        SignedPreKey::from(&bob_signed_prekey),
+        None,
+    );
+
+    let alice_result = X3DHInitiator::new(alice_identity.clone())
+        .initiate(&bundle)
+        .expect("Failed to initiate X3DH");
+    let bob = X3DHResponder::new(bob_identity, bob_signed_prekey);
+
+    // Uppercase hex on the wire still maps to the canonical identity
+    let alice_hex = alice_identity.public_key_hex();
+    let bob_result = bob
+        .respond(&alice_hex.to_uppercase(), &alice_result.ephemeral_public_key_hex)
+        .expect("Failed to respond to X3DH");
+    assert_eq!(bob_result.peer_identity_hex, alice_hex);
+
+    let session = Session::from_x3dh_response(&bob_result, &alice_hex, "bob".to_string())
+        .expect("Failed to create session");
+    assert_eq!(session.peer_identity_hex(), Some(alice_hex.as_str()));
+    println!("  ✓ Responder session records Alice's identity");
+
+    let mallory_hex = IdentityKeyPair::generate().public_key_hex();
+    match Session::from_x3dh_response(&bob_result, &mallory_hex, "bob-2".to_string()) {
+        Err(E2EEError::ProtocolError(_)) => {}
+        Err(e) => panic!("Expected ProtocolError, got {}", e),
+        Ok(_) => panic!("A different peer identity must be rejected"),
+    }
+    println!("  ✓ Mismatched peer identity is rejected");
+
+    let plain = Session::from_shared_secret([9u8; 32], true, "alice".to_string())
+        .expect("Failed to create session");
+    assert_eq!(plain.peer_identity_hex(), None);
+}
@@ -0,0 +1,48 @@
+//! Test tách initiate thành prepare/finish để soạn tin offline
+
+use e2ee_core::keys::prekey::{SignedPreKey, SignedPreKeyPair};
+use e2ee_core::keys::{IdentityKeyPair, PreKeyBundle};
+use e2ee_core::x3dh::{PendingInitiation, X3DHInitiator, X3DHResponder};
+
+#[test]
+fn test_prepare_serialize_restore_finish() {
+    println!("\n=== Test: Offline Prepared Initiation ===\n");
+
+    let alice_identity = IdentityKeyPair::generate();
+    let alice = X3DHInitiator::new(alice_identity.clone());
+
+    // Ephemeral generated and persisted before any bundle is fetched
+    let pending = alice.prepare();
+    let ephemeral_hex = pending.ephemeral_public_key_hex();
+    let stored = serde_json::to_string(&pending).expect("Failed to serialize pending initiation");
+    drop(pending);
+    println!("  ✓ Pending initiation serialized");
+
+    let bob_identity = IdentityKeyPair::generate();
+    let bob_signed_prekey = SignedPreKeyPair::generate(1, &bob_identity)
+        .expect("Failed to generate signed prekey");
+    let bundle = PreKeyBundle::new(
+        bob_identity.public_key_hex(),
+        bob_identity.verifying_key(),
+        SignedPreKey::from(&bob_signed_prekey),
+        None,
+    );
+
+    // After a restart: restore and finish, twice, from the same stored blob
+    let restored: PendingInitiation = serde_json::from_str(&stored).expect("Failed to restore");
+    let result = alice.finish(restored, &bundle).expect("Failed to finish X3DH");
+    assert_eq!(result.ephemeral_public_key_hex, ephemeral_hex);
+
+    let again: PendingInitiation = serde_json::from_str(&stored).expect("Failed to restore");
+    let pinned = alice.finish(again, &bundle).expect("Failed to finish X3DH");
+    assert_eq!(result.key_confirmation(), pinned.key_confirmation());
+    assert_eq!(result.transcript_hash, pinned.transcript_hash);
+    println!("  ✓ Restored ephemeral reproduces the same handshake");
+
+    let bob_result = X3DHResponder::new(bob_identity, bob_signed_prekey)
+        .respond(&alice_identity.public_key_hex(), &result.ephemeral_public_key_hex)
+        .expect("Failed to respond to X3DH");
+    assert!(bob_result.verify_key_confirmation(&result.key_confirmation()).is_ok());
+    assert_eq!(bob_result.transcript_hash, result.transcript_hash);
+    println!("  ✓ Responder derives the same shared secret");
+}
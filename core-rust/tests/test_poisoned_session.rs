@@ -0,0 +1,39 @@
+//! Test FFI trả về lỗi thay vì panic khi trạng thái session bị poison (chỉ có với feature test-support)
+
+#![cfg(feature = "test-support")]
+
+mod common;
+
+use common::establish_ffi_sessions;
+use e2ee_core::ffi::api::{decrypt_message_full, encrypt_message, registered_session, session_missing_messages};
+use std::thread;
+
+#[test]
+fn test_poisoned_session_returns_error() {
+    println!("\n=== Test: Poisoned Session Lock ===\n");
+
+    let (alice_session, bob_session) = establish_ffi_sessions(1171, None);
+    let envelope = encrypt_message(alice_session.clone(), b"before poison".to_vec());
+
+    // Panic while holding Bob's ratchet lock to poison it
+    let session = registered_session(&bob_session).expect("Session not registered");
+    let poisoned = thread::spawn(move || {
+        let _guard = session.double_ratchet.lock().unwrap();
+        panic!("simulated panic while holding the ratchet lock");
+    })
+    .join();
+    assert!(poisoned.is_err());
+
+    let output = encrypt_message(bob_session.clone(), b"after poison".to_vec());
+    assert!(output.starts_with("Error: "), "Expected error, got {}", output);
+    assert!(output.contains("Failed to lock DoubleRatchet"));
+
+    let result: serde_json::Value =
+        serde_json::from_str(&decrypt_message_full(bob_session.clone(), envelope)).expect("Invalid JSON");
+    assert_eq!(result["ok"], false);
+    assert!(session_missing_messages(bob_session, 3).is_empty());
+    println!("  ✓ Poisoned lock surfaces as an error value");
+
+    // Other sessions are unaffected
+    assert!(!encrypt_message(alice_session, b"still fine".to_vec()).starts_with("Error"));
+}
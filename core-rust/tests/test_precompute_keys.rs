@@ -0,0 +1,56 @@
+//! Test tính trước khóa gửi cho các đợt gửi liên tục
+
+mod common;
+
+use common::ratchet_pair;
+use e2ee_core::ratchet::{DoubleRatchet, MAX_SKIP};
+
+#[test]
+fn test_precomputed_keys_produce_identical_envelopes() {
+    println!("\n=== Test: Precomputed Send Keys ===\n");
+
+    let shared_secret = [31u8; 32];
+    let mut inline_dr = DoubleRatchet::from_shared_secret(&shared_secret, true)
+        .expect("Failed to create Double Ratchet");
+    let mut precomputed_dr = DoubleRatchet::from_shared_secret(&shared_secret, true)
+        .expect("Failed to create Double Ratchet");
+
+    precomputed_dr.precompute_send_keys(10).expect("Failed to precompute keys");
+    assert_eq!(precomputed_dr.precomputed_send_key_count(), 10);
+
+    for i in 0..10 {
+        let plaintext = format!("burst {}", i);
+        let inline = inline_dr.encrypt_envelope(plaintext.as_bytes()).expect("Failed to encrypt");
+        let cached = precomputed_dr.encrypt_envelope(plaintext.as_bytes()).expect("Failed to encrypt");
+        assert_eq!(inline.ciphertext, cached.ciphertext);
+        assert_eq!(inline.header.message_number, cached.header.message_number);
+    }
+    assert_eq!(precomputed_dr.precomputed_send_key_count(), 0);
+    println!("  ✓ 10 precomputed sends match 10 inline sends");
+
+    assert!(precomputed_dr.precompute_send_keys(MAX_SKIP as usize + 1).is_err());
+    println!("  ✓ Cache size is bounded by MAX_SKIP");
+}
+
+#[test]
+fn test_dh_ratchet_discards_precomputed_keys() {
+    let (mut alice_dr, mut bob_dr) = ratchet_pair([31u8; 32]);
+
+    let first = alice_dr.encrypt_envelope(b"hi").expect("Failed to encrypt");
+    bob_dr.decrypt_envelope(&first).expect("Failed to decrypt");
+    let reply = bob_dr.encrypt_envelope(b"reply").expect("Failed to encrypt");
+    alice_dr.decrypt_envelope(&reply).expect("Failed to decrypt reply");
+
+    // Bob precomputes, then a message carrying a new DH key forces a ratchet
+    bob_dr.precompute_send_keys(5).expect("Failed to precompute keys");
+    let rekeyed = alice_dr.encrypt_key_exchange().expect("Failed to encrypt key exchange");
+    bob_dr.decrypt_envelope(&rekeyed).expect("Failed to decrypt key exchange");
+    assert!(bob_dr.has_ratcheted());
+    assert_eq!(bob_dr.precomputed_send_key_count(), 0);
+
+    // The discarded numbers stay consumed, so numbering follows the chain position
+    let after = bob_dr.encrypt_envelope(b"after ratchet").expect("Failed to encrypt");
+    assert_eq!(after.header.message_number, 7);
+    assert_eq!(alice_dr.decrypt_envelope(&after).expect("Failed to decrypt"), b"after ratchet".to_vec());
+    println!("  ✓ DH ratchet invalidates the cache");
+}
@@ -0,0 +1,50 @@
+//! Test signed prekey và one-time prekey dùng chung một id
+
+mod common;
+
+use common::establish_ffi_sessions;
+use e2ee_core::ffi::api::{decrypt_message, encrypt_message};
+use e2ee_core::ffi::{InMemoryPreKeyStore, PreKeyStore};
+use e2ee_core::keys::prekey::{OneTimePreKeyId, SignedPreKeyId, SignedPreKeyPair};
+use e2ee_core::keys::IdentityKeyPair;
+
+#[test]
+fn test_store_keeps_colliding_ids_apart() {
+    println!("\n=== Test: Store Keeps Colliding Prekey Ids Apart ===\n");
+
+    let identity = IdentityKeyPair::generate();
+    let signed_prekey = SignedPreKeyPair::generate(1, &identity).unwrap();
+    let signed_public = signed_prekey.public_key().to_bytes();
+    let one_time_private = [7u8; 32];
+
+    let store = InMemoryPreKeyStore::new();
+    store.put_signed(SignedPreKeyId(1), signed_prekey);
+    store.put_one_time(OneTimePreKeyId(1), one_time_private);
+
+    let signed = store.get_signed(SignedPreKeyId(1)).expect("signed prekey 1");
+    assert_eq!(signed.public_key().to_bytes(), signed_public);
+    println!("  ✓ Signed prekey id 1 resolves to the signed prekey");
+
+    assert_eq!(store.take_one_time(OneTimePreKeyId(1)), Some(one_time_private));
+    println!("  ✓ One-time prekey id 1 resolves to the one-time prekey");
+
+    // Consuming the one-time prekey leaves the signed prekey in place
+    assert!(store.take_one_time(OneTimePreKeyId(1)).is_none());
+    assert!(store.get_signed(SignedPreKeyId(1)).is_some());
+    println!("  ✓ Consuming one id does not touch the other");
+}
+
+#[test]
+fn test_handshake_with_colliding_prekey_ids() {
+    println!("\n=== Test: Handshake With Colliding Prekey Ids ===\n");
+
+    let (alice_session, bob_session) = establish_ffi_sessions(1661, Some(1661));
+    println!("  ✓ Handshake completed with signed and one-time prekey both id 1661");
+
+    let envelope = encrypt_message(alice_session.clone(), b"same id".to_vec());
+    assert_eq!(decrypt_message(bob_session.clone(), envelope), b"same id".to_vec());
+
+    let reply = encrypt_message(bob_session, b"still fine".to_vec());
+    assert_eq!(decrypt_message(alice_session, reply), b"still fine".to_vec());
+    println!("  ✓ Session established from colliding ids works both ways");
+}
@@ -0,0 +1,76 @@
+//! Test sinh prekey bundle kèm private material (không dùng state toàn cục)
+
+use e2ee_core::ffi::{generate_prekey_material, InMemoryPreKeyStore, PreKeyStore, PrivatePreKeyMaterial};
+use e2ee_core::keys::IdentityKeyPair;
+use e2ee_core::keys::prekey::{OneTimePreKeyId, SignedPreKeyId};
+use e2ee_core::x3dh::{X3DHInitiator, X3DHResponder};
+use x25519_dalek::{PublicKey, StaticSecret};
+
+#[test]
+fn test_private_material_matches_bundle() {
+    println!("\n=== Test: Private PreKey Material Matches Bundle ===\n");
+
+    let identity = IdentityKeyPair::generate();
+    let (bundle, material) = generate_prekey_material(&identity, 7, &[3, 4, 5])
+        .expect("Failed to generate prekey material");
+
+    let signed_prekey = material.signed_prekey_pair().expect("Failed to rebuild signed prekey");
+    assert_eq!(signed_prekey.key_id(), 7);
+    assert_eq!(signed_prekey.public_key_hex(), bundle.signed_prekey.public_key_hex);
+    assert_eq!(signed_prekey.signature_hex(), bundle.signed_prekey.signature_hex);
+    assert!(signed_prekey.verify_signature(&identity.verifying_key()).unwrap());
+    println!("  ✓ Signed prekey private key reconstructs the bundle's public key");
+
+    let one_time_publics = material.one_time_prekey_publics().expect("Failed to derive one-time publics");
+    assert_eq!(one_time_publics.iter().map(|otp| otp.key_id).collect::<Vec<_>>(), vec![3, 4, 5]);
+    assert_eq!(bundle.one_time_prekey.as_ref(), Some(&one_time_publics[0]));
+    println!("  ✓ One-time prekey private keys reconstruct their public keys");
+
+    // Private material survives a JSON round trip
+    let json = serde_json::to_string(&material).unwrap();
+    let restored: PrivatePreKeyMaterial = serde_json::from_str(&json).unwrap();
+    assert_eq!(restored.signed_prekey_pair().unwrap().public_key_hex(), bundle.signed_prekey.public_key_hex);
+    assert_eq!(restored.one_time_prekey_publics().unwrap(), one_time_publics);
+    println!("  ✓ Private material round-trips through JSON");
+
+    // Invalid ids are rejected without producing anything
+    assert!(generate_prekey_material(&identity, 0, &[]).is_err());
+    assert!(generate_prekey_material(&identity, 8, &[9, 0]).is_err());
+    println!("  ✓ Invalid prekey ids are rejected");
+}
+
+#[test]
+fn test_stored_material_completes_handshake() {
+    println!("\n=== Test: Stored PreKey Material Completes Handshake ===\n");
+
+    let alice_identity = IdentityKeyPair::generate();
+    let bob_identity = IdentityKeyPair::generate();
+    let (bundle, material) = generate_prekey_material(&bob_identity, 1, &[1, 2])
+        .expect("Failed to generate prekey material");
+
+    // The caller picks the store; no global state is touched
+    let store = InMemoryPreKeyStore::new();
+    material.store_into(&store).expect("Failed to store prekey material");
+    println!("  ✓ Material stored in a caller-owned store");
+
+    let alice_result = X3DHInitiator::new(alice_identity.clone())
+        .initiate(&bundle.to_prekey_bundle().unwrap())
+        .expect("Failed to initiate X3DH");
+
+    let signed_prekey = store.get_signed(SignedPreKeyId(1)).expect("Missing signed prekey");
+    let one_time_private = store.take_one_time(OneTimePreKeyId(1)).expect("Missing one-time prekey");
+    let one_time_private = StaticSecret::from(one_time_private);
+    let one_time_public = PublicKey::from(&one_time_private);
+
+    let mut bob = X3DHResponder::new(bob_identity, signed_prekey);
+    bob.set_one_time_prekey(1, one_time_private, one_time_public);
+    let bob_result = bob
+        .respond(&alice_identity.public_key_hex(), &alice_result.ephemeral_public_key_hex)
+        .expect("Failed to respond to X3DH");
+
+    assert!(bob_result.verify_key_confirmation(&alice_result.key_confirmation()).is_ok());
+    println!("  ✓ Responder built from stored material derives the same secret");
+
+    assert!(store.take_one_time(OneTimePreKeyId(2)).is_some());
+    println!("  ✓ Remaining one-time prekeys are stored too");
+}
@@ -0,0 +1,28 @@
+//! Test ký lại signed prekey bằng identity mới sau khi migrate identity
+
+use e2ee_core::keys::IdentityKeyPair;
+use e2ee_core::keys::prekey::SignedPreKeyPair;
+
+#[test]
+fn test_resign_after_identity_migration() {
+    println!("\n=== Test: Re-sign Signed PreKey ===\n");
+
+    let old_identity = IdentityKeyPair::generate();
+    let new_identity = IdentityKeyPair::generate();
+    let mut prekey = SignedPreKeyPair::generate(7, &old_identity).expect("Failed to generate signed prekey");
+    let public_before = prekey.public_key_bytes();
+    let signature_before = prekey.signature_hex();
+
+    assert!(prekey.verify_signature(&new_identity.verifying_key()).is_err());
+    println!("  ✓ Old signature does not verify against the new identity");
+
+    prekey.resign(&new_identity);
+    assert_eq!(prekey.public_key_bytes(), public_before);
+    assert_eq!(prekey.key_id(), 7);
+    assert_ne!(prekey.signature_hex(), signature_before);
+    println!("  ✓ Prekey and id unchanged, signature replaced");
+
+    assert!(prekey.verify_signature(&new_identity.verifying_key()).expect("New signature must verify"));
+    assert!(prekey.verify_signature(&old_identity.verifying_key()).is_err());
+    println!("  ✓ Verifies against the new identity only");
+}
@@ -0,0 +1,75 @@
+//! Test serde trực tiếp trên các kiểu prekey công khai (SignedPreKey, OneTimePreKey, PreKeyBundle)
+
+use e2ee_core::ffi::PreKeyBundleJSON;
+use e2ee_core::keys::prekey::{OneTimePreKey, OneTimePreKeyPair, SignedPreKey, SignedPreKeyPair};
+use e2ee_core::keys::{IdentityKeyPair, PreKeyBundle};
+
+fn full_bundle(identity: &IdentityKeyPair) -> PreKeyBundle {
+    let signed_prekey = SignedPreKeyPair::generate(7, identity).expect("Failed to generate signed prekey");
+    let one_time_prekey = OneTimePreKeyPair::generate(8).expect("Failed to generate one-time prekey");
+    PreKeyBundle::new(
+        identity.public_key_hex(),
+        identity.verifying_key(),
+        SignedPreKey::from(&signed_prekey),
+        Some(OneTimePreKey::from(&one_time_prekey)),
+    )
+}
+
+#[test]
+fn test_prekey_bundle_serde_round_trip() {
+    println!("\n=== Test: PreKeyBundle Serde Round Trip ===\n");
+
+    let identity = IdentityKeyPair::generate();
+    let bundle = full_bundle(&identity);
+    let json = serde_json::to_string(&bundle).expect("Failed to serialize bundle");
+    let restored: PreKeyBundle = serde_json::from_str(&json).expect("Failed to deserialize bundle");
+
+    assert_eq!(restored.identity_public_hex(), bundle.identity_public_hex());
+    assert_eq!(restored.identity_ed25519_verifying_key(), bundle.identity_ed25519_verifying_key());
+    assert_eq!(restored.signed_prekey().public_key_hex(), bundle.signed_prekey().public_key_hex());
+    assert_eq!(restored.signed_prekey().key_id(), 7);
+    assert_eq!(restored.one_time_prekey().unwrap().public_key_hex(), bundle.one_time_prekey().unwrap().public_key_hex());
+    assert_eq!(restored.one_time_prekey().unwrap().key_id(), 8);
+    assert!(restored.verify_signature().is_ok());
+    println!("  ✓ Bundle round-trips through serde_json and still verifies");
+
+    let wire: serde_json::Value = serde_json::from_str(&json).unwrap();
+    let ffi_wire = serde_json::to_value(PreKeyBundleJSON::from_prekey_bundle(&bundle)).unwrap();
+    assert_eq!(wire, ffi_wire);
+    println!("  ✓ Same JSON shape as PreKeyBundleJSON");
+
+    let signed_json = serde_json::to_string(bundle.signed_prekey()).unwrap();
+    let signed: SignedPreKey = serde_json::from_str(&signed_json).unwrap();
+    assert!(signed.verify_signature(&identity.verifying_key()).is_ok());
+    println!("  ✓ SignedPreKey round-trips on its own");
+}
+
+#[test]
+fn test_prekey_serde_rejects_malformed_keys() {
+    println!("\n=== Test: PreKey Serde Rejects Malformed Keys ===\n");
+
+    let bundle = full_bundle(&IdentityKeyPair::generate());
+    let wire: serde_json::Value = serde_json::to_value(&bundle).unwrap();
+
+    let mut short_key = wire.clone();
+    short_key["signed_prekey"]["public_key_hex"] = serde_json::json!("abcd");
+    assert!(serde_json::from_value::<PreKeyBundle>(short_key).is_err());
+
+    let mut bad_signature = wire.clone();
+    bad_signature["signed_prekey"]["signature_hex"] = serde_json::json!("zz".repeat(64));
+    assert!(serde_json::from_value::<PreKeyBundle>(bad_signature).is_err());
+
+    let mut bad_otp = wire;
+    bad_otp["one_time_prekey"]["public_key_hex"] = serde_json::json!(12);
+    assert!(serde_json::from_value::<PreKeyBundle>(bad_otp).is_err());
+    println!("  ✓ Wrong length, invalid hex and wrong type are rejected");
+
+    // A tampered signature still decodes but no longer verifies
+    let mut tampered: serde_json::Value = serde_json::to_value(&bundle).unwrap();
+    let mut signature = hex::decode(tampered["signed_prekey"]["signature_hex"].as_str().unwrap()).unwrap();
+    signature[0] ^= 0x01;
+    tampered["signed_prekey"]["signature_hex"] = serde_json::json!(hex::encode(signature));
+    let restored: PreKeyBundle = serde_json::from_value(tampered).unwrap();
+    assert!(restored.verify_signature().is_err());
+    println!("  ✓ Tampered signature fails verification after deserializing");
+}
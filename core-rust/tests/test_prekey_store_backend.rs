@@ -0,0 +1,82 @@
+//! Test backend lưu prekey tùy biến (PreKeyStore) cho FFI
+
+mod common;
+
+use common::establish_ffi_sessions;
+use e2ee_core::ffi::api::{create_session_responder, decrypt_message, encrypt_message, set_prekey_store_backend};
+use e2ee_core::ffi::{InMemoryPreKeyStore, PreKeyStore};
+use e2ee_core::keys::prekey::{OneTimePreKeyId, SignedPreKeyId, SignedPreKeyPair};
+use std::sync::{Arc, Mutex};
+
+/// In-memory backend that records every access
+struct RecordingStore {
+    inner: InMemoryPreKeyStore,
+    log: Arc<Mutex<Vec<String>>>,
+}
+
+impl RecordingStore {
+    fn record(&self, entry: String) {
+        self.log.lock().unwrap().push(entry);
+    }
+}
+
+impl PreKeyStore for RecordingStore {
+    fn get_signed(&self, id: SignedPreKeyId) -> Option<SignedPreKeyPair> {
+        self.record(format!("get_signed {}", id));
+        self.inner.get_signed(id)
+    }
+
+    fn put_signed(&self, id: SignedPreKeyId, prekey: SignedPreKeyPair) {
+        self.record(format!("put_signed {}", id));
+        self.inner.put_signed(id, prekey)
+    }
+
+    fn get_one_time(&self, id: OneTimePreKeyId) -> Option<[u8; 32]> {
+        self.record(format!("get_one_time {}", id));
+        self.inner.get_one_time(id)
+    }
+
+    fn take_one_time(&self, id: OneTimePreKeyId) -> Option<[u8; 32]> {
+        self.record(format!("take_one_time {}", id));
+        self.inner.take_one_time(id)
+    }
+
+    fn put_one_time(&self, id: OneTimePreKeyId, private_key: [u8; 32]) {
+        self.record(format!("put_one_time {}", id));
+        self.inner.put_one_time(id, private_key)
+    }
+}
+
+#[test]
+fn test_handshake_uses_custom_prekey_backend() {
+    println!("\n=== Test: Custom PreKey Store Backend ===\n");
+
+    let log = Arc::new(Mutex::new(Vec::new()));
+    set_prekey_store_backend(Box::new(RecordingStore {
+        inner: InMemoryPreKeyStore::new(),
+        log: Arc::clone(&log),
+    }));
+
+    let (alice_session, bob_session) = establish_ffi_sessions(1441, Some(1442));
+    assert_eq!(
+        *log.lock().unwrap(),
+        vec!["put_signed 1441", "put_one_time 1442", "get_signed 1441", "get_one_time 1442", "take_one_time 1442"],
+    );
+    println!("  ✓ Bundle generation and handshake went through the backend");
+    println!("  ✓ The one-time prekey is read first and taken only after the handshake");
+
+    let envelope = encrypt_message(alice_session, b"via custom store".to_vec());
+    assert_eq!(decrypt_message(bob_session, envelope), b"via custom store".to_vec());
+    println!("  ✓ Session established from backend prekeys works");
+
+    // The one-time prekey was consumed by the first handshake
+    let reuse = create_session_responder(
+        e2ee_core::ffi::api::generate_identity_key_pair(),
+        1441,
+        Some(1442),
+        "00".repeat(32),
+        "00".repeat(32),
+    );
+    assert_eq!(reuse, "Error: Key not found: one-time prekey id 1442");
+    println!("  ✓ One-time prekey cannot be reused");
+}
@@ -0,0 +1,76 @@
+//! Test gộp prekey store (merge) và import hàng loạt (extend_signed / extend_one_time)
+
+use e2ee_core::error::E2EEError;
+use e2ee_core::ffi::{InMemoryPreKeyStore, PreKeyStore};
+use e2ee_core::keys::IdentityKeyPair;
+use e2ee_core::keys::prekey::{OneTimePreKeyId, SignedPreKeyId, SignedPreKeyPair};
+
+fn signed_prekey(id: u32, identity: &IdentityKeyPair) -> (SignedPreKeyId, SignedPreKeyPair) {
+    let prekey = SignedPreKeyPair::generate(id, identity).expect("Failed to generate signed prekey");
+    (SignedPreKeyId(id), prekey)
+}
+
+#[test]
+fn test_merge_disjoint_stores() {
+    println!("\n=== Test: Merge Disjoint PreKey Stores ===\n");
+
+    let identity = IdentityKeyPair::generate();
+    let shard_a = InMemoryPreKeyStore::new();
+    shard_a.extend_signed(vec![signed_prekey(1, &identity), signed_prekey(2, &identity)]);
+    shard_a.extend_one_time(vec![(OneTimePreKeyId(1), [1u8; 32]), (OneTimePreKeyId(2), [2u8; 32])]);
+    println!("  ✓ Bulk import filled the first shard");
+
+    let shard_b = InMemoryPreKeyStore::new();
+    let (id, prekey) = signed_prekey(3, &identity);
+    let expected_public = prekey.public_key_bytes();
+    shard_b.put_signed(id, prekey);
+    shard_b.put_one_time(OneTimePreKeyId(3), [3u8; 32]);
+
+    shard_a.merge(&shard_b).expect("Disjoint stores must merge");
+    for id in 1..=3 {
+        assert!(shard_a.get_signed(SignedPreKeyId(id)).is_some(), "Missing signed prekey {}", id);
+        assert_eq!(shard_a.take_one_time(OneTimePreKeyId(id)), Some([id as u8; 32]));
+    }
+    assert_eq!(shard_a.get_signed(SignedPreKeyId(3)).unwrap().public_key_bytes(), expected_public);
+    println!("  ✓ Merged store holds the prekeys of both shards");
+
+    assert!(shard_b.get_signed(SignedPreKeyId(3)).is_some());
+    assert!(shard_b.take_one_time(OneTimePreKeyId(3)).is_some());
+    println!("  ✓ Source store is left unchanged");
+}
+
+#[test]
+fn test_merge_conflicting_id_fails() {
+    println!("\n=== Test: Merge Conflicting PreKey Ids ===\n");
+
+    let identity = IdentityKeyPair::generate();
+    let shard_a = InMemoryPreKeyStore::new();
+    let (id, prekey) = signed_prekey(1, &identity);
+    shard_a.put_signed(id, prekey.clone());
+    shard_a.put_one_time(OneTimePreKeyId(1), [1u8; 32]);
+
+    // The same key under the same id is not a conflict
+    let duplicate = InMemoryPreKeyStore::new();
+    duplicate.put_signed(id, prekey);
+    duplicate.put_one_time(OneTimePreKeyId(1), [1u8; 32]);
+    shard_a.merge(&duplicate).expect("Identical entries must merge");
+    println!("  ✓ Identical entries under the same id merge");
+
+    let conflicting = InMemoryPreKeyStore::new();
+    conflicting.extend_signed(vec![signed_prekey(1, &identity), signed_prekey(2, &identity)]);
+    match shard_a.merge(&conflicting) {
+        Err(E2EEError::StateError(msg)) => assert!(msg.contains("signed prekey id 1"), "Unexpected error: {}", msg),
+        other => panic!("Expected StateError for a conflicting id, got {:?}", other.err()),
+    }
+    assert!(shard_a.get_signed(SignedPreKeyId(2)).is_none());
+    println!("  ✓ Conflicting signed prekey id fails and merges nothing");
+
+    let conflicting = InMemoryPreKeyStore::new();
+    conflicting.put_one_time(OneTimePreKeyId(1), [9u8; 32]);
+    match shard_a.merge(&conflicting) {
+        Err(E2EEError::StateError(msg)) => assert!(msg.contains("one-time prekey id 1"), "Unexpected error: {}", msg),
+        other => panic!("Expected StateError for a conflicting id, got {:?}", other.err()),
+    }
+    assert_eq!(shard_a.take_one_time(OneTimePreKeyId(1)), Some([1u8; 32]));
+    println!("  ✓ Conflicting one-time prekey id fails and keeps the original key");
+}
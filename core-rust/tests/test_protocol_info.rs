@@ -0,0 +1,27 @@
+//! Test protocol_info: báo cáo phiên bản crate/giao thức để kiểm tra tương thích
+
+use e2ee_core::ffi::api::protocol_info;
+use e2ee_core::ffi::SESSION_STATE_VERSION;
+use e2ee_core::message::MessageEnvelope;
+
+#[test]
+fn test_protocol_info_reports_versions_and_suites() {
+    println!("\n=== Test: protocol_info ===\n");
+
+    let info: serde_json::Value = serde_json::from_str(&protocol_info())
+        .expect("protocol_info must return valid JSON");
+
+    assert_eq!(info["crate_version"], env!("CARGO_PKG_VERSION"));
+    assert_eq!(info["envelope_version"], MessageEnvelope::VERSION);
+    assert_eq!(info["state_version"], SESSION_STATE_VERSION);
+    println!("  ✓ Versions match the library constants");
+
+    // Envelopes actually produced carry the reported version
+    let envelope = MessageEnvelope::regular(vec![0u8; 16], "00".repeat(32), 0, 1);
+    assert_eq!(envelope.version as u64, info["envelope_version"].as_u64().unwrap());
+
+    let suites = info["supported_suites"].as_array().expect("supported_suites must be an array");
+    assert!(suites.iter().any(|s| s.as_str().is_some_and(|s| s.contains("AES256GCM"))));
+    assert!(suites.iter().any(|s| s.as_str().is_some_and(|s| s.contains("XCHACHA20POLY1305"))));
+    println!("  ✓ AES-GCM and XChaCha20-Poly1305 suites are listed");
+}
@@ -0,0 +1,39 @@
+//! Test xuất trạng thái công khai của ratchet để hai bên so sánh
+
+mod common;
+
+use common::establish_ffi_sessions;
+use e2ee_core::ffi::api::{decrypt_message, encrypt_message, session_public_state};
+use e2ee_core::ratchet::PublicRatchetState;
+
+fn public_state(session_id: &str) -> PublicRatchetState {
+    let json = session_public_state(session_id.to_string());
+    serde_json::from_str(&json).unwrap_or_else(|_| panic!("Invalid public state: {}", json))
+}
+
+#[test]
+fn test_public_state_tracks_counters() {
+    println!("\n=== Test: Public Ratchet State ===\n");
+
+    let (alice_session, bob_session) = establish_ffi_sessions(1651, None);
+    let sent: Vec<_> = (0..4)
+        .map(|i| encrypt_message(alice_session.clone(), format!("m{}", i).into_bytes()))
+        .collect();
+    for envelope in &sent[..3] {
+        assert!(!decrypt_message(bob_session.clone(), envelope.clone()).is_empty());
+    }
+
+    let alice = public_state(&alice_session);
+    let bob = public_state(&bob_session);
+    assert_eq!(alice.sending_number, 4);
+    assert_eq!(bob.receiving_number, 3);
+    assert_ne!(alice.sending_number, bob.receiving_number, "An undelivered message shows up as a mismatch");
+    assert_eq!(bob.remote_dh_public.as_deref(), Some(alice.our_dh_public.as_str()));
+    println!("  ✓ Counters and DH keys reflect the exchange");
+
+    assert!(!decrypt_message(bob_session.clone(), sent[3].clone()).is_empty());
+    assert_eq!(public_state(&bob_session).receiving_number, public_state(&alice_session).sending_number);
+    println!("  ✓ Counters agree once every message is delivered");
+
+    assert!(session_public_state("missing-session-165".to_string()).starts_with("Error"));
+}
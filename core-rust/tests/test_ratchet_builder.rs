@@ -0,0 +1,99 @@
+//! Test DoubleRatchetBuilder: cấu hình ratchet (suite, context, max_skip, rekey) và interop
+
+use e2ee_core::error::E2EEError;
+use e2ee_core::ratchet::{DoubleRatchet, DoubleRatchetBuilder, SuiteDescriptor, MAX_SKIP};
+
+#[test]
+fn test_builder_config_and_interop() {
+    println!("\n=== Test: Ratchet Builder Config And Interop ===\n");
+
+    let shared_secret = [0x91; 32];
+    let builder = DoubleRatchetBuilder::new()
+        .cipher("AES-256-GCM")
+        .kdf("HKDF-SHA256")
+        .header_encryption(false)
+        .context(b"builder-test")
+        .max_skip(3)
+        .rekey_after(10);
+    let mut alice_dr = builder.build_from_shared_secret(&shared_secret, true).unwrap();
+    let mut bob_dr = builder.build_from_shared_secret(&shared_secret, false).unwrap();
+
+    let config = alice_dr.config();
+    assert_eq!(config.suite, SuiteDescriptor::default_suite());
+    assert_eq!(config.context, b"builder-test".to_vec());
+    assert_eq!(config.max_skip, 3);
+    assert_eq!(config.rekey_after, Some(10));
+    assert_eq!(bob_dr.config(), config);
+    println!("  ✓ Reported config matches the builder settings");
+
+    let envelope = alice_dr.encrypt_envelope(b"configured").unwrap();
+    assert_eq!(bob_dr.decrypt_envelope(&envelope).unwrap(), b"configured".to_vec());
+    let reply = bob_dr.encrypt_envelope(b"reply").unwrap();
+    assert_eq!(alice_dr.decrypt_envelope(&reply).unwrap(), b"reply".to_vec());
+    println!("  ✓ Identically configured peers interoperate");
+
+    // The context is part of the key schedule
+    let mut plain_bob = DoubleRatchet::from_shared_secret(&shared_secret, false).unwrap();
+    let envelope = alice_dr.encrypt_envelope(b"other context").unwrap();
+    assert!(plain_bob.decrypt_envelope(&envelope).is_err());
+    println!("  ✓ Peer with a different context cannot decrypt");
+
+    // max_skip bounds the gap the receiver will bridge
+    let mut copy = DoubleRatchet::from_state(&bob_dr.to_state().unwrap()).unwrap();
+    assert_eq!(copy.config(), config);
+    for _ in 0..3 {
+        alice_dr.encrypt_envelope(b"skipped").unwrap();
+    }
+    let far = alice_dr.encrypt_envelope(b"too far").unwrap();
+    match copy.decrypt_envelope(&far) {
+        Err(E2EEError::ProtocolError(msg)) => assert!(msg.contains("max 3"), "Unexpected error: {}", msg),
+        other => panic!("Expected ProtocolError for too large a gap, got {:?}", other),
+    }
+    println!("  ✓ Config survives save/restore and max_skip is enforced");
+}
+
+#[test]
+fn test_builder_defaults_and_unsupported_suite() {
+    println!("\n=== Test: Ratchet Builder Defaults ===\n");
+
+    let ratchet = DoubleRatchet::builder().build_from_shared_secret(&[0x92; 32], true).unwrap();
+    let config = ratchet.config();
+    assert_eq!(config, DoubleRatchet::from_shared_secret(&[0x92; 32], true).unwrap().config());
+    assert_eq!(config.max_skip, MAX_SKIP);
+    assert_eq!(config.rekey_after, None);
+    assert!(config.context.is_empty());
+    println!("  ✓ Default builder matches from_shared_secret");
+
+    for builder in [
+        DoubleRatchet::builder().cipher("ChaCha20-Poly1305"),
+        DoubleRatchet::builder().kdf("BLAKE3"),
+        DoubleRatchet::builder().header_encryption(true),
+        DoubleRatchet::builder().rekey_after(0),
+    ] {
+        assert!(matches!(
+            builder.build_from_shared_secret(&[0x92; 32], true),
+            Err(E2EEError::ProtocolError(_))
+        ));
+    }
+    println!("  ✓ Unsupported suites and a zero rekey limit are rejected");
+}
+
+#[test]
+fn test_builder_suite_is_kept_on_ratchet() {
+    println!("\n=== Test: Ratchet Builder Suite ===\n");
+
+    let builder = DoubleRatchet::builder().cipher("XChaCha20-Poly1305").context(b"suite-test");
+    let mut alice_dr = builder.build_from_shared_secret(&[0x93; 32], true).unwrap();
+    let config = alice_dr.config();
+    assert_eq!(config.suite, SuiteDescriptor::xchacha20_poly1305());
+    assert_ne!(config.suite, SuiteDescriptor::default_suite());
+    println!("  ✓ config().suite reflects the configured cipher");
+
+    let restored = DoubleRatchet::from_state(&alice_dr.to_state().unwrap()).unwrap();
+    assert_eq!(restored.config(), config);
+    let mut bob_dr = DoubleRatchetBuilder::from_config(&config).build_from_shared_secret(&[0x93; 32], false).unwrap();
+    assert_eq!(bob_dr.config(), config);
+    let envelope = alice_dr.encrypt_envelope(b"same suite").unwrap();
+    assert_eq!(bob_dr.decrypt_envelope(&envelope).unwrap(), b"same suite".to_vec());
+    println!("  ✓ Suite survives save/restore and from_config builds a matching peer");
+}
@@ -0,0 +1,87 @@
+//! Test lưu trạng thái ratchet sau mỗi lần mã hóa (encrypt-and-snapshot) và khôi phục
+
+mod common;
+
+use common::{establish_ffi_sessions, ratchet_pair};
+use e2ee_core::ffi::api::{decrypt_message, encrypt_message_with_state};
+use e2ee_core::ffi::{generate_session_id, Session};
+use e2ee_core::message::MessageEnvelope;
+use e2ee_core::ratchet::{DoubleRatchet, RatchetState, SESSION_STATE_VERSION};
+
+#[test]
+fn test_snapshot_restores_next_message_number() {
+    println!("\n=== Test: Encrypt And Snapshot ===\n");
+
+    let shared_secret = [33u8; 32];
+    let alice = Session::from_shared_secret(shared_secret, true, generate_session_id())
+        .expect("Failed to create Alice's session");
+    let bob = Session::from_shared_secret(shared_secret, false, generate_session_id())
+        .expect("Failed to create Bob's session");
+
+    let one = alice.encrypt(b"one").expect("Failed to encrypt");
+    let (envelope, state) = alice.encrypt_and_snapshot(b"two").expect("Failed to encrypt");
+    assert_eq!(envelope.header.message_number, 2);
+    assert_eq!(state.version(), SESSION_STATE_VERSION);
+    assert_eq!(state.next_message_number(), 3);
+
+    // Simulate a crash: restore from the persisted blob and keep sending
+    let restored_state = RatchetState::from_base64(&state.to_base64().expect("Failed to serialize"))
+        .expect("Failed to parse state");
+    let mut restored = DoubleRatchet::from_state(&restored_state).expect("Failed to restore");
+    let next = restored.encrypt_envelope(b"three").expect("Failed to encrypt");
+    assert_eq!(next.header.message_number, 3);
+    println!("  ✓ Restored ratchet continues at the next message number");
+
+    // Bob decrypts the messages produced before and after the restore
+    assert_eq!(bob.decrypt(&one).expect("Failed to decrypt"), b"one".to_vec());
+    assert_eq!(bob.decrypt(&envelope).expect("Failed to decrypt"), b"two".to_vec());
+    assert_eq!(bob.decrypt(&next).expect("Failed to decrypt"), b"three".to_vec());
+    println!("  ✓ Peer decrypts across the restore");
+}
+
+#[test]
+fn test_restored_ratchet_keeps_receive_state() {
+    let (mut alice_dr, mut bob_dr) = ratchet_pair([33u8; 32]);
+
+    let envelopes: Vec<_> = (0..3)
+        .map(|i| alice_dr.encrypt_envelope(format!("m{}", i).as_bytes()).expect("Failed to encrypt"))
+        .collect();
+    bob_dr.decrypt_envelope(&envelopes[2]).expect("Failed to decrypt");
+
+    // Skipped keys and received numbers survive the round trip
+    let state = RatchetState::from_base64(&bob_dr.to_state().unwrap().to_base64().unwrap()).unwrap();
+    let mut restored_bob = DoubleRatchet::from_state(&state).expect("Failed to restore");
+    assert_eq!(restored_bob.received_numbers(), vec![3]);
+    assert_eq!(restored_bob.decrypt_envelope(&envelopes[0]).expect("Failed to decrypt"), b"m0".to_vec());
+    assert_eq!(restored_bob.decrypt_envelope(&envelopes[1]).expect("Failed to decrypt"), b"m1".to_vec());
+}
+
+#[test]
+fn test_encrypt_message_with_state_ffi() {
+    let (alice_session, bob_session) = establish_ffi_sessions(1531, None);
+
+    let result: serde_json::Value =
+        serde_json::from_str(&encrypt_message_with_state(alice_session, b"persisted".to_vec())).expect("Invalid JSON");
+    assert_eq!(result["ok"], true);
+    assert!(result["error"].is_null());
+
+    let envelope_b64 = result["envelope_base64"].as_str().expect("Missing envelope").to_string();
+    let state = RatchetState::from_base64(result["state_base64"].as_str().expect("Missing state"))
+        .expect("Failed to parse state");
+    assert_eq!(state.next_message_number(), 2);
+    assert_eq!(MessageEnvelope::from_base64(&envelope_b64).unwrap().header.message_number, 1);
+    assert_eq!(decrypt_message(bob_session, envelope_b64), b"persisted".to_vec());
+
+    let unknown: serde_json::Value =
+        serde_json::from_str(&encrypt_message_with_state("unknown".to_string(), b"x".to_vec())).expect("Invalid JSON");
+    assert_eq!(unknown["ok"], false);
+    assert!(unknown["state_base64"].is_null());
+}
+
+#[test]
+fn test_closed_ratchet_has_no_state() {
+    let (mut alice_dr, _bob_dr) = ratchet_pair([33u8; 32]);
+    alice_dr.close();
+    assert!(alice_dr.to_state().is_err());
+    assert!(RatchetState::from_base64("not base64!").is_err());
+}
@@ -0,0 +1,76 @@
+//! Test rebase session lên shared secret mới mà giữ nguyên session id
+
+mod common;
+
+use common::establish_ffi_sessions;
+use e2ee_core::ffi::api::{close_session, decrypt_message, encrypt_message, rebase_session, session_routing_id};
+use e2ee_core::ffi::{generate_session_id, Session};
+use e2ee_core::ratchet::DoubleRatchet;
+
+#[test]
+fn test_rebase_session_onto_new_secret() {
+    println!("\n=== Test: Rebase Session ===\n");
+
+    let (alice_session, bob_session) = establish_ffi_sessions(1721, Some(1722));
+    let routing_id = session_routing_id(alice_session.clone());
+    let old_envelope = encrypt_message(alice_session.clone(), b"before rebase".to_vec());
+
+    let new_secret_hex = "7e".repeat(32);
+    assert_eq!(rebase_session(alice_session.clone(), new_secret_hex.clone(), true), alice_session);
+    assert_eq!(rebase_session(bob_session.clone(), new_secret_hex, false), bob_session);
+    assert_eq!(session_routing_id(alice_session.clone()), routing_id);
+    println!("  ✓ Both sessions rebased, ids and routing id unchanged");
+
+    let envelope = encrypt_message(alice_session.clone(), b"after rebase".to_vec());
+    assert_eq!(decrypt_message(bob_session.clone(), envelope), b"after rebase".to_vec());
+    let reply = encrypt_message(bob_session.clone(), b"reply".to_vec());
+    assert_eq!(decrypt_message(alice_session.clone(), reply), b"reply".to_vec());
+    println!("  ✓ Envelopes under the new secret decrypt both ways");
+
+    let output = String::from_utf8(decrypt_message(bob_session.clone(), old_envelope)).unwrap();
+    assert!(output.starts_with("Error:"), "Old envelope must not decrypt: {}", output);
+    println!("  ✓ Envelope from before the rebase fails");
+
+    assert!(rebase_session(alice_session.clone(), "zz".to_string(), true).starts_with("Error:"));
+    close_session(alice_session.clone());
+    assert!(rebase_session(alice_session, "7e".repeat(32), true).starts_with("Error:"));
+    println!("  ✓ Malformed secret and unknown session are rejected");
+}
+
+#[test]
+fn test_closed_session_cannot_be_rebased() {
+    println!("\n=== Test: Rebase Closed Session ===\n");
+
+    let session = Session::from_shared_secret([0x11; 32], true, generate_session_id()).unwrap();
+    session.close();
+    assert!(session.rebase([0x22; 32], true).is_err());
+    assert!(session.encrypt(b"still closed").is_err());
+    println!("  ✓ A closed session stays closed");
+}
+
+#[test]
+fn test_rebase_keeps_config_and_dh_ratchets() {
+    println!("\n=== Test: Rebase Keeps Config ===\n");
+
+    let builder = DoubleRatchet::builder().context(b"rebase-app").max_skip(50).rekey_after(100);
+    let alice = Session::from_shared_secret([0x33; 32], true, generate_session_id()).unwrap();
+    let bob = Session::from_shared_secret([0x33; 32], false, generate_session_id()).unwrap();
+    *alice.double_ratchet.lock().unwrap() = builder.build_from_shared_secret(&[0x33; 32], true).unwrap();
+    *bob.double_ratchet.lock().unwrap() = builder.build_from_shared_secret(&[0x33; 32], false).unwrap();
+    let config = alice.double_ratchet.lock().unwrap().config();
+
+    alice.rebase([0x44; 32], true).expect("Failed to rebase Alice");
+    bob.rebase([0x44; 32], false).expect("Failed to rebase Bob");
+    assert_eq!(alice.double_ratchet.lock().unwrap().config(), config);
+    assert_eq!(bob.double_ratchet.lock().unwrap().config(), config);
+    println!("  ✓ Context, max_skip and chain cap survive the rebase");
+
+    let envelope = alice.encrypt(b"after rebase").expect("Failed to encrypt");
+    assert_eq!(bob.decrypt(&envelope).expect("Failed to decrypt"), b"after rebase".to_vec());
+    assert!(bob.has_ratcheted().unwrap());
+    let reply = bob.encrypt(b"reply").expect("Failed to encrypt reply");
+    assert_ne!(reply.header.dh_public_key, envelope.header.dh_public_key);
+    assert_eq!(alice.decrypt(&reply).expect("Failed to decrypt reply"), b"reply".to_vec());
+    assert!(alice.has_ratcheted().unwrap());
+    println!("  ✓ The first message after the rebase runs a DH ratchet");
+}
@@ -0,0 +1,102 @@
+//! Test biên nhận (receipt) gắn số thứ tự tin nhắn vào AAD
+
+mod common;
+
+use common::ratchet_pair;
+use e2ee_core::error::E2EEError;
+
+#[test]
+fn test_receipt_identifies_acknowledged_message() {
+    println!("\n=== Test: Message Receipts ===\n");
+
+    let (mut alice_dr, mut bob_dr) = ratchet_pair([29u8; 32]);
+
+    let first = alice_dr.encrypt_envelope(b"first").expect("Failed to encrypt");
+    let second = alice_dr.encrypt_envelope(b"second").expect("Failed to encrypt");
+    bob_dr.decrypt_envelope(&first).expect("Failed to decrypt");
+    bob_dr.decrypt_envelope(&second).expect("Failed to decrypt");
+
+    let receipt = bob_dr.encrypt_receipt(second.header.message_number).expect("Failed to encrypt receipt");
+    assert_eq!(receipt.header.receipt_for, Some(2));
+
+    let decrypted = alice_dr.decrypt_envelope_full(&receipt).expect("Failed to decrypt receipt");
+    assert!(decrypted.plaintext.is_empty());
+    assert_eq!(decrypted.receipt_for, Some(second.header.message_number));
+    println!("  ✓ Receiver learns which message the receipt acknowledges");
+
+    let plain = bob_dr.encrypt_envelope(b"not a receipt").expect("Failed to encrypt");
+    assert_eq!(alice_dr.decrypt_envelope_full(&plain).expect("Failed to decrypt").receipt_for, None);
+    println!("  ✓ Regular messages carry no receipt");
+}
+
+#[test]
+fn test_receipt_number_is_tamper_evident() {
+    let (mut alice_dr, mut bob_dr) = ratchet_pair([29u8; 32]);
+
+    for text in [&b"one"[..], b"two"] {
+        let envelope = alice_dr.encrypt_envelope(text).expect("Failed to encrypt");
+        bob_dr.decrypt_envelope(&envelope).expect("Failed to decrypt");
+    }
+
+    let receipt = bob_dr.encrypt_receipt(1).expect("Failed to encrypt receipt");
+    let mut forged = receipt.clone();
+    forged.header.receipt_for = Some(2);
+    assert!(alice_dr.decrypt_envelope(&forged).is_err(), "Altered receipt number must fail");
+
+    let mut stripped = receipt.clone();
+    stripped.header.receipt_for = None;
+    assert!(alice_dr.decrypt_envelope(&stripped).is_err(), "Stripped receipt flag must fail");
+
+    assert_eq!(alice_dr.decrypt_envelope_full(&receipt).expect("Failed to decrypt").receipt_for, Some(1));
+    println!("  ✓ Referenced number is bound into the AEAD");
+}
+
+#[test]
+fn test_receipt_for_unknown_message_is_rejected() {
+    let (mut alice_dr, mut bob_dr) = ratchet_pair([29u8; 32]);
+
+    let envelope = alice_dr.encrypt_envelope(b"only one").expect("Failed to encrypt");
+    bob_dr.decrypt_envelope(&envelope).expect("Failed to decrypt");
+
+    // Bob cannot acknowledge a message he never received
+    assert!(matches!(bob_dr.encrypt_receipt(5), Err(E2EEError::ProtocolError(_))));
+
+    // Alice rejects an authenticated receipt for a message she never sent
+    let (mut carol_dr, mut dave_dr) = ratchet_pair([30u8; 32]);
+    for _ in 0..3 {
+        let envelope = carol_dr.encrypt_envelope(b"filler").expect("Failed to encrypt");
+        dave_dr.decrypt_envelope(&envelope).expect("Failed to decrypt");
+    }
+    let receipt = dave_dr.encrypt_receipt(3).expect("Failed to encrypt receipt");
+    let mut fresh_carol = e2ee_core::ratchet::DoubleRatchet::from_shared_secret(&[30u8; 32], true)
+        .expect("Failed to create Double Ratchet");
+    assert!(matches!(fresh_carol.decrypt_envelope(&receipt), Err(E2EEError::ProtocolError(_))));
+    println!("  ✓ Receipts for messages never sent or received are rejected");
+}
+
+#[test]
+fn test_rejected_receipt_leaves_state_unchanged() {
+    let (mut carol_dr, mut dave_dr) = ratchet_pair([31u8; 32]);
+    let envelope = carol_dr.encrypt_envelope(b"filler").expect("Failed to encrypt");
+    dave_dr.decrypt_envelope(&envelope).expect("Failed to decrypt");
+    let receipt = dave_dr.encrypt_receipt(1).expect("Failed to encrypt receipt");
+
+    // A fresh Carol has sent nothing, so the authenticated receipt is refused
+    let mut fresh_carol = e2ee_core::ratchet::DoubleRatchet::from_shared_secret(&[31u8; 32], true)
+        .expect("Failed to create Double Ratchet");
+    let state_before = fresh_carol.public_state();
+    for _ in 0..2 {
+        match fresh_carol.decrypt_envelope(&receipt) {
+            Err(E2EEError::ProtocolError(msg)) => assert!(msg.contains("never sent"), "{}", msg),
+            other => panic!("Expected receipt rejection, got {:?}", other),
+        }
+    }
+    assert_eq!(fresh_carol.public_state(), state_before);
+    assert!(!fresh_carol.has_received(1));
+    println!("  ✓ Rejected receipt consumes no key and marks nothing received");
+
+    // The chain did not move, so Dave's next message still decrypts in place
+    let next = dave_dr.encrypt_envelope(b"next").expect("Failed to encrypt");
+    assert_eq!(fresh_carol.decrypt_envelope(&next).expect("Failed to decrypt"), b"next");
+    println!("  ✓ Later messages decrypt normally");
+}
@@ -0,0 +1,23 @@
+//! Test từ chối envelope chứa lại chính DH public key của người nhận (reflection)
+
+mod common;
+
+use common::ratchet_pair;
+use e2ee_core::error::E2EEError;
+
+#[test]
+fn test_reflected_dh_key_rejected() {
+    let (mut alice_dr, mut bob_dr) = ratchet_pair([13u8; 32]);
+
+    // Bob's own envelope carries Bob's DH public key; a relay echoes it back to him
+    let reflected = bob_dr.encrypt_envelope(b"echo").expect("Failed to encrypt");
+    match bob_dr.decrypt_envelope(&reflected) {
+        Err(E2EEError::ProtocolError(msg)) => assert_eq!(msg, "reflected DH key"),
+        other => panic!("Expected reflected DH key error, got {:?}", other),
+    }
+
+    // A normal peer key is still accepted
+    let envelope = alice_dr.encrypt_envelope(b"hello").expect("Failed to encrypt");
+    let decrypted = bob_dr.decrypt_envelope(&envelope).expect("Failed to decrypt");
+    assert_eq!(decrypted, b"hello".to_vec());
+}
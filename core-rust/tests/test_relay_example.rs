@@ -0,0 +1,44 @@
+//! Test example relay: hai client gửi/nhận envelope qua relay theo conversation id
+
+#[path = "../examples/relay.rs"]
+#[allow(dead_code)]
+mod relay;
+
+use relay::{Client, Relay};
+use std::sync::Arc;
+
+#[test]
+fn test_two_clients_through_relay() {
+    println!("\n=== Test: Relay Example Delivery ===\n");
+
+    let relay = Arc::new(Relay::new());
+    let mut alice = Client::new("alice", Arc::clone(&relay), 1741, 1742).expect("Failed to create Alice");
+    let mut bob = Client::new("bob", Arc::clone(&relay), 1743, 1744).expect("Failed to create Bob");
+
+    alice.start_conversation("bob").expect("Failed to start conversation");
+    assert_eq!(bob.accept_handshakes().expect("Failed to accept handshake"), vec!["alice".to_string()]);
+    let conversation_id = alice.conversation_id("bob").expect("Missing conversation").to_string();
+    assert_eq!(bob.conversation_id("alice"), Some(conversation_id.as_str()));
+    println!("  ✓ Both clients route by the same conversation id");
+
+    let texts: Vec<String> = (1..=5).map(|i| format!("message {}", i)).collect();
+    for (i, text) in texts.iter().enumerate() {
+        assert_eq!(alice.send("bob", text).unwrap(), i as u64 + 1);
+    }
+    assert_eq!(bob.receive("alice").unwrap(), texts);
+    assert!(bob.receive("alice").unwrap().is_empty());
+    println!("  ✓ Messages are delivered once and in order");
+
+    bob.send("alice", "reply 1").unwrap();
+    bob.send("alice", "reply 2").unwrap();
+    alice.send("bob", "message 6").unwrap();
+    assert_eq!(alice.receive("bob").unwrap(), vec!["reply 1", "reply 2"]);
+    assert_eq!(bob.receive("alice").unwrap(), vec!["message 6"]);
+    assert_eq!(relay.conversation_len(&conversation_id), 8);
+    println!("  ✓ Replies interleave correctly in both directions");
+
+    // The bundle's one-time prekey was handed out once
+    assert!(relay.fetch_bundle("bob").is_err());
+    assert!(alice.send("carol", "nobody here").is_err());
+    println!("  ✓ Bundles are single-use and unknown peers are rejected");
+}
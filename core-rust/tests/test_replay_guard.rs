@@ -0,0 +1,68 @@
+//! Test phát hiện X3DH initiation bị replay (cùng identity và ephemeral key)
+
+use e2ee_core::error::E2EEError;
+use e2ee_core::keys::prekey::{SignedPreKey, SignedPreKeyPair};
+use e2ee_core::keys::{IdentityKeyPair, PreKeyBundle};
+use e2ee_core::x3dh::{InitiationReplayGuard, SeenInitiations, X3DHInitiator, X3DHResponder};
+use std::sync::Arc;
+
+#[test]
+fn test_replayed_initiation_is_rejected() {
+    println!("\n=== Test: Replayed X3DH Initiation ===\n");
+
+    let alice_identity = IdentityKeyPair::generate();
+    let bob_identity = IdentityKeyPair::generate();
+    let signed_prekey = SignedPreKeyPair::generate(1, &bob_identity).expect("Failed to generate signed prekey");
+    let bundle = PreKeyBundle::new(
+        bob_identity.public_key_hex(),
+        bob_identity.verifying_key(),
+        SignedPreKey::from(&signed_prekey),
+        None,
+    );
+    let initiation = X3DHInitiator::new(alice_identity.clone()).initiate(&bundle).expect("Failed to initiate X3DH");
+    let alice_hex = alice_identity.public_key_hex();
+
+    // Without a guard (the default) the no-OPK path accepts the same initiation twice
+    let unguarded = X3DHResponder::new(bob_identity.clone(), signed_prekey.clone());
+    unguarded.respond(&alice_hex, &initiation.ephemeral_public_key_hex).unwrap();
+    unguarded.respond(&alice_hex, &initiation.ephemeral_public_key_hex).unwrap();
+    println!("  ✓ Replay detection is off by default");
+
+    // A fresh responder per handshake sharing one guard, as a server would run it
+    let seen = Arc::new(SeenInitiations::default());
+    let guarded = || {
+        let mut responder = X3DHResponder::new(bob_identity.clone(), signed_prekey.clone());
+        responder.set_replay_guard(seen.clone());
+        responder
+    };
+    let first = guarded().respond(&alice_hex, &initiation.ephemeral_public_key_hex).expect("First response must succeed");
+    assert!(first.verify_key_confirmation(&initiation.key_confirmation()).is_ok());
+    match guarded().respond(&alice_hex, &initiation.ephemeral_public_key_hex) {
+        Err(E2EEError::ProtocolError(msg)) => assert_eq!(msg, "replayed initiation"),
+        other => panic!("Expected replayed initiation, got {:?}", other.err()),
+    }
+    println!("  ✓ Second response to the same initiation is rejected");
+
+    let another = X3DHInitiator::new(alice_identity).initiate(&bundle).expect("Failed to initiate X3DH");
+    guarded().respond(&alice_hex, &another.ephemeral_public_key_hex).expect("New ephemeral key must be accepted");
+    assert_eq!(seen.len(), 2);
+    println!("  ✓ A new initiation with a fresh ephemeral key is accepted");
+}
+
+#[test]
+fn test_seen_initiations_is_bounded() {
+    println!("\n=== Test: Bounded Replay Guard ===\n");
+
+    let seen = SeenInitiations::new(2);
+    let identity = [1u8; 32];
+    assert!(seen.record(&identity, &[1u8; 32]));
+    assert!(seen.record(&identity, &[2u8; 32]));
+    assert!(!seen.record(&identity, &[2u8; 32]));
+    assert!(seen.record(&identity, &[3u8; 32]));
+    assert_eq!(seen.len(), 2);
+    assert!(seen.record(&identity, &[1u8; 32]), "Oldest entry must have been forgotten");
+    println!("  ✓ Oldest initiations are forgotten beyond the capacity");
+
+    assert!(seen.record(&[9u8; 32], &[1u8; 32]));
+    println!("  ✓ The same ephemeral key from another identity is a different initiation");
+}
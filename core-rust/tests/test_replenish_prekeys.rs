@@ -0,0 +1,89 @@
+//! Test bổ sung one-time prekey và báo cáo các id đã bị dùng
+
+use e2ee_core::ffi::api::{
+    create_session_initiator_with_ephemeral, create_session_responder, generate_identity_key_pair,
+    generate_prekey_bundle, replenish_one_time_prekeys,
+};
+
+/// Run a handshake against Bob's bundle and return the responder's result
+fn handshake(bob_identity_json: &str, bundle_json: String, signed_prekey_id: u32, one_time_prekey_id: u32) -> String {
+    let init_json = create_session_initiator_with_ephemeral(generate_identity_key_pair(), bundle_json);
+    let init: serde_json::Value = serde_json::from_str(&init_json).expect("Invalid initiator JSON");
+    create_session_responder(
+        bob_identity_json.to_string(),
+        signed_prekey_id,
+        Some(one_time_prekey_id),
+        init["alice_identity_hex"].as_str().expect("Missing identity").to_string(),
+        init["alice_ephemeral_public_key_hex"].as_str().expect("Missing ephemeral").to_string(),
+    )
+}
+
+#[test]
+fn test_replenish_reports_consumed_prekeys() {
+    println!("\n=== Test: Replenish One-Time PreKeys ===\n");
+
+    let bob_identity_json = generate_identity_key_pair();
+    let bundle_json = generate_prekey_bundle(bob_identity_json.clone(), 1931, Some(1932));
+    let bob_session = handshake(&bob_identity_json, bundle_json.clone(), 1931, 1932);
+    assert!(!bob_session.starts_with("Error"), "Responder failed: {}", bob_session);
+
+    let replenished: serde_json::Value =
+        serde_json::from_str(&replenish_one_time_prekeys(bob_identity_json.clone(), 1933, 3)).unwrap();
+    assert_eq!(replenished["consumed_ids"], serde_json::json!([1932]));
+    let new_prekeys = replenished["one_time_prekeys"].as_array().unwrap();
+    let ids: Vec<u64> = new_prekeys.iter().map(|otp| otp["key_id"].as_u64().unwrap()).collect();
+    assert_eq!(ids, vec![1933, 1934, 1935]);
+    println!("  ✓ Consumed id reported next to the new batch");
+
+    let again: serde_json::Value =
+        serde_json::from_str(&replenish_one_time_prekeys(bob_identity_json.clone(), 1936, 0)).unwrap();
+    assert_eq!(again["consumed_ids"], serde_json::json!([]));
+    assert_eq!(again["one_time_prekeys"], serde_json::json!([]));
+    println!("  ✓ Consumed ids are reported only once");
+
+    // A replenished prekey works in a handshake and is reported once used
+    let mut bundle: serde_json::Value = serde_json::from_str(&bundle_json).unwrap();
+    bundle["one_time_prekey"] = new_prekeys[1].clone();
+    let bob_session = handshake(&bob_identity_json, bundle.to_string(), 1931, 1934);
+    assert!(!bob_session.starts_with("Error"), "Responder failed: {}", bob_session);
+    let after: serde_json::Value =
+        serde_json::from_str(&replenish_one_time_prekeys(bob_identity_json, 1936, 1)).unwrap();
+    assert_eq!(after["consumed_ids"], serde_json::json!([1934]));
+    println!("  ✓ New prekeys are stored and usable");
+
+    assert!(replenish_one_time_prekeys("not json".to_string(), 1, 1).starts_with("Error:"));
+    assert!(replenish_one_time_prekeys(generate_identity_key_pair(), u32::MAX, 2).starts_with("Error:"));
+    println!("  ✓ Malformed identity and overflowing ids are rejected");
+}
+
+#[test]
+fn test_failed_handshake_is_not_reported_as_consumed() {
+    println!("\n=== Test: Failed Handshake Keeps One-Time PreKey ===\n");
+
+    let bob_identity_json = generate_identity_key_pair();
+    let bundle_json = generate_prekey_bundle(bob_identity_json.clone(), 1937, Some(1938));
+
+    // An initiation with a malformed ephemeral key fails before any prekey is used up
+    let init_json = create_session_initiator_with_ephemeral(generate_identity_key_pair(), bundle_json.clone());
+    let init: serde_json::Value = serde_json::from_str(&init_json).expect("Invalid initiator JSON");
+    let failed = create_session_responder(
+        bob_identity_json.clone(),
+        1937,
+        Some(1938),
+        init["alice_identity_hex"].as_str().expect("Missing identity").to_string(),
+        "zz".repeat(32),
+    );
+    assert!(failed.starts_with("Error: X3DH handshake failed"), "Unexpected result: {}", failed);
+
+    let rotated: serde_json::Value =
+        serde_json::from_str(&replenish_one_time_prekeys(bob_identity_json.clone(), 1939, 1)).unwrap();
+    assert_eq!(rotated["consumed_ids"], serde_json::json!([]));
+    println!("  ✓ A failed handshake is not reported as consuming the prekey");
+
+    let bob_session = handshake(&bob_identity_json, bundle_json, 1937, 1938);
+    assert!(!bob_session.starts_with("Error"), "Responder failed: {}", bob_session);
+    let rotated: serde_json::Value =
+        serde_json::from_str(&replenish_one_time_prekeys(bob_identity_json, 1940, 0)).unwrap();
+    assert_eq!(rotated["consumed_ids"], serde_json::json!([1938]));
+    println!("  ✓ The prekey still works afterwards and is reported once used");
+}
@@ -0,0 +1,191 @@
+//! Test resumption ticket (0-RTT) để khôi phục session mà không cần X3DH đầy đủ
+
+use e2ee_core::clock::MockClock;
+use e2ee_core::keys::{IdentityKeyPair, PreKeyBundle};
+use e2ee_core::keys::prekey::{SignedPreKey, SignedPreKeyPair};
+use e2ee_core::error::E2EEError;
+use e2ee_core::x3dh::{SeenInitiations, X3DHInitiator, X3DHResponder, X3DHResponseResult};
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const TICKET_KEY: [u8; 32] = [42u8; 32];
+
+/// A valid X25519 public key, for tickets rejected before it is used
+const ANY_EPHEMERAL_HEX: &str = "0900000000000000000000000000000000000000000000000000000000000000";
+
+fn handshake() -> (IdentityKeyPair, X3DHInitiator, X3DHResponder, X3DHResponseResult) {
+    let alice_identity = IdentityKeyPair::generate();
+    let bob_identity = IdentityKeyPair::generate();
+    let bob_signed_prekey = SignedPreKeyPair::generate(1, &bob_identity)
+        .expect("Failed to generate signed prekey");
+
+    let prekey_bundle = PreKeyBundle::new(
+        bob_identity.public_key_hex(),
+        bob_identity.verifying_key(),
+        SignedPreKey::from(&bob_signed_prekey),
+        None,
+    );
+
+    let alice = X3DHInitiator::new(alice_identity.clone());
+    let alice_result = alice.initiate(&prekey_bundle).expect("Failed to initiate X3DH");
+
+    let mut bob = X3DHResponder::new(bob_identity, bob_signed_prekey);
+    bob.set_replay_guard(Arc::new(SeenInitiations::default()));
+    let bob_result = bob
+        .respond(&alice_identity.public_key_hex(), &alice_result.ephemeral_public_key_hex)
+        .expect("Failed to respond to X3DH");
+    assert!(bob_result.verify_key_confirmation(&alice_result.key_confirmation()).is_ok());
+
+    (alice_identity, alice, bob, bob_result)
+}
+
+fn one_hour_from_now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs() + 3600
+}
+
+#[test]
+fn test_resumed_session_derives_fresh_secret() {
+    println!("\n=== Test: Session Resumption ===\n");
+
+    let (alice_identity, alice, bob, bob_result) = handshake();
+    let ticket = bob
+        .issue_resumption_ticket(&TICKET_KEY, &bob_result, one_hour_from_now())
+        .expect("Failed to issue ticket");
+
+    // Alice needs nothing but the ticket: her initiator kept the session secret
+    let alice_resumed = alice.resume(&ticket).expect("Failed to resume");
+    let bob_resumed = bob
+        .accept_resumption_ticket(
+            &TICKET_KEY,
+            &ticket,
+            &alice_identity.public_key_hex(),
+            &alice_resumed.ephemeral_public_key_hex,
+        )
+        .expect("Failed to accept ticket");
+    assert!(bob_resumed.verify_key_confirmation(&alice_resumed.key_confirmation()).is_ok());
+
+    let mut alice_dr = alice_resumed.into_ratchet().expect("Failed to create Alice's Double Ratchet");
+    let mut bob_dr = bob_resumed.into_ratchet().expect("Failed to create Bob's Double Ratchet");
+    let envelope = alice_dr.encrypt_envelope(b"resumed").expect("Failed to encrypt");
+    assert_eq!(bob_dr.decrypt_envelope(&envelope).expect("Failed to decrypt"), b"resumed".to_vec());
+    assert!(bob_dr.has_ratcheted());
+    println!("  ✓ Both sides derive the same secret, and the first message runs a DH ratchet");
+
+    // Every ticket yields a fresh secret, never the original one
+    let second_ticket = bob
+        .issue_resumption_ticket(&TICKET_KEY, &bob_result, one_hour_from_now())
+        .expect("Failed to issue ticket");
+    let alice_second = alice.resume(&second_ticket).expect("Failed to resume");
+    let alice_again = alice.resume(&ticket).expect("Failed to resume");
+    assert!(bob_result.verify_key_confirmation(&alice_again.key_confirmation()).is_err());
+    assert_ne!(alice_second.key_confirmation(), alice_again.key_confirmation());
+    println!("  ✓ Each ticket derives a fresh secret");
+
+    // Even the same ticket yields a new secret, from a fresh ephemeral key
+    let alice_third = alice.resume(&ticket).expect("Failed to resume");
+    assert_ne!(alice_third.ephemeral_public_key_hex, alice_again.ephemeral_public_key_hex);
+    assert_ne!(alice_third.key_confirmation(), alice_again.key_confirmation());
+    println!("  ✓ Each resumption derives a fresh secret");
+}
+
+#[test]
+fn test_replayed_ticket_rejected() {
+    println!("\n=== Test: Replayed Resumption Ticket ===\n");
+
+    let (alice_identity, alice, bob, bob_result) = handshake();
+    let ticket = bob
+        .issue_resumption_ticket(&TICKET_KEY, &bob_result, one_hour_from_now())
+        .expect("Failed to issue ticket");
+    let alice_resumed = alice.resume(&ticket).expect("Failed to resume");
+    let identity_hex = alice_identity.public_key_hex();
+    bob.accept_resumption_ticket(&TICKET_KEY, &ticket, &identity_hex, &alice_resumed.ephemeral_public_key_hex)
+        .expect("Failed to accept ticket");
+    println!("  ✓ First presentation accepted");
+
+    // Replayed as is, or with a different ephemeral key, the ticket is refused
+    let replayed = bob.accept_resumption_ticket(&TICKET_KEY, &ticket, &identity_hex, &alice_resumed.ephemeral_public_key_hex);
+    assert!(matches!(replayed, Err(E2EEError::ProtocolError(_))));
+    let alice_retry = alice.resume(&ticket).expect("Failed to resume");
+    let retried = bob.accept_resumption_ticket(&TICKET_KEY, &ticket, &identity_hex, &alice_retry.ephemeral_public_key_hex);
+    assert!(matches!(retried, Err(E2EEError::ProtocolError(_))));
+    println!("  ✓ Replayed ticket rejected");
+
+    // Without a replay guard a responder cannot tell, so it refuses tickets
+    let carol_identity = IdentityKeyPair::generate();
+    let carol_signed_prekey = SignedPreKeyPair::generate(2, &carol_identity).expect("Failed to generate signed prekey");
+    let unguarded = X3DHResponder::new(carol_identity, carol_signed_prekey);
+    let ticket = unguarded
+        .issue_resumption_ticket(&TICKET_KEY, &bob_result, one_hour_from_now())
+        .expect("Failed to issue ticket");
+    assert!(matches!(
+        unguarded.accept_resumption_ticket(&TICKET_KEY, &ticket, &identity_hex, ANY_EPHEMERAL_HEX),
+        Err(E2EEError::StateError(_))
+    ));
+    println!("  ✓ Tickets need a replay guard");
+}
+
+#[test]
+fn test_ticket_for_another_initiator_rejected() {
+    let (alice_identity, _alice, bob, bob_result) = handshake();
+    let ticket = bob
+        .issue_resumption_ticket(&TICKET_KEY, &bob_result, one_hour_from_now())
+        .expect("Failed to issue ticket");
+
+    // A fresh initiator (e.g. after a restart) holds no secret for this session
+    let restarted = X3DHInitiator::new(alice_identity);
+    assert!(matches!(restarted.resume(&ticket), Err(E2EEError::KeyNotFound(_))));
+    assert!(restarted.resume(&ticket[..8]).is_err());
+}
+
+#[test]
+fn test_tampered_ticket_rejected() {
+    let (alice_identity, _alice, bob, bob_result) = handshake();
+    let mut ticket = bob
+        .issue_resumption_ticket(&TICKET_KEY, &bob_result, one_hour_from_now())
+        .expect("Failed to issue ticket");
+    let last = ticket.len() - 1;
+    ticket[last] ^= 0x01;
+
+    assert!(bob.accept_resumption_ticket(&TICKET_KEY, &ticket, &alice_identity.public_key_hex(), ANY_EPHEMERAL_HEX).is_err());
+    assert!(bob.accept_resumption_ticket(&TICKET_KEY, &ticket[..8], &alice_identity.public_key_hex(), ANY_EPHEMERAL_HEX).is_err());
+}
+
+#[test]
+fn test_expired_ticket_rejected() {
+    let (alice_identity, _alice, bob, bob_result) = handshake();
+    let ticket = bob
+        .issue_resumption_ticket(&TICKET_KEY, &bob_result, 1)
+        .expect("Failed to issue ticket");
+
+    assert!(bob.accept_resumption_ticket(&TICKET_KEY, &ticket, &alice_identity.public_key_hex(), ANY_EPHEMERAL_HEX).is_err());
+}
+
+#[test]
+fn test_ticket_expires_exactly_at_boundary_second() {
+    let (alice_identity, _alice, mut bob, bob_result) = handshake();
+    let expires_at = 1_700_000_000;
+    let ticket = bob
+        .issue_resumption_ticket(&TICKET_KEY, &bob_result, expires_at)
+        .expect("Failed to issue ticket");
+
+    let clock = Arc::new(MockClock::new(expires_at - 1));
+    bob.set_clock(clock.clone());
+    assert!(bob.accept_resumption_ticket(&TICKET_KEY, &ticket, &alice_identity.public_key_hex(), ANY_EPHEMERAL_HEX).is_ok());
+    println!("  ✓ Ticket accepted one second before expiry");
+
+    clock.advance(1);
+    assert!(bob.accept_resumption_ticket(&TICKET_KEY, &ticket, &alice_identity.public_key_hex(), ANY_EPHEMERAL_HEX).is_err());
+    println!("  ✓ Ticket rejected at the expiry second");
+}
+
+#[test]
+fn test_ticket_bound_to_initiator_identity() {
+    let (alice_identity, _alice, bob, bob_result) = handshake();
+    let ticket = bob
+        .issue_resumption_ticket(&TICKET_KEY, &bob_result, one_hour_from_now())
+        .expect("Failed to issue ticket");
+
+    let mallory_identity = IdentityKeyPair::generate();
+    assert!(bob.accept_resumption_ticket(&TICKET_KEY, &ticket, &mallory_identity.public_key_hex(), ANY_EPHEMERAL_HEX).is_err());
+    assert!(bob.accept_resumption_ticket(&[7u8; 32], &ticket, &alice_identity.public_key_hex(), ANY_EPHEMERAL_HEX).is_err());
+}
@@ -0,0 +1,77 @@
+//! Test phát hiện hai phía cùng nhận vai trò initiator (role mismatch)
+
+mod common;
+
+use common::ratchet_pair;
+use e2ee_core::error::E2EEError;
+use e2ee_core::message::{ROLE_INITIATOR, ROLE_RESPONDER};
+use e2ee_core::ratchet::DoubleRatchet;
+
+#[test]
+fn test_two_initiators_report_role_mismatch() {
+    println!("\n=== Test: Role Mismatch ===\n");
+
+    let shared_secret = [26u8; 32];
+    let mut alice_dr = DoubleRatchet::from_shared_secret(&shared_secret, true)
+        .expect("Failed to create Alice's Double Ratchet");
+    let mut bob_dr = DoubleRatchet::from_shared_secret(&shared_secret, true)
+        .expect("Failed to create Bob's Double Ratchet");
+
+    let envelope = alice_dr.encrypt_envelope(b"hello").expect("Failed to encrypt");
+    assert_eq!(envelope.header.role_hint, Some(ROLE_INITIATOR));
+
+    match bob_dr.decrypt_envelope(&envelope) {
+        Err(E2EEError::ProtocolError(msg)) => assert!(msg.contains("role mismatch"), "Unexpected message: {}", msg),
+        Err(e) => panic!("Expected role mismatch, got {}", e),
+        Ok(_) => panic!("Two initiators must not decrypt each other"),
+    }
+    println!("  ✓ Two initiators produce a clear role-mismatch error");
+}
+
+#[test]
+fn test_role_hint_is_authenticated() {
+    let (mut alice_dr, mut bob_dr) = ratchet_pair([26u8; 32]);
+
+    let reply = bob_dr.encrypt_envelope(b"reply").expect("Failed to encrypt");
+    assert_eq!(reply.header.role_hint, Some(ROLE_RESPONDER));
+
+    // Dropping the hint changes the associated data
+    let mut stripped = reply.clone();
+    stripped.header.role_hint = None;
+    assert!(alice_dr.decrypt_envelope(&stripped).is_err(), "Stripped role hint must fail");
+
+    assert_eq!(alice_dr.decrypt_envelope(&reply).expect("Failed to decrypt"), b"reply".to_vec());
+    println!("  ✓ Role hint is bound into the AEAD");
+}
+
+#[test]
+fn test_role_misconfiguration_error_names_cause() {
+    println!("\n=== Test: Role Misconfiguration Error ===\n");
+
+    let shared_secret = [27u8; 32];
+    let mut alice_dr = DoubleRatchet::from_shared_secret(&shared_secret, true).unwrap();
+    let mut bob_dr = DoubleRatchet::from_shared_secret(&shared_secret, true).unwrap();
+
+    // Both directions fail with the descriptive error, not a generic crypto failure
+    let envelope = alice_dr.encrypt_envelope(b"hello").unwrap();
+    let reply = bob_dr.encrypt_envelope(b"hi").unwrap();
+    for (receiver, envelope) in [(&mut bob_dr, &envelope), (&mut alice_dr, &reply)] {
+        match receiver.decrypt_envelope(envelope) {
+            Err(E2EEError::ProtocolError(msg)) => assert_eq!(
+                msg,
+                "likely role misconfiguration: both peers initiator? (role mismatch)"
+            ),
+            other => panic!("Expected role misconfiguration error, got {:?}", other),
+        }
+    }
+    println!("  ✓ Two initiators get a role misconfiguration error on first decrypt");
+
+    let mut carol_dr = DoubleRatchet::from_shared_secret(&shared_secret, false).unwrap();
+    let mut dave_dr = DoubleRatchet::from_shared_secret(&shared_secret, false).unwrap();
+    let envelope = carol_dr.encrypt_envelope(b"hello").unwrap();
+    match dave_dr.decrypt_envelope(&envelope) {
+        Err(E2EEError::ProtocolError(msg)) => assert!(msg.contains("both peers responder?"), "Unexpected message: {}", msg),
+        other => panic!("Expected role misconfiguration error, got {:?}", other),
+    }
+    println!("  ✓ Two responders are reported the same way");
+}
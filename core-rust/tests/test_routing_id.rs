@@ -0,0 +1,40 @@
+//! Test routing id dùng chung giữa initiator và responder
+
+mod common;
+
+use common::establish_ffi_sessions;
+use e2ee_core::ffi::api::session_routing_id;
+use e2ee_core::ffi::Session;
+
+#[test]
+fn test_both_sides_derive_same_routing_id() {
+    println!("\n=== Test: Shared Routing ID ===\n");
+
+    let (alice_session, bob_session) = establish_ffi_sessions(1141, Some(1142));
+    assert_ne!(alice_session, bob_session, "Local session ids are independent");
+
+    let alice_routing = session_routing_id(alice_session.clone());
+    let bob_routing = session_routing_id(bob_session.clone());
+    assert!(!alice_routing.starts_with("Error"), "{}", alice_routing);
+    assert_eq!(alice_routing, bob_routing);
+    assert_eq!(alice_routing.len(), 64);
+    assert_ne!(alice_routing, alice_session);
+    assert_ne!(bob_routing, bob_session);
+    println!("  ✓ Routing id matches on both sides: {}", alice_routing);
+
+    assert_eq!(
+        session_routing_id("unknown".to_string()),
+        "Error: Session not found: unknown"
+    );
+}
+
+#[test]
+fn test_routing_id_depends_on_shared_secret() {
+    let alice = Session::from_shared_secret([3u8; 32], true, "a".to_string()).expect("Failed to create session");
+    let bob = Session::from_shared_secret([3u8; 32], false, "b".to_string()).expect("Failed to create session");
+    let other = Session::from_shared_secret([4u8; 32], true, "c".to_string()).expect("Failed to create session");
+
+    assert_eq!(alice.routing_id(), bob.routing_id());
+    assert_ne!(alice.routing_id(), other.routing_id());
+    assert_ne!(alice.routing_id(), hex::encode([3u8; 32]), "Routing id must not expose the shared secret");
+}
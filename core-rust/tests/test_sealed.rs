@@ -0,0 +1,135 @@
+//! Test gửi tin nhắn một lần trực tiếp tới prekey bundle (sealed sender)
+
+use base64::{engine::general_purpose, Engine as _};
+use e2ee_core::ffi::api::{
+    generate_identity_key_pair, generate_prekey_bundle, get_public_key_hex_from_json, open_sealed,
+    open_sealed_for_device, open_sealed_with_sender, seal_to_bundle, seal_to_bundle_for_device,
+};
+use e2ee_core::message::{MessageType, SealedMessage};
+
+#[test]
+fn test_seal_and_open_roundtrip() {
+    println!("\n=== Test: Sealed Sender Roundtrip ===\n");
+
+    let alice_identity_json = generate_identity_key_pair();
+    let bob_identity_json = generate_identity_key_pair();
+    let bundle_json = generate_prekey_bundle(bob_identity_json.clone(), 1041, Some(1042));
+
+    let plaintext = b"Hello Bob, first contact!".to_vec();
+    let blob = seal_to_bundle(alice_identity_json, bundle_json, plaintext.clone());
+    assert!(!blob.starts_with("Error"), "Sealing failed: {}", blob);
+
+    // The blob is self-contained: it carries the X3DH ephemeral key and prekey ids
+    let sealed = SealedMessage::from_base64(&blob).expect("Failed to parse sealed message");
+    assert_eq!(sealed.ephemeral_public_key_hex.len(), 64);
+    assert!(hex::decode(&sealed.ephemeral_public_key_hex).is_ok());
+    assert_eq!(sealed.signed_prekey_id, 1041);
+    assert_eq!(sealed.one_time_prekey_id, Some(1042));
+    assert_eq!(sealed.envelope.message_type, MessageType::PreKey);
+    println!("  ✓ Blob contains the ephemeral key and prekey ids");
+
+    let opened = open_sealed(bob_identity_json, 1041, Some(1042), blob);
+    assert_eq!(opened, plaintext);
+    println!("  ✓ Sealed message opened successfully");
+}
+
+#[test]
+fn test_open_sealed_rejects_wrong_prekey_ids() {
+    let alice_identity_json = generate_identity_key_pair();
+    let bob_identity_json = generate_identity_key_pair();
+    let bundle_json = generate_prekey_bundle(bob_identity_json.clone(), 1043, None);
+
+    let blob = seal_to_bundle(alice_identity_json, bundle_json, b"hi".to_vec());
+    let opened = open_sealed(bob_identity_json, 1043, Some(1044), blob);
+    assert!(String::from_utf8_lossy(&opened).starts_with("Error"));
+}
+
+#[test]
+fn test_sealed_device_id_roundtrip_and_tampering() {
+    println!("\n=== Test: Sealed Message Device Routing ===\n");
+
+    let alice_identity_json = generate_identity_key_pair();
+    let bob_identity_json = generate_identity_key_pair();
+    let bundle_json = generate_prekey_bundle(bob_identity_json.clone(), 1371, None);
+
+    let blob = seal_to_bundle_for_device(alice_identity_json, bundle_json, b"to your tablet".to_vec(), 7);
+    assert!(!blob.starts_with("Error"), "Sealing failed: {}", blob);
+
+    let sealed = SealedMessage::from_base64(&blob).expect("Failed to parse sealed message");
+    assert_eq!(sealed.envelope.header.recipient_device_id, Some(7));
+    println!("  ✓ Device id travels in the envelope header");
+
+    let opened = open_sealed_for_device(bob_identity_json.clone(), 1371, None, 7, blob.clone());
+    assert_eq!(opened, b"to your tablet".to_vec());
+    println!("  ✓ Target device opens the message");
+
+    let wrong_device = open_sealed_for_device(bob_identity_json.clone(), 1371, None, 8, blob);
+    assert!(String::from_utf8_lossy(&wrong_device).contains("another device"));
+    println!("  ✓ Other device detects it is not the recipient");
+
+    // A relay rewriting the device id breaks the AEAD tag
+    let mut rerouted = sealed;
+    rerouted.envelope.header.recipient_device_id = Some(8);
+    let rerouted_blob = rerouted.to_base64().expect("Failed to serialize");
+    let opened = open_sealed_for_device(bob_identity_json, 1371, None, 8, rerouted_blob);
+    assert!(String::from_utf8_lossy(&opened).starts_with("Error: Decryption failed"));
+    println!("  ✓ Rewritten device id is detected");
+}
+
+#[test]
+fn test_open_sealed_attributes_verified_sender() {
+    let alice_identity_json = generate_identity_key_pair();
+    let bob_identity_json = generate_identity_key_pair();
+    let carol_identity_json = generate_identity_key_pair();
+    let bundle_json = generate_prekey_bundle(bob_identity_json.clone(), 1541, None);
+
+    let blob = seal_to_bundle(alice_identity_json.clone(), bundle_json, b"from alice".to_vec());
+    assert!(!blob.starts_with("Error"), "Sealing failed: {}", blob);
+
+    // A relay re-attributes the message to Carol
+    let mut spoofed = SealedMessage::from_base64(&blob).expect("Failed to parse sealed message");
+    spoofed.sender_identity_hex = get_public_key_hex_from_json(carol_identity_json);
+    let result: serde_json::Value = serde_json::from_str(&open_sealed_with_sender(
+        bob_identity_json.clone(), 1541, None, spoofed.to_base64().unwrap(),
+    )).expect("Invalid JSON");
+    assert_eq!(result["ok"], false);
+    assert!(result["sender_identity_hex"].is_null());
+    assert!(result["error"].as_str().unwrap().starts_with("Decryption failed"));
+    println!("  ✓ Spoofed sender identity makes decryption fail");
+
+    let result: serde_json::Value = serde_json::from_str(&open_sealed_with_sender(
+        bob_identity_json, 1541, None, blob,
+    )).expect("Invalid JSON");
+    assert_eq!(result["ok"], true);
+    assert_eq!(result["sender_identity_hex"], get_public_key_hex_from_json(alice_identity_json));
+    let plaintext = general_purpose::STANDARD
+        .decode(result["plaintext_base64"].as_str().expect("Missing plaintext"))
+        .expect("Invalid base64 plaintext");
+    assert_eq!(plaintext, b"from alice".to_vec());
+    println!("  ✓ Genuine message reports the verified sender identity");
+}
+
+#[test]
+fn test_failed_open_keeps_one_time_prekey() {
+    println!("\n=== Test: Failed Sealed Open Keeps One-Time PreKey ===\n");
+
+    let alice_identity_json = generate_identity_key_pair();
+    let bob_identity_json = generate_identity_key_pair();
+    let bundle_json = generate_prekey_bundle(bob_identity_json.clone(), 1045, Some(1046));
+    let blob = seal_to_bundle(alice_identity_json, bundle_json, b"first contact".to_vec());
+    assert!(!blob.starts_with("Error"), "Sealing failed: {}", blob);
+
+    // A tampered copy fails to decrypt and must not burn the one-time prekey
+    let mut tampered = SealedMessage::from_base64(&blob).expect("Failed to parse sealed message");
+    tampered.envelope.ciphertext[0] ^= 0x01;
+    let opened = open_sealed(bob_identity_json.clone(), 1045, Some(1046), tampered.to_base64().unwrap());
+    assert!(String::from_utf8_lossy(&opened).starts_with("Error: Decryption failed"));
+    println!("  ✓ Tampered sealed message is rejected");
+
+    assert_eq!(open_sealed(bob_identity_json.clone(), 1045, Some(1046), blob.clone()), b"first contact".to_vec());
+    println!("  ✓ Genuine message still opens with the same one-time prekey");
+
+    let again = open_sealed(bob_identity_json, 1045, Some(1046), blob);
+    assert!(String::from_utf8_lossy(&again).contains("one-time prekey id 1046"));
+    println!("  ✓ The one-time prekey is consumed once the open succeeds");
+}
@@ -0,0 +1,54 @@
+//! Test so sánh shared secret thời gian hằng (chỉ dùng để chẩn đoán)
+
+use e2ee_core::keys::prekey::{SignedPreKey, SignedPreKeyPair};
+use e2ee_core::keys::{IdentityKeyPair, PreKeyBundle};
+use e2ee_core::x3dh::{X3DHInitiator, X3DHResponder};
+use rand_chacha::rand_core::SeedableRng;
+use rand_chacha::ChaCha20Rng;
+
+/// Shared secret of the seeded handshake below (same seeds as the golden vectors)
+const EXPECTED_SECRET_HEX: &str = "3033d7ae81d2efbe1a84f34ecca760ec8839c75061902d92e4f5bcf79325b5cb";
+
+#[test]
+fn test_shared_secret_matches_is_exact() {
+    println!("\n=== Test: Constant-Time Shared Secret Match ===\n");
+
+    // Seeded RNGs make the secret known without reading it from either result
+    let mut alice_rng = ChaCha20Rng::from_seed([0xA1; 32]);
+    let mut bob_rng = ChaCha20Rng::from_seed([0xB0; 32]);
+
+    let alice_identity = IdentityKeyPair::generate_with_rng(&mut alice_rng);
+    let bob_identity = IdentityKeyPair::generate_with_rng(&mut bob_rng);
+    let bob_signed_prekey = SignedPreKeyPair::generate_with_rng(1, &bob_identity, &mut bob_rng)
+        .expect("Failed to generate signed prekey");
+    let bundle = PreKeyBundle::new(
+        bob_identity.public_key_hex(),
+        bob_identity.verifying_key(),
+        SignedPreKey::from(&bob_signed_prekey),
+        None,
+    );
+
+    let alice_result = X3DHInitiator::new(alice_identity.clone())
+        .initiate_with_rng(&bundle, &mut alice_rng)
+        .expect("Failed to initiate X3DH");
+    let bob_result = X3DHResponder::new(bob_identity, bob_signed_prekey)
+        .respond(&alice_identity.public_key_hex(), &alice_result.ephemeral_public_key_hex)
+        .expect("Failed to respond to X3DH");
+
+    let expected: [u8; 32] = hex::decode(EXPECTED_SECRET_HEX).unwrap().try_into().unwrap();
+    assert!(alice_result.matches(&expected));
+    assert!(bob_result.matches(&expected));
+    #[cfg(feature = "test-vectors")]
+    assert!(bob_result.matches(&alice_result.shared_secret_bytes()));
+    #[cfg(feature = "test-vectors")]
+    assert!(alice_result.matches(&bob_result.shared_secret_bytes()));
+    println!("  ✓ Matching secrets compare equal from both sides");
+
+    for bit in [0usize, 7, 128, 255] {
+        let mut flipped = expected;
+        flipped[bit / 8] ^= 1 << (bit % 8);
+        assert!(!alice_result.matches(&flipped), "Bit {} flip must not match", bit);
+        assert!(!bob_result.matches(&flipped), "Bit {} flip must not match", bit);
+    }
+    println!("  ✓ A single flipped bit does not match");
+}
@@ -0,0 +1,98 @@
+//! Test đóng session: xoá key material của ratchet và từ chối encrypt/decrypt sau đó
+
+mod common;
+
+use common::establish_ffi_sessions;
+use e2ee_core::error::E2EEError;
+use e2ee_core::ffi::api::{close_session, encrypt_message, secure_close_session};
+use e2ee_core::ffi::Session;
+use std::sync::Arc;
+
+#[test]
+fn test_closed_session_rejects_encrypt_and_decrypt() {
+    println!("\n=== Test: Secure Session Close ===\n");
+
+    let alice = Session::from_shared_secret([27u8; 32], true, "alice".to_string()).expect("Failed to create session");
+    let bob = Arc::new(Session::from_shared_secret([27u8; 32], false, "bob".to_string()).expect("Failed to create session"));
+    let envelope = alice.encrypt(b"before close").expect("Failed to encrypt");
+
+    // Close through one handle; the clone sees it too
+    let bob_clone = Arc::clone(&bob);
+    bob.close();
+
+    match bob_clone.decrypt(&envelope) {
+        Err(E2EEError::StateError(msg)) => assert_eq!(msg, "session closed"),
+        other => panic!("Expected StateError, got {:?}", other.map(|_| ())),
+    }
+    assert!(matches!(bob_clone.encrypt(b"after close"), Err(E2EEError::StateError(_))));
+    println!("  ✓ Encrypt/decrypt fail cleanly after close");
+
+    alice.close();
+    alice.close();
+    assert!(alice.encrypt(b"after close").is_err());
+}
+
+#[test]
+fn test_close_session_ffi_wipes_before_removal() {
+    let (alice_session, _bob_session) = establish_ffi_sessions(1271, None);
+    close_session(alice_session.clone());
+    assert_eq!(
+        encrypt_message(alice_session.clone(), b"gone".to_vec()),
+        format!("Error: Session not found: {}", alice_session)
+    );
+}
+
+#[cfg(feature = "test-support")]
+#[test]
+fn test_close_zeroes_chain_keys() {
+    let (mut alice_dr, mut bob_dr) = common::ratchet_pair([27u8; 32]);
+    let first = alice_dr.encrypt_envelope(b"one").expect("Failed to encrypt");
+    let _second = alice_dr.encrypt_envelope(b"two").expect("Failed to encrypt");
+    let third = alice_dr.encrypt_envelope(b"three").expect("Failed to encrypt");
+    bob_dr.decrypt_envelope(&third).expect("Failed to decrypt");
+
+    let zero_hash = ring::digest::digest(&ring::digest::SHA256, &[0u8; 32]);
+    assert_ne!(bob_dr.receiving_chain_key_hash(), zero_hash.as_ref());
+
+    bob_dr.close();
+    assert!(bob_dr.is_closed());
+    assert_eq!(bob_dr.sending_chain_key_hash(), zero_hash.as_ref());
+    assert_eq!(bob_dr.receiving_chain_key_hash(), zero_hash.as_ref());
+    // Skipped keys (messages 1 and 2) are gone as well
+    assert!(bob_dr.decrypt_envelope(&first).is_err());
+}
+
+#[test]
+fn test_is_wiped_after_close() {
+    let (mut alice_dr, mut bob_dr) = common::ratchet_pair([28u8; 32]);
+    let _skipped = alice_dr.encrypt_envelope(b"one").expect("Failed to encrypt");
+    let second = alice_dr.encrypt_envelope(b"two").expect("Failed to encrypt");
+    bob_dr.decrypt_envelope(&second).expect("Failed to decrypt");
+    assert!(!bob_dr.is_wiped());
+
+    bob_dr.close();
+    assert!(bob_dr.is_wiped());
+    assert!(!secure_close_session("unknown".to_string()));
+}
+
+#[cfg(feature = "test-support")]
+#[test]
+fn test_secure_close_session_wipes_retained_clone() {
+    println!("\n=== Test: Secure Close Session ===\n");
+
+    let (alice_session, _bob_session) = establish_ffi_sessions(1991, Some(1992));
+    let retained = e2ee_core::ffi::api::registered_session(&alice_session).expect("Session must be registered");
+    assert!(!retained.double_ratchet.lock().unwrap().is_wiped());
+
+    assert!(secure_close_session(alice_session.clone()));
+    assert!(e2ee_core::ffi::api::registered_session(&alice_session).is_none());
+    println!("  ✓ Session confirmed wiped and removed from the registry");
+
+    let dr = retained.double_ratchet.lock().unwrap();
+    assert!(dr.is_closed());
+    assert!(dr.is_wiped());
+    drop(dr);
+    assert!(matches!(retained.encrypt(b"after close"), Err(E2EEError::StateError(_))));
+    assert!(!secure_close_session(alice_session));
+    println!("  ✓ Retained clone is closed and zeroed, second close reports false");
+}
@@ -0,0 +1,27 @@
+//! Test factory tạo cặp session xác định (establish_session_pair, chỉ với test-support)
+
+#![cfg(feature = "test-support")]
+
+use e2ee_core::x3dh::establish_session_pair;
+
+#[test]
+fn test_session_pair_exchanges_messages_both_ways() {
+    println!("\n=== Test: Deterministic Session Pair ===\n");
+
+    let (mut alice, mut bob) = establish_session_pair(42);
+    let hello = alice.encrypt_envelope(b"hello bob").expect("Failed to encrypt");
+    assert_eq!(bob.decrypt_envelope(&hello).expect("Failed to decrypt"), b"hello bob".to_vec());
+    let reply = bob.encrypt_envelope(b"hello alice").expect("Failed to encrypt");
+    assert_eq!(alice.decrypt_envelope(&reply).expect("Failed to decrypt"), b"hello alice".to_vec());
+    assert!(alice.has_ratcheted() && bob.has_ratcheted());
+    println!("  ✓ Alice and Bob exchange a message pair straight away");
+
+    // The same seed reproduces the handshake: a peer from one run reads the other run
+    let (mut alice_again, _) = establish_session_pair(42);
+    let (_, mut bob_other_seed) = establish_session_pair(43);
+    let (_, mut bob_same_seed) = establish_session_pair(42);
+    let envelope = alice_again.encrypt_envelope(b"same seed").expect("Failed to encrypt");
+    assert!(bob_other_seed.decrypt_envelope(&envelope).is_err());
+    assert_eq!(bob_same_seed.decrypt_envelope(&envelope).expect("Failed to decrypt"), b"same seed".to_vec());
+    println!("  ✓ Same seed reproduces the session, another seed does not");
+}
@@ -0,0 +1,56 @@
+//! Test bộ đếm message gửi/nhận của session (metrics)
+
+mod common;
+
+use common::establish_ffi_sessions;
+use e2ee_core::ffi::api::{decrypt_message, encrypt_message, session_stats};
+use e2ee_core::ffi::{generate_session_id, Session, SessionStats};
+
+fn stats_of(session_id: &str) -> SessionStats {
+    let json = session_stats(session_id.to_string());
+    serde_json::from_str(&json).unwrap_or_else(|_| panic!("Invalid stats JSON: {}", json))
+}
+
+#[test]
+fn test_session_counts_successful_messages() {
+    println!("\n=== Test: Session Message Counters ===\n");
+
+    let alice = Session::from_shared_secret([0x78; 32], true, generate_session_id()).unwrap();
+    let bob = Session::from_shared_secret([0x78; 32], false, generate_session_id()).unwrap();
+    let envelopes: Vec<_> = (0..3).map(|i| alice.encrypt(&[i]).unwrap()).collect();
+    bob.decrypt(&envelopes[0]).unwrap();
+    bob.decrypt_full(&envelopes[1]).unwrap();
+
+    assert_eq!(alice.stats(), SessionStats { messages_sent: 3, messages_received: 0 });
+    assert_eq!(bob.stats(), SessionStats { messages_sent: 0, messages_received: 2 });
+    println!("  ✓ Three encrypts and two decrypts are counted");
+
+    let mut forged = envelopes[2].clone();
+    forged.ciphertext[0] ^= 0xff;
+    assert!(bob.decrypt(&forged).is_err());
+    assert!(bob.decrypt(&envelopes[0]).is_err());
+    assert_eq!(bob.stats().messages_received, 2);
+    println!("  ✓ Failed decrypts (forged, replayed) are not counted");
+}
+
+#[test]
+fn test_session_stats_ffi() {
+    println!("\n=== Test: Session Stats FFI ===\n");
+
+    let (alice_session, bob_session) = establish_ffi_sessions(1781, Some(1782));
+    let envelopes: Vec<_> = (0..3)
+        .map(|i| encrypt_message(alice_session.clone(), format!("message {}", i).into_bytes()))
+        .collect();
+    for envelope in &envelopes[..2] {
+        decrypt_message(bob_session.clone(), envelope.clone());
+    }
+    let output = String::from_utf8(decrypt_message(bob_session.clone(), "not an envelope".to_string())).unwrap();
+    assert!(output.starts_with("Error:"));
+
+    assert_eq!(stats_of(&alice_session), SessionStats { messages_sent: 3, messages_received: 0 });
+    assert_eq!(stats_of(&bob_session), SessionStats { messages_sent: 0, messages_received: 2 });
+    println!("  ✓ session_stats reports the counters as JSON");
+
+    assert!(session_stats("no-such-session".to_string()).starts_with("Error:"));
+    println!("  ✓ Unknown session is an error");
+}
@@ -0,0 +1,38 @@
+#![cfg(feature = "test-vectors")]
+//! Test truy cập shared secret thô (chỉ với feature test-vectors) và into_ratchet
+
+use e2ee_core::keys::prekey::{SignedPreKey, SignedPreKeyPair};
+use e2ee_core::keys::{IdentityKeyPair, PreKeyBundle};
+use e2ee_core::x3dh::{X3DHInitiator, X3DHResponder};
+
+#[test]
+fn test_shared_secret_bytes_and_into_ratchet() {
+    println!("\n=== Test: Shared Secret Access ===\n");
+
+    let alice_identity = IdentityKeyPair::generate();
+    let bob_identity = IdentityKeyPair::generate();
+    let bob_signed_prekey = SignedPreKeyPair::generate(1, &bob_identity)
+        .expect("Failed to generate signed prekey");
+    let bundle = PreKeyBundle::new(
+        bob_identity.public_key_hex(),
+        bob_identity.verifying_key(),
+        SignedPreKey::from(&bob_signed_prekey),
+        None,
+    );
+
+    let alice_result = X3DHInitiator::new(alice_identity.clone())
+        .initiate(&bundle)
+        .expect("Failed to initiate X3DH");
+    let bob_result = X3DHResponder::new(bob_identity, bob_signed_prekey)
+        .respond(&alice_identity.public_key_hex(), &alice_result.ephemeral_public_key_hex)
+        .expect("Failed to respond to X3DH");
+
+    assert_eq!(alice_result.shared_secret_bytes(), bob_result.shared_secret_bytes());
+    println!("  ✓ Accessor returns the secret both sides derived");
+
+    let mut alice_dr = alice_result.into_ratchet().expect("Failed to create Alice's Double Ratchet");
+    let mut bob_dr = bob_result.into_ratchet().expect("Failed to create Bob's Double Ratchet");
+    let envelope = alice_dr.encrypt_envelope(b"no secret in sight").expect("Failed to encrypt");
+    assert_eq!(bob_dr.decrypt_envelope(&envelope).expect("Failed to decrypt"), b"no secret in sight".to_vec());
+    println!("  ✓ into_ratchet builds working ratchets");
+}
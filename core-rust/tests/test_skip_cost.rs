@@ -0,0 +1,38 @@
+//! Test ước lượng số key bị bỏ qua trước khi giải mã (skip_cost)
+
+mod common;
+
+use common::ratchet_pair;
+use e2ee_core::error::E2EEError;
+
+#[test]
+fn test_skip_cost_reports_gap_without_advancing() {
+    println!("\n=== Test: Skip Cost Before Decrypt ===\n");
+
+    let (mut alice_dr, mut bob_dr) = ratchet_pair([0x5c; 32]);
+    let envelopes: Vec<_> = (0..501)
+        .map(|i| alice_dr.encrypt_envelope(format!("message {}", i).as_bytes()).unwrap())
+        .collect();
+
+    let before = bob_dr.public_state();
+    assert_eq!(bob_dr.skip_cost(&envelopes[0]).unwrap(), 0);
+    println!("  ✓ Next expected message costs 0");
+
+    assert_eq!(bob_dr.skip_cost(&envelopes[500]).unwrap(), 500);
+    println!("  ✓ Message 500 ahead costs 500");
+
+    assert_eq!(bob_dr.public_state(), before);
+    assert_eq!(bob_dr.decrypt_envelope(&envelopes[0]).unwrap(), b"message 0".to_vec());
+    println!("  ✓ Live chain was not advanced");
+
+    assert_eq!(bob_dr.skip_cost(&envelopes[500]).unwrap(), 499);
+    match bob_dr.skip_cost(&envelopes[0]) {
+        Err(E2EEError::ProtocolError(_)) => {}
+        other => panic!("Expected protocol error for a received message, got {:?}", other),
+    }
+    println!("  ✓ Cost follows the chain and already received messages are rejected");
+
+    assert_eq!(bob_dr.decrypt_envelope(&envelopes[10]).unwrap(), b"message 10".to_vec());
+    assert_eq!(bob_dr.skip_cost(&envelopes[5]).unwrap(), 0);
+    println!("  ✓ Messages with stored skipped keys cost 0");
+}
@@ -0,0 +1,60 @@
+//! Test liệt kê cipher suite được hỗ trợ và suite của từng session
+
+mod common;
+
+use common::{establish_ffi_sessions, ratchet_pair};
+use e2ee_core::ffi::api::{session_suite, supported_suites};
+use e2ee_core::ffi::{generate_session_id, Session};
+use e2ee_core::ratchet::{self, DoubleRatchet, SuiteDescriptor};
+
+#[test]
+fn test_default_suite_is_reported() {
+    println!("\n=== Test: Cipher Suites ===\n");
+
+    let (alice_dr, _bob_dr) = ratchet_pair([0x80; 32]);
+    let suite = alice_dr.current_suite();
+    assert_eq!(suite.cipher, "AES-256-GCM");
+    assert_eq!(suite.kdf, "HKDF-SHA256");
+    assert!(!suite.header_encryption);
+    println!("  ✓ Default ratchet reports AES-256-GCM + HKDF-SHA256");
+
+    let supported = ratchet::supported_suites();
+    assert_eq!(supported.first(), Some(&suite));
+    let from_ffi: Vec<SuiteDescriptor> = serde_json::from_str(&supported_suites()).expect("Invalid suites JSON");
+    assert_eq!(from_ffi, supported);
+    println!("  ✓ Default suite is listed first, FFI JSON round-trips");
+
+    let (alice_session, _) = establish_ffi_sessions(1801, None);
+    let session: SuiteDescriptor = serde_json::from_str(&session_suite(alice_session)).expect("Invalid suite JSON");
+    assert_eq!(session, suite);
+    assert!(session_suite("no-such-session".to_string()).starts_with("Error:"));
+    println!("  ✓ session_suite reports the session's suite");
+}
+
+#[test]
+fn test_xchacha_suite_is_reported_and_used() {
+    println!("\n=== Test: XChaCha20-Poly1305 Suite ===\n");
+
+    let xchacha = SuiteDescriptor::xchacha20_poly1305();
+    assert!(ratchet::supported_suites().contains(&xchacha));
+    let builder = DoubleRatchet::builder().cipher("XChaCha20-Poly1305");
+    let mut alice_dr = builder.build_from_shared_secret(&[0x81; 32], true).unwrap();
+    let mut bob_dr = builder.build_from_shared_secret(&[0x81; 32], false).unwrap();
+    assert_eq!(alice_dr.current_suite(), xchacha);
+    assert_eq!(bob_dr.current_suite(), xchacha);
+    println!("  ✓ Builder-built ratchets report XChaCha20-Poly1305");
+
+    let envelope = alice_dr.encrypt_envelope(b"xchacha").unwrap();
+    let mut aes_bob = DoubleRatchet::from_shared_secret(&[0x81; 32], false).unwrap();
+    assert!(aes_bob.decrypt_envelope(&envelope).is_err());
+    assert_eq!(bob_dr.decrypt_envelope(&envelope).unwrap(), b"xchacha".to_vec());
+    let reply = bob_dr.encrypt_envelope(b"reply").unwrap();
+    assert_eq!(alice_dr.decrypt_envelope(&reply).unwrap(), b"reply".to_vec());
+    println!("  ✓ XChaCha peers interoperate, an AES-GCM peer cannot decrypt");
+
+    let session = Session::from_state(&alice_dr.to_state().unwrap(), "xchacha".to_string(), None, generate_session_id()).unwrap();
+    assert_eq!(session.current_suite().unwrap(), xchacha);
+    let envelope = session.encrypt(b"restored").unwrap();
+    assert_eq!(bob_dr.decrypt_envelope(&envelope).unwrap(), b"restored".to_vec());
+    println!("  ✓ Suite survives save/restore into a session");
+}
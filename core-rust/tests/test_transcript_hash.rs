@@ -0,0 +1,73 @@
+//! Test transcript hash của handshake X3DH
+
+use e2ee_core::keys::{IdentityKeyPair, PreKeyBundle};
+use e2ee_core::keys::prekey::{OneTimePreKey, OneTimePreKeyPair, SignedPreKey, SignedPreKeyPair};
+use e2ee_core::x3dh::{calculate_transcript_hash, X3DHInitiator, X3DHResponder};
+use x25519_dalek::StaticSecret;
+
+#[test]
+fn test_both_sides_compute_same_transcript_hash() {
+    println!("\n=== Test: Handshake Transcript Hash ===\n");
+
+    let alice_identity = IdentityKeyPair::generate();
+    let bob_identity = IdentityKeyPair::generate();
+    let bob_signed_prekey = SignedPreKeyPair::generate(1, &bob_identity)
+        .expect("Failed to generate signed prekey");
+    let bob_one_time_prekey = OneTimePreKeyPair::generate(1)
+        .expect("Failed to generate one-time prekey");
+
+    let prekey_bundle = PreKeyBundle::new(
+        bob_identity.public_key_hex(),
+        bob_identity.verifying_key(),
+        SignedPreKey::from(&bob_signed_prekey),
+        Some(OneTimePreKey::from(&bob_one_time_prekey)),
+    );
+
+    let alice = X3DHInitiator::new(alice_identity.clone());
+    let alice_result = alice.initiate(&prekey_bundle).expect("Failed to initiate X3DH");
+
+    let bob_one_time_private_bytes = bob_one_time_prekey.private_key().to_bytes();
+    let bob_one_time_private = StaticSecret::from(bob_one_time_private_bytes);
+    let mut bob = X3DHResponder::new(bob_identity.clone(), bob_signed_prekey.clone());
+    bob.set_one_time_prekey(1, bob_one_time_private, *bob_one_time_prekey.public_key());
+    let bob_result = bob
+        .respond(&alice_identity.public_key_hex(), &alice_result.ephemeral_public_key_hex)
+        .expect("Failed to respond to X3DH");
+
+    let transcript_hash = alice_result.transcript_hash.expect("Missing initiator transcript hash");
+    assert_eq!(Some(transcript_hash), bob_result.transcript_hash);
+    println!("  ✓ Initiator and responder transcript hashes match");
+
+    // Recompute from the public inputs and check each one is bound
+    let ephemeral: [u8; 32] = hex::decode(&alice_result.ephemeral_public_key_hex).unwrap().try_into().unwrap();
+    let inputs = [
+        alice_identity.public_key_bytes(),
+        bob_identity.public_key_bytes(),
+        bob_signed_prekey.public_key_bytes(),
+        ephemeral,
+        bob_one_time_prekey.public_key_bytes(),
+    ];
+    let hash_of = |inputs: &[[u8; 32]; 5], with_opk: bool| {
+        calculate_transcript_hash(
+            &inputs[0],
+            &inputs[1],
+            &inputs[2],
+            &inputs[3],
+            with_opk.then_some(&inputs[4]),
+        )
+    };
+    assert_eq!(hash_of(&inputs, true), transcript_hash);
+    assert_ne!(hash_of(&inputs, false), transcript_hash, "Dropping the OPK must change the hash");
+
+    for index in 0..inputs.len() {
+        let mut changed = inputs;
+        changed[index][0] ^= 0x01;
+        assert_ne!(hash_of(&changed, true), transcript_hash, "Input {} is not bound", index);
+    }
+
+    // Swapping the identities must change the hash too
+    let mut swapped = inputs;
+    swapped.swap(0, 1);
+    assert_ne!(hash_of(&swapped, true), transcript_hash);
+    println!("  ✓ Changing any public input changes the hash");
+}
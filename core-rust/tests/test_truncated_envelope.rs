@@ -0,0 +1,60 @@
+//! Test phân biệt envelope bị cắt cụt, JSON dở dang và thiếu field
+
+use base64::{engine::general_purpose, Engine as _};
+use e2ee_core::error::E2EEError;
+use e2ee_core::message::MessageEnvelope;
+
+fn serialization_error(b64: &str) -> String {
+    match MessageEnvelope::from_base64(b64) {
+        Err(E2EEError::SerializationError(msg)) => msg,
+        Err(e) => panic!("Expected serialization error, got {}", e),
+        Ok(_) => panic!("Malformed envelope {:?} must not parse", b64),
+    }
+}
+
+fn sample_json() -> String {
+    let envelope = MessageEnvelope::regular(vec![1, 2, 3], "ab".repeat(32), 0, 1);
+    serde_json::to_string(&envelope).unwrap()
+}
+
+#[test]
+fn test_truncated_base64_reports_invalid_base64() {
+    println!("\n=== Test: Truncated Base64 Envelope ===\n");
+
+    let b64 = general_purpose::STANDARD.encode(sample_json());
+    let truncated = &b64[..b64.len() - 3];
+    let msg = serialization_error(truncated);
+    assert!(msg.starts_with("Failed to decode base64"), "Unexpected error: {}", msg);
+    println!("  ✓ Cut-off base64 is reported as invalid base64");
+}
+
+#[test]
+fn test_incomplete_json_reports_truncated_json() {
+    println!("\n=== Test: Base64 Of Incomplete JSON ===\n");
+
+    let json = sample_json();
+    let b64 = general_purpose::STANDARD.encode(&json[..json.len() / 2]);
+    let msg = serialization_error(&b64);
+    assert!(msg.starts_with("Truncated envelope JSON"), "Unexpected error: {}", msg);
+    println!("  ✓ Valid base64 of incomplete JSON is reported as truncated JSON");
+
+    let b64 = general_purpose::STANDARD.encode("{\"version\": 1,, }");
+    let msg = serialization_error(&b64);
+    assert!(msg.starts_with("Invalid envelope JSON"), "Unexpected error: {}", msg);
+    println!("  ✓ Complete but malformed JSON is reported separately");
+}
+
+#[test]
+fn test_missing_header_reports_field() {
+    println!("\n=== Test: Envelope JSON Missing Header ===\n");
+
+    let mut value: serde_json::Value = serde_json::from_str(&sample_json()).unwrap();
+    value.as_object_mut().unwrap().remove("header");
+    let b64 = general_purpose::STANDARD.encode(value.to_string());
+    assert_eq!(serialization_error(&b64), "Envelope missing required field `header`");
+    println!("  ✓ Missing header is named in the error");
+
+    let b64 = general_purpose::STANDARD.encode(sample_json());
+    assert!(MessageEnvelope::from_base64(&b64).is_ok());
+    println!("  ✓ Complete envelope still parses");
+}
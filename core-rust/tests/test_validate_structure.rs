@@ -0,0 +1,61 @@
+//! Test kiểm tra cấu trúc envelope không cần session key (validate_structure)
+
+mod common;
+
+use common::ratchet_pair;
+use e2ee_core::error::E2EEError;
+use e2ee_core::message::MessageEnvelope;
+
+#[test]
+fn test_valid_envelope_passes_structure_check() {
+    println!("\n=== Test: Validate Envelope Structure ===\n");
+
+    let (mut alice_dr, _bob_dr) = ratchet_pair([0x86; 32]);
+    let envelope = alice_dr.encrypt_envelope(b"hello").expect("Failed to encrypt");
+    envelope.validate_structure().expect("Envelope from the ratchet must pass");
+    let receipt = alice_dr.encrypt_envelope(b"").expect("Failed to encrypt");
+    receipt.validate_structure().expect("Empty-body envelope must pass");
+    println!("  ✓ Envelopes produced by the ratchet pass");
+}
+
+#[test]
+fn test_malformed_envelopes_fail_structure_check() {
+    println!("\n=== Test: Malformed Envelope Structure ===\n");
+
+    let (mut alice_dr, _bob_dr) = ratchet_pair([0x87; 32]);
+    let envelope = alice_dr.encrypt_envelope(b"hello").expect("Failed to encrypt");
+
+    let mut bad_hex = envelope.clone();
+    bad_hex.header.dh_public_key = "zz".repeat(32);
+    let mut short_key = envelope.clone();
+    short_key.header.dh_public_key.truncate(62);
+    for variant in [bad_hex, short_key] {
+        assert!(matches!(variant.validate_structure(), Err(E2EEError::SerializationError(_))));
+    }
+    println!("  ✓ Bad or short DH key hex fails with SerializationError");
+
+    let mut empty_ciphertext = envelope.clone();
+    empty_ciphertext.ciphertext.clear();
+    match empty_ciphertext.validate_structure() {
+        Err(E2EEError::ProtocolError(msg)) => assert!(msg.contains("AEAD tag"), "Unexpected error: {}", msg),
+        other => panic!("Expected ProtocolError, got {:?}", other),
+    }
+    println!("  ✓ Empty ciphertext fails");
+
+    let mut unknown_version = envelope.clone();
+    unknown_version.version = MessageEnvelope::VERSION + 1;
+    match unknown_version.validate_structure() {
+        Err(E2EEError::ProtocolError(msg)) => assert!(msg.contains("Unsupported envelope version"), "Unexpected error: {}", msg),
+        other => panic!("Expected ProtocolError, got {:?}", other),
+    }
+    println!("  ✓ Unknown version fails");
+
+    let mut zero_number = envelope.clone();
+    zero_number.header.message_number = 0;
+    let mut zero_receipt = alice_dr.encrypt_envelope(b"").expect("Failed to encrypt");
+    zero_receipt.header.receipt_for = Some(0);
+    for variant in [zero_number, zero_receipt] {
+        assert!(matches!(variant.validate_structure(), Err(E2EEError::ProtocolError(_))));
+    }
+    println!("  ✓ Counters of 0 fail");
+}
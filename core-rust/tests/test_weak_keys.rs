@@ -0,0 +1,46 @@
+//! Test từ chối private key yếu (toàn 0 hoặc toàn 0xFF) khi khôi phục identity
+
+use e2ee_core::error::E2EEError;
+use e2ee_core::ffi::IdentityKeyPairBytes;
+use e2ee_core::keys::IdentityKeyPair;
+
+fn identity_bytes_with(x25519_private: [u8; 32], ed25519_private: [u8; 32]) -> IdentityKeyPairBytes {
+    let mut bytes = IdentityKeyPairBytes::from_identity_key_pair(&IdentityKeyPair::generate());
+    bytes.x25519_private_key = x25519_private.to_vec();
+    bytes.ed25519_private_key = ed25519_private.to_vec();
+    bytes
+}
+
+#[test]
+fn test_weak_ed25519_secrets_rejected() {
+    println!("\n=== Test: Weak Identity Secrets ===\n");
+
+    let valid = IdentityKeyPairBytes::from_identity_key_pair(&IdentityKeyPair::generate());
+    let x25519_private: [u8; 32] = valid.x25519_private_key.clone().try_into().unwrap();
+
+    for weak in [[0x00u8; 32], [0xffu8; 32]] {
+        let bytes = identity_bytes_with(x25519_private, weak);
+        assert!(matches!(bytes.to_identity_key_pair(), Err(E2EEError::KeyGenerationError(_))));
+
+        let result = IdentityKeyPair::from_bytes(x25519_private, [0u8; 32], weak, [0u8; 32]);
+        assert!(matches!(result, Err(E2EEError::KeyGenerationError(_))));
+    }
+    println!("  ✓ All-zero and all-0xFF Ed25519 secrets rejected");
+}
+
+#[test]
+fn test_zero_x25519_scalar_rejected() {
+    let valid = IdentityKeyPairBytes::from_identity_key_pair(&IdentityKeyPair::generate());
+    let ed25519_private: [u8; 32] = valid.ed25519_private_key.clone().try_into().unwrap();
+
+    let bytes = identity_bytes_with([0u8; 32], ed25519_private);
+    assert!(matches!(bytes.to_identity_key_pair(), Err(E2EEError::KeyGenerationError(_))));
+}
+
+#[test]
+fn test_random_identity_round_trips() {
+    let identity = IdentityKeyPair::generate();
+    let bytes = IdentityKeyPairBytes::from_identity_key_pair(&identity);
+    let restored = bytes.to_identity_key_pair().expect("Valid key must be accepted");
+    assert_eq!(restored.public_key_bytes(), identity.public_key_bytes());
+}